@@ -0,0 +1,107 @@
+//! Benchmarks of the completions endpoint.
+//!
+//! Run with `cargo bench --bench completions`. Loading each BPE encoding
+//! once instead of per request took the handler from ~65ms to ~75µs, and
+//! generating the text of `n` choices once took `create_choices` with
+//! `n = 16` from ~205µs to ~15µs. Sampling logprobs from the tokens split
+//! once, with alternatives borrowed from the encoder instead of formatted,
+//! took `create_choices/logprobs/50` from ~1.9ms to ~0.8ms.
+//!
+//! `high_throughput` reports requests per second through the handler on
+//! one core: ~12k with regular responses, ~350k with the precomputed ones
+//! of `MockConfig::high_throughput`.
+
+use bytes::Bytes;
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use openai_mock::config::{HighThroughputConfig, MockConfig};
+use openai_mock::handlers::completions;
+use openai_mock::service::MockRequest;
+use openai_mock::state::MockState;
+use openai_mock::utils::choices::{add_logprobs, create_choices};
+use openai_mock::utils::token_counting::TokenCounter;
+use serde_json::json;
+use std::sync::Arc;
+
+const PROMPT: &str = "Summarize the following paragraph in one sentence: the quick brown \
+                      fox jumps over the lazy dog while the cat watches from the fence.";
+
+fn completion_request(n: u32) -> MockRequest {
+    let body = json!({
+        "model": "gpt-3.5-turbo-instruct",
+        "prompt": PROMPT,
+        "max_tokens": 64,
+        "n": n,
+        "echo": true,
+        "logprobs": 2,
+    });
+    http::Request::post("/v1/completions")
+        .header("content-type", "application/json")
+        .body(Bytes::from(body.to_string()))
+        .unwrap()
+}
+
+/// The whole handler, from the raw request to the serialized response.
+fn bench_handler(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let state = Arc::new(MockState::default());
+    let response = runtime.block_on(completions(state.clone(), completion_request(1)));
+    assert!(response.status().is_success());
+    let mut group = c.benchmark_group("completions");
+    for n in [1, 16] {
+        group.bench_with_input(BenchmarkId::new("handler", n), &n, |b, &n| {
+            b.iter(|| runtime.block_on(completions(state.clone(), completion_request(n))));
+            state.history.clear();
+        });
+    }
+    group.finish();
+}
+
+/// Choice generation alone, with a counter shared across iterations.
+fn bench_create_choices(c: &mut Criterion) {
+    let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+    let mut group = c.benchmark_group("create_choices");
+    for n in [1, 16] {
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter(|| create_choices(black_box(n), PROMPT, true));
+        });
+    }
+    // Large `n` with logprobs, where these are sampled in parallel.
+    for n in [4, 50] {
+        group.bench_with_input(BenchmarkId::new("logprobs", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut choices = create_choices(black_box(n), PROMPT, true);
+                add_logprobs(&mut choices, 5, &counter);
+                choices
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Requests served per second, with and without precomputed responses.
+fn bench_high_throughput(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let regular = Arc::new(MockState::default());
+    let config = MockConfig::default().with_high_throughput(HighThroughputConfig::new());
+    let precomputed = Arc::new(MockState::new(config));
+    let mut group = c.benchmark_group("high_throughput");
+    group.throughput(Throughput::Elements(1));
+    for (name, state) in [("regular", regular), ("precomputed", precomputed)] {
+        group.bench_function(name, |b| {
+            b.iter(|| runtime.block_on(completions(state.clone(), completion_request(1))));
+            state.history.clear();
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_handler, bench_create_choices, bench_high_throughput);
+criterion_main!(benches);
@@ -0,0 +1,155 @@
+//! Machine-readable description of the emulated OpenAI API surface.
+//!
+//! Test suites can query this at runtime (or over HTTP via
+//! `GET /__admin/capabilities`) to gate tests on how faithfully an endpoint
+//! is emulated, instead of discovering gaps through failures.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Date of the OpenAI API reference the emulated surface was last checked
+/// against.
+pub const EMULATED_API_VERSION: &str = "2024-11-01";
+
+/// How faithfully an endpoint is emulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fidelity {
+    /// Request validation and response shape match the real API.
+    Full,
+
+    /// The endpoint works, but some parameters are ignored or simplified.
+    Partial,
+
+    /// The endpoint exists and returns well-formed placeholder data only.
+    Stubbed,
+}
+
+/// Emulation details for one endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointCapability {
+    /// HTTP method, e.g. `POST`.
+    pub method: String,
+
+    /// Path relative to the server root, e.g. `/v1/completions`.
+    pub path: String,
+
+    /// How faithfully the endpoint is emulated.
+    pub fidelity: Fidelity,
+
+    /// Known gaps relative to the real API.
+    pub notes: Vec<String>,
+}
+
+impl EndpointCapability {
+    fn new(method: &str, path: &str, fidelity: Fidelity, notes: &[&str]) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            fidelity,
+            notes: notes.iter().map(|note| note.to_string()).collect(),
+        }
+    }
+
+    /// Map key for this endpoint, e.g. `POST /v1/completions`.
+    pub fn key(&self) -> String {
+        format!("{} {}", self.method, self.path)
+    }
+}
+
+/// Returns the OpenAI API version the mock emulates.
+pub fn emulated_api_version() -> &'static str {
+    EMULATED_API_VERSION
+}
+
+/// Returns every emulated endpoint keyed by `"METHOD /path"`.
+pub fn capabilities() -> BTreeMap<String, EndpointCapability> {
+    let endpoints = [
+        EndpointCapability::new(
+            "POST",
+            "/v1/completions",
+            Fidelity::Partial,
+            &[
+                "generated text is mock content, not model output",
+                "suffix is accepted but ignored",
+                "logit_bias only honors bans (-100) and forced tokens (100)",
+            ],
+        ),
+        EndpointCapability::new(
+            "POST",
+            "/v1/chat/completions",
+            Fidelity::Partial,
+            &[
+                "answered from fixtures, a responder or a configured chat reply",
+                "only the context window and n are validated",
+                "stream is ignored; responses are never streamed",
+            ],
+        ),
+        EndpointCapability::new(
+            "POST",
+            "/v1/embeddings",
+            Fidelity::Stubbed,
+            &["answered from fixtures only; vectors do not depend on the input"],
+        ),
+        EndpointCapability::new(
+            "POST",
+            "/v1/moderations",
+            Fidelity::Stubbed,
+            &["answered from fixtures only; results do not depend on the input"],
+        ),
+        EndpointCapability::new(
+            "GET",
+            "/v1/files",
+            Fidelity::Partial,
+            &[
+                "lists the fixture files only; uploads are not supported",
+                "limit, after, order and purpose are honored",
+            ],
+        ),
+        EndpointCapability::new(
+            "GET",
+            "/v1/files/{file_id}",
+            Fidelity::Stubbed,
+            &["answered from fixtures only"],
+        ),
+        EndpointCapability::new("GET", "/v1/models", Fidelity::Full, &[]),
+        EndpointCapability::new("GET", "/v1/models/{model}", Fidelity::Full, &[]),
+    ];
+
+    endpoints
+        .into_iter()
+        .map(|endpoint| (endpoint.key(), endpoint))
+        .collect()
+}
+
+/// Returns the fidelity of an endpoint, or `None` if it is not emulated.
+pub fn endpoint_fidelity(method: &str, path: &str) -> Option<Fidelity> {
+    capabilities()
+        .get(&format!("{} {}", method.to_uppercase(), path))
+        .map(|endpoint| endpoint.fidelity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_fidelity() {
+        assert_eq!(
+            endpoint_fidelity("post", "/v1/completions"),
+            Some(Fidelity::Partial)
+        );
+        assert_eq!(
+            endpoint_fidelity("POST", "/v1/embeddings"),
+            Some(Fidelity::Stubbed)
+        );
+        assert_eq!(endpoint_fidelity("GET", "/v1/nonexistent"), None);
+    }
+
+    #[test]
+    fn test_capability_keys_match_entries() {
+        for (key, endpoint) in capabilities() {
+            assert_eq!(key, endpoint.key());
+        }
+    }
+}
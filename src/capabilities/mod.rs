@@ -0,0 +1,5 @@
+pub mod api_surface;
+pub use api_surface::{
+    capabilities, emulated_api_version, endpoint_fidelity, EndpointCapability, Fidelity,
+    EMULATED_API_VERSION,
+};
@@ -0,0 +1,81 @@
+//! A fixed assistant reply for chat completion requests.
+//!
+//! The mock does not generate chat completions; without a fixture for
+//! `/v1/chat/completions`, it does not serve them at all. Tests that only
+//! care about plumbing can instead have every chat request answered with
+//! one fixed message or tool call:
+//!
+//! ```yaml
+//! generation:
+//!   chat_reply:
+//!     tool_calls:
+//!       - name: get_weather
+//!         arguments: {"city": "Paris"}
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The assistant message every chat completion request no fixture matches
+/// is answered with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatReply {
+    /// The text of the message.
+    #[serde(default)]
+    pub content: Option<String>,
+
+    /// Functions the message calls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallReply>,
+}
+
+/// A function call of a [`ChatReply`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallReply {
+    /// The name of the function.
+    pub name: String,
+
+    /// The arguments of the call: a JSON value, serialized into the
+    /// `arguments` string of the response, or a string sent as is.
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl ChatReply {
+    /// A reply with text `content`.
+    pub fn text(content: &str) -> Self {
+        Self {
+            content: Some(content.to_string()),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// Adds a call of function `name` with `arguments`.
+    pub fn tool_call(mut self, name: &str, arguments: Value) -> Self {
+        self.tool_calls.push(ToolCallReply {
+            name: name.to_string(),
+            arguments,
+        });
+        self
+    }
+
+    /// The finish reason of a choice with this message.
+    pub fn finish_reason(&self) -> &'static str {
+        if self.tool_calls.is_empty() {
+            "stop"
+        } else {
+            "tool_calls"
+        }
+    }
+}
+
+impl ToolCallReply {
+    /// The `arguments` string of the call in the response.
+    pub fn arguments_json(&self) -> String {
+        match &self.arguments {
+            Value::String(arguments) => arguments.clone(),
+            Value::Null => "{}".to_string(),
+            arguments => arguments.to_string(),
+        }
+    }
+}
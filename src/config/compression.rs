@@ -0,0 +1,56 @@
+//! Compression of response bodies, negotiated with `Accept-Encoding`.
+
+use serde::{Deserialize, Serialize};
+
+/// Whether and which responses are compressed for clients that accept
+/// `gzip` or `br`, as the real API does.
+///
+/// ```yaml
+/// compression:
+///   min_size: 0
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// Whether responses are compressed. Clients not sending
+    /// `Accept-Encoding` always get plain responses.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Size in bytes below which complete bodies are sent uncompressed.
+    /// Streamed bodies are always compressed.
+    #[serde(default = "default_min_size")]
+    pub min_size: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_min_size() -> usize {
+    1024
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            min_size: default_min_size(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Never compresses responses.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    /// Compresses complete bodies of at least `min_size` bytes.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
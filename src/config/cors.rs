@@ -0,0 +1,93 @@
+//! Cross-origin resource sharing, for browser and WASM clients developed
+//! against the mock.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Which cross-origin requests browsers may make to the mock.
+///
+/// ```yaml
+/// cors:
+///   allowed_origins: ["http://localhost:3000"]
+///   max_age: 10s
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to read responses, e.g. `http://localhost:3000`.
+    /// Every origin is allowed when empty.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    /// Request headers allowed in cross-origin requests, e.g.
+    /// `Authorization`. Every header a preflight request asks for is
+    /// allowed when empty.
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+
+    /// How long browsers may cache the answer to a preflight request.
+    #[serde(default, with = "crate::config::duration::option")]
+    pub max_age: Option<Duration>,
+
+    /// Whether requests with credentials (cookies, HTTP authentication)
+    /// may read responses.
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    /// Allows every origin and request header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows `origin`. Once an origin is allowed, the others are not.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.allowed_origins.push(origin.to_string());
+        self
+    }
+
+    /// Allows request header `name`. Once a header is allowed, the others
+    /// are not.
+    pub fn allow_header(mut self, name: &str) -> Self {
+        self.allowed_headers.push(name.to_string());
+        self
+    }
+
+    /// Lets browsers cache the answer to a preflight request for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Lets requests with credentials read responses.
+    pub fn allow_credentials(mut self) -> Self {
+        self.allow_credentials = true;
+        self
+    }
+
+    /// Whether `origin` may read responses.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.is_empty()
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let cors: CorsConfig = serde_yaml::from_str(
+            "allowed_origins: [\"http://localhost:3000\"]\nmax_age: 10s\n",
+        )
+        .unwrap();
+        assert_eq!(cors.max_age, Some(Duration::from_secs(10)));
+        assert!(cors.allows_origin("http://localhost:3000"));
+        assert!(!cors.allows_origin("http://localhost:8080"));
+        assert!(CorsConfig::new().allows_origin("http://localhost:8080"));
+    }
+}
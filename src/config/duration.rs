@@ -0,0 +1,89 @@
+//! Serde helpers for `Duration` fields in configuration.
+//!
+//! Durations are written as integer milliseconds (`250`) or as strings with
+//! a unit suffix (`"250ms"`, `"1.5s"`), and always serialized as integer
+//! milliseconds.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Millis(u64),
+    Text(String),
+}
+
+/// Parses a duration such as `"250ms"`, `"2s"` or `"1.5s"`. A bare number
+/// is interpreted as milliseconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, scale) = if let Some(ms) = text.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(s) = text.strip_suffix('s') {
+        (s, 1000.0)
+    } else {
+        (text, 1.0)
+    };
+
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", text))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("invalid duration '{}'", text));
+    }
+    Ok(Duration::from_secs_f64(value * scale / 1000.0))
+}
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    match RawDuration::deserialize(deserializer)? {
+        RawDuration::Millis(ms) => Ok(Duration::from_millis(ms)),
+        RawDuration::Text(text) => parse_duration(&text).map_err(D::Error::custom),
+    }
+}
+
+/// The same helpers for `Option<Duration>` fields.
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => super::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        match Option::<RawDuration>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(RawDuration::Millis(ms)) => Ok(Some(Duration::from_millis(ms))),
+            Some(RawDuration::Text(text)) => parse_duration(&text).map(Some).map_err(D::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("40").unwrap(), Duration::from_millis(40));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("-1ms").is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! The OpenAI endpoints the mock can serve.
+
+use serde::{Deserialize, Serialize};
+
+/// An emulated OpenAI endpoint that can be enabled or disabled through
+/// [`MockConfig::endpoints`](crate::config::MockConfig::endpoints).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// `POST /v1/completions`.
+    Completions,
+
+    /// `GET /v1/models`.
+    ListModels,
+
+    /// `GET /v1/models/{model}`.
+    RetrieveModel,
+}
+
+impl Endpoint {
+    /// Every endpoint the mock implements.
+    pub const ALL: [Endpoint; 3] = [
+        Endpoint::Completions,
+        Endpoint::ListModels,
+        Endpoint::RetrieveModel,
+    ];
+
+    /// The HTTP method of the endpoint.
+    pub fn method(&self) -> &'static str {
+        match self {
+            Endpoint::Completions => "POST",
+            Endpoint::ListModels | Endpoint::RetrieveModel => "GET",
+        }
+    }
+
+    /// The request path of the endpoint.
+    pub fn path(&self) -> &'static str {
+        match self {
+            Endpoint::Completions => "/v1/completions",
+            Endpoint::ListModels => "/v1/models",
+            Endpoint::RetrieveModel => "/v1/models/{model}",
+        }
+    }
+}
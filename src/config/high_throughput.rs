@@ -0,0 +1,64 @@
+//! A load-testing mode serving one precomputed completion.
+
+use serde::{Deserialize, Serialize};
+
+/// Serves every completion request with the same response, computed
+/// once, for load tests where the mock must not be the bottleneck.
+///
+/// Only the `id` and `created` fields change between responses. Requests
+/// are not validated, authenticated, delayed, recorded or matched against
+/// stubs, and the prompt is not tokenized, so `usage` counts the
+/// completion tokens only.
+///
+/// ```yaml
+/// high_throughput:
+///   model: gpt-3.5-turbo-instruct
+///   text: This is a mock completion.
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighThroughputConfig {
+    /// The `model` of every response, also used to split the streamed
+    /// text into tokens.
+    #[serde(default = "default_model")]
+    pub model: String,
+
+    /// The text of the single choice of every response.
+    #[serde(default = "default_text")]
+    pub text: String,
+}
+
+fn default_model() -> String {
+    "gpt-3.5-turbo-instruct".to_string()
+}
+
+fn default_text() -> String {
+    "This is a mock completion.".to_string()
+}
+
+impl Default for HighThroughputConfig {
+    fn default() -> Self {
+        Self {
+            model: default_model(),
+            text: default_text(),
+        }
+    }
+}
+
+impl HighThroughputConfig {
+    /// Serves the default text as `gpt-3.5-turbo-instruct`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `model` of every response.
+    pub fn model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Sets the text of every response.
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+}
@@ -0,0 +1,100 @@
+//! Per-API-key behavior.
+//!
+//! A client juggling several keys (rotating them, or falling back to a
+//! second key when the first is throttled) needs each key to behave
+//! differently. A profile gives one key its own latency, error rate, usage
+//! tier, set of usable models and spending budget; the key is picked from the
+//! `Authorization: Bearer <key>` header:
+//!
+//! ```toml
+//! [auth.profiles."sk-primary"]
+//! tier = "free"
+//! error_rate = 0.5
+//! budget = 0.05
+//!
+//! [auth.profiles."sk-fallback"]
+//! latency = "800ms"
+//! models = ["gpt-3.5-turbo-instruct"]
+//! ```
+
+use crate::config::{Latency, UsageTier};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Settings for one API key, replacing the model, endpoint and global
+/// settings for requests made with it. A key with a profile is accepted
+/// when authentication is enabled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyProfile {
+    /// Delay before every response to the key.
+    #[serde(default)]
+    pub latency: Option<Latency>,
+
+    /// Fraction of the key's requests that fail with a `500` server error.
+    #[serde(default)]
+    pub error_rate: Option<f64>,
+
+    /// Usage tier whose rate limits are advertised to the key, replacing
+    /// its entry in `auth.tiers`.
+    #[serde(default)]
+    pub tier: Option<UsageTier>,
+
+    /// Model ids the key may use; other models fail with
+    /// `404 model_not_found` and are left out of `/v1/models`. Every model
+    /// may be used when `None`.
+    #[serde(default)]
+    pub models: Option<BTreeSet<String>>,
+
+    /// Simulated spend in USD after which the key's requests fail with
+    /// `429 insufficient_quota`. Spend is the token usage of each generated
+    /// completion priced at its model's `price_per_1k_tokens`. Unlimited
+    /// when `None`.
+    #[serde(default)]
+    pub budget: Option<f64>,
+}
+
+impl KeyProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the latency of the key.
+    pub fn latency(mut self, latency: impl Into<Latency>) -> Self {
+        self.latency = Some(latency.into());
+        self
+    }
+
+    /// Sets the fraction of the key's requests that fail.
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = Some(rate);
+        self
+    }
+
+    /// Puts the key in usage `tier`.
+    pub fn tier(mut self, tier: UsageTier) -> Self {
+        self.tier = Some(tier);
+        self
+    }
+
+    /// Lets the key use `model`. Once a model is allowed, the others are
+    /// denied.
+    pub fn allow_model(mut self, model: &str) -> Self {
+        self.models
+            .get_or_insert_with(BTreeSet::new)
+            .insert(model.to_string());
+        self
+    }
+
+    /// Sets the budget of the key in USD.
+    pub fn budget(mut self, usd: f64) -> Self {
+        self.budget = Some(usd);
+        self
+    }
+
+    /// Whether the key may use `model`.
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.models
+            .as_ref()
+            .is_none_or(|models| models.contains(model))
+    }
+}
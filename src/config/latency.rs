@@ -0,0 +1,201 @@
+//! Simulated response latency.
+//!
+//! A fixed delay is enough to exercise timeouts, but retry policies and
+//! hedging are tuned against the shape of the latency distribution (its
+//! p99 in particular), so latency can also be drawn from a distribution.
+//! In configuration files a plain duration means a fixed delay:
+//!
+//! ```yaml
+//! latency: 250ms
+//! routes:
+//!   completions:
+//!     latency: { distribution: log_normal, median: 400ms, sigma: 0.5 }
+//! ```
+
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::Duration;
+
+/// A latency distribution. Samples are clamped at zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case")]
+pub enum LatencyDistribution {
+    /// Always `delay`.
+    Fixed {
+        #[serde(with = "crate::config::duration")]
+        delay: Duration,
+    },
+
+    /// Uniformly distributed between `min` and `max`.
+    Uniform {
+        #[serde(with = "crate::config::duration")]
+        min: Duration,
+        #[serde(with = "crate::config::duration")]
+        max: Duration,
+    },
+
+    /// Normally distributed around `mean`.
+    Normal {
+        #[serde(with = "crate::config::duration")]
+        mean: Duration,
+        #[serde(with = "crate::config::duration")]
+        std_dev: Duration,
+    },
+
+    /// Log-normally distributed: most responses are close to `median`,
+    /// with a long tail whose weight grows with `sigma`, like real API
+    /// latencies.
+    LogNormal {
+        #[serde(with = "crate::config::duration")]
+        median: Duration,
+        sigma: f64,
+    },
+}
+
+/// Simulated latency: a fixed delay or a [`LatencyDistribution`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Latency(pub LatencyDistribution);
+
+impl Default for Latency {
+    fn default() -> Self {
+        Latency::fixed(Duration::ZERO)
+    }
+}
+
+impl From<Duration> for Latency {
+    fn from(delay: Duration) -> Self {
+        Latency::fixed(delay)
+    }
+}
+
+impl From<LatencyDistribution> for Latency {
+    fn from(distribution: LatencyDistribution) -> Self {
+        Latency(distribution)
+    }
+}
+
+impl Latency {
+    /// Always `delay`.
+    pub fn fixed(delay: Duration) -> Self {
+        Latency(LatencyDistribution::Fixed { delay })
+    }
+
+    /// Uniformly distributed between `min` and `max`.
+    pub fn uniform(min: Duration, max: Duration) -> Self {
+        Latency(LatencyDistribution::Uniform { min, max })
+    }
+
+    /// Normally distributed around `mean`.
+    pub fn normal(mean: Duration, std_dev: Duration) -> Self {
+        Latency(LatencyDistribution::Normal { mean, std_dev })
+    }
+
+    /// Log-normally distributed around `median`.
+    pub fn log_normal(median: Duration, sigma: f64) -> Self {
+        Latency(LatencyDistribution::LogNormal { median, sigma })
+    }
+
+    /// Whether every sample is zero.
+    pub fn is_zero(&self) -> bool {
+        match &self.0 {
+            LatencyDistribution::Fixed { delay } => delay.is_zero(),
+            LatencyDistribution::Uniform { max, .. } => max.is_zero(),
+            _ => false,
+        }
+    }
+
+    /// Draws one delay.
+    pub fn sample(&self) -> Duration {
+        self.sample_with(&mut rand::thread_rng())
+    }
+
+    /// Draws one delay using `rng`.
+    pub fn sample_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Duration {
+        let seconds = match &self.0 {
+            LatencyDistribution::Fixed { delay } => return *delay,
+            LatencyDistribution::Uniform { min, max } if min >= max => return *min,
+            LatencyDistribution::Uniform { min, max } => {
+                rng.gen_range(min.as_secs_f64()..max.as_secs_f64())
+            }
+            LatencyDistribution::Normal { mean, std_dev } => {
+                match Normal::new(mean.as_secs_f64(), std_dev.as_secs_f64()) {
+                    Ok(normal) => normal.sample(rng),
+                    Err(_) => mean.as_secs_f64(),
+                }
+            }
+            LatencyDistribution::LogNormal { median, sigma } => {
+                match LogNormal::new(median.as_secs_f64().ln(), *sigma) {
+                    Ok(log_normal) => log_normal.sample(rng),
+                    Err(_) => median.as_secs_f64(),
+                }
+            }
+        };
+
+        if seconds.is_finite() && seconds > 0.0 {
+            Duration::from_secs_f64(seconds)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLatency {
+    Fixed(#[serde(with = "crate::config::duration")] Duration),
+    Distribution(LatencyDistribution),
+}
+
+impl<'de> Deserialize<'de> for Latency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match RawLatency::deserialize(deserializer)? {
+            RawLatency::Fixed(delay) => Latency::fixed(delay),
+            RawLatency::Distribution(distribution) => Latency(distribution),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_parse_latency() {
+        let latency: Latency = serde_json::from_str(r#""250ms""#).unwrap();
+        assert_eq!(latency, Latency::fixed(Duration::from_millis(250)));
+
+        let latency: Latency =
+            serde_json::from_str(r#"{"distribution": "uniform", "min": 10, "max": "20ms"}"#).unwrap();
+        assert_eq!(latency, Latency::uniform(Duration::from_millis(10), Duration::from_millis(20)));
+
+        let latency: Latency =
+            serde_json::from_str(r#"{"distribution": "log_normal", "median": "1s", "sigma": 0.5}"#)
+                .unwrap();
+        assert_eq!(latency, Latency::log_normal(Duration::from_secs(1), 0.5));
+    }
+
+    #[test]
+    fn test_samples_follow_distribution() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let uniform = Latency::uniform(Duration::from_millis(10), Duration::from_millis(20));
+        for _ in 0..100 {
+            let delay = uniform.sample_with(&mut rng);
+            assert!(delay >= Duration::from_millis(10) && delay < Duration::from_millis(20));
+        }
+
+        let log_normal = Latency::log_normal(Duration::from_millis(100), 0.5);
+        let mut samples: Vec<Duration> = (0..1001).map(|_| log_normal.sample_with(&mut rng)).collect();
+        samples.sort();
+        let median = samples[500].as_secs_f64();
+        assert!((0.08..0.12).contains(&median), "median {}", median);
+
+        // Negative normal samples are clamped.
+        let normal = Latency::normal(Duration::ZERO, Duration::from_millis(10));
+        assert!((0..100).all(|_| normal.sample_with(&mut rng) < Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,766 @@
+//! Configuration controlling how the mock server behaves.
+
+use crate::config::{
+    ChatReply, CompressionConfig, CorsConfig, Endpoint, HighThroughputConfig, KeyProfile, Latency,
+    ModelConfig, OrganizationConfig, UsageTier,
+};
+use crate::faults::{ChaosConfig, OverloadFault, RateLimitFault, ResponseFault, StreamFault};
+use crate::fixtures::ResponseFixtures;
+use crate::generation::{SharedGenerator, TextGenerator};
+use crate::hooks::{LifecycleHooks, Responders};
+use crate::mirror::MirrorSink;
+use crate::scenario::{CannedResponse, ScenarioRule};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+/// Top-level configuration shared by all handlers.
+///
+/// Wrap it in a [`MockState`](crate::state::MockState) and pass it to
+/// [`configure_completion_routes_with`](crate::routes::configure_completion_routes_with)
+/// or [`MockServer::start_with`](crate::server::MockServer::start_with) to
+/// change the behavior of the mock endpoints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// Settings for generated content.
+    #[serde(default)]
+    pub generation: GenerationConfig,
+
+    /// Settings for streamed (`stream: true`) responses.
+    #[serde(default)]
+    pub streaming: StreamingConfig,
+
+    /// Faults injected into responses.
+    #[serde(default)]
+    pub faults: FaultConfig,
+
+    /// Feature access per organization id, selected by the
+    /// `OpenAI-Organization` header. Requests without the header, or for
+    /// organizations not listed here, are unrestricted.
+    #[serde(default)]
+    pub organizations: HashMap<String, OrganizationConfig>,
+
+    /// Endpoints that are served; the others answer `404`. Every endpoint
+    /// is served when `None`.
+    #[serde(default)]
+    pub endpoints: Option<BTreeSet<Endpoint>>,
+
+    /// Delay before every response is sent: a fixed duration or a
+    /// distribution.
+    #[serde(default)]
+    pub latency: Latency,
+
+    /// Settings per endpoint, replacing the global ones.
+    #[serde(default)]
+    pub routes: BTreeMap<Endpoint, RouteConfig>,
+
+    /// Overrides of the global settings per model id.
+    #[serde(default)]
+    pub models: HashMap<String, ModelConfig>,
+
+    /// API key authentication.
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    /// Secondary sink receiving a copy of every request.
+    #[serde(default)]
+    pub mirror: Option<MirrorSink>,
+
+    /// Rules mapping matching requests to canned responses, latencies or
+    /// errors. The first matching rule applies.
+    #[serde(default)]
+    pub rules: Vec<ScenarioRule>,
+
+    /// Response bodies served verbatim to requests no rule matched. Not
+    /// available in scenario files; load them with
+    /// [`ResponseFixtures::from_dir`].
+    #[serde(skip)]
+    pub fixtures: ResponseFixtures,
+
+    /// Callbacks invoked as requests are served. Not available in
+    /// scenario files.
+    #[serde(skip)]
+    pub hooks: LifecycleHooks,
+
+    /// Closures producing the responses of whole endpoints. Not available
+    /// in scenario files.
+    #[serde(skip)]
+    pub responders: Responders,
+
+    /// What happens to requests no stub (canned route response, rule,
+    /// responder, fixture or prompt response) matches.
+    #[serde(default)]
+    pub unmatched: UnmatchedRequests,
+
+    /// Cross-origin requests browsers may make. Responses carry no CORS
+    /// headers and preflight requests are not answered when `None`.
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+
+    /// Compression of response bodies for clients sending
+    /// `Accept-Encoding`.
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// Largest request body accepted, in bytes; larger requests fail with
+    /// `413 request_too_large`. [`DEFAULT_MAX_BODY_SIZE`] when `None`.
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+
+    /// Prefix every route is mounted under, e.g. `/openai` to serve
+    /// completions at `/openai/v1/completions`. Requests are handled (and
+    /// recorded) with the prefix removed from their path, so stubs,
+    /// fixtures and faults still use paths like `/v1/completions`.
+    #[serde(default)]
+    pub base_path: Option<String>,
+
+    /// Serves every completion with one precomputed response, skipping
+    /// everything else the mock does per request. Off when `None`.
+    #[serde(default)]
+    pub high_throughput: Option<HighThroughputConfig>,
+}
+
+/// Largest request body accepted unless configured otherwise: 2 MiB.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Settings for generated content.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    /// Whether the `n` choices of one request may have identical text.
+    #[serde(default)]
+    pub duplicate_choices: DuplicateChoices,
+
+    /// How the completion text is produced.
+    #[serde(default)]
+    pub strategy: GenerationStrategy,
+
+    /// Completion texts chosen by prompt, consulted before `strategy`. The
+    /// first entry matching the prompt applies; each prompt of a batched
+    /// completion request is matched on its own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompt_responses: Vec<PromptResponse>,
+
+    /// Produces the completion text instead of `strategy`, for models
+    /// without a `generation` of their own. Not available in scenario
+    /// files.
+    #[serde(skip)]
+    pub generator: Option<SharedGenerator>,
+
+    /// Answers chat completion requests no fixture matches. They are not
+    /// served when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_reply: Option<ChatReply>,
+}
+
+impl GenerationConfig {
+    /// The text of the first prompt response matching one of `prompts`.
+    pub fn prompt_response(&self, prompts: &[&str]) -> Option<&str> {
+        self.prompt_responses
+            .iter()
+            .find(|response| {
+                prompts
+                    .iter()
+                    .any(|prompt| prompt.contains(response.prompt_contains.as_str()))
+            })
+            .map(|response| response.text.as_str())
+    }
+}
+
+/// A completion text served whenever a prompt contains a substring,
+/// instead of generated text.
+///
+/// ```yaml
+/// generation:
+///   prompt_responses:
+///     - prompt_contains: refund
+///       text: I have started your refund.
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptResponse {
+    pub prompt_contains: String,
+    pub text: String,
+}
+
+/// A prompt response being added with `when_prompt_contains`, completed by
+/// `respond_with`.
+#[derive(Debug, Clone)]
+pub struct WhenPromptContains<T> {
+    pub(crate) parent: T,
+    pub(crate) prompt_contains: String,
+}
+
+impl WhenPromptContains<MockConfig> {
+    /// Completes every request whose prompt contains the substring with
+    /// `text`.
+    pub fn respond_with(mut self, text: &str) -> MockConfig {
+        self.parent.generation.prompt_responses.push(PromptResponse {
+            prompt_contains: self.prompt_contains,
+            text: text.to_string(),
+        });
+        self.parent
+    }
+}
+
+/// How the text of a completion is produced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GenerationStrategy {
+    /// The completion is empty; only the prompt is returned, and only when
+    /// the request sets `echo`.
+    #[default]
+    Echo,
+
+    /// Every choice completes with the same text, cut to `max_tokens`.
+    Fixed { text: String },
+
+    /// Pseudo-natural prose from a word-level Markov chain over a built-in
+    /// corpus, between half of and all of `max_tokens` long. With a
+    /// `seed`, the same prompt and choice index always get the same text.
+    Markov {
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+
+    /// Markov chain prose derived from a stable hash of the model, prompt
+    /// and `seed`: identical requests get identical completions across
+    /// runs and machines, for snapshot tests.
+    Deterministic {
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// What happens to a request no stub matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchedRequests {
+    /// The request is answered with generated content, as if no stubs
+    /// were configured.
+    #[default]
+    Generate,
+
+    /// The request is answered `404` with an OpenAI-style error.
+    NotFound,
+
+    /// The request is answered `404` and recorded as unexpected (see
+    /// [`RecordedRequest::unexpected`](crate::state::RecordedRequest::unexpected)).
+    /// The server handle fails the test when it is dropped with unexpected
+    /// requests recorded or expectations unmet.
+    Fail,
+}
+
+/// Whether multiple choices of one response may be identical.
+///
+/// Real APIs sometimes return duplicate choices (especially at low
+/// temperature), so clients that deduplicate need both behaviors testable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateChoices {
+    /// Choices are returned as generated, duplicates included.
+    #[default]
+    Allow,
+
+    /// Choices whose text repeats an earlier one are generated again, so
+    /// every choice has distinct text unless `max_tokens` or `stop` leave
+    /// no room for it.
+    Forbid,
+}
+
+/// Settings for streamed responses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamingConfig {
+    /// Delay between consecutive chunks.
+    #[serde(default, with = "crate::config::duration")]
+    pub chunk_delay: Duration,
+
+    /// Emit `: keep-alive` SSE comment lines whenever the gap between
+    /// chunks exceeds this interval, as the real API does under load.
+    #[serde(default, with = "crate::config::duration::option")]
+    pub keep_alive: Option<Duration>,
+
+    /// Maximum number of streams served at once. Further streaming
+    /// requests are rejected with `429`. Unlimited when `None`.
+    #[serde(default)]
+    pub max_concurrent_streams: Option<usize>,
+
+    /// Interleave chunks of concurrent streams round-robin instead of
+    /// leaving the order to the runtime.
+    #[serde(default)]
+    pub fair_scheduling: bool,
+
+    /// How much text each chunk carries.
+    #[serde(default)]
+    pub granularity: ChunkGranularity,
+}
+
+/// How much text each streamed chunk carries.
+///
+/// Real backends differ, and client buffering or markdown rendering bugs
+/// often show up only at particular granularities. In scenario files it is
+/// written `token`, `word`, `sentence` or `{ tokens: 3 }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "GranularityRepr", into = "GranularityRepr")]
+pub enum ChunkGranularity {
+    /// One token per chunk, as the real API usually streams.
+    #[default]
+    Token,
+
+    /// One word, with its leading whitespace, per chunk.
+    Word,
+
+    /// One sentence per chunk, ending after `.`, `!`, `?` or a newline.
+    Sentence,
+
+    /// A fixed number of tokens per chunk.
+    Tokens(usize),
+}
+
+/// Wire form of [`ChunkGranularity`], the same in YAML, TOML and JSON
+/// (serde_yaml would otherwise expect a `!tokens` tag).
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GranularityRepr {
+    Named(NamedGranularity),
+    Tokens { tokens: usize },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NamedGranularity {
+    Token,
+    Word,
+    Sentence,
+}
+
+impl From<GranularityRepr> for ChunkGranularity {
+    fn from(repr: GranularityRepr) -> Self {
+        match repr {
+            GranularityRepr::Named(NamedGranularity::Token) => ChunkGranularity::Token,
+            GranularityRepr::Named(NamedGranularity::Word) => ChunkGranularity::Word,
+            GranularityRepr::Named(NamedGranularity::Sentence) => ChunkGranularity::Sentence,
+            GranularityRepr::Tokens { tokens } => ChunkGranularity::Tokens(tokens),
+        }
+    }
+}
+
+impl From<ChunkGranularity> for GranularityRepr {
+    fn from(granularity: ChunkGranularity) -> Self {
+        match granularity {
+            ChunkGranularity::Token => GranularityRepr::Named(NamedGranularity::Token),
+            ChunkGranularity::Word => GranularityRepr::Named(NamedGranularity::Word),
+            ChunkGranularity::Sentence => GranularityRepr::Named(NamedGranularity::Sentence),
+            ChunkGranularity::Tokens(tokens) => GranularityRepr::Tokens { tokens },
+        }
+    }
+}
+
+/// Settings for one endpoint. Unset fields fall back to the global
+/// settings.
+///
+/// They can also be changed while the server is running, see
+/// [`MockServerHandle::set_route`](crate::server::MockServerHandle::set_route).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Replaces the global `latency` for this endpoint.
+    #[serde(default)]
+    pub latency: Option<Latency>,
+
+    /// Replaces the global `faults.error_rate` for this endpoint.
+    #[serde(default)]
+    pub error_rate: Option<f64>,
+
+    /// Response served to every request to this endpoint that passes
+    /// authentication, instead of the emulated one.
+    #[serde(default)]
+    pub respond: Option<CannedResponse>,
+}
+
+impl RouteConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the latency of the endpoint.
+    pub fn latency(mut self, latency: impl Into<Latency>) -> Self {
+        self.latency = Some(latency.into());
+        self
+    }
+
+    /// Sets the fraction of requests that fail with a `500` server error.
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = Some(rate);
+        self
+    }
+
+    /// Serves `response` instead of the emulated response.
+    pub fn respond(mut self, response: CannedResponse) -> Self {
+        self.respond = Some(response);
+        self
+    }
+
+    /// These settings, with unset fields taken from `base`.
+    pub fn layered_over(&self, base: &RouteConfig) -> RouteConfig {
+        RouteConfig {
+            latency: self.latency.clone().or_else(|| base.latency.clone()),
+            error_rate: self.error_rate.or(base.error_rate),
+            respond: self.respond.clone().or_else(|| base.respond.clone()),
+        }
+    }
+}
+
+/// API key authentication settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Keys accepted in the `Authorization: Bearer <key>` header. Requests
+    /// are not authenticated when empty.
+    #[serde(default)]
+    pub api_keys: HashSet<String>,
+
+    /// Usage tier of each API key, whose limits are advertised in the
+    /// `x-ratelimit-*` headers of responses to requests with the key.
+    #[serde(default)]
+    pub tiers: HashMap<String, UsageTier>,
+
+    /// Usage tier of keys not listed in `tiers`. No limits are advertised
+    /// for them when `None`.
+    #[serde(default)]
+    pub default_tier: Option<UsageTier>,
+
+    /// Behavior of each API key. Keys with a profile are accepted like
+    /// those in `api_keys`.
+    #[serde(default)]
+    pub profiles: HashMap<String, KeyProfile>,
+}
+
+impl AuthConfig {
+    /// Whether requests must carry one of `api_keys` or a key with a
+    /// profile.
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || !self.profiles.is_empty()
+    }
+
+    /// Whether `key` is accepted.
+    pub fn accepts(&self, key: &str) -> bool {
+        self.api_keys.contains(key) || self.profiles.contains_key(key)
+    }
+
+    /// The profile of `key`, if it has one.
+    pub fn profile(&self, key: &str) -> Option<&KeyProfile> {
+        self.profiles.get(key)
+    }
+}
+
+/// Fault injection settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultConfig {
+    /// Failure injected partway through streamed (`stream: true`) responses.
+    #[serde(default)]
+    pub stream: Option<StreamFault>,
+
+    /// Fraction of requests (between `0.0` and `1.0`) that fail with a
+    /// `500` server error.
+    #[serde(default)]
+    pub error_rate: f64,
+
+    /// Fractions of requests failing with a `500` server error per request
+    /// path, replacing `error_rate`. Paths are written as routed, e.g.
+    /// `/v1/chat/completions` or `/v1/models/{model}`, and may name
+    /// endpoints served from fixtures.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub error_rates: BTreeMap<String, f64>,
+
+    /// Requests rejected with `429 rate_limit_exceeded`.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitFault>,
+
+    /// Requests rejected with `503 engine_overloaded`.
+    #[serde(default)]
+    pub overloaded: Option<OverloadFault>,
+
+    /// Failure injected into every response.
+    #[serde(default)]
+    pub response: Option<ResponseFault>,
+
+    /// A random mix of failures injected into every endpoint, on top of
+    /// the faults above.
+    #[serde(default)]
+    pub chaos: Option<ChaosConfig>,
+}
+
+impl FaultConfig {
+    /// The fraction of requests to `path` that fail.
+    pub fn error_rate_for(&self, path: &str) -> f64 {
+        self.error_rates.get(path).copied().unwrap_or(self.error_rate)
+    }
+}
+
+impl MockConfig {
+    /// Sets the delay between consecutive streamed chunks.
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.streaming.chunk_delay = delay;
+        self
+    }
+
+    /// Sets how much text each streamed chunk carries.
+    pub fn with_chunk_granularity(mut self, granularity: ChunkGranularity) -> Self {
+        self.streaming.granularity = granularity;
+        self
+    }
+
+    /// Answers cross-origin requests as configured by `cors`.
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Sets which responses are compressed.
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Rejects request bodies larger than `bytes`.
+    pub fn with_max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    /// The largest request body accepted, in bytes.
+    pub fn body_limit(&self) -> usize {
+        self.max_body_size.unwrap_or(DEFAULT_MAX_BODY_SIZE)
+    }
+
+    /// Mounts every route under `base_path`, e.g. `/openai`.
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        self.base_path = Some(base_path.to_string());
+        self
+    }
+
+    /// The prefix routes are mounted under, with a leading and without a
+    /// trailing slash; empty when they are mounted at the root.
+    pub fn route_prefix(&self) -> String {
+        match self.base_path.as_deref().map(|path| path.trim().trim_matches('/')) {
+            Some(path) if !path.is_empty() => format!("/{}", path),
+            _ => String::new(),
+        }
+    }
+
+    /// Serves every completion with the response `high_throughput`
+    /// describes, precomputed once.
+    pub fn with_high_throughput(mut self, high_throughput: HighThroughputConfig) -> Self {
+        self.high_throughput = Some(high_throughput);
+        self
+    }
+
+    /// Registers the feature access of an organization.
+    pub fn with_organization(mut self, id: &str, organization: OrganizationConfig) -> Self {
+        self.organizations.insert(id.to_string(), organization);
+        self
+    }
+
+    /// Sets whether the `n` choices of one request may be identical.
+    pub fn with_duplicate_choices(mut self, duplicates: DuplicateChoices) -> Self {
+        self.generation.duplicate_choices = duplicates;
+        self
+    }
+
+    /// Emits `: keep-alive` comments during inter-chunk gaps longer than
+    /// `interval`.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.streaming.keep_alive = Some(interval);
+        self
+    }
+
+    /// Limits the number of concurrently served streams.
+    pub fn with_max_concurrent_streams(mut self, max: usize) -> Self {
+        self.streaming.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Accepts `key` as an API key. Once a key is added, requests without
+    /// a valid key are rejected with `401`.
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.auth.api_keys.insert(key.to_string());
+        self
+    }
+
+    /// Puts API key `key` in usage `tier`.
+    pub fn with_key_tier(mut self, key: &str, tier: UsageTier) -> Self {
+        self.auth.tiers.insert(key.to_string(), tier);
+        self
+    }
+
+    /// Accepts API key `key`, serving its requests as set by `profile`.
+    pub fn with_key_profile(mut self, key: &str, profile: KeyProfile) -> Self {
+        self.auth.profiles.insert(key.to_string(), profile);
+        self
+    }
+
+    /// Serves only `endpoints`; the others answer `404`.
+    pub fn with_endpoints(mut self, endpoints: impl IntoIterator<Item = Endpoint>) -> Self {
+        self.endpoints = Some(endpoints.into_iter().collect());
+        self
+    }
+
+    /// Sets how the completion text is produced.
+    pub fn with_generation_strategy(mut self, strategy: GenerationStrategy) -> Self {
+        self.generation.strategy = strategy;
+        self
+    }
+
+    /// Completes every completion request with `text` and answers every
+    /// chat completion request with an assistant message of `text`, for
+    /// tests that only exercise plumbing.
+    pub fn with_fixed_response(mut self, text: &str) -> Self {
+        self.generation.strategy = GenerationStrategy::Fixed {
+            text: text.to_string(),
+        };
+        self.generation.chat_reply = Some(ChatReply::text(text));
+        self
+    }
+
+    /// Answers chat completion requests no fixture matches with `reply`.
+    pub fn with_chat_reply(mut self, reply: ChatReply) -> Self {
+        self.generation.chat_reply = Some(reply);
+        self
+    }
+
+    /// Produces the completion text with `generator` instead of the
+    /// generation strategy.
+    pub fn with_text_generator(mut self, generator: impl TextGenerator + 'static) -> Self {
+        self.generation.generator = Some(SharedGenerator::new(generator));
+        self
+    }
+
+    /// Starts mapping prompts containing `needle` to a fixed completion,
+    /// e.g. `config.when_prompt_contains("refund").respond_with("...")`.
+    /// Mappings are tried in the order added.
+    pub fn when_prompt_contains(self, needle: &str) -> WhenPromptContains<MockConfig> {
+        WhenPromptContains {
+            parent: self,
+            prompt_contains: needle.to_string(),
+        }
+    }
+
+    /// Appends a scenario rule; rules are tried in the order added.
+    pub fn with_rule(mut self, rule: ScenarioRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Serves matching requests from `fixtures`.
+    pub fn with_fixtures(mut self, fixtures: ResponseFixtures) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// Sets the lifecycle callbacks.
+    pub fn with_hooks(mut self, hooks: LifecycleHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Sets the closures producing the responses of whole endpoints.
+    pub fn with_responders(mut self, responders: Responders) -> Self {
+        self.responders = responders;
+        self
+    }
+
+    /// Sets what happens to requests no stub matches.
+    pub fn with_unmatched(mut self, behavior: UnmatchedRequests) -> Self {
+        self.unmatched = behavior;
+        self
+    }
+
+    /// Mirrors every incoming request to `sink`.
+    pub fn with_mirror(mut self, sink: MirrorSink) -> Self {
+        self.mirror = Some(sink);
+        self
+    }
+
+    /// Delays every response by `latency`, a fixed [`Duration`] or a
+    /// [`Latency`] distribution.
+    pub fn with_latency(mut self, latency: impl Into<Latency>) -> Self {
+        self.latency = latency.into();
+        self
+    }
+
+    /// Delays responses of `endpoint` by `latency` instead of the global
+    /// latency.
+    pub fn with_route_latency(mut self, endpoint: Endpoint, latency: impl Into<Latency>) -> Self {
+        self.routes.entry(endpoint).or_default().latency = Some(latency.into());
+        self
+    }
+
+    /// Replaces the global settings for `endpoint`.
+    pub fn with_route(mut self, endpoint: Endpoint, route: RouteConfig) -> Self {
+        self.routes.insert(endpoint, route);
+        self
+    }
+
+    /// Overrides the global settings for `model`.
+    pub fn with_model(mut self, model: &str, overrides: ModelConfig) -> Self {
+        self.models.insert(model.to_string(), overrides);
+        self
+    }
+
+    /// Makes a fraction (between `0.0` and `1.0`) of requests fail with a
+    /// `500` server error.
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.faults.error_rate = rate;
+        self
+    }
+
+    /// Rejects requests with `429 rate_limit_exceeded` as set by `fault`.
+    pub fn with_rate_limit_fault(mut self, fault: RateLimitFault) -> Self {
+        self.faults.rate_limit = Some(fault);
+        self
+    }
+
+    /// Rejects requests with `503 engine_overloaded` as set by `fault`.
+    pub fn with_overload_fault(mut self, fault: OverloadFault) -> Self {
+        self.faults.overloaded = Some(fault);
+        self
+    }
+
+    /// Injects the random mix of failures set by `chaos` into every
+    /// endpoint.
+    pub fn with_chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.faults.chaos = Some(chaos);
+        self
+    }
+
+    /// Injects `fault` into every response.
+    pub fn with_response_fault(mut self, fault: ResponseFault) -> Self {
+        self.faults.response = Some(fault);
+        self
+    }
+
+    /// Sets the fraction of requests to `path` that fail with a `500`
+    /// server error.
+    pub fn with_path_error_rate(mut self, path: &str, rate: f64) -> Self {
+        self.faults.error_rates.insert(path.to_string(), rate);
+        self
+    }
+
+    /// The latency applied to requests to `endpoint`.
+    pub fn route_latency(&self, endpoint: Endpoint) -> &Latency {
+        self.routes
+            .get(&endpoint)
+            .and_then(|route| route.latency.as_ref())
+            .unwrap_or(&self.latency)
+    }
+
+    /// Whether `endpoint` is served.
+    pub fn is_endpoint_enabled(&self, endpoint: Endpoint) -> bool {
+        self.endpoints
+            .as_ref()
+            .is_none_or(|endpoints| endpoints.contains(&endpoint))
+    }
+
+    /// Sets the fault injected into streamed responses.
+    pub fn with_stream_fault(mut self, fault: StreamFault) -> Self {
+        self.faults.stream = Some(fault);
+        self
+    }
+}
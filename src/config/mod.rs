@@ -0,0 +1,27 @@
+pub mod chat_reply;
+pub mod compression;
+pub mod cors;
+pub mod duration;
+pub mod endpoint;
+pub mod high_throughput;
+pub mod key_profile;
+pub mod latency;
+pub mod mock_config;
+pub mod model_config;
+pub mod organization;
+pub mod tier;
+pub use chat_reply::{ChatReply, ToolCallReply};
+pub use compression::CompressionConfig;
+pub use cors::CorsConfig;
+pub use endpoint::Endpoint;
+pub use high_throughput::HighThroughputConfig;
+pub use key_profile::KeyProfile;
+pub use latency::{Latency, LatencyDistribution};
+pub use mock_config::{
+    MockConfig, AuthConfig, ChunkGranularity, DuplicateChoices, FaultConfig, GenerationConfig,
+    GenerationStrategy, PromptResponse, RouteConfig, StreamingConfig, UnmatchedRequests,
+    WhenPromptContains, DEFAULT_MAX_BODY_SIZE,
+};
+pub use model_config::ModelConfig;
+pub use organization::OrganizationConfig;
+pub use tier::{TierLimits, UsageTier};
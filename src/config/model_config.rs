@@ -0,0 +1,111 @@
+//! Per-model overrides of the global settings.
+//!
+//! Applications that mix models see very different behavior from each;
+//! overrides let one configuration give, say, `gpt-4o` a higher latency and
+//! `o1` an occasional failure, or add a model the mock does not know:
+//!
+//! ```toml
+//! [models."gpt-4o"]
+//! latency = "300ms"
+//!
+//! [models."o1"]
+//! error_rate = 0.05
+//!
+//! [models."ft:gpt-4o-mini:acme::abc123"]
+//! owned_by = "acme"
+//! endpoints = ["completions"]
+//! ```
+
+use crate::config::{Endpoint, GenerationStrategy, Latency};
+use crate::utils::token_counting::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Settings for one model. Unset fields fall back to the built-in
+/// description of the model (see
+/// [`ModelRegistry`](crate::state::ModelRegistry)) and to the global
+/// settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelConfig {
+    /// Organization reported as the model's owner by `/v1/models`.
+    #[serde(default)]
+    pub owned_by: Option<String>,
+
+    /// Maximum number of prompt plus completion tokens.
+    #[serde(default)]
+    pub context_window: Option<u32>,
+
+    /// Tokenizer encoding.
+    #[serde(default)]
+    pub encoding: Option<Encoding>,
+
+    /// Endpoints the model may be used with.
+    #[serde(default)]
+    pub endpoints: Option<BTreeSet<Endpoint>>,
+
+    /// Replaces the global `generation.strategy` for this model.
+    #[serde(default)]
+    pub generation: Option<GenerationStrategy>,
+
+    /// Replaces the global and per-endpoint `latency` for this model.
+    #[serde(default)]
+    pub latency: Option<Latency>,
+
+    /// Replaces the global `faults.error_rate` for this model.
+    #[serde(default)]
+    pub error_rate: Option<f64>,
+
+    /// Simulated price in USD per 1000 tokens, charged against the budget
+    /// of the caller's API key.
+    #[serde(default)]
+    pub price_per_1k_tokens: Option<f64>,
+}
+
+impl ModelConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the latency of this model.
+    pub fn latency(mut self, latency: impl Into<Latency>) -> Self {
+        self.latency = Some(latency.into());
+        self
+    }
+
+    /// Sets the maximum number of prompt plus completion tokens of this
+    /// model.
+    pub fn context_window(mut self, tokens: u32) -> Self {
+        self.context_window = Some(tokens);
+        self
+    }
+
+    /// Sets the tokenizer encoding of this model.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Sets the endpoints the model may be used with.
+    pub fn endpoints(mut self, endpoints: impl IntoIterator<Item = Endpoint>) -> Self {
+        self.endpoints = Some(endpoints.into_iter().collect());
+        self
+    }
+
+    /// Sets how this model's completion text is produced.
+    pub fn generation(mut self, strategy: GenerationStrategy) -> Self {
+        self.generation = Some(strategy);
+        self
+    }
+
+    /// Sets the fraction of this model's requests that fail.
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = Some(rate);
+        self
+    }
+
+    /// Sets the simulated price in USD per 1000 tokens of this model.
+    pub fn price_per_1k_tokens(mut self, price: f64) -> Self {
+        self.price_per_1k_tokens = Some(price);
+        self
+    }
+}
@@ -0,0 +1,52 @@
+//! Simulated organization-level feature access.
+//!
+//! Real OpenAI organizations differ in which models and beta endpoints
+//! they can use. These settings let a test pretend to be an organization
+//! without access to, say, a new model or the Responses API, so feature
+//! detection and fallback paths can be exercised.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Features an organization is denied. The organization is selected by
+/// the `OpenAI-Organization` request header.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrganizationConfig {
+    /// Model ids the organization may not use. Requests for them fail with
+    /// `404 model_not_found`.
+    #[serde(default)]
+    pub denied_models: HashSet<String>,
+
+    /// Endpoint paths (e.g. `/v1/responses`) the organization may not
+    /// call. Requests to them fail with a `403` permission error.
+    #[serde(default)]
+    pub denied_endpoints: HashSet<String>,
+}
+
+impl OrganizationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies access to a model.
+    pub fn deny_model(mut self, model: &str) -> Self {
+        self.denied_models.insert(model.to_string());
+        self
+    }
+
+    /// Denies access to an endpoint path.
+    pub fn deny_endpoint(mut self, path: &str) -> Self {
+        self.denied_endpoints.insert(path.to_string());
+        self
+    }
+
+    /// Whether the organization may use `model`.
+    pub fn allows_model(&self, model: &str) -> bool {
+        !self.denied_models.contains(model)
+    }
+
+    /// Whether the organization may call the endpoint at `path`.
+    pub fn allows_endpoint(&self, path: &str) -> bool {
+        !self.denied_endpoints.contains(path)
+    }
+}
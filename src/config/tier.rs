@@ -0,0 +1,77 @@
+//! Simulated OpenAI usage tiers.
+//!
+//! The real API assigns every organization a usage tier that decides its
+//! rate limits, and advertises those limits in `x-ratelimit-*` response
+//! headers. Assigning tiers to API keys lets a test check that a client
+//! discovers its limits from the headers and adapts when a key is upgraded
+//! mid-run (see
+//! [`MockServerHandle::set_key_tier`](crate::server::MockServerHandle::set_key_tier)).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A usage tier, written `free` or `tier-1` to `tier-5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum UsageTier {
+    #[serde(rename = "free")]
+    Free,
+    #[serde(rename = "tier-1")]
+    Tier1,
+    #[serde(rename = "tier-2")]
+    Tier2,
+    #[serde(rename = "tier-3")]
+    Tier3,
+    #[serde(rename = "tier-4")]
+    Tier4,
+    #[serde(rename = "tier-5")]
+    Tier5,
+}
+
+/// The limits of a [`UsageTier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TierLimits {
+    /// Requests allowed per minute.
+    pub requests_per_minute: u32,
+
+    /// Tokens allowed per minute.
+    pub tokens_per_minute: u32,
+
+    /// Requests allowed in flight at once.
+    pub max_concurrency: u32,
+}
+
+impl UsageTier {
+    /// The limits of the tier, modeled on those of `gpt-4o-mini`.
+    pub fn limits(&self) -> TierLimits {
+        let (requests_per_minute, tokens_per_minute, max_concurrency) = match self {
+            UsageTier::Free => (3, 40_000, 1),
+            UsageTier::Tier1 => (500, 200_000, 5),
+            UsageTier::Tier2 => (5_000, 2_000_000, 10),
+            UsageTier::Tier3 => (5_000, 4_000_000, 25),
+            UsageTier::Tier4 => (10_000, 10_000_000, 50),
+            UsageTier::Tier5 => (30_000, 150_000_000, 100),
+        };
+        TierLimits {
+            requests_per_minute,
+            tokens_per_minute,
+            max_concurrency,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageTier::Free => "free",
+            UsageTier::Tier1 => "tier-1",
+            UsageTier::Tier2 => "tier-2",
+            UsageTier::Tier3 => "tier-3",
+            UsageTier::Tier4 => "tier-4",
+            UsageTier::Tier5 => "tier-5",
+        }
+    }
+}
+
+impl fmt::Display for UsageTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
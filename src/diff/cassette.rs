@@ -0,0 +1,105 @@
+//! Recorded traffic replayed by [`replay`](fn@crate::diff::replay).
+//!
+//! A cassette is a list of request/response pairs captured from the real
+//! API, stored either as a JSON array or as JSON Lines (one interaction per
+//! line):
+//!
+//! ```json
+//! {
+//!   "request": {"method": "POST", "path": "/v1/completions", "body": {"model": "gpt-3.5-turbo-instruct", "prompt": "Hi"}},
+//!   "response": {"status": 200, "body": {"id": "cmpl-...", "object": "text_completion", "choices": []}}
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A recorded request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    /// HTTP method, e.g. `POST`.
+    pub method: String,
+
+    /// Request path, e.g. `/v1/completions`.
+    pub path: String,
+
+    /// Request headers sent along when replaying, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// The request body; `null` for requests without one.
+    #[serde(default)]
+    pub body: Value,
+}
+
+/// The response the real API returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    /// HTTP status code.
+    pub status: u16,
+
+    /// The response body. Bodies that were not JSON (e.g. event streams)
+    /// are stored as strings.
+    #[serde(default)]
+    pub body: Value,
+}
+
+/// One recorded request and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+/// Recorded traffic, in the order it was captured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Parses a cassette stored as a JSON array or as JSON Lines.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+        if text.trim_start().starts_with('[') {
+            return serde_json::from_str(text).map_err(invalid);
+        }
+
+        let interactions = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(invalid))
+            .collect::<io::Result<_>>()?;
+        Ok(Self { interactions })
+    }
+
+    /// Loads a cassette from `path` (see [`Cassette::parse`]).
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_array_and_lines() {
+        let line = r#"{"request": {"method": "GET", "path": "/v1/models"}, "response": {"status": 200, "body": {"object": "list"}}}"#;
+
+        let from_lines = Cassette::parse(&format!("{}\n\n{}\n", line, line)).unwrap();
+        assert_eq!(from_lines.interactions.len(), 2);
+        assert_eq!(from_lines.interactions[0].request.body, Value::Null);
+
+        let from_array = Cassette::parse(&format!("[{}, {}]", line, line)).unwrap();
+        assert_eq!(from_array, from_lines);
+
+        let err = Cassette::parse("{not json").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod cassette;
+pub mod replay;
+pub mod structure;
+pub use cassette::{Cassette, CassetteRequest, CassetteResponse, Interaction};
+pub use replay::{replay, DiffReport, InteractionDiff};
+pub use structure::{structural_diff, Difference};
@@ -0,0 +1,150 @@
+//! Replaying a [`Cassette`] through the mock.
+
+use crate::config::MockConfig;
+use crate::diff::{structural_diff, Cassette, CassetteRequest, Difference, Interaction};
+use crate::service::dispatch;
+use crate::state::MockState;
+use bytes::Bytes;
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use http::{Method, Request};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// Differences found for one recorded interaction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InteractionDiff {
+    /// Position of the interaction in the cassette.
+    pub index: usize,
+
+    pub method: String,
+    pub path: String,
+
+    /// Empty when the mock's response matches the recorded one.
+    pub differences: Vec<Difference>,
+}
+
+/// Outcome of [`replay`]: one entry per recorded interaction.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DiffReport {
+    pub interactions: Vec<InteractionDiff>,
+}
+
+impl DiffReport {
+    /// Whether every response matched its recording.
+    pub fn is_clean(&self) -> bool {
+        self.interactions.iter().all(|i| i.differences.is_empty())
+    }
+
+    /// The interactions with at least one difference.
+    pub fn mismatches(&self) -> impl Iterator<Item = &InteractionDiff> {
+        self.interactions
+            .iter()
+            .filter(|i| !i.differences.is_empty())
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for interaction in self.mismatches() {
+            writeln!(
+                f,
+                "#{} {} {}",
+                interaction.index, interaction.method, interaction.path
+            )?;
+            for difference in &interaction.differences {
+                writeln!(f, "  {}", difference)?;
+            }
+        }
+        write!(
+            f,
+            "{} of {} interactions differ",
+            self.mismatches().count(),
+            self.interactions.len()
+        )
+    }
+}
+
+/// Replays every request of `cassette` through a mock configured with
+/// `config` and compares each response with the recorded one using
+/// [`structural_diff`].
+///
+/// Requests are served in-process, in cassette order, against a single
+/// mock instance, so stateful configuration (sequences, scenarios) sees
+/// them as it would during a test run.
+pub async fn replay(cassette: &Cassette, config: MockConfig) -> DiffReport {
+    let state = Arc::new(MockState::new(config));
+
+    let mut report = DiffReport::default();
+    for (index, Interaction { request, response }) in cassette.interactions.iter().enumerate() {
+        let mut interaction = InteractionDiff {
+            index,
+            method: request.method.clone(),
+            path: request.path.clone(),
+            differences: Vec::new(),
+        };
+        let req = match to_http_request(request, &state) {
+            Ok(req) => req,
+            Err(reason) => {
+                interaction
+                    .differences
+                    .push(Difference::InvalidRequest { reason });
+                report.interactions.push(interaction);
+                continue;
+            }
+        };
+
+        let resp = dispatch(state.clone(), req).await;
+        let status = resp.status().as_u16();
+        let body = resp.into_body().to_bytes().await.unwrap_or_default();
+        let body = serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()));
+
+        if status != response.status {
+            interaction.differences.push(Difference::Status {
+                recorded: response.status,
+                mock: status,
+            });
+        }
+        interaction
+            .differences
+            .extend(structural_diff(&response.body, &body));
+        report.interactions.push(interaction);
+    }
+    report
+}
+
+/// The HTTP request replaying `request` against the mock of `state`, or
+/// why the recording is not a valid request.
+fn to_http_request(request: &CassetteRequest, state: &MockState) -> Result<Request<Bytes>, String> {
+    let method = Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| format!("invalid method '{}'", request.method))?;
+    // Cassettes record the real API's paths, without the mock's base path.
+    let path = format!("{}{}", state.config.route_prefix(), request.path);
+    let uri = path
+        .parse()
+        .map_err(|_| format!("invalid path '{}'", request.path))?;
+
+    let body = if request.body.is_null() {
+        Bytes::new()
+    } else {
+        Bytes::from(request.body.to_string())
+    };
+    let mut req = Request::new(body);
+    *req.method_mut() = method;
+    *req.uri_mut() = uri;
+    for (name, value) in &request.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            req.headers_mut().insert(name, value);
+        }
+    }
+    if !request.body.is_null() {
+        req.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    Ok(req)
+}
@@ -0,0 +1,185 @@
+//! Structural comparison of JSON bodies.
+//!
+//! Generated text, ids and timestamps never match between the mock and the
+//! real API, so bodies are compared by shape only: which fields exist and
+//! what JSON type they hold.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// One way in which the mock's body differs from the recorded one.
+///
+/// Paths are written like `choices[].logprobs`, where `[]` stands for every
+/// element of an array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Difference {
+    /// The status codes differ.
+    Status { recorded: u16, mock: u16 },
+
+    /// The recorded body has a field the mock's lacks.
+    MissingField { path: String },
+
+    /// The mock's body has a field the recorded one lacks.
+    ExtraField { path: String },
+
+    /// A field holds a different JSON type.
+    TypeMismatch {
+        path: String,
+        recorded: &'static str,
+        mock: &'static str,
+    },
+
+    /// The recorded request cannot be replayed, e.g. for an invalid method
+    /// or path; nothing was compared.
+    InvalidRequest { reason: String },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Status { recorded, mock } => {
+                write!(f, "status: recorded {}, mock {}", recorded, mock)
+            }
+            Difference::MissingField { path } => write!(f, "missing field: {}", path),
+            Difference::ExtraField { path } => write!(f, "extra field: {}", path),
+            Difference::TypeMismatch {
+                path,
+                recorded,
+                mock,
+            } => write!(
+                f,
+                "type mismatch at {}: recorded {}, mock {}",
+                path, recorded, mock
+            ),
+            Difference::InvalidRequest { reason } => write!(f, "invalid request: {}", reason),
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Records `difference` once; array elements repeat the same paths.
+fn push(out: &mut Vec<Difference>, difference: Difference) {
+    if !out.contains(&difference) {
+        out.push(difference);
+    }
+}
+
+fn compare(path: &str, recorded: &Value, mock: &Value, out: &mut Vec<Difference>) {
+    match (recorded, mock) {
+        (Value::Object(recorded), Value::Object(mock)) => {
+            for (key, value) in recorded {
+                let path = child_path(path, key);
+                match mock.get(key) {
+                    Some(mock_value) => compare(&path, value, mock_value, out),
+                    None => push(out, Difference::MissingField { path }),
+                }
+            }
+            for key in mock.keys().filter(|key| !recorded.contains_key(*key)) {
+                push(
+                    out,
+                    Difference::ExtraField {
+                        path: child_path(path, key),
+                    },
+                );
+            }
+        }
+        (Value::Array(recorded), Value::Array(mock)) => {
+            let path = format!("{}[]", path);
+            for (recorded, mock) in recorded.iter().zip(mock) {
+                compare(&path, recorded, mock, out);
+            }
+        }
+        (recorded, mock) if type_name(recorded) != type_name(mock) => push(
+            out,
+            Difference::TypeMismatch {
+                path: if path.is_empty() {
+                    "$".to_string()
+                } else {
+                    path.to_string()
+                },
+                recorded: type_name(recorded),
+                mock: type_name(mock),
+            },
+        ),
+        _ => {}
+    }
+}
+
+/// Lists the structural differences between a recorded body and the
+/// mock's body for the same request. Field values are ignored; only
+/// missing and extra fields and differing JSON types are reported.
+pub fn structural_diff(recorded: &Value, mock: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    compare("", recorded, mock, &mut differences);
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_values_are_ignored() {
+        let recorded = json!({"id": "cmpl-1", "choices": [{"text": "a", "index": 0}]});
+        let mock = json!({"id": "cmpl-2", "choices": [{"text": "b", "index": 0}, {"text": "c", "index": 1}]});
+        assert!(structural_diff(&recorded, &mock).is_empty());
+    }
+
+    #[test]
+    fn test_reports_shape_differences() {
+        let recorded = json!({
+            "choices": [
+                {"text": "a", "logprobs": null, "finish_reason": "stop"},
+                {"text": "b", "logprobs": null, "finish_reason": "stop"},
+            ],
+            "system_fingerprint": "fp_1",
+        });
+        let mock = json!({
+            "choices": [
+                {"text": "a", "logprobs": {}, "extra": 1},
+                {"text": "b", "logprobs": {}, "extra": 1},
+            ],
+        });
+
+        assert_eq!(
+            structural_diff(&recorded, &mock),
+            vec![
+                Difference::MissingField {
+                    path: "choices[].finish_reason".to_string()
+                },
+                Difference::TypeMismatch {
+                    path: "choices[].logprobs".to_string(),
+                    recorded: "null",
+                    mock: "object",
+                },
+                Difference::ExtraField {
+                    path: "choices[].extra".to_string()
+                },
+                Difference::MissingField {
+                    path: "system_fingerprint".to_string()
+                },
+            ]
+        );
+    }
+}
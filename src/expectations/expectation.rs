@@ -0,0 +1,355 @@
+//! Expected calls and their verification against the request history.
+
+use crate::expectations::Operation;
+use crate::scenario::{Matchers, NormalizedRequest, RequestMatcher};
+use crate::state::RecordedRequest;
+use std::fmt;
+use std::sync::Mutex;
+
+/// How many calls an expectation allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Times {
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+}
+
+impl Times {
+    /// Whether `calls` calls meet the expectation.
+    pub fn allows(&self, calls: usize) -> bool {
+        match *self {
+            Times::Exactly(times) => calls == times,
+            Times::AtLeast(times) => calls >= times,
+            Times::AtMost(times) => calls <= times,
+        }
+    }
+}
+
+impl fmt::Display for Times {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (bound, times) = match *self {
+            Times::Exactly(times) => ("exactly", times),
+            Times::AtLeast(times) => ("at least", times),
+            Times::AtMost(times) => ("at most", times),
+        };
+        let plural = if times == 1 { "" } else { "s" };
+        write!(f, "{} {} call{}", bound, times, plural)
+    }
+}
+
+/// Calls to an [`Operation`] that a test expects the system under test to
+/// make: how many, and which conditions they meet. By default the
+/// operation must be called at least once.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    pub operation: Operation,
+    pub matchers: Matchers,
+    pub times: Times,
+}
+
+impl Expectation {
+    pub fn new(operation: impl Into<Operation>) -> Self {
+        Self {
+            operation: operation.into(),
+            matchers: Matchers::default(),
+            times: Times::AtLeast(1),
+        }
+    }
+
+    /// Only counts calls `matcher` accepts, e.g. a
+    /// [`RequestMatch`](crate::scenario::RequestMatch). Every matcher
+    /// added must accept a call.
+    pub fn matching(mut self, matcher: impl RequestMatcher + 'static) -> Self {
+        self.matchers.push(matcher);
+        self
+    }
+
+    /// Whether `request` is a call the expectation counts.
+    pub fn matches(&self, request: &NormalizedRequest) -> bool {
+        self.operation.matches(request.method(), request.path()) && self.matchers.matches(request)
+    }
+
+    /// The operation and conditions, e.g.
+    /// `POST /v1/chat/completions where model is gpt-4`.
+    pub fn describe(&self) -> String {
+        let conditions: Vec<String> = self.matchers.iter().map(|m| m.describe()).collect();
+        if conditions.is_empty() {
+            self.operation.to_string()
+        } else {
+            format!("{} where {}", self.operation, conditions.join(" and "))
+        }
+    }
+}
+
+impl From<Operation> for Expectation {
+    fn from(operation: Operation) -> Self {
+        Self::new(operation)
+    }
+}
+
+/// The expectations set on one mock instance, including those on the
+/// order of calls.
+#[derive(Debug, Default)]
+pub struct Expectations {
+    list: Mutex<Vec<Expectation>>,
+    sequences: Mutex<Vec<Vec<Expectation>>>,
+}
+
+impl Expectations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `expectation`, returning its index.
+    pub fn add(&self, expectation: Expectation) -> usize {
+        let mut list = self.list.lock().unwrap();
+        list.push(expectation);
+        list.len() - 1
+    }
+
+    /// Changes the expectation at `index`.
+    pub fn update(&self, index: usize, change: impl FnOnce(Expectation) -> Expectation) {
+        let mut list = self.list.lock().unwrap();
+        if let Some(expectation) = list.get_mut(index) {
+            *expectation = change(expectation.clone());
+        }
+    }
+
+    /// Expects calls matching `sequence` to have been made in that order.
+    /// Other calls may come before, between and after them; the call
+    /// counts of the expectations are not checked.
+    pub fn add_sequence(&self, sequence: Vec<Expectation>) {
+        self.sequences.lock().unwrap().push(sequence);
+    }
+
+    /// Every expectation, in the order added.
+    pub fn all(&self) -> Vec<Expectation> {
+        self.list.lock().unwrap().clone()
+    }
+
+    /// Forgets every expectation.
+    pub fn clear(&self) {
+        self.list.lock().unwrap().clear();
+        self.sequences.lock().unwrap().clear();
+    }
+
+    /// Checks every expectation against `requests`.
+    ///
+    /// # Returns
+    ///
+    /// One line per expectation whose call count is not met, e.g.
+    /// `expected exactly 2 calls to POST /v1/chat/completions, received 1`,
+    /// then one per sequence of calls not made in order.
+    pub fn verify(&self, requests: &[RecordedRequest]) -> Vec<String> {
+        let normalized: Vec<NormalizedRequest> =
+            requests.iter().map(RecordedRequest::normalized).collect();
+        let mut failures = self.verify_counts(&normalized);
+        let sequences = self.sequences.lock().unwrap().clone();
+        failures.extend(
+            sequences
+                .iter()
+                .filter_map(|sequence| verify_sequence(sequence, requests, &normalized)),
+        );
+        failures
+    }
+
+    fn verify_counts(&self, requests: &[NormalizedRequest]) -> Vec<String> {
+        self.all()
+            .iter()
+            .filter_map(|expectation| {
+                let calls = requests
+                    .iter()
+                    .filter(|request| expectation.matches(request))
+                    .count();
+                (!expectation.times.allows(calls)).then(|| {
+                    format!(
+                        "expected {} to {}, received {}",
+                        expectation.times,
+                        expectation.describe(),
+                        calls
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks that calls matching `sequence` appear in `requests` in order,
+/// each matched by the first call after the one matching the previous
+/// expectation.
+///
+/// # Returns
+///
+/// The failure, naming the first expectation not met in order and listing
+/// the calls the sequence is about, if the order is wrong.
+fn verify_sequence(
+    sequence: &[Expectation],
+    records: &[RecordedRequest],
+    requests: &[NormalizedRequest],
+) -> Option<String> {
+    let mut next = 0;
+    for (position, expectation) in sequence.iter().enumerate() {
+        match requests[next..].iter().position(|request| expectation.matches(request)) {
+            Some(offset) => next += offset + 1,
+            None => {
+                let order = sequence
+                    .iter()
+                    .map(Expectation::describe)
+                    .collect::<Vec<_>>()
+                    .join(", then ");
+                let mut failure = format!("expected calls in order: {}", order);
+                if position > 0 {
+                    failure.push_str(&format!(
+                        "; no call to {} after the call to {}",
+                        expectation.describe(),
+                        sequence[position - 1].describe()
+                    ));
+                } else {
+                    failure.push_str(&format!("; no call to {}", expectation.describe()));
+                }
+                let relevant: Vec<String> = records
+                    .iter()
+                    .zip(requests)
+                    .filter(|(_, request)| sequence.iter().any(|e| e.matches(request)))
+                    .map(|(record, _)| format!("#{} {} {}", record.id, record.method, record.path))
+                    .collect();
+                if !relevant.is_empty() {
+                    failure.push_str(&format!(" (received {})", relevant.join(", ")));
+                }
+                return Some(failure);
+            }
+        }
+    }
+    None
+}
+
+/// An expectation being set on a running server; see
+/// [`MockServerHandle::expect`](crate::server::MockServerHandle::expect).
+/// Each method changes the expectation in place.
+pub struct ExpectationBuilder<'a> {
+    expectations: &'a Expectations,
+    index: usize,
+}
+
+impl<'a> ExpectationBuilder<'a> {
+    #[cfg(any(feature = "actix-web", test))]
+    pub(crate) fn new(expectations: &'a Expectations, index: usize) -> Self {
+        Self {
+            expectations,
+            index,
+        }
+    }
+
+    fn update(self, change: impl FnOnce(Expectation) -> Expectation) -> Self {
+        self.expectations.update(self.index, change);
+        self
+    }
+
+    /// Only counts calls `matcher` accepts.
+    pub fn matching(self, matcher: impl RequestMatcher + 'static) -> Self {
+        self.update(|expectation| expectation.matching(matcher))
+    }
+
+    /// Expects exactly `times` calls.
+    pub fn times(self, times: usize) -> Self {
+        self.update(|expectation| Expectation {
+            times: Times::Exactly(times),
+            ..expectation
+        })
+    }
+
+    /// Expects `times` calls or more.
+    pub fn at_least(self, times: usize) -> Self {
+        self.update(|expectation| Expectation {
+            times: Times::AtLeast(times),
+            ..expectation
+        })
+    }
+
+    /// Expects `times` calls or fewer.
+    pub fn at_most(self, times: usize) -> Self {
+        self.update(|expectation| Expectation {
+            times: Times::AtMost(times),
+            ..expectation
+        })
+    }
+
+    /// Expects no call at all.
+    pub fn never(self) -> Self {
+        self.times(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::RequestMatch;
+    use crate::state::RequestHistory;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_verify_counts() {
+        let history = RequestHistory::new();
+        for model in ["gpt-4", "gpt-4", "gpt-4o"] {
+            let body = json!({"model": model, "messages": []});
+            history.record("POST", "/v1/chat/completions", BTreeMap::new(), body);
+        }
+        let requests = history.all();
+
+        let expectations = Expectations::new();
+        let gpt_4 = RequestMatch {
+            model: Some("gpt-4".to_string()),
+            ..Default::default()
+        };
+        let index = expectations.add(Expectation::new(Operation::ChatCompletions));
+        ExpectationBuilder::new(&expectations, index)
+            .matching(gpt_4)
+            .times(2);
+        assert!(expectations.verify(&requests).is_empty());
+
+        expectations.add(Expectation::new(Operation::Embeddings));
+        expectations.add(Expectation::new(Operation::ChatCompletions).matching(
+            |request: &NormalizedRequest| request.model() == Some("gpt-4o"),
+        ));
+        let index = expectations.add(Expectation::new(Operation::ChatCompletions));
+        ExpectationBuilder::new(&expectations, index).at_most(1);
+        assert_eq!(
+            expectations.verify(&requests),
+            vec![
+                "expected at least 1 call to POST /v1/embeddings, received 0",
+                "expected at most 1 call to POST /v1/chat/completions, received 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_order() {
+        let history = RequestHistory::new();
+        let chat = json!({"model": "gpt-4o", "messages": []});
+        history.record("POST", "/v1/moderations", BTreeMap::new(), json!({"input": "Hi"}));
+        history.record("POST", "/v1/chat/completions", BTreeMap::new(), chat.clone());
+        history.record("POST", "/v1/chat/completions", BTreeMap::new(), chat);
+
+        let expectations = Expectations::new();
+        expectations.add_sequence(vec![
+            Operation::Moderations.into(),
+            Operation::ChatCompletions.into(),
+        ]);
+        assert!(expectations.verify(&history.all()).is_empty());
+
+        expectations.add_sequence(vec![
+            Operation::ChatCompletions.into(),
+            Operation::Moderations.into(),
+        ]);
+        assert_eq!(
+            expectations.verify(&history.all()),
+            vec![
+                "expected calls in order: POST /v1/chat/completions, then POST /v1/moderations; \
+                 no call to POST /v1/moderations after the call to POST /v1/chat/completions \
+                 (received #0 POST /v1/moderations, #1 POST /v1/chat/completions, \
+                 #2 POST /v1/chat/completions)"
+            ]
+        );
+    }
+}
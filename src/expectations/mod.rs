@@ -0,0 +1,32 @@
+//! Expected calls, verified once the test has run.
+//!
+//! Where scenario rules decide how the mock answers, expectations check
+//! what the system under test sent: which operations it called, with which
+//! requests, how many times and in which order.
+//!
+//! ```no_run
+//! # #[cfg(feature = "actix-web")] {
+//! use openai_mock::expectations::Operation;
+//! use openai_mock::scenario::RequestMatch;
+//! use openai_mock::server::MockServer;
+//!
+//! let mock = MockServer::builder().start().unwrap();
+//! mock.expect(Operation::ChatCompletions)
+//!     .matching(RequestMatch {
+//!         model: Some("gpt-4o".to_string()),
+//!         ..Default::default()
+//!     })
+//!     .times(2);
+//!
+//! // ... run the code under test against `mock.addr()` ...
+//!
+//! mock.verify();
+//! # }
+//! ```
+
+pub mod expectation;
+pub mod operation;
+pub mod unmatched;
+pub use expectation::{Expectation, ExpectationBuilder, Expectations, Times};
+pub use operation::Operation;
+pub use unmatched::{closest_stub, unmatched_report, ClosestStub};
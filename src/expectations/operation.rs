@@ -0,0 +1,107 @@
+//! The OpenAI operations an expectation can be set on.
+
+use crate::config::Endpoint;
+use std::fmt;
+
+/// An OpenAI operation, identified by its method and path. Operations the
+/// mock serves from fixtures alone, such as chat completions, can be
+/// expected as well as those it emulates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `POST /v1/completions`.
+    Completions,
+
+    /// `POST /v1/chat/completions`.
+    ChatCompletions,
+
+    /// `POST /v1/embeddings`.
+    Embeddings,
+
+    /// `POST /v1/moderations`.
+    Moderations,
+
+    /// `GET /v1/models`.
+    ListModels,
+
+    /// `GET /v1/models/{model}`.
+    RetrieveModel,
+
+    /// Any request to this exact path, whatever its method.
+    Path(String),
+}
+
+impl Operation {
+    /// The HTTP method of the operation; `None` for [`Operation::Path`].
+    pub fn method(&self) -> Option<&'static str> {
+        match self {
+            Operation::Completions
+            | Operation::ChatCompletions
+            | Operation::Embeddings
+            | Operation::Moderations => Some("POST"),
+            Operation::ListModels | Operation::RetrieveModel => Some("GET"),
+            Operation::Path(_) => None,
+        }
+    }
+
+    /// Whether a `method` request to `path` invokes the operation.
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if self.method().is_some_and(|expected| expected != method) {
+            return false;
+        }
+        match self {
+            Operation::Completions => path == "/v1/completions",
+            Operation::ChatCompletions => path == "/v1/chat/completions",
+            Operation::Embeddings => path == "/v1/embeddings",
+            Operation::Moderations => path == "/v1/moderations",
+            Operation::ListModels => path == "/v1/models",
+            Operation::RetrieveModel => path
+                .strip_prefix("/v1/models/")
+                .is_some_and(|model| !model.is_empty()),
+            Operation::Path(expected) => path == expected,
+        }
+    }
+}
+
+impl From<Endpoint> for Operation {
+    fn from(endpoint: Endpoint) -> Self {
+        match endpoint {
+            Endpoint::Completions => Operation::Completions,
+            Endpoint::ListModels => Operation::ListModels,
+            Endpoint::RetrieveModel => Operation::RetrieveModel,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    /// Formats the operation as its method and path, e.g.
+    /// `POST /v1/chat/completions`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self {
+            Operation::Completions => "/v1/completions",
+            Operation::ChatCompletions => "/v1/chat/completions",
+            Operation::Embeddings => "/v1/embeddings",
+            Operation::Moderations => "/v1/moderations",
+            Operation::ListModels => "/v1/models",
+            Operation::RetrieveModel => "/v1/models/{model}",
+            Operation::Path(path) => return write!(f, "{}", path),
+        };
+        write!(f, "{} {}", self.method().unwrap_or_default(), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_matching() {
+        assert!(Operation::ChatCompletions.matches("POST", "/v1/chat/completions"));
+        assert!(!Operation::ChatCompletions.matches("GET", "/v1/chat/completions"));
+        assert!(Operation::RetrieveModel.matches("GET", "/v1/models/gpt-4"));
+        assert!(!Operation::RetrieveModel.matches("GET", "/v1/models"));
+        assert!(Operation::Path("/v1/files".to_string()).matches("DELETE", "/v1/files"));
+
+        assert_eq!(Operation::from(Endpoint::ListModels).to_string(), "GET /v1/models");
+        assert_eq!(Operation::Path("/v1/files".to_string()).to_string(), "/v1/files");
+    }
+}
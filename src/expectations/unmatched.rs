@@ -0,0 +1,136 @@
+//! Reports on requests no stub matched: which stub came closest, and
+//! which of its conditions the request failed.
+
+use crate::config::{Endpoint, MockConfig};
+use crate::scenario::{Mismatch, NormalizedRequest, RequestMatch, RequestMatcher};
+use serde_json::Value;
+
+/// The stub that came closest to matching a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosestStub {
+    /// The stub, e.g. `rule #0 (prompt contains "refund")`.
+    pub stub: String,
+
+    /// The conditions of the stub the request failed.
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// The stub of `config` whose conditions `request` misses the fewest of:
+/// a scenario rule or prompt response, which only apply to completions,
+/// or the fixtures of the request's endpoint. Earlier stubs win ties.
+/// `None` when no stub applies to the endpoint.
+pub fn closest_stub(config: &MockConfig, request: &NormalizedRequest) -> Option<ClosestStub> {
+    let mut candidates = Vec::new();
+    let completions = request.path() == Endpoint::Completions.path();
+
+    for (index, rule) in config.rules.iter().enumerate().filter(|_| completions) {
+        let mut mismatches = rule.when.mismatches(request);
+        for matcher in rule.matchers.iter().filter(|m| !m.matches(request)) {
+            mismatches.push(Mismatch {
+                field: "matcher".to_string(),
+                expected: matcher.describe(),
+                actual: "no match".to_string(),
+            });
+        }
+        candidates.push(ClosestStub {
+            stub: format!("rule #{} ({})", index, rule.when.describe()),
+            mismatches,
+        });
+    }
+
+    for response in config.generation.prompt_responses.iter().filter(|_| completions) {
+        let when = RequestMatch {
+            prompt_contains: Some(response.prompt_contains.clone()),
+            ..Default::default()
+        };
+        candidates.push(ClosestStub {
+            stub: format!("prompt response for {:?}", response.prompt_contains),
+            mismatches: when.mismatches(request),
+        });
+    }
+
+    if let Some(models) = config.fixtures.models(request.path()) {
+        let expected = Value::from(models).to_string();
+        candidates.push(ClosestStub {
+            stub: format!("fixtures of {}", request.path()),
+            mismatches: vec![Mismatch {
+                field: "model".to_string(),
+                expected: format!("one of {}", expected),
+                actual: request
+                    .model()
+                    .map_or_else(|| "missing".to_string(), |model| Value::from(model).to_string()),
+            }],
+        });
+    }
+
+    candidates
+        .into_iter()
+        .reduce(|closest, candidate| {
+            if candidate.mismatches.len() < closest.mismatches.len() {
+                candidate
+            } else {
+                closest
+            }
+        })
+}
+
+/// Describes why `request` matched no stub of `config`: the closest stub
+/// followed by one indented line per failed condition.
+pub fn unmatched_report(config: &MockConfig, request: &NormalizedRequest) -> String {
+    let Some(closest) = closest_stub(config, request) else {
+        return format!("no stub applies to {}", request.path());
+    };
+    let mut report = format!("closest stub: {}", closest.stub);
+    for mismatch in &closest.mismatches {
+        report.push_str(&format!("\n  - {}", mismatch));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::ResponseFixtures;
+    use serde_json::json;
+
+    #[test]
+    fn test_closest_stub() {
+        let rules = r#"
+rules:
+  - when: { model: gpt-4, prompt_contains: refund }
+    respond: { body: { id: refund } }
+  - when: { prompt_contains: weather }
+    respond: { body: { id: weather } }
+"#;
+        let config = MockConfig::from_yaml_str(rules).unwrap();
+        let request = NormalizedRequest::new(
+            "POST",
+            "/v1/completions",
+            json!({"model": "gpt-4o", "prompt": "Say hello"}),
+        );
+        assert_eq!(
+            unmatched_report(&config, &request),
+            "closest stub: rule #1 (prompt contains \"weather\")\n  - prompt: expected to contain \"weather\", got \"Say hello\""
+        );
+
+        let mut fixtures = ResponseFixtures::new();
+        fixtures.insert("embeddings", "text-embedding-3-small", "{}".to_string());
+        let config = MockConfig::default().with_fixtures(fixtures);
+        let request = NormalizedRequest::new(
+            "POST",
+            "/v1/embeddings",
+            json!({"model": "text-embedding-ada-002"}),
+        );
+        let closest = closest_stub(&config, &request).unwrap();
+        assert_eq!(closest.stub, "fixtures of /v1/embeddings");
+        assert_eq!(
+            closest.mismatches[0].to_string(),
+            r#"model: expected one of ["text-embedding-3-small"], got "text-embedding-ada-002""#
+        );
+
+        assert_eq!(
+            unmatched_report(&MockConfig::default(), &request),
+            "no stub applies to /v1/embeddings"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Typed query-parameter parsing shared by the list (`GET`) endpoints.
+//!
+//! OpenAI's list endpoints accept a common set of query parameters
+//! (`limit`, `after`, `order` and, for files, `purpose`). `ListQuery`
+//! parses and validates them in one place and rejects invalid values with
+//! the same `invalid_request_error` payloads the real API returns.
+//! `GET /v1/models` and fixture-served `GET /v1/files` listings are paged
+//! with it.
+
+use crate::models::{ErrorResponse, OpenAIError};
+use crate::service::MockResponse;
+#[cfg(feature = "actix-web")]
+use actix_web::{dev::Payload, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "actix-web")]
+use std::future::{ready, Ready};
+
+/// Number of items returned when `limit` is not supplied.
+pub const DEFAULT_LIST_LIMIT: u32 = 20;
+
+/// Largest `limit` accepted by the list endpoints.
+pub const MAX_LIST_LIMIT: u32 = 100;
+
+/// Sort order for list endpoints, keyed on the `created_at` timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// The intended purpose of an uploaded file, used to filter file listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilePurpose {
+    #[serde(rename = "assistants")]
+    Assistants,
+    #[serde(rename = "batch")]
+    Batch,
+    #[serde(rename = "fine-tune")]
+    FineTune,
+    #[serde(rename = "vision")]
+    Vision,
+    #[serde(rename = "user_data")]
+    UserData,
+}
+
+impl FilePurpose {
+    const ALL: [FilePurpose; 5] = [
+        FilePurpose::Assistants,
+        FilePurpose::Batch,
+        FilePurpose::FineTune,
+        FilePurpose::Vision,
+        FilePurpose::UserData,
+    ];
+
+    /// Returns the wire representation of the purpose.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::Batch => "batch",
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::Vision => "vision",
+            FilePurpose::UserData => "user_data",
+        }
+    }
+}
+
+/// Raw, untyped query string as received. Every field is kept as a string
+/// so that invalid values reach our own validation instead of failing in
+/// serde with a framework-specific error.
+#[derive(Debug, Default, Deserialize)]
+struct RawListQuery {
+    limit: Option<String>,
+    after: Option<String>,
+    order: Option<String>,
+    purpose: Option<String>,
+}
+
+/// Validated pagination and filtering parameters for list endpoints.
+///
+/// With the `actix-web` feature, use it directly as a handler argument:
+///
+/// ```ignore
+/// async fn list_files(query: ListQuery) -> impl Responder { /* ... */ }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListQuery {
+    /// Maximum number of items to return (1..=100); the endpoint's default
+    /// when not supplied.
+    pub limit: Option<u32>,
+
+    /// Cursor: only return items after the object with this ID.
+    pub after: Option<String>,
+
+    /// Sort order by creation time (default `desc`).
+    pub order: SortOrder,
+
+    /// Only return files with this purpose.
+    pub purpose: Option<FilePurpose>,
+}
+
+impl ListQuery {
+    /// Parses and validates a raw query string (without the leading `?`).
+    pub fn from_query_str(query: &str) -> Result<Self, QueryError> {
+        let raw: RawListQuery = parse_raw_query(query)?;
+
+        let limit = raw.limit.as_deref().map(parse_limit).transpose()?;
+
+        let after = match raw.after {
+            Some(value) if value.is_empty() => {
+                return Err(QueryError::new(
+                    "after",
+                    "Invalid 'after': empty string. Expected a string with minimum length 1, but got an empty string instead.",
+                    Some("empty_string"),
+                ));
+            }
+            other => other,
+        };
+
+        let order = match raw.order.as_deref() {
+            None => SortOrder::default(),
+            Some("asc") => SortOrder::Asc,
+            Some("desc") => SortOrder::Desc,
+            Some(other) => {
+                return Err(QueryError::new(
+                    "order",
+                    &format!(
+                        "Invalid value: '{}'. Supported values are: 'asc' and 'desc'.",
+                        other
+                    ),
+                    Some("invalid_value"),
+                ));
+            }
+        };
+
+        let purpose = match raw.purpose.as_deref() {
+            None => None,
+            Some(value) => Some(parse_purpose(value)?),
+        };
+
+        Ok(Self {
+            limit,
+            after,
+            order,
+            purpose,
+        })
+    }
+
+    /// The page of `items` the query selects, and whether more items
+    /// follow it. Items are sorted by the creation time and ID `key`
+    /// returns, in `order`, start after the item with ID `after` (none if
+    /// there is no such item) and number at most `limit`, or
+    /// `default_limit`.
+    pub fn page<T>(
+        &self,
+        mut items: Vec<T>,
+        key: impl Fn(&T) -> (u64, String),
+        default_limit: usize,
+    ) -> (Vec<T>, bool) {
+        items.sort_by_cached_key(|item| key(item));
+        if self.order == SortOrder::Desc {
+            items.reverse();
+        }
+        if let Some(after) = &self.after {
+            let start = items
+                .iter()
+                .position(|item| key(item).1 == *after)
+                .map_or(items.len(), |index| index + 1);
+            items.drain(..start);
+        }
+        let limit = self.limit.map_or(default_limit, |limit| limit as usize);
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+        (items, has_more)
+    }
+}
+
+fn parse_raw_query(query: &str) -> Result<RawListQuery, QueryError> {
+    serde_urlencoded::from_str(query)
+        .map_err(|e| QueryError::new("query", &format!("Invalid query string: {}", e), None))
+}
+
+fn parse_limit(value: &str) -> Result<u32, QueryError> {
+    let limit: i64 = value.parse().map_err(|_| {
+        QueryError::new(
+            "limit",
+            &format!(
+                "Invalid 'limit': expected an integer, but got '{}' instead.",
+                value
+            ),
+            Some("invalid_type"),
+        )
+    })?;
+
+    if limit < 1 {
+        return Err(QueryError::new(
+            "limit",
+            &format!(
+                "Invalid 'limit': integer below minimum value. Expected a value >= 1, but got {} instead.",
+                limit
+            ),
+            Some("integer_below_min_value"),
+        ));
+    }
+    if limit > MAX_LIST_LIMIT as i64 {
+        return Err(QueryError::new(
+            "limit",
+            &format!(
+                "Invalid 'limit': integer above maximum value. Expected a value <= {}, but got {} instead.",
+                MAX_LIST_LIMIT, limit
+            ),
+            Some("integer_above_max_value"),
+        ));
+    }
+
+    Ok(limit as u32)
+}
+
+fn parse_purpose(value: &str) -> Result<FilePurpose, QueryError> {
+    FilePurpose::ALL
+        .iter()
+        .copied()
+        .find(|purpose| purpose.as_str() == value)
+        .ok_or_else(|| {
+            let supported = FilePurpose::ALL
+                .iter()
+                .map(|p| format!("'{}'", p.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            QueryError::new(
+                "purpose",
+                &format!(
+                    "Invalid value: '{}'. Supported values are: {}.",
+                    value, supported
+                ),
+                Some("invalid_value"),
+            )
+        })
+}
+
+#[cfg(feature = "actix-web")]
+impl FromRequest for ListQuery {
+    type Error = QueryError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(ListQuery::from_query_str(req.query_string()))
+    }
+}
+
+/// An invalid query parameter, rendered as an OpenAI-style
+/// `invalid_request_error` with a `400 Bad Request` status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// The offending query parameter.
+    pub param: String,
+
+    /// Human readable description of the problem.
+    pub message: String,
+
+    /// Machine readable error code, if any.
+    pub code: Option<String>,
+}
+
+impl QueryError {
+    pub fn new(param: &str, message: &str, code: Option<&str>) -> Self {
+        Self {
+            param: param.to_string(),
+            message: message.to_string(),
+            code: code.map(str::to_string),
+        }
+    }
+
+    /// The `400 Bad Request` response reporting the error.
+    pub fn to_response(&self) -> MockResponse {
+        ErrorResponse::from(self.clone()).to_response()
+    }
+}
+
+impl From<QueryError> for ErrorResponse {
+    fn from(error: QueryError) -> Self {
+        let error = OpenAIError {
+            param: Some(error.param),
+            code: error.code,
+            ..OpenAIError::invalid_request(error.message)
+        };
+        ErrorResponse::new(StatusCode::BAD_REQUEST, error)
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[cfg(feature = "actix-web")]
+impl ResponseError for QueryError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::BAD_REQUEST
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        ErrorResponse::from(self.clone()).error_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let query = ListQuery::from_query_str("").unwrap();
+        assert_eq!(query, ListQuery::default());
+        assert_eq!(query.limit, None);
+        assert_eq!(query.order, SortOrder::Desc);
+    }
+
+    #[test]
+    fn test_valid_params() {
+        let query =
+            ListQuery::from_query_str("limit=5&after=file-abc&order=asc&purpose=fine-tune")
+                .unwrap();
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.after.as_deref(), Some("file-abc"));
+        assert_eq!(query.order, SortOrder::Asc);
+        assert_eq!(query.purpose, Some(FilePurpose::FineTune));
+    }
+
+    #[test]
+    fn test_invalid_limit() {
+        let err = ListQuery::from_query_str("limit=0").unwrap_err();
+        assert_eq!(err.param, "limit");
+        assert_eq!(err.code.as_deref(), Some("integer_below_min_value"));
+
+        let err = ListQuery::from_query_str("limit=101").unwrap_err();
+        assert_eq!(err.code.as_deref(), Some("integer_above_max_value"));
+
+        let err = ListQuery::from_query_str("limit=ten").unwrap_err();
+        assert_eq!(err.code.as_deref(), Some("invalid_type"));
+    }
+
+    #[test]
+    fn test_invalid_order_and_purpose() {
+        let err = ListQuery::from_query_str("order=up").unwrap_err();
+        assert_eq!(err.param, "order");
+        assert_eq!(
+            err.message,
+            "Invalid value: 'up'. Supported values are: 'asc' and 'desc'."
+        );
+
+        let err = ListQuery::from_query_str("purpose=nope").unwrap_err();
+        assert_eq!(err.param, "purpose");
+        assert_eq!(err.code.as_deref(), Some("invalid_value"));
+    }
+
+    #[test]
+    fn test_page() {
+        let items = vec![(3, "c"), (1, "a"), (2, "b"), (2, "d")];
+        let key = |item: &(u64, &str)| (item.0, item.1.to_string());
+        let page = |query: &str, default_limit| {
+            let query = ListQuery::from_query_str(query).unwrap();
+            let (page, has_more) = query.page(items.clone(), key, default_limit);
+            (page.iter().map(|item| item.1).collect::<Vec<_>>(), has_more)
+        };
+        assert_eq!(page("", 20), (vec!["c", "d", "b", "a"], false));
+        assert_eq!(page("", 2), (vec!["c", "d"], true));
+        assert_eq!(page("order=asc&limit=2", 20), (vec!["a", "b"], true));
+        assert_eq!(page("order=asc&after=b", 20), (vec!["d", "c"], false));
+        assert_eq!(page("after=file-missing", 20), (vec![], false));
+    }
+
+    #[tokio::test]
+    async fn test_error_response_shape() {
+        let err = ListQuery::from_query_str("after=").unwrap_err();
+        let resp = err.to_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let body = resp.into_body().to_bytes().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["param"], "after");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod list_query;
+pub use list_query::{ListQuery, QueryError, SortOrder, FilePurpose};
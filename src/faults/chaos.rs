@@ -0,0 +1,140 @@
+//! Chaos mode: a mix of faults injected at random into every endpoint.
+
+use crate::faults::StreamFault;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Injects a random mix of failures into requests to every endpoint, for
+/// soak tests that check a client copes with all of them at once rather
+/// than with one fault at a time.
+///
+/// Each kind of failure has its own rate, a fraction of requests between
+/// `0.0` and `1.0`, rolled independently:
+///
+/// ```yaml
+/// faults:
+///   chaos:
+///     rate_limited: 0.05
+///     server_errors: 0.02
+///     timeouts: 0.01
+///     stream_truncations: 0.05
+///     latency_spikes: 0.1
+///     spike: 3s
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    /// Requests rejected with `429 rate_limit_exceeded`, told to retry
+    /// after one second.
+    #[serde(default)]
+    pub rate_limited: f64,
+
+    /// Requests failing with a `500` server error.
+    #[serde(default)]
+    pub server_errors: f64,
+
+    /// Requests that are never answered, so the client has to time out.
+    #[serde(default)]
+    pub timeouts: f64,
+
+    /// Streamed responses closed after a few chunks, before `[DONE]`.
+    #[serde(default)]
+    pub stream_truncations: f64,
+
+    /// Requests delayed by an extra `spike` on top of their latency.
+    #[serde(default)]
+    pub latency_spikes: f64,
+
+    /// Extra delay of a latency spike.
+    #[serde(default = "default_spike", with = "crate::config::duration")]
+    pub spike: Duration,
+}
+
+fn default_spike() -> Duration {
+    Duration::from_secs(2)
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            rate_limited: 0.0,
+            server_errors: 0.0,
+            timeouts: 0.0,
+            stream_truncations: 0.0,
+            latency_spikes: 0.0,
+            spike: default_spike(),
+        }
+    }
+}
+
+/// Most valid chunks sent before a stream is truncated.
+const MAX_CHUNKS_BEFORE_TRUNCATION: usize = 4;
+
+impl ChaosConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the fraction of requests rejected with `429`.
+    pub fn rate_limited(mut self, rate: f64) -> Self {
+        self.rate_limited = rate;
+        self
+    }
+
+    /// Sets the fraction of requests failing with `500`.
+    pub fn server_errors(mut self, rate: f64) -> Self {
+        self.server_errors = rate;
+        self
+    }
+
+    /// Sets the fraction of requests never answered.
+    pub fn timeouts(mut self, rate: f64) -> Self {
+        self.timeouts = rate;
+        self
+    }
+
+    /// Sets the fraction of streams truncated.
+    pub fn stream_truncations(mut self, rate: f64) -> Self {
+        self.stream_truncations = rate;
+        self
+    }
+
+    /// Delays a fraction of requests by an extra `spike`.
+    pub fn latency_spikes(mut self, rate: f64, spike: Duration) -> Self {
+        self.latency_spikes = rate;
+        self.spike = spike;
+        self
+    }
+
+    /// Whether a request is hit by a failure occurring at `rate`.
+    pub fn roll(rate: f64) -> bool {
+        rate > 0.0 && rand::thread_rng().gen::<f64>() < rate
+    }
+
+    /// The fault to inject into a stream: a truncation after a random
+    /// number of chunks, for a fraction `stream_truncations` of streams.
+    pub fn stream_fault(&self) -> Option<StreamFault> {
+        if !Self::roll(self.stream_truncations) {
+            return None;
+        }
+        Some(StreamFault::Truncate {
+            after_chunks: rand::thread_rng().gen_range(1..=MAX_CHUNKS_BEFORE_TRUNCATION),
+            mid_json: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolls() {
+        assert!(!ChaosConfig::roll(0.0));
+        assert!(ChaosConfig::roll(1.0));
+
+        assert_eq!(ChaosConfig::new().stream_fault(), None);
+        let fault = ChaosConfig::new().stream_truncations(1.0).stream_fault().unwrap();
+        assert!((1..=MAX_CHUNKS_BEFORE_TRUNCATION).contains(&fault.after_chunks()));
+    }
+}
@@ -0,0 +1,10 @@
+pub mod chaos;
+pub mod overload_fault;
+pub mod rate_limit_fault;
+pub mod response_fault;
+pub mod stream_fault;
+pub use chaos::ChaosConfig;
+pub use overload_fault::OverloadFault;
+pub use rate_limit_fault::RateLimitFault;
+pub use response_fault::ResponseFault;
+pub use stream_fault::StreamFault;
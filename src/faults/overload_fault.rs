@@ -0,0 +1,64 @@
+//! Injected `503` responses of an overloaded model.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Rejects requests with the `503` error the real API returns when a
+/// model is overloaded, which clients often retry differently from a
+/// `500`:
+///
+/// ```yaml
+/// faults:
+///   overloaded:
+///     rate: 0.3
+///     models: [gpt-4]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverloadFault {
+    /// Fraction of requests (between `0.0` and `1.0`) rejected.
+    #[serde(default = "always")]
+    pub rate: f64,
+
+    /// Models that are overloaded. Every request is affected when empty,
+    /// including those to endpoints without a model.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub models: BTreeSet<String>,
+}
+
+fn always() -> f64 {
+    1.0
+}
+
+impl Default for OverloadFault {
+    fn default() -> Self {
+        Self {
+            rate: always(),
+            models: BTreeSet::new(),
+        }
+    }
+}
+
+impl OverloadFault {
+    /// Overloads every model.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects only a fraction of requests.
+    pub fn rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Overloads `model`. Once a model is named, the others are not
+    /// overloaded.
+    pub fn model(mut self, model: &str) -> Self {
+        self.models.insert(model.to_string());
+        self
+    }
+
+    /// Whether requests for `model` are affected.
+    pub fn affects(&self, model: Option<&str>) -> bool {
+        self.models.is_empty() || model.is_some_and(|model| self.models.contains(model))
+    }
+}
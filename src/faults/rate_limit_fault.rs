@@ -0,0 +1,82 @@
+//! Injected `429 rate_limit_exceeded` responses.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Rejects requests with the `429 rate_limit_exceeded` error of the real
+/// API, along with the `Retry-After` and `x-ratelimit-*` headers clients
+/// back off by.
+///
+/// Requests are rejected at random (`rate`), once `after_requests`
+/// requests were made in the current `window`, or both:
+///
+/// ```yaml
+/// faults:
+///   rate_limit:
+///     after_requests: 3
+///     window: 10s
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitFault {
+    /// Fraction of requests (between `0.0` and `1.0`) rejected at random.
+    /// They are told to retry after `retry_after`.
+    #[serde(default)]
+    pub rate: f64,
+
+    /// Requests allowed per `window`; later requests in the window are
+    /// rejected and told to retry when it resets.
+    #[serde(default)]
+    pub after_requests: Option<u32>,
+
+    /// Length of the window `after_requests` is counted in.
+    #[serde(default = "default_window", with = "crate::config::duration")]
+    pub window: Duration,
+
+    /// Wait advertised to requests rejected at random.
+    #[serde(default = "default_retry_after", with = "crate::config::duration")]
+    pub retry_after: Duration,
+}
+
+fn default_window() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_retry_after() -> Duration {
+    Duration::from_secs(1)
+}
+
+impl Default for RateLimitFault {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            after_requests: None,
+            window: default_window(),
+            retry_after: default_retry_after(),
+        }
+    }
+}
+
+impl RateLimitFault {
+    /// Rejects a fraction of requests at random.
+    pub fn random(rate: f64) -> Self {
+        Self {
+            rate,
+            ..Self::default()
+        }
+    }
+
+    /// Rejects every request after the first `requests` in each `window`.
+    pub fn after_requests(requests: u32, window: Duration) -> Self {
+        Self {
+            after_requests: Some(requests),
+            window,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the wait advertised to requests rejected at random.
+    pub fn retry_after(mut self, wait: Duration) -> Self {
+        self.retry_after = wait;
+        self
+    }
+}
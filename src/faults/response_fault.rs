@@ -0,0 +1,120 @@
+//! Faults that replace the whole response to a request.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// A failure injected into every response, for testing how clients cope
+/// with a misbehaving server rather than with an API error.
+///
+/// The malformed-body variants only affect successful, non-streamed
+/// responses; errors and streams are sent unchanged. The connection
+/// faults affect every response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFault {
+    /// Accept the request but never respond, or respond normally only
+    /// after `respond_after`, so client timeouts and deadline propagation
+    /// can be exercised.
+    Hang {
+        #[serde(default, with = "crate::config::duration::option")]
+        respond_after: Option<Duration>,
+    },
+
+    /// Cut the JSON body off halfway, so it no longer parses.
+    InvalidJson,
+
+    /// Send the body with `content_type` instead of `application/json`.
+    WrongContentType {
+        #[serde(default = "default_content_type")]
+        content_type: String,
+    },
+
+    /// Send valid JSON in which every field has the wrong type, e.g. a
+    /// number where the API returns a string.
+    SchemaViolation,
+
+    /// Close the connection without sending a response.
+    CloseConnection,
+
+    /// Send the status line and headers of the response, then close the
+    /// connection instead of sending the body.
+    ResetAfterHeaders,
+}
+
+fn default_content_type() -> String {
+    "text/html; charset=utf-8".to_string()
+}
+
+impl ResponseFault {
+    /// The content type and body to send instead of the JSON `body`, or
+    /// `None` if this fault leaves bodies alone.
+    pub fn malform(&self, body: &[u8]) -> Option<(String, Vec<u8>)> {
+        match self {
+            ResponseFault::Hang { .. }
+            | ResponseFault::CloseConnection
+            | ResponseFault::ResetAfterHeaders => None,
+            ResponseFault::InvalidJson => Some((
+                "application/json".to_string(),
+                body[..body.len() / 2].to_vec(),
+            )),
+            ResponseFault::WrongContentType { content_type } => {
+                Some((content_type.clone(), body.to_vec()))
+            }
+            ResponseFault::SchemaViolation => {
+                let mut value: Value = serde_json::from_slice(body).ok()?;
+                if let Value::Object(fields) = &mut value {
+                    for field in fields.values_mut() {
+                        *field = mistyped(field);
+                    }
+                }
+                Some((
+                    "application/json".to_string(),
+                    value.to_string().into_bytes(),
+                ))
+            }
+        }
+    }
+}
+
+/// A value of a different JSON type than `value`.
+fn mistyped(value: &Value) -> Value {
+    match value {
+        Value::String(_) => Value::from(0),
+        Value::Number(number) => Value::String(number.to_string()),
+        Value::Bool(flag) => Value::String(flag.to_string()),
+        Value::Array(_) => Value::Object(Default::default()),
+        Value::Object(_) => Value::Array(Vec::new()),
+        Value::Null => Value::Bool(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_malform() {
+        let body = json!({"id": "cmpl-1", "created": 1, "choices": [], "usage": {}}).to_string();
+
+        let (_, invalid) = ResponseFault::InvalidJson.malform(body.as_bytes()).unwrap();
+        assert!(serde_json::from_slice::<Value>(&invalid).is_err());
+
+        let (_, mistyped) = ResponseFault::SchemaViolation
+            .malform(body.as_bytes())
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&mistyped).unwrap(),
+            json!({"id": 0, "created": "1", "choices": {}, "usage": []})
+        );
+
+        assert_eq!(
+            ResponseFault::Hang {
+                respond_after: None
+            }
+            .malform(body.as_bytes()),
+            None
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Faults that are injected into otherwise valid SSE streams.
+
+use serde::{Deserialize, Serialize};
+
+/// A failure injected partway through a streamed response.
+///
+/// Every variant first emits `after_chunks` valid chunks so that clients
+/// have already started consuming the stream when the failure happens.
+/// This makes resumption and retry logic for interrupted streams testable
+/// deterministically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamFault {
+    /// Emit an OpenAI-format `error` event and close the stream without
+    /// sending `[DONE]`.
+    ErrorEvent { after_chunks: usize },
+
+    /// Fail the response body, dropping the connection the same way a
+    /// crashing upstream (500) does mid-stream.
+    ServerError { after_chunks: usize },
+
+    /// Close the connection cleanly before `[DONE]` is sent. With
+    /// `mid_json`, the next chunk is cut off halfway through its JSON, so
+    /// clients must detect the incomplete stream rather than silently
+    /// returning partial text.
+    Truncate {
+        after_chunks: usize,
+        #[serde(default)]
+        mid_json: bool,
+    },
+}
+
+impl StreamFault {
+    /// Number of valid chunks sent before the fault fires.
+    pub fn after_chunks(&self) -> usize {
+        match self {
+            StreamFault::ErrorEvent { after_chunks }
+            | StreamFault::ServerError { after_chunks }
+            | StreamFault::Truncate { after_chunks, .. } => *after_chunks,
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod response_fixtures;
+pub use response_fixtures::ResponseFixtures;
@@ -0,0 +1,239 @@
+//! Canned responses loaded from a directory of JSON files.
+//!
+//! Teams often want the mock to return realistic responses captured from
+//! the real API. Those can be checked into the repository as fixture files
+//! and served verbatim:
+//!
+//! ```text
+//! fixtures/
+//!   completions/
+//!     default.json        served for every /v1/completions request
+//!     gpt-4.json          served when the request's model is gpt-4
+//!   chat/completions/
+//!     default.json        served for every /v1/chat/completions request
+//! ```
+//!
+//! A fixture's directory is the endpoint path below `/v1`; its file stem is
+//! either a model name or `default`.
+//!
+//! Files ending in `.json.hbs` instead of `.json` are Handlebars templates
+//! rendered per request (see [`templates`](crate::templates)), e.g.
+//! `chat/completions/default.json.hbs`.
+
+use crate::templates::{render_template, validate_template, TemplateError};
+use crate::service::{body_response, MockResponse};
+use bytes::Bytes;
+use http::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File stem of the fixture served when no model-specific one exists.
+const DEFAULT_FIXTURE: &str = "default";
+
+/// A fixture body, served verbatim or rendered as a template.
+///
+/// The body is shared by every response serving it, so large fixtures are
+/// not copied per request.
+#[derive(Debug, Clone)]
+struct Fixture {
+    body: Bytes,
+    template: bool,
+}
+
+impl Fixture {
+    fn text(&self) -> &str {
+        // Bodies are only ever built from strings.
+        std::str::from_utf8(&self.body).unwrap_or_default()
+    }
+}
+
+/// Fixtures of one endpoint.
+#[derive(Debug, Clone, Default)]
+struct EndpointFixtures {
+    by_model: HashMap<String, Fixture>,
+    default: Option<Fixture>,
+}
+
+/// Response bodies loaded by [`ResponseFixtures::from_dir`], keyed by
+/// endpoint and model.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseFixtures {
+    endpoints: HashMap<String, EndpointFixtures>,
+}
+
+impl ResponseFixtures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every `.json` file below `dir`.
+    ///
+    /// Fails if the directory cannot be read or a fixture is not valid
+    /// JSON, so broken fixtures are caught when the mock starts rather than
+    /// when they are served.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut fixtures = Self::new();
+        fixtures.load_dir(dir.as_ref(), "")?;
+        Ok(fixtures)
+    }
+
+    fn load_dir(&mut self, dir: &Path, endpoint: &str) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if path.is_dir() {
+                let nested = if endpoint.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", endpoint, name)
+                };
+                self.load_dir(&path, &nested)?;
+            } else if let Some(matcher) = name.strip_suffix(".json.hbs") {
+                if endpoint.is_empty() {
+                    log::warn!("ignoring fixture {} outside an endpoint directory", path.display());
+                    continue;
+                }
+                let template = fs::read_to_string(&path)?;
+                self.insert_template(endpoint, matcher, template).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid fixture {}: {}", path.display(), e),
+                    )
+                })?;
+            } else if let Some(matcher) = name.strip_suffix(".json") {
+                if endpoint.is_empty() {
+                    log::warn!("ignoring fixture {} outside an endpoint directory", path.display());
+                    continue;
+                }
+                let body = fs::read_to_string(&path)?;
+                serde_json::from_str::<Value>(&body).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid JSON in fixture {}: {}", path.display(), e),
+                    )
+                })?;
+                self.insert(endpoint, matcher, body);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a fixture for `endpoint` (the path below `/v1`, e.g.
+    /// `completions`), served for `model`, or for every model when `model`
+    /// is `default`.
+    pub fn insert(&mut self, endpoint: &str, model: &str, body: String) {
+        self.insert_fixture(endpoint, model, Fixture { body: body.into(), template: false });
+    }
+
+    /// Like [`insert`](Self::insert), but `template` is rendered per
+    /// request. Fails if it is not a valid template.
+    pub fn insert_template(
+        &mut self,
+        endpoint: &str,
+        model: &str,
+        template: String,
+    ) -> Result<(), TemplateError> {
+        validate_template(&template)?;
+        self.insert_fixture(endpoint, model, Fixture { body: template.into(), template: true });
+        Ok(())
+    }
+
+    fn insert_fixture(&mut self, endpoint: &str, model: &str, fixture: Fixture) {
+        let fixtures = self.endpoints.entry(endpoint.to_string()).or_default();
+        if model == DEFAULT_FIXTURE {
+            fixtures.default = Some(fixture);
+        } else {
+            fixtures.by_model.insert(model.to_string(), fixture);
+        }
+    }
+
+    /// Whether no fixtures are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// The endpoints (paths below `/v1`) that have at least one fixture.
+    pub fn endpoints(&self) -> impl Iterator<Item = &str> {
+        self.endpoints.keys().map(String::as_str)
+    }
+
+    /// The models with their own fixture for requests to `path`, sorted.
+    /// `None` when the endpoint has no fixtures at all.
+    pub fn models(&self, path: &str) -> Option<Vec<&str>> {
+        let fixtures = self.endpoints.get(path.strip_prefix("/v1/")?)?;
+        let mut models: Vec<&str> = fixtures.by_model.keys().map(String::as_str).collect();
+        models.sort_unstable();
+        Some(models)
+    }
+
+    fn find(&self, path: &str, model: &str) -> Option<&Fixture> {
+        let endpoint = path.strip_prefix("/v1/")?;
+        let fixtures = self.endpoints.get(endpoint)?;
+        fixtures.by_model.get(model).or(fixtures.default.as_ref())
+    }
+
+    /// Returns the raw body (or unrendered template) to serve for a request
+    /// to `path` (e.g. `/v1/completions`) with `model`, if a fixture
+    /// matches.
+    pub fn lookup(&self, path: &str, model: &str) -> Option<&str> {
+        self.find(path, model).map(Fixture::text)
+    }
+
+    /// Returns the response to a request to `path` with JSON body
+    /// `request`, if a fixture matches the request's `model`. Templates
+    /// are rendered with the request as context.
+    pub fn respond(&self, path: &str, request: &Value) -> Option<MockResponse> {
+        let model = request["model"].as_str().unwrap_or_default();
+        let fixture = self.find(path, model)?;
+        let body = if fixture.template {
+            match render_template(fixture.text(), request) {
+                Ok(body) => body.into(),
+                Err(e) => return Some(e.to_response()),
+            }
+        } else {
+            fixture.body.clone()
+        };
+        Some(body_response(StatusCode::OK, "application/json", body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_dir() {
+        let dir = std::env::temp_dir().join(format!("openai-mock-fixtures-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("completions")).unwrap();
+        fs::create_dir_all(dir.join("chat/completions")).unwrap();
+        fs::write(dir.join("completions/default.json"), r#"{"id": "default"}"#).unwrap();
+        fs::write(dir.join("completions/gpt-4.json"), r#"{"id":   "gpt-4"}"#).unwrap();
+        fs::write(dir.join("chat/completions/default.json"), r#"{"id": "chat"}"#).unwrap();
+        fs::write(dir.join("completions/notes.txt"), "not a fixture").unwrap();
+        fs::write(dir.join("completions/gpt-4o.json.hbs"), r#"{"id": "{{model}}"}"#).unwrap();
+
+        let fixtures = ResponseFixtures::from_dir(&dir).unwrap();
+        assert_eq!(fixtures.lookup("/v1/completions", "gpt-4"), Some(r#"{"id":   "gpt-4"}"#));
+        assert_eq!(fixtures.lookup("/v1/completions", "davinci"), Some(r#"{"id": "default"}"#));
+        assert_eq!(fixtures.lookup("/v1/chat/completions", "gpt-4"), Some(r#"{"id": "chat"}"#));
+        assert_eq!(fixtures.lookup("/v1/embeddings", "gpt-4"), None);
+        assert_eq!(fixtures.lookup("/v1/completions", "gpt-4o"), Some(r#"{"id": "{{model}}"}"#));
+
+        fs::write(dir.join("completions/broken.json.hbs"), "{{#if model}}").unwrap();
+        let err = ResponseFixtures::from_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(dir.join("completions/broken.json.hbs")).unwrap();
+
+        fs::write(dir.join("completions/broken.json"), "{").unwrap();
+        let err = ResponseFixtures::from_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
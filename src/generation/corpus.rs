@@ -0,0 +1,191 @@
+//! Completions sampled from a user-supplied corpus.
+//!
+//! Demo environments driven by the mock look more convincing when its
+//! answers are real text about the right subject. A corpus is a file of
+//! such answers, from which each completion is picked at random:
+//!
+//! - a `.jsonl` file holds one answer per line, as
+//!   `{"text": "...", "keywords": ["refund", "invoice"]}`. Answers with
+//!   keywords are only picked for prompts containing one of them (ignoring
+//!   case); answers without are picked when no keyword matches.
+//! - any other file is plain text, with answers separated by blank lines.
+
+use crate::generation::{GeneratedText, GenerationContext, TextGenerator};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// One answer of a corpus.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CorpusEntry {
+    /// The completion text.
+    pub text: String,
+
+    /// Words a prompt must contain for the answer to be picked, lowercase.
+    /// Picked for prompts no keyed answer matches when empty.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+/// Completes prompts with answers sampled from a corpus.
+#[derive(Debug, Clone)]
+pub struct CorpusGenerator {
+    entries: Arc<[CorpusEntry]>,
+}
+
+impl CorpusGenerator {
+    /// A generator over `entries`.
+    ///
+    /// Fails if there are none, since there would be nothing to answer.
+    pub fn new(entries: impl IntoIterator<Item = CorpusEntry>) -> io::Result<Self> {
+        let entries: Vec<CorpusEntry> = entries
+            .into_iter()
+            .map(|entry| CorpusEntry {
+                keywords: entry.keywords.iter().map(|word| word.to_lowercase()).collect(),
+                ..entry
+            })
+            .collect();
+        if entries.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the corpus is empty"));
+        }
+        Ok(Self {
+            entries: entries.into(),
+        })
+    }
+
+    /// A generator over the paragraphs of `text`, separated by blank
+    /// lines.
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let mut paragraphs = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        for line in text.lines().chain([""]) {
+            if line.trim().is_empty() {
+                if !paragraph.is_empty() {
+                    paragraphs.push(paragraph.join("\n"));
+                    paragraph.clear();
+                }
+            } else {
+                paragraph.push(line);
+            }
+        }
+        Self::new(paragraphs.into_iter().map(|text| CorpusEntry {
+            text,
+            keywords: Vec::new(),
+        }))
+    }
+
+    /// A generator over the entries of JSONL `text`, one [`CorpusEntry`]
+    /// per non-empty line.
+    pub fn from_jsonl(text: &str) -> io::Result<Self> {
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid corpus entry on line {}: {}", index + 1, e),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<CorpusEntry>>>()?;
+        Self::new(entries)
+    }
+
+    /// Loads the corpus at `path`: JSONL if its extension is `jsonl`, plain
+    /// text otherwise.
+    ///
+    /// Fails if the file cannot be read, is not valid JSONL, or holds no
+    /// answer, so broken corpora are caught when the mock starts rather
+    /// than when it answers.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let corpus = if path.extension().is_some_and(|extension| extension == "jsonl") {
+            Self::from_jsonl(&text)
+        } else {
+            Self::from_text(&text)
+        };
+        corpus.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    /// The answers `prompt` may be completed with: those with a keyword it
+    /// contains, or else those without keywords, or else all of them.
+    pub fn candidates(&self, prompt: &str) -> Vec<&CorpusEntry> {
+        let prompt = prompt.to_lowercase();
+        let keyed: Vec<&CorpusEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.keywords.iter().any(|word| prompt.contains(word.as_str())))
+            .collect();
+        if !keyed.is_empty() {
+            return keyed;
+        }
+        let unkeyed: Vec<&CorpusEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.keywords.is_empty())
+            .collect();
+        if !unkeyed.is_empty() {
+            return unkeyed;
+        }
+        self.entries.iter().collect()
+    }
+}
+
+impl TextGenerator for CorpusGenerator {
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        let candidates = self.candidates(ctx.prompt);
+        let entry = candidates
+            .choose(&mut thread_rng())
+            .expect("a corpus is never empty");
+        GeneratedText::new(entry.text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_candidates() {
+        let corpus = CorpusGenerator::from_jsonl(
+            r#"{"text": "Your refund is on its way.", "keywords": ["Refund", "money back"]}
+{"text": "Your invoice is attached.", "keywords": ["invoice"]}
+
+{"text": "Thanks for reaching out!"}"#,
+        )
+        .unwrap();
+        let texts = |prompt: &str| -> Vec<&str> {
+            corpus
+                .candidates(prompt)
+                .into_iter()
+                .map(|entry| entry.text.as_str())
+                .collect()
+        };
+        assert_eq!(texts("Where is my REFUND?"), ["Your refund is on its way."]);
+        assert_eq!(texts("I want my money back"), ["Your refund is on its way."]);
+        assert_eq!(texts("Hello"), ["Thanks for reaching out!"]);
+
+        let error = CorpusGenerator::from_jsonl("{\"text\": 1}").unwrap_err();
+        assert!(error.to_string().contains("line 1"));
+        assert!(CorpusGenerator::from_text("\n \n").is_err());
+    }
+
+    #[test]
+    fn test_text_corpus() {
+        let corpus = CorpusGenerator::from_text("First answer,\non two lines.\n\n\nSecond answer.\n")
+            .unwrap();
+        let texts: Vec<&str> = corpus
+            .candidates("anything")
+            .into_iter()
+            .map(|entry| entry.text.as_str())
+            .collect();
+        assert_eq!(texts, ["First answer,\non two lines.", "Second answer."]);
+    }
+}
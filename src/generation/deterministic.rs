@@ -0,0 +1,149 @@
+//! Completions derived from a hash of their inputs.
+//!
+//! The text of a completion depends only on the model id, the prompt, the
+//! choice index, `max_tokens` and a seed, through a hash and a
+//! pseudo-random generator that are fixed by this crate rather than by the
+//! standard library or `rand`. Identical requests therefore get identical
+//! completions across runs, machines and toolchains, which snapshot tests
+//! rely on.
+
+use crate::generation::{GeneratedText, GenerationContext, MarkovGenerator, TextGenerator};
+
+/// Generates Markov chain prose (see [`MarkovGenerator`]) whose every
+/// random choice is derived from a hash of the model, prompt and seed.
+#[derive(Debug, Clone, Default)]
+pub struct DeterministicGenerator {
+    markov: MarkovGenerator,
+    seed: u64,
+}
+
+impl DeterministicGenerator {
+    /// A generator over the built-in corpus, mixing `seed` into the hash
+    /// so different seeds give different (but still stable) texts.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            markov: MarkovGenerator::builtin(),
+            seed,
+        }
+    }
+
+    /// The same generator drawing its words from `markov`'s corpus.
+    pub fn with_markov(mut self, markov: MarkovGenerator) -> Self {
+        self.markov = markov;
+        self
+    }
+}
+
+impl TextGenerator for DeterministicGenerator {
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        let mut hash = Fnv1a::default();
+        hash.write(ctx.model.as_bytes());
+        hash.write(&[0]);
+        hash.write(ctx.prompt.as_bytes());
+        hash.write(&[0]);
+        hash.write(&self.seed.to_le_bytes());
+        hash.write(&(ctx.choice_index as u64).to_le_bytes());
+
+        let mut rng = SplitMix64(hash.0);
+        let text = self.markov.walk(ctx.max_tokens, ctx.counter, |n| {
+            (rng.next() % n as u64) as usize
+        });
+        GeneratedText::new(text)
+    }
+}
+
+/// 64-bit FNV-1a, a hash whose output is specified.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// SplitMix64, a pseudo-random generator whose output is specified.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CompletionRequest;
+    use crate::utils::token_counting::TokenCounter;
+
+    #[test]
+    fn test_deterministic_text() {
+        let request = CompletionRequest::default();
+        let counter = TokenCounter::approximate();
+        let generate_choice = |generator: &DeterministicGenerator, model: &str, prompt: &str, choice_index| {
+            generator
+                .generate(&GenerationContext {
+                    request: &request,
+                    model,
+                    prompt,
+                    prompt_index: 0,
+                    choice_index,
+                    max_tokens: 32,
+                    counter: &counter,
+                })
+                .text
+        };
+        let generate = |generator: &DeterministicGenerator, model: &str, prompt: &str| {
+            generate_choice(generator, model, prompt, 0)
+        };
+
+        let generator = DeterministicGenerator::new(0);
+        let text = generate(&generator, "gpt-4o", "Summarize the report");
+        assert!(!text.is_empty());
+        assert_eq!(generate(&generator, "gpt-4o", "Summarize the report"), text);
+        assert_eq!(
+            generate(&DeterministicGenerator::new(0), "gpt-4o", "Summarize the report"),
+            text
+        );
+        // Pinned so that changes to the output, which would break users'
+        // snapshots, are deliberate.
+        assert_eq!(text, " The report ends with a small budget. A clear goal and a");
+
+        let others = [
+            generate(&generator, "gpt-4o-mini", "Summarize the report"),
+            generate(&generator, "gpt-4o", "Summarize the memo"),
+            generate(&DeterministicGenerator::new(1), "gpt-4o", "Summarize the report"),
+        ];
+        assert!(others.iter().any(|other| *other != text));
+
+        let choices: Vec<_> = (1..4)
+            .map(|choice_index| generate_choice(&generator, "gpt-4o", "Summarize the report", choice_index))
+            .collect();
+        assert_eq!(generate_choice(&generator, "gpt-4o", "Summarize the report", 2), choices[1]);
+        assert!(choices.iter().any(|choice| *choice != text));
+    }
+
+    #[test]
+    fn test_stable_primitives() {
+        let mut hash = Fnv1a::default();
+        hash.write(b"a");
+        assert_eq!(hash.0, 0xaf63_dc4c_8601_ec8c);
+
+        let mut rng = SplitMix64(0);
+        assert_eq!(rng.next(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next(), 0x6e78_9e6a_a1b9_65f4);
+    }
+}
@@ -0,0 +1,138 @@
+//! Applying a request's `logit_bias` to generated text.
+//!
+//! Only the extremes have an effect, as they do on a real model for most
+//! purposes: a bias of -100 bans a token, so its text never appears in the
+//! completion, and a bias of 100 makes the completion nothing but the
+//! favored tokens. That is enough to check bias maps end to end. Token IDs
+//! are decoded with the model's encoding; the approximate tokenizer has no
+//! vocabulary, so with it biases are ignored.
+
+use crate::utils::token_counting::TokenCounter;
+use std::collections::HashMap;
+
+/// The banned and favored tokens of a request's `logit_bias`, as text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogitBias {
+    banned: Vec<String>,
+    favored: Vec<String>,
+}
+
+impl LogitBias {
+    /// The tokens of `bias` (token IDs as strings, mapped to biases)
+    /// decoded with `counter`. Keys that are not token IDs of the encoding
+    /// are skipped.
+    pub fn new(bias: &HashMap<String, i32>, counter: &TokenCounter) -> Self {
+        let mut ids: Vec<(u32, i32)> = bias
+            .iter()
+            .filter_map(|(id, bias)| Some((id.trim().parse().ok()?, *bias)))
+            .collect();
+        // Favored tokens are cycled in a stable order.
+        ids.sort_unstable();
+
+        let mut banned = Vec::new();
+        let mut favored = Vec::new();
+        for (id, bias) in ids {
+            let text = counter.decode(&[id]);
+            if text.is_empty() {
+                continue;
+            }
+            if bias <= -100 {
+                banned.push(text);
+            } else if bias >= 100 {
+                favored.push(text);
+            }
+        }
+        Self { banned, favored }
+    }
+
+    /// Whether the bias changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.banned.is_empty() && self.favored.is_empty()
+    }
+
+    /// `text` with the bias applied: the favored tokens repeated up to
+    /// `max_tokens` tokens if there are any, `text` otherwise, with every
+    /// word containing a banned token (spaces aside) dropped and any banned
+    /// token left across words removed.
+    pub fn apply(&self, text: String, max_tokens: u32, counter: &TokenCounter) -> String {
+        let text = if self.favored.is_empty() {
+            text
+        } else {
+            self.repeat_favored(max_tokens, counter)
+        };
+        if self.banned.is_empty() {
+            return text;
+        }
+
+        let mut kept = String::with_capacity(text.len());
+        for (i, word) in text.split(' ').enumerate() {
+            let banned = self.banned.iter().any(|token| {
+                let token = token.trim();
+                !token.is_empty() && word.contains(token)
+            });
+            if banned {
+                continue;
+            }
+            if i > 0 {
+                kept.push(' ');
+            }
+            kept.push_str(word);
+        }
+        // Each removal shortens the text, so this ends.
+        while let Some(token) = self.banned.iter().find(|token| kept.contains(token.as_str())) {
+            kept = kept.replace(token.as_str(), "");
+        }
+        kept
+    }
+
+    /// The favored tokens in turn, as many as fit in `max_tokens` tokens.
+    fn repeat_favored(&self, max_tokens: u32, counter: &TokenCounter) -> String {
+        let mut text = String::new();
+        let mut tokens = 0;
+        for token in self.favored.iter().cycle() {
+            let token_count = counter.count_tokens(token).max(1);
+            if tokens + token_count > max_tokens {
+                break;
+            }
+            text.push_str(token);
+            tokens += token_count;
+        }
+        text
+    }
+}
+
+#[cfg(all(test, feature = "tiktoken"))]
+mod tests {
+    use super::*;
+
+    // "Hello" and " world" in the encoding of gpt-3.5-turbo-instruct.
+    const HELLO: &str = "9906";
+    const WORLD: &str = "1917";
+
+    fn bias(entries: &[(&str, i32)]) -> HashMap<String, i32> {
+        entries.iter().map(|(id, bias)| (id.to_string(), *bias)).collect()
+    }
+
+    #[test]
+    fn test_banned_tokens() {
+        let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+        let banned = LogitBias::new(&bias(&[(WORLD, -100), ("not an id", -100)]), &counter);
+        let text = banned.apply(" Hello world, worldwide world".into(), 16, &counter);
+        assert_eq!(text, " Hello");
+
+        let banned = LogitBias::new(&bias(&[(HELLO, -100)]), &counter);
+        assert_eq!(banned.apply("Say hello, Hello!".into(), 16, &counter), "Say hello,");
+        assert!(LogitBias::new(&bias(&[(WORLD, 5)]), &counter).is_empty());
+    }
+
+    #[test]
+    fn test_favored_tokens() {
+        let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+        let favored = LogitBias::new(&bias(&[(WORLD, 100), (HELLO, 100)]), &counter);
+        assert_eq!(favored.apply(" ignored".into(), 5, &counter), " worldHello worldHello world");
+
+        // Banned tokens are removed from the favored ones too.
+        let mixed = LogitBias::new(&bias(&[(WORLD, 100), (HELLO, -100)]), &counter);
+        assert_eq!(mixed.apply(" ignored".into(), 3, &counter), " world world world");
+    }
+}
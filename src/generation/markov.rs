@@ -0,0 +1,222 @@
+//! Pseudo-natural text from a word-level Markov chain.
+//!
+//! Each word is followed by one of the words that follow it in a corpus,
+//! picked at random, so the output reads like plausible (if meaningless)
+//! prose of varying length. That is enough for tests that render,
+//! summarize or measure completions, where an empty or echoed completion
+//! is not.
+
+use crate::generation::{GeneratedText, GenerationContext, TextGenerator};
+use crate::utils::token_counting::TokenCounter;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// The corpus of [`MarkovGenerator::builtin`]: plain, topic-neutral prose.
+const BUILTIN_CORPUS: &str = "\
+The team reviewed the report and agreed on the next steps. \
+The next steps depend on the results of the first phase of the project. \
+Each phase of the project has a clear goal and a small budget. \
+A clear goal helps the team focus on the work that matters most. \
+The work that matters most is often the work that is easiest to measure. \
+Some results were better than expected, and some were worse. \
+The results of the survey show that most customers are happy with the service. \
+Most customers want faster answers and simpler prices. \
+Simpler prices make the offer easier to compare with the rest of the market. \
+The market has changed a lot over the last few years. \
+Over the last few years the company has grown in every region. \
+In every region the team found new partners and new customers. \
+New partners bring new ideas, and new ideas need time to grow. \
+It takes time to build trust, but trust makes every project easier. \
+The report ends with a short summary of the main risks and the plan to address them.";
+
+/// A word-level Markov chain: the words of a corpus, with the indices of
+/// the words that follow each.
+#[derive(Debug)]
+struct Chain {
+    words: Vec<String>,
+    successors: Vec<Vec<usize>>,
+    /// Words starting a sentence of the corpus.
+    starts: Vec<usize>,
+}
+
+impl Chain {
+    fn new(corpus: &str) -> Self {
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        let mut words = Vec::new();
+        let mut successors: Vec<Vec<usize>> = Vec::new();
+        let mut starts = Vec::new();
+        let mut previous: Option<usize> = None;
+
+        for word in corpus.split_whitespace() {
+            let index = *indices.entry(word).or_insert_with(|| {
+                words.push(word.to_string());
+                successors.push(Vec::new());
+                words.len() - 1
+            });
+            match previous {
+                Some(previous) if !ends_sentence(&words[previous]) => {
+                    successors[previous].push(index)
+                }
+                _ => starts.push(index),
+            }
+            previous = Some(index);
+        }
+
+        Self {
+            words,
+            successors,
+            starts,
+        }
+    }
+}
+
+fn ends_sentence(word: &str) -> bool {
+    word.ends_with(['.', '!', '?'])
+}
+
+/// Generates pseudo-natural text from a word-level Markov chain, between
+/// half of and all of the request's `max_tokens`.
+#[derive(Debug, Clone)]
+pub struct MarkovGenerator {
+    chain: Arc<Chain>,
+    seed: Option<u64>,
+}
+
+impl MarkovGenerator {
+    /// A generator over a small built-in corpus of plain English prose.
+    pub fn builtin() -> Self {
+        static CHAIN: OnceLock<Arc<Chain>> = OnceLock::new();
+        Self {
+            chain: CHAIN.get_or_init(|| Arc::new(Chain::new(BUILTIN_CORPUS))).clone(),
+            seed: None,
+        }
+    }
+
+    /// A generator over the words of `corpus`, or over the built-in corpus
+    /// if `corpus` has no words.
+    pub fn from_corpus(corpus: &str) -> Self {
+        let chain = Chain::new(corpus);
+        if chain.words.is_empty() {
+            return Self::builtin();
+        }
+        Self {
+            chain: Arc::new(chain),
+            seed: None,
+        }
+    }
+
+    /// Draws words from a generator seeded with `seed` instead of a random
+    /// one, so every completion of the same prompt and choice index is the
+    /// same.
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Text of at most `max_tokens` tokens as counted by `counter`, drawn
+    /// using `rng`.
+    pub fn generate_with<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        max_tokens: u32,
+        counter: &TokenCounter,
+    ) -> String {
+        self.walk(max_tokens, counter, |n| rng.gen_range(0..n))
+    }
+
+    /// Text of at most `max_tokens` tokens as counted by `counter`, each
+    /// random choice among `n` options made by `pick(n)`.
+    pub(crate) fn walk(
+        &self,
+        max_tokens: u32,
+        counter: &TokenCounter,
+        mut pick: impl FnMut(usize) -> usize,
+    ) -> String {
+        let chain = &*self.chain;
+        if max_tokens == 0 {
+            return String::new();
+        }
+        let min_tokens = max_tokens.div_ceil(2);
+        let target = min_tokens + pick((max_tokens - min_tokens + 1) as usize) as u32;
+
+        let mut text = String::new();
+        let mut piece = String::new();
+        let mut tokens = 0;
+        let mut current = chain.starts[pick(chain.starts.len())];
+        loop {
+            // Words are counted one by one with their leading space, as
+            // the encoder splits text on spaces before merging.
+            piece.clear();
+            piece.push(' ');
+            piece.push_str(&chain.words[current]);
+            let piece_tokens = counter.count_tokens(&piece);
+            if tokens + piece_tokens > target {
+                break;
+            }
+            text.push_str(&piece);
+            tokens += piece_tokens;
+
+            let successors = &chain.successors[current];
+            current = if successors.is_empty() {
+                chain.starts[pick(chain.starts.len())]
+            } else {
+                successors[pick(successors.len())]
+            };
+        }
+        text
+    }
+}
+
+impl Default for MarkovGenerator {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl TextGenerator for MarkovGenerator {
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        let text = match self.seed {
+            Some(seed) => {
+                // Each choice of each prompt gets its own stream.
+                let stream = ((ctx.choice_index as u64) << 32).wrapping_add(ctx.prompt_index as u64);
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(stream));
+                self.generate_with(&mut rng, ctx.max_tokens, ctx.counter)
+            }
+            None => self.generate_with(&mut thread_rng(), ctx.max_tokens, ctx.counter),
+        };
+        GeneratedText::new(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markov_text() {
+        let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+        let generator = MarkovGenerator::builtin();
+        let mut rng = StdRng::seed_from_u64(3);
+        for max_tokens in [1, 16, 100] {
+            let text = generator.generate_with(&mut rng, max_tokens, &counter);
+            assert!(counter.count_tokens(&text) <= max_tokens, "{:?}", text);
+            if max_tokens > 1 {
+                assert!(counter.count_tokens(&text) * 3 >= max_tokens, "{:?}", text);
+                assert!(text.starts_with(' '));
+            }
+            // Every word follows a word it follows in the corpus.
+            for pair in text.split_whitespace().collect::<Vec<_>>().windows(2) {
+                if !ends_sentence(pair[0]) {
+                    assert!(BUILTIN_CORPUS.contains(&format!("{} {}", pair[0], pair[1])));
+                }
+            }
+        }
+        assert_eq!(generator.generate_with(&mut rng, 0, &counter), "");
+
+        let generator = MarkovGenerator::from_corpus("one two. one three.");
+        let text = generator.generate_with(&mut rng, 50, &counter);
+        assert!(text.split_whitespace().all(|word| ["one", "two.", "three."].contains(&word)));
+    }
+}
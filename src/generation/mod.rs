@@ -0,0 +1,10 @@
+pub mod corpus;
+pub mod deterministic;
+pub mod logit_bias;
+pub mod markov;
+pub mod text_generator;
+pub use corpus::{CorpusEntry, CorpusGenerator};
+pub use deterministic::DeterministicGenerator;
+pub use logit_bias::LogitBias;
+pub use markov::MarkovGenerator;
+pub use text_generator::{GeneratedText, GenerationContext, SharedGenerator, TextGenerator};
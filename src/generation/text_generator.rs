@@ -0,0 +1,130 @@
+//! Producing the text of generated completions.
+//!
+//! Requests no stub matches are completed with text from a
+//! [`TextGenerator`]. The built-in [`GenerationStrategy`] variants are
+//! generators; users plug in their own, e.g. to fill completions with
+//! domain-appropriate fake content, with
+//! [`MockConfig::with_text_generator`](crate::config::MockConfig::with_text_generator):
+//!
+//! ```
+//! use openai_mock::config::MockConfig;
+//! use openai_mock::generation::{GeneratedText, GenerationContext};
+//!
+//! let config = MockConfig::default().with_text_generator(|ctx: &GenerationContext| {
+//!     GeneratedText::new(format!("Ticket summary for {:?}", ctx.prompt))
+//! });
+//! ```
+//!
+//! Whatever a generator returns is cut to the request's `max_tokens`.
+
+use crate::config::GenerationStrategy;
+use crate::generation::{DeterministicGenerator, MarkovGenerator};
+use crate::models::completion::CompletionRequest;
+use crate::utils::token_counting::TokenCounter;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a generator knows about the completion it produces.
+#[derive(Clone, Copy)]
+pub struct GenerationContext<'a> {
+    /// The request being completed.
+    pub request: &'a CompletionRequest,
+
+    /// The id of the model the request is served as.
+    pub model: &'a str,
+
+    /// The text of the prompt being completed, token ID prompts decoded.
+    pub prompt: &'a str,
+
+    /// Position of `prompt` in a batched request, `0` otherwise.
+    pub prompt_index: usize,
+
+    /// Which of the prompt's `n` choices (or `best_of` candidates) this
+    /// completion is for. Generators vary their text with it, so that
+    /// choices differ.
+    pub choice_index: usize,
+
+    /// Most tokens the completion may have.
+    pub max_tokens: u32,
+
+    /// The tokenizer of the model.
+    pub counter: &'a TokenCounter,
+}
+
+/// The text a generator produced for one completion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeneratedText {
+    /// The completion, not including any echoed prompt.
+    pub text: String,
+}
+
+impl GeneratedText {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+}
+
+impl From<String> for GeneratedText {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&str> for GeneratedText {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Produces the text of generated completions.
+pub trait TextGenerator: Send + Sync {
+    /// The completion of the prompt described by `ctx`.
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText;
+}
+
+impl<F> TextGenerator for F
+where
+    F: Fn(&GenerationContext<'_>) -> GeneratedText + Send + Sync,
+{
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        self(ctx)
+    }
+}
+
+impl TextGenerator for GenerationStrategy {
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        match self {
+            GenerationStrategy::Echo => GeneratedText::default(),
+            GenerationStrategy::Fixed { text } => GeneratedText::new(text.as_str()),
+            GenerationStrategy::Markov { seed } => {
+                MarkovGenerator::builtin().with_seed(*seed).generate(ctx)
+            }
+            GenerationStrategy::Deterministic { seed } => {
+                DeterministicGenerator::new(*seed).generate(ctx)
+            }
+        }
+    }
+}
+
+/// A user-supplied [`TextGenerator`], shared by every request of a mock
+/// instance.
+#[derive(Clone)]
+pub struct SharedGenerator(Arc<dyn TextGenerator>);
+
+impl SharedGenerator {
+    pub fn new(generator: impl TextGenerator + 'static) -> Self {
+        Self(Arc::new(generator))
+    }
+}
+
+impl fmt::Debug for SharedGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedGenerator")
+    }
+}
+
+impl TextGenerator for SharedGenerator {
+    fn generate(&self, ctx: &GenerationContext<'_>) -> GeneratedText {
+        self.0.generate(ctx)
+    }
+}
@@ -0,0 +1,216 @@
+//! Handlers for the non-OpenAI administrative routes of the mock.
+
+use crate::capabilities::{capabilities, emulated_api_version};
+use crate::config::{Endpoint, RouteConfig, UsageTier};
+#[cfg(feature = "actix-web")]
+use crate::service::actix::{serve_actix, to_actix_response};
+use crate::service::{empty_response, json_body, json_response, path_param, MockRequest, MockResponse};
+use crate::state::MockState;
+#[cfg(feature = "actix-web")]
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Handles `GET /__admin/capabilities`.
+///
+/// Returns the emulated API version and the fidelity of every emulated
+/// endpoint, so external test harnesses can gate tests on emulation
+/// fidelity.
+pub async fn get_capabilities() -> MockResponse {
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "api_version": emulated_api_version(),
+            "crate_version": env!("CARGO_PKG_VERSION"),
+            "endpoints": capabilities(),
+        }),
+    )
+}
+
+/// Serves [`get_capabilities`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn capabilities_handler() -> HttpResponse {
+    to_actix_response(get_capabilities().await)
+}
+
+/// Handles `GET /__admin/routes`, returning the effective settings of
+/// every endpoint.
+pub async fn list_routes(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    json_response(StatusCode::OK, &state.routes.all())
+}
+
+/// Serves [`list_routes`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn list_routes_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, list_routes).await
+}
+
+/// The endpoint named by the `{endpoint}` segment of the path.
+///
+/// # Returns
+///
+/// `Err` with an empty `404` response for an unknown endpoint.
+fn endpoint_param(http_req: &MockRequest) -> Result<Endpoint, MockResponse> {
+    let name = path_param(http_req, "endpoint").unwrap_or_default();
+    serde_json::from_value(Value::from(name)).map_err(|_| empty_response(StatusCode::NOT_FOUND))
+}
+
+/// Handles `PUT /__admin/routes/{endpoint}`, overriding the latency, error
+/// rate or canned response of an endpoint while the server is running.
+///
+/// Returns the effective settings of the endpoint.
+pub async fn set_route(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let endpoint = match endpoint_param(&http_req) {
+        Ok(endpoint) => endpoint,
+        Err(rejected) => return rejected,
+    };
+    let route: RouteConfig = match json_body(&http_req) {
+        Ok(route) => route,
+        Err(rejected) => return rejected,
+    };
+    state.routes.set(endpoint, route);
+    json_response(StatusCode::OK, &state.routes.get(endpoint))
+}
+
+/// Serves [`set_route`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn set_route_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, set_route).await
+}
+
+/// Handles `DELETE /__admin/routes/{endpoint}`, restoring the configured
+/// settings of an endpoint.
+pub async fn clear_route(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let endpoint = match endpoint_param(&http_req) {
+        Ok(endpoint) => endpoint,
+        Err(rejected) => return rejected,
+    };
+    state.routes.clear(endpoint);
+    json_response(StatusCode::OK, &state.routes.get(endpoint))
+}
+
+/// Serves [`clear_route`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn clear_route_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, clear_route).await
+}
+
+/// Body of `PUT /__admin/keys/{key}/tier`.
+#[derive(Debug, Deserialize)]
+pub struct SetTierRequest {
+    pub tier: UsageTier,
+}
+
+fn key_tier(state: &MockState, key: &str) -> MockResponse {
+    let tier = state.key_tiers.tier(key);
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "key": key,
+            "tier": tier,
+            "limits": tier.map(|tier| tier.limits()),
+        }),
+    )
+}
+
+/// Handles `GET /__admin/keys/{key}/tier`, returning the usage tier of an
+/// API key and its limits.
+pub async fn get_key_tier(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    key_tier(&state, path_param(&http_req, "key").unwrap_or_default())
+}
+
+/// Serves [`get_key_tier`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn get_key_tier_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, get_key_tier).await
+}
+
+/// Handles `PUT /__admin/keys/{key}/tier`, moving an API key to another
+/// usage tier while the server is running.
+pub async fn set_key_tier(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let body: SetTierRequest = match json_body(&http_req) {
+        Ok(body) => body,
+        Err(rejected) => return rejected,
+    };
+    let key = path_param(&http_req, "key").unwrap_or_default();
+    state.key_tiers.set_tier(key, body.tier);
+    key_tier(&state, key)
+}
+
+/// Serves [`set_key_tier`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn set_key_tier_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, set_key_tier).await
+}
+
+/// Body of `PUT /__admin/scenarios/{scenario}`.
+#[derive(Debug, Deserialize)]
+pub struct SetScenarioStateRequest {
+    pub state: String,
+}
+
+/// Handles `GET /__admin/scenarios`, returning the state of every scenario
+/// that has left its initial `Started` state.
+pub async fn list_scenarios(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    json_response(StatusCode::OK, &state.scenario_states.all())
+}
+
+/// Serves [`list_scenarios`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn list_scenarios_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, list_scenarios).await
+}
+
+/// Handles `PUT /__admin/scenarios/{scenario}`, moving a scenario to the
+/// state in the body.
+pub async fn set_scenario_state(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let body: SetScenarioStateRequest = match json_body(&http_req) {
+        Ok(body) => body,
+        Err(rejected) => return rejected,
+    };
+    let scenario = path_param(&http_req, "scenario").unwrap_or_default();
+    state.scenario_states.set(scenario, &body.state);
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "scenario": scenario,
+            "state": state.scenario_states.get(scenario),
+        }),
+    )
+}
+
+/// Serves [`set_scenario_state`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn set_scenario_state_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, set_scenario_state).await
+}
@@ -0,0 +1,98 @@
+//! Enforces the API key authentication configured in `MockConfig::auth`.
+
+use crate::config::{AuthConfig, KeyProfile, MockConfig};
+use crate::scenario::InjectedError;
+use crate::service::{MockRequest, MockResponse};
+use crate::state::MockState;
+use http::header::AUTHORIZATION;
+use http::HeaderMap;
+
+/// The API key sent as `Authorization: Bearer <key>`, if any.
+pub fn bearer_key(http_req: &MockRequest) -> Option<&str> {
+    bearer_token(http_req.headers())
+}
+
+/// The API key in the `Authorization: Bearer <key>` header of `headers`,
+/// if any.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Checks that the request carries one of the configured API keys as
+/// `Authorization: Bearer <key>`. Always passes when authentication is
+/// disabled.
+///
+/// # Returns
+///
+/// `Err` with the `401` response the real API returns for a missing or
+/// unknown key.
+pub fn check_api_key(http_req: &MockRequest, config: &MockConfig) -> Result<(), MockResponse> {
+    authenticate(http_req.headers(), &config.auth).map_err(|error| error.to_response())
+}
+
+/// Checks that `headers` carry one of the keys `auth` accepts, as
+/// [`check_api_key`] does for a whole request.
+///
+/// # Returns
+///
+/// `Err` with the `401` error for a missing key, or the `401
+/// invalid_api_key` error for an unknown one.
+pub fn authenticate(headers: &HeaderMap, auth: &AuthConfig) -> Result<(), InjectedError> {
+    if !auth.is_enabled() {
+        return Ok(());
+    }
+
+    match bearer_token(headers) {
+        Some(key) if auth.accepts(key) => Ok(()),
+        Some(key) => Err(InjectedError::invalid_api_key(key)),
+        None => Err(InjectedError::missing_api_key()),
+    }
+}
+
+/// The profile of the request's API key, if it has one.
+pub fn key_profile<'a>(http_req: &MockRequest, config: &'a MockConfig) -> Option<&'a KeyProfile> {
+    config.auth.profile(bearer_key(http_req)?)
+}
+
+/// Checks that the request's API key may use `model`.
+///
+/// # Returns
+///
+/// `Err` with the `404 model_not_found` error the real API returns for a
+/// model the key has no access to.
+pub fn check_key_allows_model(
+    http_req: &MockRequest,
+    config: &MockConfig,
+    model: &str,
+) -> Result<(), MockResponse> {
+    match key_profile(http_req, config) {
+        Some(profile) if !profile.allows_model(model) => {
+            Err(InjectedError::model_not_found(model).to_response())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the request's API key has budget left (see
+/// [`KeyProfile::budget`]).
+///
+/// # Returns
+///
+/// `Err` with the `429 insufficient_quota` error the real API returns once
+/// an account runs out of credit.
+pub fn check_quota(http_req: &MockRequest, state: &MockState) -> Result<(), MockResponse> {
+    let Some(key) = bearer_key(http_req) else {
+        return Ok(());
+    };
+    match state.config.auth.profile(key).and_then(|profile| profile.budget) {
+        Some(budget) if state.key_spend.spent(key) >= budget => {
+            Err(InjectedError::insufficient_quota().to_response())
+        }
+        _ => Ok(()),
+    }
+}
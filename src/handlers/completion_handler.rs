@@ -1,129 +1,499 @@
 //! This module handles HTTP requests for generating text completions.
 //!
-//! It provides the `completions_handler` function, which processes incoming
-//! completion requests, validates them, and returns appropriate responses.
+//! It provides the `completions` function, which processes incoming
+//! completion requests, validates them, and returns appropriate responses,
+//! and `completions_handler`, which serves it to actix-web.
 
-use crate::models::{CompletionRequest, CompletionResponse, Usage};
+use crate::handlers::{
+    advertise_rate_limits, bearer_key, check_api_key, check_chaos, check_key_allows_model,
+    check_model_supports, check_organization_access, check_overloaded, check_quota,
+    check_rate_limit_fault, check_route_error, finish_request, inject_response_fault,
+    malform_response, receive_request, record_response, run_request_hook, run_response_hook,
+    simulate_latency, unmatched_request,
+};
+use crate::hooks::StreamEndSummary;
+use crate::models::completion::Choice;
+use crate::models::{CompletionRequest, CompletionResponse, ErrorResponse, OpenAIError, Usage};
+use crate::scenario::{apply_rules, NormalizedRequest};
+#[cfg(feature = "actix-web")]
+use crate::service::actix::serve_actix;
+use crate::service::{json_body, json_response, MockRequest, MockResponse};
+use crate::state::{MockState, ModelSpec, RequestOutcome};
 use crate::validators::{
     validate_temperature, validate_top_p, validate_n, validate_max_tokens,
     validate_presence_penalty, validate_frequency_penalty, validate_best_of,
-    validate_logprobs, validate_stop,
+    validate_logprobs, validate_stop, validate_logit_bias,
 };
 use crate::validators::StopSequence;
 use crate::validators::validate_required_fields;
-use actix_web::{web, HttpResponse, Responder};
-use serde_json::json;
+use crate::validators::{check_prompt_tokens, prompt_token_counts, validate_prompt, ItemError};
+#[cfg(feature = "actix-web")]
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::StatusCode;
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
 use crate::utils::utils::{generate_uuid, get_current_timestamp};
-use crate::utils::choices::create_choices;
+use crate::config::{DuplicateChoices, Endpoint};
+use crate::generation::{DeterministicGenerator, GenerationContext, LogitBias, TextGenerator};
+use crate::utils::choices::{
+    add_logprobs, append_best_of, append_completion, create_choices, make_choices_distinct,
+    PARALLEL_CHOICES,
+};
+use crate::streaming::{completion_events, sse_response, StreamEnd, StreamOptions};
+use crate::utils::token_counting::TokenCounter;
 
 /// Handles the `/completions` endpoint for generating text completions.
 ///
 /// This asynchronous function processes a `CompletionRequest`, validates
 /// the required and optional fields, generates completion choices, and
 /// constructs a `CompletionResponse`. In case of validation errors, it
-/// returns a `BadRequest` response with relevant error messages.
+/// returns a `BadRequest` response with relevant error messages; requests
+/// without a valid API key (when authentication is enabled) or from an
+/// organization or API key without access to the model are rejected first, and
+/// requests matching a scenario rule or a response fixture get the canned
+/// response. Requests no stub matches are completed, or answered `404`,
+/// as set by `MockConfig::unmatched`. In high-throughput mode
+/// (`MockConfig::high_throughput`) every request gets the precomputed
+/// response instead, without any of the above. The model's entry in the `ModelRegistry` decides whether it
+/// may be used here, its latency, error rate, tokenizer and generated
+/// text; the endpoint's settings, which may be changed at runtime, apply
+/// where the model has none.
 ///
 /// # Parameters
 ///
-/// - `req`: A JSON payload deserialized into `CompletionRequest`.
+/// - `state`: The shared `MockState`, providing configuration and the
+///   request history.
+/// - `http_req`: The HTTP request, used for request history, mirroring
+///   and lifecycle hooks. Its JSON body is deserialized into a
+///   `CompletionRequest`, while scenario rules, fixtures and the request
+///   history see it as sent, unknown fields included.
 ///
 /// # Returns
 ///
-/// An `HttpResponse` containing the `CompletionResponse` on success or
-/// an error message on failure.
+/// A response containing the `CompletionResponse` on success or an error
+/// message on failure. When `stream` is set, the response is sent as
+/// server-sent events instead.
+pub async fn completions(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    if let Some(precomputed) = &state.precomputed {
+        return precomputed.respond(wants_stream(&http_req));
+    }
+    let started = Instant::now();
+    let body: Value = match json_body(&http_req) {
+        Ok(body) => body,
+        Err(rejected) => return rejected,
+    };
+    let req = match CompletionRequest::deserialize(&body) {
+        Ok(req) => req,
+        Err(e) => {
+            return ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                OpenAIError::invalid_request(format!("Invalid request body: {}", e)),
+            )
+            .to_response()
+        }
+    };
+    let record_id = receive_request(&http_req, &state, body.clone());
+
+    let mut response = match run_request_hook(&state, record_id, &http_req, &body).await {
+        Some(response) => {
+            finish_request(&state, record_id, &response);
+            response
+        }
+        None => serve(&http_req, &req, &state, &body, record_id).await,
+    };
+    if !response.status().is_success() {
+        state.history.finish(
+            record_id,
+            RequestOutcome::Failed { status: response.status().as_u16() },
+            None,
+        );
+    }
+    response = malform_response(&state, response).await;
+    advertise_rate_limits(&http_req, &state, record_id, &mut response);
+
+    response = record_response(&state, record_id, response);
+    run_response_hook(&state, record_id, &response, started).await;
+    response
+}
+
+/// Whether the body of `http_req` sets `stream`, read without the rest of
+/// the request for high-throughput mode.
+fn wants_stream(http_req: &MockRequest) -> bool {
+    #[derive(Deserialize)]
+    struct StreamFlag {
+        #[serde(default)]
+        stream: Option<bool>,
+    }
+    serde_json::from_slice::<StreamFlag>(http_req.body())
+        .is_ok_and(|flag| flag.stream == Some(true))
+}
+
+/// Serves [`completions`] to actix-web.
+#[cfg(feature = "actix-web")]
 pub async fn completions_handler(
-    req: web::Json<CompletionRequest>,
-) -> impl Responder {
-    // Validate the required fields using the validator
-    if let Err(validation_error) = validate_required_fields(&req) {
-        return HttpResponse::BadRequest().json(json!({
-            "error": {
-                "message": validation_error.to_string(),
-                "type": "invalid_request_error",
-                "param": "model",
-                "code": null,
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, completions).await
+}
+
+/// Applies the simulated latency, access checks and canned responses, then
+/// produces the completion.
+async fn serve(
+    http_req: &MockRequest,
+    req: &CompletionRequest,
+    state: &Arc<MockState>,
+    body: &Value,
+    record_id: usize,
+) -> MockResponse {
+    let model = state.models.resolve(&req.model);
+    let route = state.routes.get(Endpoint::Completions);
+    simulate_latency(http_req, state, &route, Some(&model)).await;
+    inject_response_fault(state).await;
+
+    if let Err(denied) = check_api_key(http_req, &state.config)
+        .and_then(|()| check_organization_access(http_req, &state.config, &req.model))
+        .and_then(|()| check_key_allows_model(http_req, &state.config, &req.model))
+        .and_then(|()| check_quota(http_req, state))
+        .and_then(|()| check_model_supports(&model, Endpoint::Completions))
+        .and_then(|()| check_rate_limit_fault(http_req, state, Some(&req.model)))
+        .and_then(|()| check_overloaded(state, Some(&req.model)))
+        .and_then(|()| check_chaos(http_req, state, Some(&req.model)))
+        .and_then(|()| check_route_error(http_req, state, &route, Some(&model)))
+    {
+        return denied;
+    }
+
+    let request = NormalizedRequest::from_http(http_req, body.clone());
+    let canned = match &route.respond {
+        Some(canned) => Some(canned.to_response(body)),
+        None => {
+            apply_rules(
+                &state.config.rules,
+                &state.rule_calls,
+                &state.scenario_states,
+                &request,
+            )
+            .await
+        }
+    };
+    let canned = canned
+        .or_else(|| {
+            state
+                .config
+                .responders
+                .response(Endpoint::Completions, &request)
+                .map(|response| response.to_response(body))
+        })
+        .or_else(|| state.config.fixtures.respond(http_req.uri().path(), body));
+    match canned {
+        Some(canned) => {
+            finish_request(state, record_id, &canned);
+            canned
+        }
+        None => {
+            let counter = TokenCounter::for_encoding(model.encoding);
+            let prompt = req.parsed_prompt();
+            let prompts = prompt
+                .as_ref()
+                .map(|prompt| prompt.texts(&counter))
+                .unwrap_or_default();
+            let prompts: Vec<&str> = prompts.iter().map(|text| text.as_ref()).collect();
+            if state.config.generation.prompt_response(&prompts).is_none() {
+                if let Some(unmatched) = unmatched_request(http_req, state, record_id) {
+                    return unmatched;
+                }
             }
-        }));
+            complete(req, state, &model, bearer_key(http_req), record_id)
+        }
+    }
+}
+
+/// Validates the request and produces the completion response for `model`,
+/// recording its usage under `record_id` in the request history and
+/// charging it to `api_key`.
+fn complete(
+    req: &CompletionRequest,
+    state: &Arc<MockState>,
+    model: &ModelSpec,
+    api_key: Option<&str>,
+    record_id: usize,
+) -> MockResponse {
+    // Validate the required fields using the validator
+    if let Err(validation_error) = validate_required_fields(req) {
+        return validation_error.to_response();
+    }
+
+    // Validate every prompt item, reporting each failure by index
+    if let Err(item_errors) = validate_prompt(req.prompt.as_ref()) {
+        return item_errors_response(&item_errors);
     }
 
     // Validate optional fields
     let validators = [
-        ("temperature", validate_temperature(req.temperature)),
-        ("top_p", validate_top_p(req.top_p)),
-        ("n", validate_n(req.n)),
-        ("max_tokens", validate_max_tokens(req.max_tokens)),
-        ("presence_penalty", validate_presence_penalty(req.presence_penalty)),
-        ("frequency_penalty", validate_frequency_penalty(req.frequency_penalty)),
-        ("logprobs", validate_logprobs(req.logprobs)),
-        ("stop", validate_stop(req.stop.clone())),
-        ("best_of", validate_best_of(req.best_of, req.n)),
+        validate_temperature(req.temperature),
+        validate_top_p(req.top_p),
+        validate_n(req.n),
+        validate_max_tokens(req.max_tokens),
+        validate_presence_penalty(req.presence_penalty),
+        validate_frequency_penalty(req.frequency_penalty),
+        validate_logprobs(req.logprobs),
+        validate_stop(req.stop.as_ref()),
+        validate_best_of(req.best_of, req.n, req.stream),
+        validate_logit_bias(req.logit_bias.as_ref()),
     ];
 
-    // Check each validation result
-    for (field, result) in validators {
-        if let Err(validation_error) = result {
-            return HttpResponse::BadRequest().json(json!({
-                "error": {
-                    "message": validation_error,
-                    "type": "invalid_request_error",
-                    "param": field,
-                    "code": null,
-                }
-            }));
-        }
+    // Report the first failure, with its param and code
+    if let Some(validation_error) = validators.into_iter().find_map(Result::err) {
+        return validation_error.to_response();
     }
 
     // Mock processing logic
-    let prompt = req.prompt.clone().unwrap_or_default();
     let max_tokens = req.max_tokens.unwrap_or(16);
+    let token_counter = TokenCounter::for_encoding(model.encoding);
+
+    // Reject prompts that do not fit in the model's context window
+    let prompt_counts = prompt_token_counts(req.prompt.as_ref(), &token_counter);
+    if let Err(error) = check_prompt_tokens(&prompt_counts, max_tokens, model.context_window) {
+        return error.to_response();
+    }
+
     let n = req.n.unwrap_or(1);
+    // With `best_of`, more candidates are generated than returned.
+    let best_of = req.best_of.unwrap_or(n);
     let echo = req.echo.unwrap_or(false);
     let logprobs = req.logprobs;
 
-    let stop_sequences = match &req.stop {
-        Some(StopSequence::Single(s)) => vec![s.clone()],
-        Some(StopSequence::Multiple(v)) => v.clone(),
-        None => Vec::new(),
+    let stop_sequences: &[String] = match &req.stop {
+        Some(StopSequence::Single(s)) => std::slice::from_ref(s),
+        Some(StopSequence::Multiple(v)) => v,
+        None => &[],
     };
 
-    let choices = create_choices(
-        n,
-        &prompt.to_string(),
-        &stop_sequences,
-        max_tokens,
-        echo,
-        logprobs,
-        &req.model
-    );
+    // Token ID prompts are decoded, so they echo and match like text.
+    let prompt = req.parsed_prompt();
+    let mut prompts = prompt
+        .as_ref()
+        .map(|prompt| prompt.texts(&token_counter))
+        .unwrap_or_default();
+    if prompts.is_empty() {
+        prompts.push(Cow::Borrowed(""));
+    }
 
+    // Each prompt of a batch gets its own `n` choices, indexed in prompt
+    // order as the API does.
+    let generator = model.text_generator(&state.config.generation);
+    let logit_bias = req
+        .logit_bias
+        .as_ref()
+        .map(|bias| LogitBias::new(bias, &token_counter))
+        .unwrap_or_default();
+    let mut choices = Vec::with_capacity(prompts.len() * n.max(0) as usize);
+    let mut completion_tokens = 0;
+    for (prompt_index, prompt_text) in prompts.iter().enumerate() {
+        let prompt_text = prompt_text.as_ref();
+        let mut prompt_choices = create_choices(n, prompt_text, echo);
+        let generate = |choice_index| match state.config.generation.prompt_response(&[prompt_text]) {
+            Some(text) => text.to_string(),
+            None => {
+                let generated = generator.generate(&GenerationContext {
+                    request: req,
+                    model: &model.id,
+                    prompt: prompt_text,
+                    prompt_index,
+                    choice_index,
+                    max_tokens,
+                    counter: &token_counter,
+                });
+                logit_bias.apply(generated.text, max_tokens, &token_counter)
+            }
+        };
+        if best_of > n {
+            let candidates: Vec<String> = (0..best_of as usize).map(generate).collect();
+            completion_tokens += append_best_of(
+                &mut prompt_choices,
+                &candidates,
+                max_tokens,
+                stop_sequences,
+                &token_counter,
+            );
+        } else {
+            // Each choice is generated on its own, so they differ.
+            let complete = |(choice_index, choice): (usize, &mut Choice)| {
+                append_completion(
+                    std::slice::from_mut(choice),
+                    &generate(choice_index),
+                    max_tokens,
+                    stop_sequences,
+                    &token_counter,
+                );
+            };
+            if prompt_choices.len() >= PARALLEL_CHOICES {
+                prompt_choices.par_iter_mut().enumerate().for_each(complete);
+            } else {
+                prompt_choices.iter_mut().enumerate().for_each(complete);
+            }
+        }
+        if state.config.generation.duplicate_choices == DuplicateChoices::Forbid {
+            // A repeated text is generated again for a later choice index,
+            // then as seeded prose for strategies that ignore the index.
+            make_choices_distinct(&mut prompt_choices, |choice, attempt| {
+                let choice_index = choice.index as usize;
+                let text = if attempt == 1 {
+                    generate(choice_index + n as usize)
+                } else {
+                    let generated = DeterministicGenerator::new(attempt as u64).generate(&GenerationContext {
+                        request: req,
+                        model: &model.id,
+                        prompt: prompt_text,
+                        prompt_index,
+                        choice_index,
+                        max_tokens,
+                        counter: &token_counter,
+                    });
+                    logit_bias.apply(generated.text, max_tokens, &token_counter)
+                };
+                let mut fresh = Choice {
+                    index: choice.index,
+                    ..create_choices(1, prompt_text, echo).remove(0)
+                };
+                append_completion(
+                    std::slice::from_mut(&mut fresh),
+                    &text,
+                    max_tokens,
+                    stop_sequences,
+                    &token_counter,
+                );
+                *choice = fresh;
+            });
+        }
+        if let Some(logprobs) = logprobs {
+            add_logprobs(&mut prompt_choices, logprobs, &token_counter);
+        }
+
+        let echoed = echo.then_some(prompt_text);
+        completion_tokens += self::completion_tokens(&prompt_choices, echoed, &token_counter);
+        for mut choice in prompt_choices {
+            choice.index += prompt_index as i32 * n;
+            choices.push(choice);
+        }
+    }
+
+    let prompt_tokens = prompt_counts.iter().sum();
     let response = CompletionResponse {
         id: format!("cmpl-mock-id-{}", generate_uuid()),
         object: "text_completion".to_string(),
         created: get_current_timestamp().timestamp() as u64,
         model: req.model.clone(),
         choices,
-        usage: Usage {
-            prompt_tokens: count_tokens(&prompt.to_string()),
-            completion_tokens: max_tokens,
-            total_tokens: count_tokens(&prompt.to_string()) + max_tokens,
-        },
+        usage: Usage::new(prompt_tokens, completion_tokens),
     };
-    HttpResponse::Ok().json(response)
+
+    if req.stream.unwrap_or(false) {
+        let streaming = &state.config.streaming;
+        let Some(permit) = state.streams.try_acquire(streaming.max_concurrent_streams) else {
+            return ErrorResponse::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                OpenAIError::new(
+                    "Too many concurrent streams. Please retry after an active stream finishes.",
+                    "requests",
+                )
+                .code("rate_limit_exceeded"),
+            )
+            .to_response();
+        };
+
+        let prompt_tokens = response.usage.prompt_tokens;
+        let history_state = state.clone();
+        let api_key = api_key.map(str::to_string);
+        let model = model.clone();
+        let on_end = move |end: StreamEnd| {
+            history_state.config.hooks.spawn_stream_end(StreamEndSummary {
+                id: record_id,
+                completed: end.completed,
+                tokens_sent: end.tokens_sent,
+            });
+            let outcome = if end.completed {
+                RequestOutcome::Completed
+            } else {
+                RequestOutcome::Cancelled
+            };
+            let usage = Usage::new(prompt_tokens, end.tokens_sent);
+            let total_tokens = usage.total_tokens;
+            history_state.history.finish(record_id, outcome, Some(usage));
+            if let Some(key) = &api_key {
+                history_state.key_spend.charge(key, model.cost(total_tokens));
+            }
+        };
+
+        let faults = state.faults.get();
+        let stream_fault = faults
+            .stream
+            .or_else(|| faults.chaos.and_then(|chaos| chaos.stream_fault()));
+        return sse_response(
+            completion_events(response, token_counter, streaming.granularity),
+            StreamOptions {
+                fault: stream_fault,
+                chunk_delay: streaming.chunk_delay,
+                keep_alive: streaming.keep_alive,
+                on_end: Some(Box::new(on_end)),
+                permit: Some(permit),
+                fair_scheduling: streaming.fair_scheduling,
+            },
+        );
+    }
+
+    state.history.finish(record_id, RequestOutcome::Completed, Some(response.usage.clone()));
+    if let Some(key) = api_key {
+        state.key_spend.charge(key, model.cost(response.usage.total_tokens));
+    }
+    json_response(StatusCode::OK, &response)
 }
 
-/// Counts the number of tokens in a given text.
-///
-/// This is a mock implementation that simply counts whitespace-separated
-/// words. In a real-world scenario, a proper tokenizer should be used.
-///
-/// # Parameters
-///
-/// - `text`: The input string to tokenize.
+/// Builds a `BadRequest` response for per-item validation failures.
 ///
-/// # Returns
-///
-/// The number of tokens as a `u32`.
-fn count_tokens(text: &str) -> u32 {
-    // This is a placeholder. In a real scenario, you might use a tokenizer.
-    text.split_whitespace().count() as u32
-}
\ No newline at end of file
+/// The first failure is reported in the standard `error` envelope so that
+/// ordinary clients keep working, and every failure is listed under
+/// `errors`, each with its indexed `param` (e.g. `prompt[3]`).
+fn item_errors_response(errors: &[ItemError]) -> MockResponse {
+    let to_error = |error: &ItemError| {
+        OpenAIError::invalid_request(error.message.as_str())
+            .param(error.param.as_str())
+            .code(error.code.as_str())
+    };
+
+    json_response(
+        StatusCode::BAD_REQUEST,
+        &json!({
+            "error": to_error(&errors[0]),
+            "errors": errors.iter().map(to_error).collect::<Vec<_>>(),
+        }),
+    )
+}
+
+/// The completion tokens of the `choices` of one prompt, summed over every
+/// choice as the API does for `n > 1`. The `echoed` prompt at the start of
+/// each text is not part of the completion.
+fn completion_tokens(choices: &[Choice], echoed: Option<&str>, counter: &TokenCounter) -> u32 {
+    let mut total = 0;
+    let mut previous: Option<(&str, u32)> = None;
+    for choice in choices {
+        let text = match echoed {
+            Some(prompt) => choice.text.strip_prefix(prompt).unwrap_or_default(),
+            None => choice.text.as_str(),
+        };
+        // Choices usually share their text; count it once.
+        let tokens = match previous {
+            Some((previous_text, tokens)) if previous_text == text => tokens,
+            _ => counter.count_tokens(text),
+        };
+        previous = Some((text, tokens));
+        total += tokens;
+    }
+    total
+}
@@ -0,0 +1,189 @@
+//! Handlers for the runtime control API under `/__mock`, which lets
+//! external test harnesses in any language reconfigure and inspect a
+//! running mock over HTTP.
+
+use crate::config::{Endpoint, FaultConfig, RouteConfig};
+#[cfg(feature = "actix-web")]
+use crate::service::actix::serve_actix;
+use crate::service::{empty_response, json_body, json_response, MockRequest, MockResponse};
+use crate::state::MockState;
+#[cfg(feature = "actix-web")]
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Body of `PATCH /__mock/config`. Only the settings that can change while
+/// the server is running are accepted; omitted ones are left as they are.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigPatch {
+    /// Overrides per endpoint, as with `PUT /__admin/routes/{endpoint}`.
+    #[serde(default)]
+    pub routes: BTreeMap<Endpoint, RouteConfig>,
+
+    /// Replacement fault settings, as with `PUT /__mock/faults`.
+    #[serde(default)]
+    pub faults: Option<FaultConfig>,
+}
+
+fn effective_config(state: &MockState) -> MockResponse {
+    let mut config = state.config.clone();
+    config.routes = state.routes.all();
+    config.faults = state.faults.get();
+    json_response(StatusCode::OK, &config)
+}
+
+/// Handles `GET /__mock/config`, returning the configuration in effect,
+/// including the settings changed at runtime.
+pub async fn get_config(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    effective_config(&state)
+}
+
+/// Serves [`get_config`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn get_config_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, get_config).await
+}
+
+/// Handles `PATCH /__mock/config`, applying a [`ConfigPatch`].
+///
+/// Returns the configuration in effect afterwards.
+pub async fn patch_config(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let patch: ConfigPatch = match json_body(&http_req) {
+        Ok(patch) => patch,
+        Err(rejected) => return rejected,
+    };
+    for (endpoint, route) in patch.routes {
+        state.routes.set(endpoint, route);
+    }
+    if let Some(faults) = patch.faults {
+        state.faults.set(faults);
+    }
+    effective_config(&state)
+}
+
+/// Serves [`patch_config`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn patch_config_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, patch_config).await
+}
+
+/// Handles `GET /__mock/requests`, returning every request received so
+/// far, oldest first.
+pub async fn list_requests(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "object": "list",
+            "data": state.history.all(),
+        }),
+    )
+}
+
+/// Serves [`list_requests`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn list_requests_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, list_requests).await
+}
+
+/// Handles `DELETE /__mock/requests`, forgetting the recorded requests.
+pub async fn clear_requests(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    state.history.clear();
+    empty_response(StatusCode::NO_CONTENT)
+}
+
+/// Serves [`clear_requests`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn clear_requests_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, clear_requests).await
+}
+
+/// Handles `POST /__mock/reset`, returning the mock to the state it
+/// started in (see [`MockState::reset`]).
+pub async fn reset(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    state.reset();
+    empty_response(StatusCode::NO_CONTENT)
+}
+
+/// Serves [`reset`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn reset_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, reset).await
+}
+
+/// Handles `GET /__mock/faults`, returning the fault settings in effect.
+pub async fn get_faults(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    json_response(StatusCode::OK, &state.faults.get())
+}
+
+/// Serves [`get_faults`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn get_faults_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, get_faults).await
+}
+
+/// Handles `PUT /__mock/faults`, replacing the fault settings, e.g.
+/// `{"error_rate": 0.5}` or
+/// `{"stream": {"type": "truncate", "after_chunks": 2}}`.
+pub async fn set_faults(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let faults: FaultConfig = match json_body(&http_req) {
+        Ok(faults) => faults,
+        Err(rejected) => return rejected,
+    };
+    state.faults.set(faults);
+    json_response(StatusCode::OK, &state.faults.get())
+}
+
+/// Serves [`set_faults`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn set_faults_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, set_faults).await
+}
+
+/// Handles `DELETE /__mock/faults`, restoring the configured fault
+/// settings.
+pub async fn clear_faults(state: Arc<MockState>, _http_req: MockRequest) -> MockResponse {
+    state.faults.clear();
+    json_response(StatusCode::OK, &state.faults.get())
+}
+
+/// Serves [`clear_faults`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn clear_faults_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, clear_faults).await
+}
@@ -0,0 +1,275 @@
+//! This module serves endpoints the mock does not emulate from response
+//! fixtures alone, such as the `/v1/chat/completions` or `/v1/embeddings`
+//! fixtures of a preset.
+
+use crate::config::{ChatReply, Endpoint, RouteConfig};
+use crate::extractors::list_query::{ListQuery, DEFAULT_LIST_LIMIT};
+use crate::handlers::{
+    advertise_rate_limits, check_api_key, check_chaos, check_overloaded, check_quota,
+    check_rate_limit_fault, check_route_error, finish_request, inject_response_fault,
+    malform_response, receive_request, record_response, run_request_hook, run_response_hook,
+    unmatched_request,
+};
+use crate::models::{ErrorResponse, OpenAIError, Usage};
+use crate::scenario::NormalizedRequest;
+#[cfg(feature = "actix-web")]
+use crate::service::actix::serve_actix;
+use crate::service::{json_response, MockBody, MockRequest, MockResponse};
+use crate::state::MockState;
+use crate::utils::token_counting::{MessageOverhead, TokenCounter};
+use crate::utils::utils::{generate_uuid, get_current_timestamp};
+use crate::validators::{chat_messages, validate_messages_context_length, validate_n};
+#[cfg(feature = "actix-web")]
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The paths of the endpoints that have response fixtures but are not
+/// emulated by the mock, e.g. `/v1/embeddings`, sorted. Endpoints the
+/// mock emulates serve their fixtures from their own handlers.
+pub fn fixture_paths(state: &MockState) -> Vec<String> {
+    let emulated: Vec<&str> = Endpoint::ALL.iter().map(Endpoint::path).collect();
+    let mut paths: Vec<String> = state
+        .config
+        .fixtures
+        .endpoints()
+        .map(|endpoint| format!("/v1/{}", endpoint))
+        .filter(|path| !emulated.contains(&path.as_str()))
+        .collect();
+    let has_chat_path = paths.iter().any(|path| path == CHAT_PATH);
+    let answers_chat =
+        state.config.generation.chat_reply.is_some() || state.config.responders.has_chat();
+    if answers_chat && !has_chat_path {
+        paths.push(CHAT_PATH.to_string());
+    }
+    paths.sort();
+    paths
+}
+
+/// Path of the chat completions endpoint, answered with the configured
+/// [`ChatReply`] when no fixture matches.
+const CHAT_PATH: &str = "/v1/chat/completions";
+
+/// Handles any request to a fixture-only endpoint.
+///
+/// The fixture is chosen by the `model` of the JSON body, if there is one,
+/// and served verbatim, or rendered with the body as context when it is a
+/// template. Bodies that are not JSON (e.g. multipart uploads) are served
+/// the `default` fixture. `GET /v1/files` listings are filtered and paged by
+/// their query parameters. Chat completion requests are answered by the
+/// chat responder (see [`Responders::chat`](crate::hooks::Responders::chat))
+/// if one is registered, and those no fixture matches get the configured
+/// `generation.chat_reply`, if any. Requests may instead be rejected by the
+/// injected rate limit or once the API key's budget is spent, fail as set
+/// in `faults.error_rates` for the path or by chaos mode, or be rejected
+/// because their chat `messages` exceed the model's context window.
+pub async fn fixture(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let started = Instant::now();
+    let body: Value = serde_json::from_slice(http_req.body()).unwrap_or(Value::Null);
+    let record_id = receive_request(&http_req, &state, body.clone());
+
+    let mut response = match run_request_hook(&state, record_id, &http_req, &body).await {
+        Some(response) => response,
+        None => {
+            inject_response_fault(&state).await;
+            match check_api_key(&http_req, &state.config)
+                .and_then(|()| check_quota(&http_req, &state))
+                .and_then(|()| check_rate_limit_fault(&http_req, &state, body["model"].as_str()))
+                .and_then(|()| check_overloaded(&state, body["model"].as_str()))
+                .and_then(|()| check_chaos(&http_req, &state, body["model"].as_str()))
+                .and_then(|()| check_route_error(&http_req, &state, &RouteConfig::default(), None))
+                .and_then(|()| check_messages_fit(&state, &body))
+            {
+                Err(denied) => denied,
+                Ok(()) => serve_fixture(&http_req, &state, &body, record_id),
+            }
+        }
+    };
+    finish_request(&state, record_id, &response);
+    response = malform_response(&state, response).await;
+    advertise_rate_limits(&http_req, &state, record_id, &mut response);
+    response = record_response(&state, record_id, response);
+    run_response_hook(&state, record_id, &response, started).await;
+    response
+}
+
+/// Serves [`fixture`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn fixture_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, fixture).await
+}
+
+/// Rejects chat messages that do not fit in the model's context window,
+/// before any fixture is served.
+fn check_messages_fit(state: &MockState, body: &Value) -> Result<(), MockResponse> {
+    let (Some(model), Some(messages)) = (body["model"].as_str(), body.get("messages")) else {
+        return Ok(());
+    };
+    let model = state.models.resolve(model);
+    let max_tokens = body["max_completion_tokens"]
+        .as_u64()
+        .or_else(|| body["max_tokens"].as_u64())
+        .map(|max_tokens| max_tokens as u32);
+    validate_messages_context_length(
+        messages,
+        max_tokens,
+        model.context_window,
+        &TokenCounter::for_encoding(model.encoding)
+            .with_message_overhead(MessageOverhead::for_model(&model.id)),
+    )
+    .map_err(|error| error.to_response())
+}
+
+fn serve_fixture(
+    http_req: &MockRequest,
+    state: &MockState,
+    body: &Value,
+    record_id: usize,
+) -> MockResponse {
+    let path = http_req.uri().path();
+    if path == CHAT_PATH {
+        let request = NormalizedRequest::from_http(http_req, body.clone());
+        if let Some(response) = state.config.responders.chat_response(&request) {
+            return response.to_response(body);
+        }
+    }
+    if let Some(response) = state.config.fixtures.respond(path, body) {
+        return page_file_list(http_req, response);
+    }
+    if let Some(unmatched) = unmatched_request(http_req, state, record_id) {
+        return unmatched;
+    }
+    if let (CHAT_PATH, Some(reply)) = (path, &state.config.generation.chat_reply) {
+        let n = body["n"]
+            .as_i64()
+            .map(|n| n.clamp(i32::MIN.into(), i32::MAX.into()) as i32);
+        if let Err(error) = validate_n(n) {
+            return error.to_response();
+        }
+        let n = n.map_or(1, |n| n as u32);
+        return json_response(StatusCode::OK, &chat_reply_body(state, body, reply, n));
+    }
+
+    let message = format!(
+        "No fixture for model '{}' at {} {}.",
+        body["model"].as_str().unwrap_or_default(),
+        http_req.method(),
+        path
+    );
+    ErrorResponse::new(
+        StatusCode::NOT_FOUND,
+        OpenAIError::invalid_request(message).param("model"),
+    )
+    .to_response()
+}
+
+/// Path of the Files API listing.
+const FILES_PATH: &str = "/v1/files";
+
+/// `response` with the files listed by a `GET /v1/files` fixture filtered
+/// by `purpose` and paged by `limit`, `after` and `order`, like the real
+/// listing. Other responses are returned unchanged.
+fn page_file_list(http_req: &MockRequest, response: MockResponse) -> MockResponse {
+    if http_req.method() != Method::GET || http_req.uri().path() != FILES_PATH {
+        return response;
+    }
+    let query = match ListQuery::from_query_str(http_req.uri().query().unwrap_or_default()) {
+        Ok(query) => query,
+        Err(error) => return error.to_response(),
+    };
+    let MockBody::Full(bytes) = response.body() else {
+        return response;
+    };
+    let Ok(mut list) = serde_json::from_slice::<Value>(bytes) else {
+        return response;
+    };
+    let Some(Value::Array(files)) = list.get_mut("data").map(Value::take) else {
+        return response;
+    };
+
+    let files: Vec<Value> = files
+        .into_iter()
+        .filter(|file| {
+            query
+                .purpose
+                .is_none_or(|purpose| file["purpose"] == purpose.as_str())
+        })
+        .collect();
+    let key = |file: &Value| {
+        let created_at = file["created_at"].as_u64().unwrap_or_default();
+        (
+            created_at,
+            file["id"].as_str().unwrap_or_default().to_string(),
+        )
+    };
+    let (files, has_more) = query.page(files, key, DEFAULT_LIST_LIMIT as usize);
+    list["data"] = Value::Array(files);
+    list["has_more"] = Value::Bool(has_more);
+    json_response(response.status(), &list)
+}
+
+/// The `chat.completion` response answering `body` with `reply`, in each
+/// of `n` choices.
+fn chat_reply_body(state: &MockState, body: &Value, reply: &ChatReply, n: u32) -> Value {
+    let model = body["model"].as_str().unwrap_or_default();
+    let spec = state.models.resolve(model);
+    let counter = TokenCounter::for_encoding(spec.encoding)
+        .with_message_overhead(MessageOverhead::for_model(model));
+
+    let tool_calls: Vec<Value> = reply
+        .tool_calls
+        .iter()
+        .map(|call| {
+            json!({
+                "id": format!("call_{}", generate_uuid().replace('-', "")),
+                "type": "function",
+                "function": {"name": call.name, "arguments": call.arguments_json()},
+            })
+        })
+        .collect();
+    let mut message = json!({
+        "role": "assistant",
+        "content": reply.content,
+        "refusal": null,
+    });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    let choices: Vec<Value> = (0..n)
+        .map(|index| {
+            json!({
+                "index": index,
+                "message": message,
+                "logprobs": null,
+                "finish_reason": reply.finish_reason(),
+            })
+        })
+        .collect();
+
+    let content_tokens = reply
+        .content
+        .as_deref()
+        .map_or(0, |content| counter.count_tokens(content));
+    let call_tokens: u32 = reply
+        .tool_calls
+        .iter()
+        .map(|call| counter.count_tokens(&call.name) + counter.count_tokens(&call.arguments_json()))
+        .sum();
+    let reply_tokens = content_tokens + call_tokens;
+    let prompt_tokens = counter.count_messages_tokens(&chat_messages(&body["messages"]));
+    json!({
+        "id": format!("chatcmpl-{}", generate_uuid()),
+        "object": "chat.completion",
+        "created": get_current_timestamp().timestamp(),
+        "model": model,
+        "choices": choices,
+        "usage": Usage::new(prompt_tokens, reply_tokens.saturating_mul(n)),
+    })
+}
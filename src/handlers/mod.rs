@@ -1,2 +1,50 @@
+pub mod admin_handler;
+pub mod auth;
 pub mod completion_handler;
-pub use completion_handler::completions_handler;
\ No newline at end of file
+pub mod control_handler;
+pub mod fixture_handler;
+pub mod models_handler;
+pub mod organization;
+pub mod rate_limit_headers;
+pub mod request_log;
+pub mod route_behavior;
+pub use admin_handler::{
+    clear_route, get_capabilities, get_key_tier, list_routes, list_scenarios, set_key_tier,
+    set_route, set_scenario_state,
+};
+#[cfg(feature = "actix-web")]
+pub use admin_handler::{
+    capabilities_handler, clear_route_handler, get_key_tier_handler, list_routes_handler,
+    list_scenarios_handler, set_key_tier_handler, set_route_handler, set_scenario_state_handler,
+};
+pub use auth::{
+    authenticate, bearer_key, bearer_token, check_api_key, check_key_allows_model, check_quota,
+    key_profile,
+};
+pub use completion_handler::completions;
+#[cfg(feature = "actix-web")]
+pub use completion_handler::completions_handler;
+pub use control_handler::{
+    clear_faults, clear_requests, get_config, get_faults, list_requests, patch_config, reset,
+    set_faults, ConfigPatch,
+};
+#[cfg(feature = "actix-web")]
+pub use control_handler::{
+    clear_faults_handler, clear_requests_handler, get_config_handler, get_faults_handler,
+    list_requests_handler, patch_config_handler, reset_handler, set_faults_handler,
+};
+pub use fixture_handler::{fixture, fixture_paths};
+#[cfg(feature = "actix-web")]
+pub use fixture_handler::fixture_handler;
+pub use models_handler::{check_model_supports, list_models, retrieve_model};
+#[cfg(feature = "actix-web")]
+pub use models_handler::{list_models_handler, retrieve_model_handler};
+pub use organization::check_organization_access;
+pub use rate_limit_headers::advertise_rate_limits;
+pub use request_log::{
+    finish_request, receive_request, record_response, run_request_hook, run_response_hook,
+};
+pub use route_behavior::{
+    check_chaos, check_overloaded, check_rate_limit_fault, check_route_error,
+    inject_response_fault, malform_response, serve_route, simulate_latency, unmatched_request,
+};
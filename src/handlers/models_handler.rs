@@ -0,0 +1,147 @@
+//! This module handles the Models API (`/v1/models`), which is derived
+//! from the instance's `ModelRegistry`.
+
+use crate::config::Endpoint;
+use crate::extractors::ListQuery;
+use crate::handlers::{
+    advertise_rate_limits, finish_request, key_profile, malform_response, receive_request,
+    record_response, run_request_hook, run_response_hook, serve_route,
+};
+use crate::models::{ErrorResponse, ModelList, OpenAIError};
+#[cfg(feature = "actix-web")]
+use crate::service::actix::serve_actix;
+use crate::service::{json_response, path_param, MockRequest, MockResponse};
+use crate::state::{MockState, ModelSpec};
+#[cfg(feature = "actix-web")]
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::StatusCode;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Handles `GET /v1/models`, listing every registered model the API key
+/// may use, newest first, paged by the `limit`, `after` and `order` query
+/// parameters.
+pub async fn list_models(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let started = Instant::now();
+    let record_id = receive_request(&http_req, &state, Value::Null);
+
+    let mut response = match run_request_hook(&state, record_id, &http_req, &Value::Null).await {
+        Some(response) => response,
+        None => {
+            serve_route(&http_req, &state, Endpoint::ListModels, || {
+                let query = match ListQuery::from_query_str(http_req.uri().query().unwrap_or_default()) {
+                    Ok(query) => query,
+                    Err(error) => return error.to_response(),
+                };
+                let profile = key_profile(&http_req, &state.config);
+                let models: Vec<ModelSpec> = state
+                    .models
+                    .models()
+                    .into_iter()
+                    .filter(|model| profile.is_none_or(|profile| profile.allows_model(&model.id)))
+                    .collect();
+                // The real endpoint is not paged, so every model is listed
+                // unless a `limit` is given.
+                let (models, _) = query.page(models, |model| (model.created, model.id.clone()), usize::MAX);
+                json_response(
+                    StatusCode::OK,
+                    &ModelList {
+                        object: "list".to_string(),
+                        data: models.iter().map(ModelSpec::to_model).collect(),
+                    },
+                )
+            })
+            .await
+        }
+    };
+    finish_request(&state, record_id, &response);
+    response = malform_response(&state, response).await;
+    advertise_rate_limits(&http_req, &state, record_id, &mut response);
+    response = record_response(&state, record_id, response);
+    run_response_hook(&state, record_id, &response, started).await;
+    response
+}
+
+/// Serves [`list_models`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn list_models_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, list_models).await
+}
+
+/// Handles `GET /v1/models/{model}`.
+///
+/// Unregistered models, and models the API key may not use, are answered
+/// with the `404 model_not_found` error of the real API.
+pub async fn retrieve_model(state: Arc<MockState>, http_req: MockRequest) -> MockResponse {
+    let started = Instant::now();
+    let record_id = receive_request(&http_req, &state, Value::Null);
+
+    let id = path_param(&http_req, "model").unwrap_or_default();
+    let mut response = match run_request_hook(&state, record_id, &http_req, &Value::Null).await {
+        Some(response) => response,
+        None => {
+            serve_route(&http_req, &state, Endpoint::RetrieveModel, || {
+                model_response(&http_req, &state, id)
+            })
+            .await
+        }
+    };
+    finish_request(&state, record_id, &response);
+    response = malform_response(&state, response).await;
+    advertise_rate_limits(&http_req, &state, record_id, &mut response);
+    response = record_response(&state, record_id, response);
+    run_response_hook(&state, record_id, &response, started).await;
+    response
+}
+
+/// Serves [`retrieve_model`] to actix-web.
+#[cfg(feature = "actix-web")]
+pub async fn retrieve_model_handler(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    serve_actix(http_req, payload, state, retrieve_model).await
+}
+
+fn model_response(http_req: &MockRequest, state: &MockState, id: &str) -> MockResponse {
+    let allowed = key_profile(http_req, &state.config).is_none_or(|profile| profile.allows_model(id));
+    match state.models.get(id).filter(|_| allowed) {
+        Some(model) => json_response(StatusCode::OK, &model.to_model()),
+        None => ErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            OpenAIError::invalid_request(format!("The model '{}' does not exist", id))
+                .param("model")
+                .code("model_not_found"),
+        )
+        .to_response(),
+    }
+}
+
+/// Checks that `model` may be used with `endpoint`.
+///
+/// # Returns
+///
+/// `Err` with the `404` error the real API returns when a model is sent to
+/// an endpoint it does not support.
+pub fn check_model_supports(model: &ModelSpec, endpoint: Endpoint) -> Result<(), MockResponse> {
+    if model.supports(endpoint) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "The model `{}` is not supported in the {} endpoint.",
+        model.id,
+        endpoint.path().trim_start_matches('/')
+    );
+    Err(ErrorResponse::new(
+        StatusCode::NOT_FOUND,
+        OpenAIError::invalid_request(message).param("model"),
+    )
+    .to_response())
+}
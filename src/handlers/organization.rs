@@ -0,0 +1,50 @@
+//! Enforces the simulated organization feature access configured in
+//! `MockConfig::organizations`.
+
+use crate::config::MockConfig;
+use crate::models::{ErrorResponse, OpenAIError};
+use crate::scenario::InjectedError;
+use crate::service::{MockRequest, MockResponse};
+use http::StatusCode;
+
+/// Header selecting the organization a request is made on behalf of.
+pub const ORGANIZATION_HEADER: &str = "OpenAI-Organization";
+
+/// Checks that the requesting organization may call this endpoint with
+/// `model`.
+///
+/// # Returns
+///
+/// `Err` with the permission error the real API returns when access is
+/// denied: `403` for a denied endpoint, `404 model_not_found` for a denied
+/// model.
+pub fn check_organization_access(
+    http_req: &MockRequest,
+    config: &MockConfig,
+    model: &str,
+) -> Result<(), MockResponse> {
+    let Some(organization) = http_req
+        .headers()
+        .get(ORGANIZATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|id| config.organizations.get(id))
+    else {
+        return Ok(());
+    };
+
+    let path = http_req.uri().path();
+    if !organization.allows_endpoint(path) {
+        return Err(ErrorResponse::new(
+            StatusCode::FORBIDDEN,
+            OpenAIError::invalid_request(format!("Your organization does not have access to {}.", path))
+                .code("unsupported_feature"),
+        )
+        .to_response());
+    }
+
+    if !organization.allows_model(model) {
+        return Err(InjectedError::model_not_found(model).to_response());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Advertises the rate limits of the caller's usage tier in the
+//! `x-ratelimit-*` headers the real API sends.
+
+use crate::handlers::bearer_key;
+use crate::service::{MockRequest, MockResponse};
+use crate::state::MockState;
+use http::header::{HeaderName, HeaderValue};
+use std::time::Duration;
+
+/// Adds the rate limit headers of the request's API key to `response`, if
+/// the key has a usage tier, and counts the request and the tokens
+/// recorded for it against the key's current window.
+///
+/// Besides the standard headers, `x-mock-usage-tier` and
+/// `x-mock-max-concurrency` report the tier and its concurrency limit.
+pub fn advertise_rate_limits(
+    http_req: &MockRequest,
+    state: &MockState,
+    record_id: usize,
+    response: &mut MockResponse,
+) {
+    let Some(key) = bearer_key(http_req) else {
+        return;
+    };
+    let Some(tier) = state.key_tiers.tier(key) else {
+        return;
+    };
+
+    let tokens = state
+        .history
+        .get(record_id)
+        .and_then(|record| record.usage)
+        .map_or(0, |usage| usage.total_tokens);
+    let usage = state.key_tiers.record(key, tokens);
+    let limits = tier.limits();
+
+    let reset = format_reset(usage.reset_in);
+    let headers = [
+        ("x-ratelimit-limit-requests", limits.requests_per_minute.to_string()),
+        ("x-ratelimit-limit-tokens", limits.tokens_per_minute.to_string()),
+        (
+            "x-ratelimit-remaining-requests",
+            limits.requests_per_minute.saturating_sub(usage.requests).to_string(),
+        ),
+        (
+            "x-ratelimit-remaining-tokens",
+            limits.tokens_per_minute.saturating_sub(usage.tokens).to_string(),
+        ),
+        ("x-ratelimit-reset-requests", reset.clone()),
+        ("x-ratelimit-reset-tokens", reset),
+        ("x-mock-usage-tier", tier.to_string()),
+        ("x-mock-max-concurrency", limits.max_concurrency.to_string()),
+    ];
+    for (name, value) in headers {
+        let name = HeaderName::from_static(name);
+        // Keep the headers of an injected rate limit error.
+        if response.headers().contains_key(&name) {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+}
+
+/// Formats a reset interval the way the real API does, e.g. `17ms`, `6s`
+/// or `1m0s`.
+pub(crate) fn format_reset(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_reset() {
+        assert_eq!(format_reset(Duration::from_millis(17)), "17ms");
+        assert_eq!(format_reset(Duration::from_millis(6_400)), "6s");
+        assert_eq!(format_reset(Duration::from_secs(60)), "1m0s");
+    }
+}
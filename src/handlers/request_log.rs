@@ -0,0 +1,147 @@
+//! Bookkeeping shared by every OpenAI endpoint handler: request history,
+//! mirroring and lifecycle hooks.
+
+use crate::hooks::{RequestSummary, ResponseSummary};
+use crate::mirror::{mirror_request, MirroredRequest};
+use crate::service::{MockBody, MockRequest, MockResponse};
+use crate::state::{MockState, RecordedResponse, RequestOutcome};
+use chrono::Utc;
+use futures::StreamExt;
+use http::header::CONTENT_TYPE;
+use http::Response;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Records an incoming request in the history and mirrors it to the
+/// configured sink, if any.
+///
+/// # Returns
+///
+/// The id of the history record.
+pub fn receive_request(http_req: &MockRequest, state: &MockState, body: Value) -> usize {
+    if let Some(sink) = &state.config.mirror {
+        mirror_request(sink, MirroredRequest::new(http_req, body.clone()));
+    }
+    let headers = http_req
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    state
+        .history
+        .record(http_req.method().as_str(), http_req.uri().path(), headers, body)
+}
+
+/// Records the outcome of a fully produced `response`: `Completed` on
+/// success, `Failed` otherwise.
+pub fn finish_request(state: &MockState, record_id: usize, response: &MockResponse) {
+    let outcome = if response.status().is_success() {
+        RequestOutcome::Completed
+    } else {
+        RequestOutcome::Failed { status: response.status().as_u16() }
+    };
+    state.history.finish(record_id, outcome, None);
+}
+
+/// Records `response` in the history as the one sent for the request.
+/// Streamed bodies are recorded chunk by chunk as they are sent.
+///
+/// # Returns
+///
+/// The response, to be sent as before.
+pub fn record_response(
+    state: &Arc<MockState>,
+    record_id: usize,
+    response: MockResponse,
+) -> MockResponse {
+    let (head, body) = response.into_parts();
+    let recorded = |body| RecordedResponse {
+        status: head.status.as_u16(),
+        body,
+        timestamp: Utc::now(),
+    };
+
+    let bytes = match body {
+        MockBody::Full(bytes) => bytes,
+        MockBody::Stream(chunks) => {
+            state.history.respond(record_id, recorded(Value::Null));
+            let state = state.clone();
+            let chunks = chunks.inspect(move |chunk| {
+                if let Ok(chunk) = chunk {
+                    state
+                        .history
+                        .extend_response(record_id, &String::from_utf8_lossy(chunk));
+                }
+            });
+            return Response::from_parts(head, MockBody::from_stream(chunks));
+        }
+    };
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::from(String::from_utf8_lossy(&bytes).into_owned()))
+    };
+    state.history.respond(record_id, recorded(body));
+    Response::from_parts(head, bytes.into())
+}
+
+/// Runs the `on_request` hook, if any.
+///
+/// # Returns
+///
+/// The response the hook chose to answer the request with, if any.
+pub async fn run_request_hook(
+    state: &MockState,
+    record_id: usize,
+    http_req: &MockRequest,
+    body: &Value,
+) -> Option<MockResponse> {
+    let hook = state.config.hooks.on_request.as_ref()?;
+    let canned = hook(RequestSummary {
+        id: record_id,
+        method: http_req.method().to_string(),
+        path: http_req.uri().path().to_string(),
+        model: body["model"].as_str().map(str::to_string),
+        body: body.clone(),
+    })
+    .await?;
+    Some(canned.to_response(body))
+}
+
+/// Runs the `on_response` hook, if any, for a response to a request
+/// received at `started`. The returned future does not borrow `response`,
+/// so it may be awaited while the response is still being sent.
+pub fn run_response_hook(
+    state: &MockState,
+    record_id: usize,
+    response: &MockResponse,
+    started: Instant,
+) -> impl Future<Output = ()> + Send + 'static {
+    let run = state.config.hooks.on_response.as_ref().map(|hook| {
+        let streamed = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .is_some_and(|content_type| content_type == "text/event-stream");
+
+        hook(ResponseSummary {
+            id: record_id,
+            status: response.status().as_u16(),
+            streamed,
+            usage: state.history.get(record_id).and_then(|record| record.usage),
+            elapsed: started.elapsed(),
+        })
+    });
+    async move {
+        if let Some(run) = run {
+            run.await;
+        }
+    }
+}
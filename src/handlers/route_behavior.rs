@@ -0,0 +1,318 @@
+//! Simulated behavior shared by every OpenAI endpoint handler: latency,
+//! random server errors and canned responses, as set per endpoint (see
+//! [`RouteTable`](crate::state::RouteTable)), per model and per API key
+//! (see [`KeyProfile`](crate::config::KeyProfile)).
+
+use crate::config::{Endpoint, RouteConfig, UnmatchedRequests};
+use crate::faults::{ChaosConfig, ResponseFault};
+use crate::handlers::{bearer_key, check_api_key, check_quota, key_profile};
+use crate::handlers::organization::ORGANIZATION_HEADER;
+use crate::handlers::rate_limit_headers::format_reset;
+use crate::models::{ErrorResponse, OpenAIError};
+use crate::scenario::{InjectedError, NormalizedRequest, RateLimitKind, RateLimitUsage};
+use crate::service::{
+    empty_response, route_pattern, MockBody, MockRequest, MockResponse,
+};
+use crate::state::{MockState, ModelSpec};
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use http::{Response, StatusCode};
+use rand::Rng;
+use serde_json::Value;
+use std::io;
+use std::time::Duration;
+
+/// Waits for the simulated latency: the API key's latency if its profile
+/// has one, else `model`'s own latency, else the endpoint's, else the
+/// global latency.
+pub async fn simulate_latency(
+    http_req: &MockRequest,
+    state: &MockState,
+    route: &RouteConfig,
+    model: Option<&ModelSpec>,
+) {
+    let latency = key_profile(http_req, &state.config)
+        .and_then(|profile| profile.latency.as_ref())
+        .or_else(|| model.and_then(|model| model.latency.as_ref()))
+        .or(route.latency.as_ref())
+        .unwrap_or(&state.config.latency);
+    if !latency.is_zero() {
+        tokio::time::sleep(latency.sample()).await;
+    }
+}
+
+/// Applies the injected [`ResponseFault`] before the request is served:
+/// waits out a [`Hang`](ResponseFault::Hang), which never returns without
+/// `respond_after`. Chaos mode (see [`ChaosConfig`]) may add a latency
+/// spike or never return.
+pub async fn inject_response_fault(state: &MockState) {
+    let faults = state.faults.get();
+    match faults.response {
+        Some(ResponseFault::Hang { respond_after: Some(delay) }) => tokio::time::sleep(delay).await,
+        Some(ResponseFault::Hang { respond_after: None }) => std::future::pending().await,
+        _ => {}
+    }
+    if let Some(chaos) = faults.chaos {
+        if ChaosConfig::roll(chaos.latency_spikes) {
+            tokio::time::sleep(chaos.spike).await;
+        }
+        if ChaosConfig::roll(chaos.timeouts) {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Replaces the body of a successful JSON `response` as set by the
+/// injected [`ResponseFault`], if any (see [`ResponseFault::malform`]), or
+/// breaks off the connection for the connection faults.
+pub async fn malform_response(state: &MockState, response: MockResponse) -> MockResponse {
+    let Some(fault) = state.faults.get().response else {
+        return response;
+    };
+    match fault {
+        ResponseFault::CloseConnection => return broken_body(response, false),
+        ResponseFault::ResetAfterHeaders => return broken_body(response, true),
+        _ => {}
+    }
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (head, body) = response.into_parts();
+    let Ok(body) = body.to_bytes().await else {
+        return empty_response(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    let Some((content_type, malformed)) = fault.malform(&body) else {
+        return Response::from_parts(head, body.into());
+    };
+    let mut response = Response::from_parts(head, MockBody::from(malformed));
+    if let Ok(value) = HeaderValue::from_str(&content_type) {
+        response.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    response
+}
+
+/// `response` with its body replaced by one that fails, which makes the
+/// server drop the connection. With `headers_first`, the body first
+/// yields an empty frame and gives the server a turn to flush it, so the
+/// headers are always written before the failure; without, the failure
+/// comes before them.
+fn broken_body(response: MockResponse, headers_first: bool) -> MockResponse {
+    let (head, _) = response.into_parts();
+    let first_frame = headers_first.then(|| Ok(Bytes::new()));
+    let body = stream::iter(first_frame).chain(stream::once(async move {
+        if headers_first {
+            tokio::task::yield_now().await;
+        }
+        Err(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "injected connection fault",
+        ))
+    }));
+    Response::from_parts(head, MockBody::from_stream(body))
+}
+
+/// Fails a fraction of requests with a `500` server error. The fraction is
+/// that of the API key's profile if it sets one, else `model`'s own error
+/// rate, else the endpoint's, else the one set for the request path in
+/// `faults.error_rates`, else the global one. Failures use the exact error
+/// envelope of the real API (see [`InjectedError::server_error`]).
+pub fn check_route_error(
+    http_req: &MockRequest,
+    state: &MockState,
+    route: &RouteConfig,
+    model: Option<&ModelSpec>,
+) -> Result<(), MockResponse> {
+    let rate = key_profile(http_req, &state.config)
+        .and_then(|profile| profile.error_rate)
+        .or_else(|| model.and_then(|model| model.error_rate))
+        .or(route.error_rate)
+        .unwrap_or_else(|| {
+            state.faults.get().error_rate_for(&route_pattern(http_req))
+        });
+    if rate <= 0.0 || rand::thread_rng().gen::<f64>() >= rate {
+        return Ok(());
+    }
+
+    Err(InjectedError::server_error().to_response())
+}
+
+/// Rejects the request with `503 engine_overloaded` when the injected
+/// overload (see [`OverloadFault`](crate::faults::OverloadFault)) affects
+/// `model`.
+pub fn check_overloaded(state: &MockState, model: Option<&str>) -> Result<(), MockResponse> {
+    let Some(fault) = state.faults.get().overloaded else {
+        return Ok(());
+    };
+    if !fault.affects(model) || fault.rate <= 0.0 || rand::thread_rng().gen::<f64>() >= fault.rate {
+        return Ok(());
+    }
+    Err(InjectedError::engine_overloaded().to_response())
+}
+
+/// Wait advertised to requests rejected by chaos mode.
+const CHAOS_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Requests per minute reported by chaos mode's rate limit errors to keys
+/// without a usage tier.
+const CHAOS_REQUEST_LIMIT: u32 = 60;
+
+/// Organization named in rate limit errors to requests without an
+/// `OpenAI-Organization` header.
+const DEFAULT_ORGANIZATION: &str = "org-mock";
+
+/// Rejects the request with `429 rate_limit_exceeded` when the injected
+/// rate limit (see [`RateLimitFault`](crate::faults::RateLimitFault))
+/// applies, with the `retry-after`, `retry-after-ms` and
+/// `x-ratelimit-*-requests` headers set to when the request may be retried.
+pub fn check_rate_limit_fault(
+    http_req: &MockRequest,
+    state: &MockState,
+    model: Option<&str>,
+) -> Result<(), MockResponse> {
+    let Some(fault) = state.faults.get().rate_limit else {
+        return Ok(());
+    };
+    let (requests, reset_in) = state.faults.count_request(fault.window);
+    let (limit, retry_after) = match fault.after_requests {
+        Some(limit) if requests > limit => (limit, reset_in),
+        _ if fault.rate > 0.0 && rand::thread_rng().gen::<f64>() < fault.rate => {
+            (fault.after_requests.unwrap_or(requests - 1), fault.retry_after)
+        }
+        _ => return Ok(()),
+    };
+    let used = (requests - 1).min(limit);
+    Err(rate_limit_response(http_req, model, limit, used, retry_after))
+}
+
+/// The `429 rate_limit_exceeded` response to a request after `used` of
+/// `limit` requests, with the headers telling it to retry after
+/// `retry_after`.
+fn rate_limit_response(
+    http_req: &MockRequest,
+    model: Option<&str>,
+    limit: u32,
+    used: u32,
+    retry_after: Duration,
+) -> MockResponse {
+    let organization = http_req
+        .headers()
+        .get(ORGANIZATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(DEFAULT_ORGANIZATION);
+    let error = InjectedError::rate_limit_exceeded(
+        model.unwrap_or("default"),
+        organization,
+        RateLimitUsage {
+            kind: RateLimitKind::Requests,
+            limit: limit.into(),
+            used: used.into(),
+            requested: 1,
+            retry_after,
+        },
+    );
+    let mut response = error.to_response();
+    let headers = [
+        ("retry-after", retry_after.as_millis().div_ceil(1000).to_string()),
+        ("retry-after-ms", retry_after.as_millis().to_string()),
+        ("x-ratelimit-limit-requests", limit.to_string()),
+        ("x-ratelimit-remaining-requests", (limit - used).to_string()),
+        ("x-ratelimit-reset-requests", format_reset(retry_after)),
+    ];
+    for (name, value) in headers {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(name), value);
+        }
+    }
+    response
+}
+
+/// Rejects the request with `429 rate_limit_exceeded` or fails it with a
+/// `500` server error, at the rates set by the injected
+/// [`ChaosConfig`].
+pub fn check_chaos(
+    http_req: &MockRequest,
+    state: &MockState,
+    model: Option<&str>,
+) -> Result<(), MockResponse> {
+    let Some(chaos) = state.faults.get().chaos else {
+        return Ok(());
+    };
+    if ChaosConfig::roll(chaos.rate_limited) {
+        // Report the limit of the key's tier as used up.
+        let limit = bearer_key(http_req)
+            .and_then(|key| state.key_tiers.tier(key))
+            .map_or(CHAOS_REQUEST_LIMIT, |tier| tier.limits().requests_per_minute);
+        return Err(rate_limit_response(http_req, model, limit, limit, CHAOS_RETRY_AFTER));
+    }
+    if ChaosConfig::roll(chaos.server_errors) {
+        return Err(InjectedError::server_error().to_response());
+    }
+    Ok(())
+}
+
+/// Serves a request to `endpoint` that does not depend on a model: waits
+/// for the latency, checks the API key and its budget, then answers with a
+/// random error, the endpoint's canned response, its responder (see
+/// [`Responders`](crate::hooks::Responders)) or, failing those,
+/// `respond()`.
+pub async fn serve_route(
+    http_req: &MockRequest,
+    state: &MockState,
+    endpoint: Endpoint,
+    respond: impl FnOnce() -> MockResponse,
+) -> MockResponse {
+    let route = state.routes.get(endpoint);
+    simulate_latency(http_req, state, &route, None).await;
+    inject_response_fault(state).await;
+
+    if let Err(denied) = check_api_key(http_req, &state.config)
+        .and_then(|()| check_quota(http_req, state))
+        .and_then(|()| check_rate_limit_fault(http_req, state, None))
+        .and_then(|()| check_overloaded(state, None))
+        .and_then(|()| check_chaos(http_req, state, None))
+        .and_then(|()| check_route_error(http_req, state, &route, None))
+    {
+        return denied;
+    }
+    if let Some(canned) = &route.respond {
+        return canned.to_response(&Value::Null);
+    }
+    let request = NormalizedRequest::from_http(http_req, Value::Null);
+    match state.config.responders.response(endpoint, &request) {
+        Some(response) => response.to_response(&Value::Null),
+        None => respond(),
+    }
+}
+
+/// Answers a request no stub matched, as set by
+/// [`MockConfig::unmatched`](crate::config::MockConfig::unmatched). Returns
+/// `None` when it should be served generated content instead.
+pub fn unmatched_request(
+    http_req: &MockRequest,
+    state: &MockState,
+    record_id: usize,
+) -> Option<MockResponse> {
+    match state.config.unmatched {
+        UnmatchedRequests::Generate => return None,
+        UnmatchedRequests::NotFound => {}
+        UnmatchedRequests::Fail => state.history.mark_unexpected(record_id),
+    }
+
+    let message = format!("No stub matches {} {}.", http_req.method(), http_req.uri().path());
+    Some(
+        ErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            OpenAIError::invalid_request(message).code("unmatched_request"),
+        )
+        .to_response(),
+    )
+}
@@ -0,0 +1,150 @@
+//! User-supplied callbacks invoked as requests move through the mock.
+//!
+//! Hooks let users add validation, logging or failure injection of their
+//! own without waiting for the crate to support each case:
+//!
+//! - `on_request` sees every request before it is served and may answer
+//!   it with a [`CannedResponse`] instead.
+//! - `on_response` sees the status and usage of every response.
+//! - `on_stream_end` sees how each streamed response ended.
+
+use crate::models::Usage;
+use crate::scenario::CannedResponse;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An incoming request, as passed to `on_request`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestSummary {
+    /// Id of the request in the request history.
+    pub id: usize,
+
+    /// HTTP method, e.g. `POST`.
+    pub method: String,
+
+    /// Request path, e.g. `/v1/completions`.
+    pub path: String,
+
+    /// The `model` of the request body, if any.
+    pub model: Option<String>,
+
+    /// The parsed request body.
+    pub body: Value,
+}
+
+/// A response, as passed to `on_response`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseSummary {
+    /// Id of the request in the request history.
+    pub id: usize,
+
+    /// HTTP status code.
+    pub status: u16,
+
+    /// Whether the body is streamed; `usage` is then reported by
+    /// `on_stream_end` instead.
+    pub streamed: bool,
+
+    /// Token usage of the response, if any.
+    pub usage: Option<Usage>,
+
+    /// Time taken to produce the response, including simulated latency.
+    pub elapsed: Duration,
+}
+
+/// The end of a streamed response, as passed to `on_stream_end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamEndSummary {
+    /// Id of the request in the request history.
+    pub id: usize,
+
+    /// `true` if every event was sent, `false` if the client disconnected
+    /// first.
+    pub completed: bool,
+
+    /// Completion tokens delivered before the stream ended.
+    pub tokens_sent: u32,
+}
+
+/// Callback run before a request is served. Returning a response answers
+/// the request with it.
+pub type RequestHook =
+    Arc<dyn Fn(RequestSummary) -> BoxFuture<'static, Option<CannedResponse>> + Send + Sync>;
+
+/// Callback run once a response is produced.
+pub type ResponseHook = Arc<dyn Fn(ResponseSummary) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Callback run when a streamed response ends.
+pub type StreamEndHook = Arc<dyn Fn(StreamEndSummary) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// The lifecycle callbacks of a mock instance.
+#[derive(Clone, Default)]
+pub struct LifecycleHooks {
+    pub on_request: Option<RequestHook>,
+    pub on_response: Option<ResponseHook>,
+    pub on_stream_end: Option<StreamEndHook>,
+}
+
+impl fmt::Debug for LifecycleHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LifecycleHooks")
+            .field("on_request", &self.on_request.is_some())
+            .field("on_response", &self.on_response.is_some())
+            .field("on_stream_end", &self.on_stream_end.is_some())
+            .finish()
+    }
+}
+
+impl LifecycleHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the callback run before each request is served.
+    pub fn on_request<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(RequestSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<CannedResponse>> + Send + 'static,
+    {
+        self.on_request = Some(Arc::new(move |request| Box::pin(hook(request))));
+        self
+    }
+
+    /// Sets the callback run once each response is produced.
+    pub fn on_response<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ResponseSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_response = Some(Arc::new(move |response| Box::pin(hook(response))));
+        self
+    }
+
+    /// Sets the callback run when each streamed response ends.
+    pub fn on_stream_end<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StreamEndSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_stream_end = Some(Arc::new(move |end| Box::pin(hook(end))));
+        self
+    }
+
+    /// Runs `on_stream_end` in the background. Called from synchronous
+    /// code (the end of a body), so the hook is spawned rather than
+    /// awaited; it is skipped if no runtime is available, e.g. during
+    /// shutdown.
+    pub fn spawn_stream_end(&self, end: StreamEndSummary) {
+        let Some(hook) = &self.on_stream_end else {
+            return;
+        };
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(hook(end));
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod lifecycle;
+pub mod responders;
+pub use lifecycle::{
+    LifecycleHooks, RequestHook, RequestSummary, ResponseHook, ResponseSummary, StreamEndHook,
+    StreamEndSummary,
+};
+pub use responders::{Responder, Responders};
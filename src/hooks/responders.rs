@@ -0,0 +1,171 @@
+//! User-supplied closures producing the responses of whole endpoints.
+//!
+//! Where scenario rules and fixtures are not flexible enough, a closure
+//! registered for an endpoint, or for chat completions, computes the
+//! response of every request to it. Responders take precedence over
+//! fixtures and generated responses, but not over endpoint canned
+//! responses, hooks or scenario rules, which are more specific.
+
+use crate::config::Endpoint;
+use crate::models::completion::CompletionRequest;
+use crate::models::ChatCompletionRequest;
+use crate::scenario::{CannedResponse, NormalizedRequest};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Closure producing the response to a request. Returning `None` serves
+/// the request as if no responder were registered.
+pub type Responder = Arc<dyn Fn(&NormalizedRequest) -> Option<CannedResponse> + Send + Sync>;
+
+/// The responders of a mock instance, one per endpoint at most.
+#[derive(Clone, Default)]
+pub struct Responders {
+    by_endpoint: BTreeMap<Endpoint, Responder>,
+    /// `/v1/chat/completions` is served from fixtures rather than emulated,
+    /// so it has no [`Endpoint`].
+    chat: Option<Responder>,
+}
+
+impl fmt::Debug for Responders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        set.entries(self.by_endpoint.keys());
+        if self.chat.is_some() {
+            set.entry(&format_args!("ChatCompletions"));
+        }
+        set.finish()
+    }
+}
+
+impl Responders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers every request to `endpoint` with the response `responder`
+    /// returns. Replaces a previous responder of the endpoint.
+    pub fn respond<F>(mut self, endpoint: Endpoint, responder: F) -> Self
+    where
+        F: Fn(&NormalizedRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.by_endpoint
+            .insert(endpoint, Arc::new(move |request| Some(responder(request))));
+        self
+    }
+
+    /// Answers every completions request with the response `responder`
+    /// returns for the parsed request.
+    pub fn completions<F>(mut self, responder: F) -> Self
+    where
+        F: Fn(&CompletionRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.by_endpoint.insert(
+            Endpoint::Completions,
+            Arc::new(move |request| {
+                let request: CompletionRequest =
+                    serde_json::from_value(request.body().clone()).ok()?;
+                Some(responder(&request))
+            }),
+        );
+        self
+    }
+
+    /// Answers every chat completions request with the response
+    /// `responder` returns for the parsed request, ahead of the chat
+    /// fixtures and `generation.chat_reply`.
+    pub fn chat<F>(mut self, responder: F) -> Self
+    where
+        F: Fn(&ChatCompletionRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.chat = Some(Arc::new(move |request| {
+            let request: ChatCompletionRequest =
+                serde_json::from_value(request.body().clone()).ok()?;
+            Some(responder(&request))
+        }));
+        self
+    }
+
+    /// The response of the responder registered for `endpoint`, if any.
+    pub fn response(
+        &self,
+        endpoint: Endpoint,
+        request: &NormalizedRequest,
+    ) -> Option<CannedResponse> {
+        self.by_endpoint.get(&endpoint)?(request)
+    }
+
+    /// The response of the chat completions responder, if any.
+    pub fn chat_response(&self, request: &NormalizedRequest) -> Option<CannedResponse> {
+        self.chat.as_ref()?(request)
+    }
+
+    /// Whether a chat completions responder is registered.
+    pub fn has_chat(&self) -> bool {
+        self.chat.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_endpoint.is_empty() && self.chat.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_responders_per_endpoint() {
+        let responders = Responders::new()
+            .completions(|request| {
+                CannedResponse::new(200, json!({"echo": request.prompt.clone()}))
+            })
+            .respond(Endpoint::ListModels, |_| {
+                CannedResponse::new(503, json!({}))
+            });
+
+        let request = NormalizedRequest::new(
+            "POST",
+            "/v1/completions",
+            json!({"model": "gpt-3.5-turbo-instruct", "prompt": "Hi"}),
+        );
+        let response = responders
+            .response(Endpoint::Completions, &request)
+            .unwrap();
+        assert_eq!(response.body, json!({"echo": "Hi"}));
+
+        let request = NormalizedRequest::new("GET", "/v1/models", Value::Null);
+        assert_eq!(
+            responders
+                .response(Endpoint::ListModels, &request)
+                .unwrap()
+                .status,
+            503
+        );
+        assert!(responders
+            .response(Endpoint::RetrieveModel, &request)
+            .is_none());
+        assert_eq!(format!("{:?}", responders), "{Completions, ListModels}");
+    }
+
+    #[test]
+    fn test_chat_responder() {
+        let responders = Responders::new()
+            .chat(|request| CannedResponse::new(200, json!({"messages": request.messages.len()})));
+        let request = NormalizedRequest::new(
+            "POST",
+            "/v1/chat/completions",
+            json!({"model": "gpt-4o", "messages": [{"role": "user", "content": "Hi"}]}),
+        );
+        let response = responders.chat_response(&request).unwrap();
+        assert_eq!(response.body, json!({"messages": 1}));
+
+        // Bodies that are not chat requests are left to the fixtures.
+        let request =
+            NormalizedRequest::new("POST", "/v1/chat/completions", json!({"prompt": "Hi"}));
+        assert!(responders.chat_response(&request).is_none());
+        assert!(Responders::new().chat_response(&request).is_none());
+        assert_eq!(format!("{:?}", responders), "{ChatCompletions}");
+    }
+}
@@ -1,6 +1,40 @@
+pub mod capabilities;
 pub mod models;
+// Handlers reject requests with `Err(response)`, as actix handlers did.
+#[allow(clippy::result_large_err)]
 pub mod handlers;
+#[cfg(feature = "actix-web")]
 pub mod routes;
+pub mod extractors;
+pub mod config;
+pub mod diff;
+pub mod expectations;
+pub mod state;
+pub mod faults;
+pub mod fixtures;
+pub mod generation;
+pub mod hooks;
+pub mod mirror;
+pub mod presets;
+pub mod scenario;
+pub mod streaming;
+pub mod templates;
+pub mod server;
+#[allow(clippy::result_large_err)]
+pub mod service;
 pub mod validators;
 pub mod utils;
-pub mod tests;
\ No newline at end of file
+#[cfg(feature = "actix-web")]
+#[allow(clippy::module_inception)]
+pub mod tests;
+
+pub use capabilities::emulated_api_version;
+pub use models::{
+    ChatCompletionMessage, ChatCompletionRequest, Choice, CompletionChunk, CompletionRequest,
+    CompletionResponse, Prompt, Usage,
+};
+pub use scenario::{NormalizedRequest, RequestMatcher};
+pub use server::serve;
+pub use utils::token_counting::{calculate_usage, ChatMessage, MessageOverhead, TokenCounter};
+#[cfg(feature = "tls")]
+pub use server::serve_tls;
@@ -0,0 +1,316 @@
+//! Command-line entry point running the mock as a standalone server.
+//!
+//! ```text
+//! openai-mock serve [--preset NAME] [--config FILE] [--fixtures DIR]
+//!                   [--corpus FILE] [--host ADDRESS] [--port PORT]
+//!                   [--workers N]
+//! openai-mock diff CASSETTE [--preset NAME] [--config FILE] [--fixtures DIR]
+//!                  [--corpus FILE] [--json]
+//! ```
+
+use openai_mock::config::MockConfig;
+use openai_mock::diff::{replay, Cassette};
+use openai_mock::fixtures::ResponseFixtures;
+use openai_mock::generation::{CorpusGenerator, SharedGenerator};
+use openai_mock::presets::Preset;
+use openai_mock::server::{BindConfig, MockServer};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: openai-mock serve [OPTIONS]
+       openai-mock diff <CASSETTE> [OPTIONS]
+
+Commands:
+  serve               Run the mock as an HTTP server (default)
+  diff                Replay recorded traffic through the mock and report
+                      where its responses differ in structure
+
+Options:
+  --preset <NAME>     Start from a ready-made configuration (available: demo)
+  --config <FILE>     Load the configuration from a YAML or TOML scenario file
+  --fixtures <DIR>    Serve the response fixtures found in DIR
+  --corpus <FILE>     Complete prompts with answers sampled from FILE, plain
+                      text paragraphs or JSONL entries with keywords
+  --host <ADDRESS>    Address to listen on [default: 127.0.0.1] (serve)
+  --port <PORT>       Port to listen on [default: 8000] (serve)
+  --workers <N>       Worker threads serving connections [default: 1] (serve)
+  --json              Print the report as JSON (diff)
+  -h, --help          Print this help";
+
+const DEFAULT_PORT: u16 = 8000;
+
+/// Options selecting the mock's configuration, shared by all commands.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ConfigArgs {
+    preset: Option<Preset>,
+    config: Option<PathBuf>,
+    fixtures: Option<PathBuf>,
+    corpus: Option<PathBuf>,
+}
+
+/// Options of the `serve` command.
+#[derive(Debug, Clone, PartialEq)]
+struct ServeArgs {
+    config: ConfigArgs,
+    host: IpAddr,
+    port: u16,
+    workers: Option<usize>,
+}
+
+impl Default for ServeArgs {
+    fn default() -> Self {
+        Self {
+            config: ConfigArgs::default(),
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: DEFAULT_PORT,
+            workers: None,
+        }
+    }
+}
+
+/// Options of the `diff` command.
+#[derive(Debug, Clone, PartialEq)]
+struct DiffArgs {
+    config: ConfigArgs,
+    cassette: PathBuf,
+    json: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Serve(ServeArgs),
+    Diff(DiffArgs),
+    Help,
+}
+
+/// Parses the arguments following the program name. `serve` is the
+/// default command and may be omitted.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+    let diff = match args.peek().map(String::as_str) {
+        Some("serve") => {
+            args.next();
+            false
+        }
+        Some("diff") => {
+            args.next();
+            true
+        }
+        _ => false,
+    };
+
+    let mut serve = ServeArgs::default();
+    let mut cassette = None;
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for {}", flag))
+        };
+
+        match flag.as_str() {
+            "--preset" => {
+                serve.config.preset = Some(value()?.parse().map_err(|e| format!("{}", e))?)
+            }
+            "--config" => serve.config.config = Some(PathBuf::from(value()?)),
+            "--fixtures" => serve.config.fixtures = Some(PathBuf::from(value()?)),
+            "--corpus" => serve.config.corpus = Some(PathBuf::from(value()?)),
+            "--json" if diff => json = true,
+            path if diff && cassette.is_none() && !path.starts_with('-') => {
+                cassette = Some(PathBuf::from(path))
+            }
+            "--host" if !diff => {
+                let host = value()?;
+                serve.host = host
+                    .parse()
+                    .map_err(|_| format!("invalid address '{}'", host))?;
+            }
+            "--port" if !diff => {
+                let port = value()?;
+                serve.port = port
+                    .parse()
+                    .map_err(|_| format!("invalid port '{}'", port))?;
+            }
+            "--workers" if !diff => {
+                let workers = value()?;
+                serve.workers = match workers.parse() {
+                    Ok(workers) if workers > 0 => Some(workers),
+                    _ => return Err(format!("invalid worker count '{}'", workers)),
+                };
+            }
+            other => return Err(format!("unexpected argument '{}'", other)),
+        }
+    }
+
+    if serve.config.preset.is_some() && serve.config.config.is_some() {
+        return Err("--preset and --config cannot be used together".to_string());
+    }
+    if !diff {
+        return Ok(Command::Serve(serve));
+    }
+    let cassette = cassette.ok_or_else(|| "missing cassette for diff".to_string())?;
+    Ok(Command::Diff(DiffArgs {
+        config: serve.config,
+        cassette,
+        json,
+    }))
+}
+
+/// Builds the configuration described by `args`.
+fn load_config(args: &ConfigArgs) -> Result<MockConfig, String> {
+    let mut config = match (&args.preset, &args.config) {
+        (Some(preset), _) => preset.config(),
+        (None, Some(path)) => MockConfig::from_file(path)
+            .map_err(|e| format!("cannot load {}: {}", path.display(), e))?,
+        (None, None) => MockConfig::default(),
+    };
+    if let Some(dir) = &args.fixtures {
+        config.fixtures = ResponseFixtures::from_dir(dir)
+            .map_err(|e| format!("cannot load fixtures from {}: {}", dir.display(), e))?;
+    }
+    if let Some(path) = &args.corpus {
+        let corpus = CorpusGenerator::from_file(path)
+            .map_err(|e| format!("cannot load corpus: {}", e))?;
+        config.generation.generator = Some(SharedGenerator::new(corpus));
+    }
+    Ok(config)
+}
+
+fn serve(args: ServeArgs) -> Result<(), String> {
+    let config = load_config(&args.config)?;
+    let bind = BindConfig::port(args.port).address(args.host);
+    let mut builder = MockServer::builder().config(config).bind(bind);
+    if let Some(workers) = args.workers {
+        builder = builder.workers(workers);
+    }
+    let server = builder
+        .start()
+        .map_err(|e| format!("cannot start server: {}", e))?;
+
+    match args.config.preset {
+        Some(preset) => println!(
+            "openai-mock ({} preset) listening on {}",
+            preset,
+            server.base_url()
+        ),
+        None => println!("openai-mock listening on {}", server.base_url()),
+    }
+
+    // The server runs on its own thread until the process is stopped.
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Replays the cassette and prints the report. Returns whether every
+/// response matched its recording.
+fn diff(args: DiffArgs) -> Result<bool, String> {
+    let config = load_config(&args.config)?;
+    let cassette = Cassette::from_file(&args.cassette)
+        .map_err(|e| format!("cannot load {}: {}", args.cassette.display(), e))?;
+    let report = actix_rt::System::new().block_on(replay(&cassette, config));
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        println!("{}", json);
+    } else {
+        println!("{}", report);
+    }
+    Ok(report.is_clean())
+}
+
+fn main() -> ExitCode {
+    let result = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        Ok(Command::Serve(args)) => serve(args).map(|()| true),
+        Ok(Command::Diff(args)) => diff(args),
+        Err(e) => Err(format!("{}\n\n{}", e, USAGE)),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        // Differences were found and reported.
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_serve_args() {
+        assert_eq!(parse(&[]), Ok(Command::Serve(ServeArgs::default())));
+
+        let Ok(Command::Serve(args)) = parse(&[
+            "serve",
+            "--preset",
+            "demo",
+            "--host=0.0.0.0",
+            "--port",
+            "9000",
+            "--workers",
+            "4",
+        ]) else {
+            panic!("expected the serve command");
+        };
+        assert_eq!(args.config.preset, Some(Preset::Demo));
+        assert_eq!(args.host, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(args.port, 9000);
+        assert_eq!(args.workers, Some(4));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert!(parse(&["--preset", "nope"])
+            .unwrap_err()
+            .contains("unknown preset"));
+        assert!(parse(&["--port"]).unwrap_err().contains("missing value"));
+        assert!(parse(&["--workers", "0"]).unwrap_err().contains("invalid worker count"));
+        assert!(parse(&["--preset", "demo", "--config", "a.yaml"]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["diff"]).unwrap_err().contains("missing cassette"));
+        assert!(parse(&["diff", "a.jsonl", "--port", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_diff_args() {
+        let Ok(Command::Diff(args)) = parse(&[
+            "diff",
+            "traffic.jsonl",
+            "--preset=demo",
+            "--corpus",
+            "answers.jsonl",
+            "--json",
+        ]) else {
+            panic!("expected the diff command");
+        };
+        assert_eq!(args.cassette, PathBuf::from("traffic.jsonl"));
+        assert_eq!(args.config.preset, Some(Preset::Demo));
+        assert_eq!(args.config.corpus, Some(PathBuf::from("answers.jsonl")));
+        assert!(args.json);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod sink;
+pub use sink::{mirror_request, MirrorSink, MirroredRequest};
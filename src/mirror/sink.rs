@@ -0,0 +1,149 @@
+//! Mirroring of incoming requests to a secondary sink.
+//!
+//! Traffic generated by a test suite is often useful elsewhere, e.g. to
+//! feed an analytics pipeline or a service under development. When a
+//! [`MirrorSink`] is configured, a copy of every request is delivered to it
+//! in the background; the mock's own response never waits for the sink and
+//! never fails because of it.
+
+use crate::service::MockRequest;
+use http::header::AUTHORIZATION;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// How long delivery of one request to an HTTP sink may take.
+const HTTP_SINK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A copy of an incoming request, as delivered to a [`MirrorSink`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirroredRequest {
+    /// HTTP method, e.g. `POST`.
+    pub method: String,
+
+    /// Request path, e.g. `/v1/completions`.
+    pub path: String,
+
+    /// Request headers. The `Authorization` value is redacted.
+    pub headers: BTreeMap<String, String>,
+
+    /// The parsed request body.
+    pub body: Value,
+
+    /// When the request was received.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MirroredRequest {
+    pub fn new(http_req: &MockRequest, body: Value) -> Self {
+        let headers = http_req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = if name == AUTHORIZATION {
+                    "[redacted]".to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str().to_string(), value)
+            })
+            .collect();
+
+        Self {
+            method: http_req.method().to_string(),
+            path: http_req.uri().path().to_string(),
+            headers,
+            body,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Where mirrored requests are delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MirrorSink {
+    /// Each request is `POST`ed as JSON to `url`. Only plain `http://`
+    /// URLs are supported.
+    Http { url: String },
+
+    /// Each request is sent on a channel. Not available in configuration
+    /// files.
+    #[serde(skip)]
+    Channel(UnboundedSender<MirroredRequest>),
+}
+
+/// Delivers `request` to `sink` without waiting for it.
+///
+/// Failures are logged and otherwise ignored. Delivery to an HTTP sink is
+/// spawned on the current tokio runtime, so this must be called from a
+/// handler.
+pub fn mirror_request(sink: &MirrorSink, request: MirroredRequest) {
+    match sink {
+        MirrorSink::Channel(sender) => {
+            // A dropped receiver just means nobody is listening anymore.
+            let _ = sender.send(request);
+        }
+        MirrorSink::Http { url } => {
+            let url = url.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HTTP_SINK_TIMEOUT, post_json(&url, &request)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => log::warn!("failed to mirror request to {}: {}", url, e),
+                    Err(_) => log::warn!("timed out mirroring request to {}", url),
+                }
+            });
+        }
+    }
+}
+
+/// Sends `request` as the JSON body of an HTTP/1.1 `POST` to `url`.
+async fn post_json(url: &str, request: &MirroredRequest) -> io::Result<()> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only http:// mirror URLs are supported",
+        )
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let body = serde_json::to_vec(request)?;
+    let mut stream = TcpStream::connect(address).await?;
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        authority,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let status = String::from_utf8_lossy(&response)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok());
+    match status {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(io::Error::other(format!("sink answered {}", status))),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "sink sent no valid HTTP response",
+        )),
+    }
+}
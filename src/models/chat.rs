@@ -0,0 +1,121 @@
+//! This module defines the data structures for chat completion requests,
+//! which the mock serves from fixtures and records for inspection.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Represents a request payload for the Chat Completions API.
+///
+/// Only the commonly inspected fields are typed; everything else the
+/// request carries is kept in `extra`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChatCompletionRequest {
+    /// ID of the model to use.
+    pub model: String,
+
+    /// The conversation so far.
+    pub messages: Vec<ChatCompletionMessage>,
+
+    /// The maximum number of tokens to generate (deprecated by the API in
+    /// favor of `max_completion_tokens`).
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+
+    /// The maximum number of tokens to generate, reasoning included.
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
+
+    /// Sampling temperature to use.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling probability.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+
+    /// How many choices to generate.
+    #[serde(default)]
+    pub n: Option<i32>,
+
+    /// Whether to stream the response.
+    #[serde(default)]
+    pub stream: Option<bool>,
+
+    /// Sequences where generation stops: a string or an array of
+    /// strings, as sent.
+    #[serde(default)]
+    pub stop: Option<Value>,
+
+    /// Tools the model may call, as sent.
+    #[serde(default)]
+    pub tools: Option<Vec<Value>>,
+
+    /// Which tool the model must call, as sent.
+    #[serde(default)]
+    pub tool_choice: Option<Value>,
+
+    /// Requested response format, as sent.
+    #[serde(default)]
+    pub response_format: Option<Value>,
+
+    /// A unique identifier representing the end-user.
+    #[serde(default)]
+    pub user: Option<String>,
+
+    /// Every other field of the request.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl ChatCompletionRequest {
+    /// The text of the `system` and `developer` messages, one entry per
+    /// message.
+    pub fn system_prompts(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .filter(|message| message.role == "system" || message.role == "developer")
+            .map(ChatCompletionMessage::text)
+            .collect()
+    }
+}
+
+/// One message of a chat completion request.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChatCompletionMessage {
+    /// `system`, `developer`, `user`, `assistant` or `tool`.
+    pub role: String,
+
+    /// A string, or an array of content parts; absent for assistant
+    /// messages that only call tools.
+    #[serde(default)]
+    pub content: Option<Value>,
+
+    /// Optional name of the participant.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Tool calls made by an assistant message, as sent.
+    #[serde(default)]
+    pub tool_calls: Option<Vec<Value>>,
+
+    /// The tool call a `tool` message answers.
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatCompletionMessage {
+    /// The text of the message: its string content, or its text parts
+    /// joined by newlines.
+    pub fn text(&self) -> String {
+        match &self.content {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        }
+    }
+}
@@ -3,7 +3,9 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use crate::utils::token_counting::TokenCounter;
 use crate::validators::StopSequence;
 
 /// Represents a request payload for the Completions API.
@@ -17,7 +19,9 @@ pub struct CompletionRequest {
 
     /// The prompt(s) to generate completions for.
     ///
-    /// Can be a string, an array of strings, or `null`.
+    /// Can be a string, an array of strings, an array of token IDs, an
+    /// array of token ID arrays, or `null`. Kept as sent so every invalid
+    /// item can be reported; see [`CompletionRequest::parsed_prompt`].
     #[serde(default)]
     pub prompt: Option<Value>,
 
@@ -118,6 +122,8 @@ impl Default for CompletionRequest {
     /// # Example
     ///
     /// ```
+    /// use openai_mock::models::CompletionRequest;
+    ///
     /// let default_request = CompletionRequest::default();
     /// ```
     fn default() -> Self {
@@ -142,6 +148,46 @@ impl Default for CompletionRequest {
     }
 }
 
+impl CompletionRequest {
+    /// The `prompt` of the request, if it is one of the accepted shapes.
+    pub fn parsed_prompt(&self) -> Option<Prompt> {
+        self.prompt
+            .as_ref()
+            .and_then(|prompt| Prompt::deserialize(prompt).ok())
+    }
+}
+
+/// The prompt(s) of a completion request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Prompt {
+    /// A single text prompt.
+    Text(String),
+
+    /// A batch of text prompts.
+    Texts(Vec<String>),
+
+    /// A single prompt given as token IDs of the model's encoding.
+    Tokens(Vec<u32>),
+
+    /// A batch of prompts given as token IDs.
+    TokenBatch(Vec<Vec<u32>>),
+}
+
+impl Prompt {
+    /// The text of each prompt, with token IDs decoded by `counter`.
+    pub fn texts(&self, counter: &TokenCounter) -> Vec<Cow<'_, str>> {
+        match self {
+            Prompt::Text(text) => vec![Cow::Borrowed(text.as_str())],
+            Prompt::Texts(texts) => texts.iter().map(|text| Cow::Borrowed(text.as_str())).collect(),
+            Prompt::Tokens(ids) => vec![Cow::Owned(counter.decode(ids))],
+            Prompt::TokenBatch(batch) => {
+                batch.iter().map(|ids| Cow::Owned(counter.decode(ids))).collect()
+            }
+        }
+    }
+}
+
 /// Represents a response from the Completions API.
 ///
 /// Contains generated completions along with usage statistics.
@@ -166,6 +212,27 @@ pub struct CompletionResponse {
     pub usage: Usage,
 }
 
+/// Represents a single chunk of a streamed completion.
+///
+/// Sent as an SSE `data:` event when the request sets `stream: true`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletionChunk {
+    /// Unique identifier for the completion, shared by all chunks.
+    pub id: String,
+
+    /// The object type (always "text_completion").
+    pub object: String,
+
+    /// Creation time in epoch seconds.
+    pub created: u64,
+
+    /// The model used for the completion.
+    pub model: String,
+
+    /// The partial choices contained in this chunk.
+    pub choices: Vec<Choice>,
+}
+
 /// Represents a single completion choice.
 ///
 /// Contains the generated text and additional metadata.
@@ -200,14 +267,16 @@ pub struct Logprobs {
     /// Indices of tokens in the original text.
     pub text_offset: Vec<usize>,
 
-    /// Top log probabilities for each token position.
-    pub top_logprobs: Vec<HashMap<String, f32>>,
+    /// Top log probabilities for each token position. Generated
+    /// alternatives borrow their text from the tokenizer rather than
+    /// allocating it for every token.
+    pub top_logprobs: Vec<HashMap<Cow<'static, str>, f32>>,
 }
 
 /// Represents usage statistics for a completion.
 ///
 /// Tracks the number of tokens consumed in the prompt and completion.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Usage {
     /// The number of tokens in the prompt.
     pub prompt_tokens: u32,
@@ -217,4 +286,16 @@ pub struct Usage {
 
     /// The total number of tokens used.
     pub total_tokens: u32,
-}
\ No newline at end of file
+}
+
+impl Usage {
+    /// The usage of `prompt_tokens` prompt and `completion_tokens`
+    /// completion tokens.
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! The error envelope of the API: `{"error": {"message", "type", "param",
+//! "code"}}`.
+//!
+//! Every error the mock returns is built from these types, and custom stubs
+//! can use them to return errors of the same shape:
+//!
+//! ```
+//! use http::StatusCode;
+//! use openai_mock::models::{ErrorResponse, OpenAIError};
+//!
+//! let error = ErrorResponse::new(
+//!     StatusCode::BAD_REQUEST,
+//!     OpenAIError::invalid_request("Unsupported value: 'tools'").param("tools"),
+//! );
+//! assert_eq!(error.to_json()["error"]["param"], "tools");
+//! ```
+
+use crate::service::{body_response, MockResponse};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// An API error, as found under `error` in an error response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAIError {
+    /// Human readable description of the error.
+    pub message: String,
+
+    /// Category of the error, e.g. `invalid_request_error`.
+    pub r#type: String,
+
+    /// The request parameter the error refers to.
+    #[serde(default)]
+    pub param: Option<String>,
+
+    /// Machine readable error code.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+impl OpenAIError {
+    /// An error of type `error_type`, with neither `param` nor `code`.
+    pub fn new(message: impl Into<String>, error_type: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            r#type: error_type.into(),
+            param: None,
+            code: None,
+        }
+    }
+
+    /// An `invalid_request_error`.
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(message, "invalid_request_error")
+    }
+
+    /// A `server_error`.
+    pub fn server_error(message: impl Into<String>) -> Self {
+        Self::new(message, "server_error")
+    }
+
+    /// Sets the request parameter the error refers to.
+    pub fn param(mut self, param: impl Into<String>) -> Self {
+        self.param = Some(param.into());
+        self
+    }
+
+    /// Sets the machine readable error code.
+    pub fn code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+}
+
+impl fmt::Display for OpenAIError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for OpenAIError {}
+
+/// An error response: an [`OpenAIError`] in the `error` envelope, and the
+/// HTTP status it is returned with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: OpenAIError,
+
+    /// Not part of the body; `400 Bad Request` when deserialized.
+    #[serde(skip, default = "default_status")]
+    pub status: StatusCode,
+}
+
+fn default_status() -> StatusCode {
+    StatusCode::BAD_REQUEST
+}
+
+impl ErrorResponse {
+    pub fn new(status: StatusCode, error: OpenAIError) -> Self {
+        Self { error, status }
+    }
+
+    /// The body of the response.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({ "error": self.error })
+    }
+
+    /// The body of the response formatted like the real API's: fields in
+    /// the order `message`, `type`, `param`, `code`, indented by four
+    /// spaces.
+    pub fn to_body(&self) -> String {
+        let mut body = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut body, formatter);
+        // Strings and options always serialize.
+        let _ = self.serialize(&mut serializer);
+        body.push(b'\n');
+        String::from_utf8(body).unwrap_or_default()
+    }
+
+    /// The HTTP response.
+    pub fn to_response(&self) -> MockResponse {
+        body_response(self.status, "application/json", self.to_body())
+    }
+}
+
+impl From<ErrorResponse> for MockResponse {
+    fn from(error: ErrorResponse) -> Self {
+        error.to_response()
+    }
+}
+
+impl fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for ErrorResponse {}
+
+#[cfg(feature = "actix-web")]
+impl actix_web::ResponseError for ErrorResponse {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::from_u16(self.status.as_u16())
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        actix_web::HttpResponse::build(self.status_code())
+            .content_type("application/json")
+            .body(self.to_body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body() {
+        let error = ErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            OpenAIError::invalid_request("No such model").param("model").code("model_not_found"),
+        );
+        assert_eq!(
+            error.to_body(),
+            "{\n    \"error\": {\n        \"message\": \"No such model\",\n        \"type\": \"invalid_request_error\",\n        \"param\": \"model\",\n        \"code\": \"model_not_found\"\n    }\n}\n"
+        );
+
+        let parsed: ErrorResponse = serde_json::from_str(&error.to_body()).unwrap();
+        assert_eq!(parsed.error, error.error);
+        assert_eq!(parsed.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::to_value(OpenAIError::server_error("Oops")).unwrap(),
+            serde_json::json!({"message": "Oops", "type": "server_error", "param": null, "code": null})
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Failures of asynchronous resources (assistant runs, fine-tuning jobs and
+//! batches).
+//!
+//! The mock does not emulate these resources itself, but stubs (scenario
+//! rules, fixtures or hooks) can describe one that ended in `failed` with
+//! the same shapes the real API returns, so failure-path UI and retry logic
+//! can be exercised:
+//!
+//! ```
+//! use openai_mock::models::{AsyncResource, RunErrorCode};
+//!
+//! let run = AsyncResource::Run.failed("run_abc123", RunErrorCode::RateLimitExceeded.into());
+//! assert_eq!(run["status"], "failed");
+//! assert_eq!(run["last_error"]["code"], "rate_limit_exceeded");
+//! ```
+
+use crate::utils::utils::get_current_timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// The error a failed resource reports: a machine readable code and a
+/// human readable message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastError {
+    pub code: String,
+    pub message: String,
+}
+
+impl LastError {
+    pub fn new(code: &str, message: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Codes of a failed assistant run's `last_error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunErrorCode {
+    ServerError,
+    RateLimitExceeded,
+    InvalidPrompt,
+}
+
+impl RunErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunErrorCode::ServerError => "server_error",
+            RunErrorCode::RateLimitExceeded => "rate_limit_exceeded",
+            RunErrorCode::InvalidPrompt => "invalid_prompt",
+        }
+    }
+
+    /// The message the real API reports with the code.
+    pub fn message(&self) -> &'static str {
+        match self {
+            RunErrorCode::ServerError => "Sorry, something went wrong.",
+            RunErrorCode::RateLimitExceeded => {
+                "You exceeded your current quota, please check your plan and billing details."
+            }
+            RunErrorCode::InvalidPrompt => {
+                "Your prompt was flagged as potentially violating our usage policy."
+            }
+        }
+    }
+}
+
+impl From<RunErrorCode> for LastError {
+    fn from(code: RunErrorCode) -> Self {
+        LastError::new(code.as_str(), code.message())
+    }
+}
+
+/// Codes of a failed fine-tuning job's `error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuningErrorCode {
+    InvalidTrainingFile,
+    InvalidValidationFile,
+    InvalidNExamples,
+    ExceededQuota,
+    ServerError,
+}
+
+impl FineTuningErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FineTuningErrorCode::InvalidTrainingFile => "invalid_training_file",
+            FineTuningErrorCode::InvalidValidationFile => "invalid_validation_file",
+            FineTuningErrorCode::InvalidNExamples => "invalid_n_examples",
+            FineTuningErrorCode::ExceededQuota => "exceeded_quota",
+            FineTuningErrorCode::ServerError => "server_error",
+        }
+    }
+
+    /// The message the real API reports with the code.
+    pub fn message(&self) -> &'static str {
+        match self {
+            FineTuningErrorCode::InvalidTrainingFile => {
+                "The job failed due to an invalid training file. Expected file to have JSONL format, where every line is a valid JSON dictionary."
+            }
+            FineTuningErrorCode::InvalidValidationFile => {
+                "The job failed due to an invalid validation file. Expected file to have JSONL format, where every line is a valid JSON dictionary."
+            }
+            FineTuningErrorCode::InvalidNExamples => {
+                "Training file has 5 example(s), but must have at least 10 examples."
+            }
+            FineTuningErrorCode::ExceededQuota => {
+                "Creating this fine-tuning job would exceed your hard limit, please check your plan and billing details."
+            }
+            FineTuningErrorCode::ServerError => "The job failed due to an internal error.",
+        }
+    }
+
+    /// The request parameter the error refers to, if any.
+    pub fn param(&self) -> Option<&'static str> {
+        match self {
+            FineTuningErrorCode::InvalidTrainingFile | FineTuningErrorCode::InvalidNExamples => {
+                Some("training_file")
+            }
+            FineTuningErrorCode::InvalidValidationFile => Some("validation_file"),
+            FineTuningErrorCode::ExceededQuota | FineTuningErrorCode::ServerError => None,
+        }
+    }
+}
+
+impl From<FineTuningErrorCode> for LastError {
+    fn from(code: FineTuningErrorCode) -> Self {
+        LastError::new(code.as_str(), code.message())
+    }
+}
+
+/// Codes of a failed batch's `errors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchErrorCode {
+    InvalidJsonLine,
+    EmptyFile,
+    TooManyTasks,
+    DuplicateCustomId,
+    TokenLimitExceeded,
+}
+
+impl BatchErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchErrorCode::InvalidJsonLine => "invalid_json_line",
+            BatchErrorCode::EmptyFile => "empty_file",
+            BatchErrorCode::TooManyTasks => "too_many_tasks",
+            BatchErrorCode::DuplicateCustomId => "duplicate_custom_id",
+            BatchErrorCode::TokenLimitExceeded => "token_limit_exceeded",
+        }
+    }
+
+    /// The message the real API reports with the code.
+    pub fn message(&self) -> &'static str {
+        match self {
+            BatchErrorCode::InvalidJsonLine => "This line is not parseable as valid JSON.",
+            BatchErrorCode::EmptyFile => {
+                "The input file is empty. Please ensure that the batch contains at least one request."
+            }
+            BatchErrorCode::TooManyTasks => {
+                "The batch input file is larger than the 50000 maximum number of requests."
+            }
+            BatchErrorCode::DuplicateCustomId => {
+                "The custom_id for this request is a duplicate of another request. The custom_id parameter must be unique for each request in a batch."
+            }
+            BatchErrorCode::TokenLimitExceeded => {
+                "Enqueued token limit reached for your organization. Please try again once some in_progress batches have been completed."
+            }
+        }
+    }
+}
+
+impl From<BatchErrorCode> for LastError {
+    fn from(code: BatchErrorCode) -> Self {
+        LastError::new(code.as_str(), code.message())
+    }
+}
+
+/// An asynchronous resource that can end in `failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsyncResource {
+    /// An assistant run (`thread.run`), reporting `last_error`.
+    Run,
+
+    /// A fine-tuning job (`fine_tuning.job`), reporting `error`.
+    FineTuningJob,
+
+    /// A batch (`batch`), reporting a list of `errors`.
+    Batch,
+}
+
+impl AsyncResource {
+    /// The body of resource `id` after it failed with `error`, in the
+    /// shape the real API returns it.
+    pub fn failed(&self, id: &str, error: LastError) -> Value {
+        let now = get_current_timestamp().timestamp();
+        match self {
+            AsyncResource::Run => json!({
+                "id": id,
+                "object": "thread.run",
+                "created_at": now,
+                "status": "failed",
+                "started_at": now,
+                "failed_at": now,
+                "completed_at": null,
+                "cancelled_at": null,
+                "expires_at": null,
+                "last_error": error,
+            }),
+            AsyncResource::FineTuningJob => {
+                let param = FINE_TUNING_CODES
+                    .iter()
+                    .find(|code| code.as_str() == error.code)
+                    .and_then(FineTuningErrorCode::param);
+                json!({
+                    "id": id,
+                    "object": "fine_tuning.job",
+                    "created_at": now,
+                    "finished_at": null,
+                    "status": "failed",
+                    "fine_tuned_model": null,
+                    "trained_tokens": null,
+                    "error": {
+                        "code": error.code,
+                        "message": error.message,
+                        "param": param,
+                    },
+                })
+            }
+            AsyncResource::Batch => json!({
+                "id": id,
+                "object": "batch",
+                "created_at": now,
+                "status": "failed",
+                "failed_at": now,
+                "output_file_id": null,
+                "error_file_id": null,
+                "errors": {
+                    "object": "list",
+                    "data": [{
+                        "code": error.code,
+                        "message": error.message,
+                        "param": null,
+                        "line": null,
+                    }],
+                },
+            }),
+        }
+    }
+}
+
+const FINE_TUNING_CODES: [FineTuningErrorCode; 5] = [
+    FineTuningErrorCode::InvalidTrainingFile,
+    FineTuningErrorCode::InvalidValidationFile,
+    FineTuningErrorCode::InvalidNExamples,
+    FineTuningErrorCode::ExceededQuota,
+    FineTuningErrorCode::ServerError,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_shapes() {
+        let job = AsyncResource::FineTuningJob.failed("ftjob-1", FineTuningErrorCode::InvalidNExamples.into());
+        assert_eq!(job["error"]["code"], "invalid_n_examples");
+        assert_eq!(job["error"]["param"], "training_file");
+
+        let batch = AsyncResource::Batch.failed("batch_1", LastError::new("custom", "Custom failure"));
+        assert_eq!(batch["errors"]["data"][0]["message"], "Custom failure");
+
+        let error: LastError = serde_json::from_value(json!({"code": "server_error", "message": "x"})).unwrap();
+        assert_eq!(AsyncResource::Run.failed("run_1", error)["last_error"]["message"], "x");
+    }
+}
@@ -1,2 +1,14 @@
+pub mod chat;
 pub mod completion;
-pub use completion::{CompletionRequest, CompletionResponse, Choice, Usage};
\ No newline at end of file
+pub mod error;
+pub mod last_error;
+pub mod model;
+pub use chat::{ChatCompletionMessage, ChatCompletionRequest};
+pub use completion::{
+    CompletionRequest, CompletionResponse, CompletionChunk, Choice, Prompt, Usage,
+};
+pub use error::{ErrorResponse, OpenAIError};
+pub use last_error::{
+    AsyncResource, BatchErrorCode, FineTuningErrorCode, LastError, RunErrorCode,
+};
+pub use model::{Model, ModelList};
@@ -0,0 +1,29 @@
+//! This module defines the data structures returned by the Models API.
+
+use serde::{Deserialize, Serialize};
+
+/// A model as described by `GET /v1/models`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Model {
+    /// The model identifier, e.g. `gpt-4o`.
+    pub id: String,
+
+    /// Always `model`.
+    pub object: String,
+
+    /// Unix timestamp (in seconds) of when the model was created.
+    pub created: u64,
+
+    /// The organization that owns the model.
+    pub owned_by: String,
+}
+
+/// The response of `GET /v1/models`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelList {
+    /// Always `list`.
+    pub object: String,
+
+    /// The available models.
+    pub data: Vec<Model>,
+}
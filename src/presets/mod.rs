@@ -0,0 +1,2 @@
+pub mod preset;
+pub use preset::{Preset, UnknownPreset};
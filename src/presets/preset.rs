@@ -0,0 +1,153 @@
+//! Ready-made configurations for running the mock as a standalone service.
+//!
+//! The `demo` preset is meant as a one-command local stand-in for OpenAI
+//! when developing applications offline:
+//!
+//! ```text
+//! openai-mock serve --preset demo
+//! ```
+//!
+//! It serves every emulated endpoint without authentication or simulated
+//! latency, and answers chat completions, embeddings, moderations and the
+//! Files API with bundled demo fixtures.
+
+use crate::config::{MockConfig, ModelConfig};
+use crate::fixtures::ResponseFixtures;
+use std::fmt;
+use std::str::FromStr;
+
+/// Fixture templates of the demo preset, as `(endpoint, model, template)`.
+const DEMO_TEMPLATES: [(&str, &str, &str); 1] = [
+    ("chat/completions", "default", include_str!("demo/chat_completions.json.hbs")),
+];
+
+/// Fixtures of the demo preset, as `(endpoint, model, body)`.
+const DEMO_FIXTURES: [(&str, &str, &str); 5] = [
+    ("embeddings", "default", include_str!("demo/embeddings.json")),
+    ("moderations", "default", include_str!("demo/moderations.json")),
+    ("files", "default", include_str!("demo/files.json")),
+    ("files/file-demo-training", "default", include_str!("demo/file_training.json")),
+    ("files/file-demo-handbook", "default", include_str!("demo/file_handbook.json")),
+];
+
+/// Models listed by the demo preset in addition to the built-in ones.
+const DEMO_MODELS: [&str; 3] = ["text-embedding-3-small", "text-embedding-3-large", "omni-moderation-latest"];
+
+/// A named, ready-made configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Every endpoint enabled, seeded with demo data and generous defaults.
+    Demo,
+}
+
+impl Preset {
+    /// Every available preset.
+    pub const ALL: [Preset; 1] = [Preset::Demo];
+
+    /// The name used to select the preset, e.g. on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Demo => "demo",
+        }
+    }
+
+    /// Builds the configuration of the preset.
+    pub fn config(&self) -> MockConfig {
+        match self {
+            Preset::Demo => demo_config(),
+        }
+    }
+}
+
+fn demo_config() -> MockConfig {
+    let mut fixtures = ResponseFixtures::new();
+    for (endpoint, model, template) in DEMO_TEMPLATES {
+        fixtures
+            .insert_template(endpoint, model, template.to_string())
+            .expect("bundled demo templates are valid");
+    }
+    for (endpoint, model, body) in DEMO_FIXTURES {
+        fixtures.insert(endpoint, model, body.to_string());
+    }
+
+    let mut config = MockConfig::default().with_fixtures(fixtures);
+    for model in DEMO_MODELS {
+        config = config.with_model(
+            model,
+            ModelConfig {
+                owned_by: Some("system".to_string()),
+                ..ModelConfig::default()
+            },
+        );
+    }
+    config
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing the name of a preset that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPreset(pub String);
+
+impl fmt::Display for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let available: Vec<&str> = Preset::ALL.iter().map(Preset::name).collect();
+        write!(
+            f,
+            "unknown preset '{}' (available: {})",
+            self.0,
+            available.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownPreset {}
+
+impl FromStr for Preset {
+    type Err = UnknownPreset;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+            .ok_or_else(|| UnknownPreset(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_preset() {
+        assert_eq!("demo".parse::<Preset>(), Ok(Preset::Demo));
+        let err = "prod".parse::<Preset>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown preset 'prod' (available: demo)");
+    }
+
+    #[test]
+    fn test_demo_fixtures_are_valid_json() {
+        for (endpoint, _, body) in DEMO_FIXTURES {
+            assert!(
+                serde_json::from_str::<serde_json::Value>(body).is_ok(),
+                "invalid demo fixture for {}",
+                endpoint
+            );
+        }
+        for (endpoint, _, template) in DEMO_TEMPLATES {
+            let rendered = crate::templates::render_template(template, &serde_json::json!({})).unwrap();
+            assert!(
+                serde_json::from_str::<serde_json::Value>(&rendered).is_ok(),
+                "invalid demo template for {}",
+                endpoint
+            );
+        }
+        let config = Preset::Demo.config();
+        assert!(config.fixtures.lookup("/v1/embeddings", "text-embedding-3-small").is_some());
+        assert!(config.models.contains_key("omni-moderation-latest"));
+    }
+}
@@ -0,0 +1,67 @@
+use actix_web::web;
+use crate::handlers::{
+    capabilities_handler, clear_route_handler, get_key_tier_handler, list_routes_handler,
+    list_scenarios_handler, set_key_tier_handler, set_route_handler, set_scenario_state_handler,
+};
+use crate::state::MockState;
+
+/// Registers the administrative routes under `/__admin` that do not depend
+/// on a mock instance.
+pub fn configure_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/__admin/capabilities").route(web::get().to(capabilities_handler)),
+    );
+}
+
+/// Registers every administrative route under `/__admin`, including those
+/// controlling the given `MockState`, under its base path if it has one:
+///
+/// - `GET /__admin/routes` lists the settings of every endpoint.
+/// - `PUT /__admin/routes/{endpoint}` overrides the settings of an
+///   endpoint with the [`RouteConfig`](crate::config::RouteConfig) in the
+///   body.
+/// - `DELETE /__admin/routes/{endpoint}` restores its configured settings.
+/// - `GET /__admin/keys/{key}/tier` returns the usage tier of an API key.
+/// - `PUT /__admin/keys/{key}/tier` moves the key to the tier in the body,
+///   e.g. `{"tier": "tier-2"}`.
+/// - `GET /__admin/scenarios` lists the state of every scenario.
+/// - `PUT /__admin/scenarios/{scenario}` moves a scenario to the state in
+///   the body, e.g. `{"state": "ready"}`.
+pub fn configure_admin_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let prefix = state.config.route_prefix();
+        cfg.service(
+            web::resource(format!("{}/__admin/capabilities", prefix))
+                .route(web::get().to(capabilities_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__admin/routes", prefix))
+                .app_data(state.clone())
+                .route(web::get().to(list_routes_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__admin/routes/{{endpoint}}", prefix))
+                .app_data(state.clone())
+                .route(web::put().to(set_route_handler))
+                .route(web::delete().to(clear_route_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__admin/keys/{{key}}/tier", prefix))
+                .app_data(state.clone())
+                .route(web::get().to(get_key_tier_handler))
+                .route(web::put().to(set_key_tier_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__admin/scenarios", prefix))
+                .app_data(state.clone())
+                .route(web::get().to(list_scenarios_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__admin/scenarios/{{scenario}}", prefix))
+                .app_data(state)
+                .route(web::put().to(set_scenario_state_handler)),
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use actix_web::web;
+use crate::routes::{
+    configure_admin_routes_with, configure_completion_routes_with, configure_control_routes_with,
+    configure_fixture_routes_with, configure_model_routes_with,
+};
+use crate::state::MockState;
+
+/// Registers every route of a mock instance using the given `MockState`:
+/// the emulated OpenAI endpoints, the fixture-only endpoints, and the
+/// `/__admin` and `/__mock` control routes. This is what
+/// [`MockServer`](crate::server::MockServer) serves.
+pub fn configure_all_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        configure_completion_routes_with(state.clone())(cfg);
+        configure_model_routes_with(state.clone())(cfg);
+        configure_fixture_routes_with(state.clone())(cfg);
+        configure_admin_routes_with(state.clone())(cfg);
+        configure_control_routes_with(state)(cfg);
+    }
+}
@@ -1,9 +1,37 @@
 use actix_web::web;
+use crate::config::{Endpoint, MockConfig};
 use crate::handlers::completions_handler;
+use crate::state::MockState;
 
+/// Registers the completion routes with a fresh, default `MockState`.
 pub fn configure_completion_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/v1")
-            .route("/completions", web::post().to(completions_handler)),
-    );
-}
\ No newline at end of file
+    configure_completion_routes_with(web::Data::new(MockState::default()))(cfg);
+}
+
+/// Registers the completion routes with a fresh `MockState` mounted under
+/// `base_path`, e.g. `/openai` to serve `/openai/v1/completions`.
+pub fn configure_completion_routes_at(base_path: &str) -> impl FnOnce(&mut web::ServiceConfig) {
+    let config = MockConfig::default().with_base_path(base_path);
+    configure_completion_routes_with(web::Data::new(MockState::new(config)))
+}
+
+/// Registers the completion routes using the given `MockState`.
+///
+/// The state is attached to each registered resource, so several mocks
+/// with different settings can be mounted in the same application.
+/// Endpoints disabled in the configuration are not registered, and the
+/// others are mounted under the configured
+/// [`base_path`](crate::config::MockConfig::base_path), if any.
+pub fn configure_completion_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        if state.config.is_endpoint_enabled(Endpoint::Completions) {
+            cfg.service(
+                web::resource(format!("{}/v1/completions", state.config.route_prefix()))
+                    .app_data(state)
+                    .route(web::post().to(completions_handler)),
+            );
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use actix_web::web;
+use crate::handlers::{
+    clear_faults_handler, clear_requests_handler, get_config_handler, get_faults_handler,
+    list_requests_handler, patch_config_handler, reset_handler, set_faults_handler,
+};
+use crate::state::MockState;
+
+/// Registers the runtime control API under `/__mock`, itself under the
+/// base path of `state` if it has one:
+///
+/// - `GET /__mock/config` returns the configuration in effect.
+/// - `PATCH /__mock/config` changes endpoint settings and faults, e.g.
+///   `{"routes": {"completions": {"error_rate": 1.0}}}`.
+/// - `GET /__mock/requests` lists the requests received so far.
+/// - `DELETE /__mock/requests` forgets them.
+/// - `POST /__mock/reset` returns the mock to the state it started in.
+/// - `GET /__mock/faults` returns the fault settings in effect.
+/// - `PUT /__mock/faults` replaces them with the
+///   [`FaultConfig`](crate::config::FaultConfig) in the body.
+/// - `DELETE /__mock/faults` restores the configured fault settings.
+pub fn configure_control_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let prefix = state.config.route_prefix();
+        cfg.service(
+            web::resource(format!("{}/__mock/config", prefix))
+                .app_data(state.clone())
+                .route(web::get().to(get_config_handler))
+                .route(web::patch().to(patch_config_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__mock/requests", prefix))
+                .app_data(state.clone())
+                .route(web::get().to(list_requests_handler))
+                .route(web::delete().to(clear_requests_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__mock/reset", prefix))
+                .app_data(state.clone())
+                .route(web::post().to(reset_handler)),
+        );
+        cfg.service(
+            web::resource(format!("{}/__mock/faults", prefix))
+                .app_data(state)
+                .route(web::get().to(get_faults_handler))
+                .route(web::put().to(set_faults_handler))
+                .route(web::delete().to(clear_faults_handler)),
+        );
+    }
+}
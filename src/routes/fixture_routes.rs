@@ -0,0 +1,25 @@
+use actix_web::web;
+use crate::handlers::{fixture_handler, fixture_paths};
+use crate::state::MockState;
+
+/// Registers a route for every endpoint that has response fixtures but is
+/// not emulated by the mock, e.g. `/v1/embeddings`. Such routes answer
+/// every method with the fixture. They are mounted under the base path of
+/// `state`, if any.
+///
+/// Endpoints the mock emulates keep their own routes; their fixtures are
+/// served by those handlers.
+pub fn configure_fixture_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let prefix = state.config.route_prefix();
+        for path in fixture_paths(&state) {
+            cfg.service(
+                web::resource(format!("{}{}", prefix, path))
+                    .app_data(state.clone())
+                    .default_service(web::to(fixture_handler)),
+            );
+        }
+    }
+}
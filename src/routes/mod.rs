@@ -1,2 +1,14 @@
+pub mod admin_routes;
+pub mod all_routes;
 pub mod completion_routes;
-pub use completion_routes::configure_completion_routes;
\ No newline at end of file
+pub mod control_routes;
+pub mod fixture_routes;
+pub mod model_routes;
+pub use admin_routes::{configure_admin_routes, configure_admin_routes_with};
+pub use all_routes::configure_all_routes_with;
+pub use completion_routes::{
+    configure_completion_routes, configure_completion_routes_at, configure_completion_routes_with,
+};
+pub use control_routes::configure_control_routes_with;
+pub use fixture_routes::configure_fixture_routes_with;
+pub use model_routes::configure_model_routes_with;
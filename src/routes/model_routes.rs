@@ -0,0 +1,29 @@
+use actix_web::web;
+use crate::config::Endpoint;
+use crate::handlers::{list_models_handler, retrieve_model_handler};
+use crate::state::MockState;
+
+/// Registers the Models API routes (`/v1/models`) using the given
+/// `MockState`, under its base path. Endpoints disabled in the
+/// configuration are not registered.
+pub fn configure_model_routes_with(
+    state: web::Data<MockState>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let prefix = state.config.route_prefix();
+        if state.config.is_endpoint_enabled(Endpoint::ListModels) {
+            cfg.service(
+                web::resource(format!("{}/v1/models", prefix))
+                    .app_data(state.clone())
+                    .route(web::get().to(list_models_handler)),
+            );
+        }
+        if state.config.is_endpoint_enabled(Endpoint::RetrieveModel) {
+            cfg.service(
+                web::resource(format!("{}/v1/models/{{model}}", prefix))
+                    .app_data(state)
+                    .route(web::get().to(retrieve_model_handler)),
+            );
+        }
+    }
+}
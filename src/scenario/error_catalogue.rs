@@ -0,0 +1,327 @@
+//! Errors the real API returns, with the exact status, message, `type`,
+//! `param` and `code` it uses.
+//!
+//! They are [`InjectedError`] constructors, so the same errors the mock
+//! raises itself can be injected by scenario rules and sequences:
+//!
+//! ```
+//! use openai_mock::scenario::{InjectedError, RequestMatch, ScenarioRule};
+//!
+//! let rule = ScenarioRule {
+//!     when: RequestMatch {
+//!         model: Some("gpt-4".to_string()),
+//!         ..Default::default()
+//!     },
+//!     error: Some(InjectedError::insufficient_quota()),
+//!     ..Default::default()
+//! };
+//! ```
+
+use crate::scenario::InjectedError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The limit a `rate_limit_exceeded` error reports as reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKind {
+    /// Requests per minute (RPM).
+    Requests,
+
+    /// Tokens per minute (TPM).
+    Tokens,
+}
+
+impl RateLimitKind {
+    /// The error `type` of the limit, e.g. `requests`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKind::Requests => "requests",
+            RateLimitKind::Tokens => "tokens",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            RateLimitKind::Requests => "requests per min (RPM)",
+            RateLimitKind::Tokens => "tokens per min (TPM)",
+        }
+    }
+}
+
+/// The usage reported by a `rate_limit_exceeded` error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitUsage {
+    pub kind: RateLimitKind,
+
+    /// The limit per minute.
+    pub limit: u64,
+
+    /// How much of the limit was used before the request.
+    pub used: u64,
+
+    /// How much the request asked for.
+    pub requested: u64,
+
+    /// How long until the request would fit within the limit.
+    pub retry_after: Duration,
+}
+
+impl InjectedError {
+    fn catalogued(
+        status: u16,
+        message: String,
+        error_type: &str,
+        param: Option<&str>,
+        code: Option<&str>,
+    ) -> Self {
+        Self {
+            status,
+            message,
+            error_type: Some(error_type.to_string()),
+            code: code.map(str::to_string),
+            param: param.map(str::to_string),
+        }
+    }
+
+    /// `429 insufficient_quota`: the account has run out of credit.
+    pub fn insufficient_quota() -> Self {
+        Self::catalogued(
+            429,
+            "You exceeded your current quota, please check your plan and billing details. For more information on this error, read the docs: https://platform.openai.com/docs/guides/error-codes/api-errors.".to_string(),
+            "insufficient_quota",
+            None,
+            Some("insufficient_quota"),
+        )
+    }
+
+    /// `400 context_length_exceeded` for a completion whose prompt plus
+    /// `max_tokens` does not fit in the model's context window.
+    pub fn context_length_exceeded(
+        context_window: u32,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    ) -> Self {
+        Self::catalogued(
+            400,
+            format!(
+                "This model's maximum context length is {} tokens, however you requested {} tokens ({} in your prompt; {} for the completion). Please reduce your prompt; or completion length.",
+                context_window,
+                prompt_tokens + completion_tokens,
+                prompt_tokens,
+                completion_tokens
+            ),
+            "invalid_request_error",
+            None,
+            Some("context_length_exceeded"),
+        )
+    }
+
+    /// `400 context_length_exceeded` for chat messages that do not fit in
+    /// the model's context window.
+    pub fn messages_context_length_exceeded(context_window: u32, message_tokens: u32) -> Self {
+        Self::catalogued(
+            400,
+            format!(
+                "This model's maximum context length is {} tokens. However, your messages resulted in {} tokens. Please reduce the length of the messages.",
+                context_window, message_tokens
+            ),
+            "invalid_request_error",
+            Some("messages"),
+            Some("context_length_exceeded"),
+        )
+    }
+
+    /// `400 context_length_exceeded` for chat messages plus `max_tokens`
+    /// that do not fit in the model's context window.
+    pub fn chat_context_length_exceeded(
+        context_window: u32,
+        message_tokens: u32,
+        completion_tokens: u32,
+    ) -> Self {
+        Self::catalogued(
+            400,
+            format!(
+                "This model's maximum context length is {} tokens. However, you requested {} tokens ({} in the messages, {} in the completion). Please reduce the length of the messages or completion.",
+                context_window,
+                message_tokens + completion_tokens,
+                message_tokens,
+                completion_tokens
+            ),
+            "invalid_request_error",
+            Some("messages"),
+            Some("context_length_exceeded"),
+        )
+    }
+
+    /// `429 rate_limit_exceeded`: `organization` reached a per-minute
+    /// limit for `model`.
+    pub fn rate_limit_exceeded(model: &str, organization: &str, usage: RateLimitUsage) -> Self {
+        Self::catalogued(
+            429,
+            format!(
+                "Rate limit reached for {} in organization {} on {}: Limit {}, Used {}, Requested {}. Please try again in {}. Visit https://platform.openai.com/account/rate-limits to learn more.",
+                model,
+                organization,
+                usage.kind.description(),
+                usage.limit,
+                usage.used,
+                usage.requested,
+                format_retry_after(usage.retry_after)
+            ),
+            usage.kind.as_str(),
+            None,
+            Some("rate_limit_exceeded"),
+        )
+    }
+
+    /// `401 invalid_api_key`: `key` is not a valid API key. Like the real
+    /// API, only the start and end of long keys are shown.
+    pub fn invalid_api_key(key: &str) -> Self {
+        Self::catalogued(
+            401,
+            format!(
+                "Incorrect API key provided: {}. You can find your API key at https://platform.openai.com/account/api-keys.",
+                mask_key(key)
+            ),
+            "invalid_request_error",
+            None,
+            Some("invalid_api_key"),
+        )
+    }
+
+    /// `401` for a request without an API key.
+    pub fn missing_api_key() -> Self {
+        Self::catalogued(
+            401,
+            "You didn't provide an API key. You need to provide your API key in an Authorization header using Bearer auth (i.e. Authorization: Bearer YOUR_KEY).".to_string(),
+            "invalid_request_error",
+            None,
+            None,
+        )
+    }
+
+    /// `404 model_not_found`: `model` does not exist or the caller has no
+    /// access to it.
+    pub fn model_not_found(model: &str) -> Self {
+        Self::catalogued(
+            404,
+            format!(
+                "The model `{}` does not exist or you do not have access to it.",
+                model
+            ),
+            "invalid_request_error",
+            None,
+            Some("model_not_found"),
+        )
+    }
+
+    /// `413 request_too_large`: the request body is larger than `limit`
+    /// bytes.
+    pub fn request_too_large(limit: usize) -> Self {
+        Self::catalogued(
+            413,
+            format!(
+                "Request too large. The request body must not exceed {} bytes; please reduce the size of your request.",
+                limit
+            ),
+            "invalid_request_error",
+            None,
+            Some("request_too_large"),
+        )
+    }
+
+    /// `503 engine_overloaded`: the model is overloaded with other
+    /// requests.
+    pub fn engine_overloaded() -> Self {
+        Self::catalogued(
+            503,
+            "The engine is currently overloaded, please try again later".to_string(),
+            "server_error",
+            None,
+            Some("engine_overloaded"),
+        )
+    }
+
+    /// `500 server_error`: the generic internal error.
+    pub fn server_error() -> Self {
+        Self::catalogued(
+            500,
+            "The server had an error while processing your request. Sorry about that!".to_string(),
+            "server_error",
+            None,
+            None,
+        )
+    }
+}
+
+/// Keeps the first 8 and last 4 characters of `key`, masking the rest.
+fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        return key.to_string();
+    }
+    let mut masked: String = chars[..8].iter().collect();
+    masked.push_str(&"*".repeat(chars.len() - 12));
+    masked.extend(&chars[chars.len() - 4..]);
+    masked
+}
+
+/// Formats a wait the way rate limit messages do: `120ms`, `1.5s` or
+/// `1m30s`.
+fn format_retry_after(wait: Duration) -> String {
+    let millis = wait.as_millis();
+    if millis < 1000 {
+        return format!("{}ms", millis);
+    }
+    let minutes = millis / 60_000;
+    let seconds = (millis % 60_000) as f64 / 1000.0;
+    if minutes == 0 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m{}s", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogue_messages() {
+        let error = InjectedError::rate_limit_exceeded(
+            "gpt-4",
+            "org-acme",
+            RateLimitUsage {
+                kind: RateLimitKind::Tokens,
+                limit: 10000,
+                used: 9500,
+                requested: 800,
+                retry_after: Duration::from_millis(1800),
+            },
+        );
+        assert_eq!(
+            error.message,
+            "Rate limit reached for gpt-4 in organization org-acme on tokens per min (TPM): Limit 10000, Used 9500, Requested 800. Please try again in 1.8s. Visit https://platform.openai.com/account/rate-limits to learn more."
+        );
+        assert_eq!(error.error_type.as_deref(), Some("tokens"));
+
+        let error = InjectedError::context_length_exceeded(4097, 162, 4000);
+        assert_eq!(
+            error.message,
+            "This model's maximum context length is 4097 tokens, however you requested 4162 tokens (162 in your prompt; 4000 for the completion). Please reduce your prompt; or completion length."
+        );
+    }
+
+    #[test]
+    fn test_masking_and_waits() {
+        assert_eq!(mask_key("sk-test"), "sk-test");
+        assert_eq!(
+            mask_key("sk-abcdefghijklmnop1234"),
+            "sk-abcde***********1234"
+        );
+        assert_eq!(format_retry_after(Duration::from_millis(120)), "120ms");
+        assert_eq!(format_retry_after(Duration::from_secs(20)), "20s");
+        assert_eq!(format_retry_after(Duration::from_secs(90)), "1m30s");
+    }
+}
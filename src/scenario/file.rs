@@ -0,0 +1,161 @@
+//! Loading mock behavior from scenario files.
+//!
+//! A scenario file is the declarative form of [`MockConfig`], written in
+//! YAML or TOML, so mock behavior can be defined without writing Rust:
+//!
+//! ```yaml
+//! streaming:
+//!   chunk_delay: 20ms
+//! rules:
+//!   - when: { model: gpt-4, prompt_contains: weather }
+//!     latency: 300ms
+//!     respond:
+//!       body: { id: cmpl-canned, object: text_completion, choices: [] }
+//!   - when: { model: gpt-4o }
+//!     error: { status: 429, type: requests, message: Rate limit reached, code: rate_limit_exceeded }
+//! ```
+
+use crate::config::MockConfig;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Why a scenario file could not be loaded.
+#[derive(Debug)]
+pub enum ScenarioFileError {
+    /// The file could not be read.
+    Io(io::Error),
+
+    /// The file is not valid YAML for a scenario.
+    Yaml(serde_yaml::Error),
+
+    /// The file is not valid TOML for a scenario.
+    Toml(toml::de::Error),
+
+    /// The file extension is neither `.yaml`/`.yml` nor `.toml`.
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for ScenarioFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioFileError::Io(e) => write!(f, "failed to read scenario file: {}", e),
+            ScenarioFileError::Yaml(e) => write!(f, "invalid YAML scenario: {}", e),
+            ScenarioFileError::Toml(e) => write!(f, "invalid TOML scenario: {}", e),
+            ScenarioFileError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported scenario file '{}': expected a .yaml, .yml or .toml extension",
+                path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScenarioFileError::Io(e) => Some(e),
+            ScenarioFileError::Yaml(e) => Some(e),
+            ScenarioFileError::Toml(e) => Some(e),
+            ScenarioFileError::UnsupportedFormat(_) => None,
+        }
+    }
+}
+
+impl MockConfig {
+    /// Parses a YAML scenario.
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, ScenarioFileError> {
+        serde_yaml::from_str(yaml).map_err(ScenarioFileError::Yaml)
+    }
+
+    /// Parses a TOML scenario.
+    pub fn from_toml_str(toml: &str) -> Result<Self, ScenarioFileError> {
+        toml::from_str(toml).map_err(ScenarioFileError::Toml)
+    }
+
+    /// Loads a scenario file, choosing the format by its extension.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ScenarioFileError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("yaml") | Some("yml") => {
+                Self::from_yaml_str(&std::fs::read_to_string(path).map_err(ScenarioFileError::Io)?)
+            }
+            Some("toml") => {
+                Self::from_toml_str(&std::fs::read_to_string(path).map_err(ScenarioFileError::Io)?)
+            }
+            _ => Err(ScenarioFileError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_yaml_scenario() {
+        let config = MockConfig::from_yaml_str(
+            r#"
+streaming:
+  chunk_delay: 20ms
+  granularity: { tokens: 3 }
+rules:
+  - when: { model: gpt-4, prompt_contains: weather }
+    latency: 300ms
+    respond:
+      body: { id: cmpl-canned }
+  - when: { model: gpt-4o }
+    error: { status: 429, message: Rate limit reached, code: rate_limit_exceeded }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.streaming.chunk_delay, Duration::from_millis(20));
+        assert_eq!(config.streaming.granularity, crate::config::ChunkGranularity::Tokens(3));
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].latency, Some(Duration::from_millis(300)));
+        assert_eq!(config.rules[0].respond.as_ref().unwrap().status, 200);
+        assert_eq!(config.rules[1].error.as_ref().unwrap().status, 429);
+    }
+
+    #[test]
+    fn test_toml_scenario() {
+        let config = MockConfig::from_toml_str(
+            r#"
+latency = "50ms"
+streaming = { granularity = "sentence" }
+
+[[rules]]
+when = { prompt_contains = "boom" }
+error = { status = 500, message = "The server had an error" }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.latency, Duration::from_millis(50).into());
+        assert_eq!(config.streaming.granularity, crate::config::ChunkGranularity::Sentence);
+        assert_eq!(config.rules[0].when.prompt_contains.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn test_invalid_scenarios() {
+        assert!(matches!(
+            MockConfig::from_yaml_str("rules: 3"),
+            Err(ScenarioFileError::Yaml(_))
+        ));
+        assert!(matches!(
+            MockConfig::from_file("scenario.json"),
+            Err(ScenarioFileError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            MockConfig::from_file("does-not-exist.yaml"),
+            Err(ScenarioFileError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+//! Conditions on arbitrary fields of the request body, written as JSONPath
+//! expressions (RFC 9535).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::{JsonPath, ParseError};
+
+/// A JSONPath condition on the request body.
+///
+/// Without `equals`, the condition holds when the path selects at least
+/// one node; with it, when one of the selected nodes equals `equals`:
+///
+/// ```yaml
+/// when:
+///   json_path:
+///     - path: $.tools[0].function.name
+///       equals: get_weather
+///     - path: $.tool_choice
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonPathMatch {
+    /// The expression, e.g. `$.tools[0].function.name`.
+    pub path: JsonPath,
+
+    /// Value one of the selected nodes must equal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<Value>,
+}
+
+impl JsonPathMatch {
+    /// A condition holding when `path` selects at least one node.
+    pub fn exists(path: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            path: JsonPath::parse(path)?,
+            equals: None,
+        })
+    }
+
+    /// A condition holding when a node selected by `path` equals `value`.
+    pub fn equals(path: &str, value: impl Into<Value>) -> Result<Self, ParseError> {
+        Ok(Self {
+            path: JsonPath::parse(path)?,
+            equals: Some(value.into()),
+        })
+    }
+
+    /// Whether the condition holds for `body`.
+    pub fn matches(&self, body: &Value) -> bool {
+        let nodes = self.path.query(body);
+        match &self.equals {
+            Some(expected) => nodes.iter().any(|node| *node == expected),
+            None => !nodes.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_path_match() {
+        let body = json!({
+            "model": "gpt-4o",
+            "tools": [
+                {"type": "function", "function": {"name": "get_weather"}},
+                {"type": "function", "function": {"name": "get_time"}},
+            ],
+        });
+
+        let first_tool = JsonPathMatch::equals("$.tools[0].function.name", "get_weather").unwrap();
+        assert!(first_tool.matches(&body));
+        let any_tool = JsonPathMatch::equals("$.tools[*].function.name", "get_time").unwrap();
+        assert!(any_tool.matches(&body));
+        assert!(
+            !JsonPathMatch::equals("$.tools[0].function.name", "get_time")
+                .unwrap()
+                .matches(&body)
+        );
+
+        assert!(JsonPathMatch::exists("$.tools").unwrap().matches(&body));
+        assert!(!JsonPathMatch::exists("$.tool_choice")
+            .unwrap()
+            .matches(&body));
+        assert!(JsonPathMatch::exists("$.tools[").is_err());
+    }
+
+    #[test]
+    fn test_json_path_serde() {
+        let condition: JsonPathMatch = serde_yaml::from_str("{ path: '$.n', equals: 2 }").unwrap();
+        assert!(condition.matches(&json!({"n": 2})));
+        assert!(!condition.matches(&json!({"n": "2"})));
+        assert!(serde_yaml::from_str::<JsonPathMatch>("{ path: 'n' }").is_err());
+    }
+}
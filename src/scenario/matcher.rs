@@ -0,0 +1,252 @@
+//! The extension point for deciding which requests a rule applies to.
+//!
+//! [`RequestMatch`](crate::scenario::RequestMatch) covers the common
+//! conditions. Anything else, e.g. semantic prompt matching using
+//! embeddings, can be provided by implementing [`RequestMatcher`], in this
+//! crate or in a companion crate, and attaching it to a rule with
+//! [`ScenarioRule::matching`](crate::scenario::ScenarioRule::matching).
+//!
+//! `RequestMatcher` and [`NormalizedRequest`] are part of the stable
+//! public API: new methods are only added with default implementations,
+//! and new request data only through new accessors.
+
+use crate::service::MockRequest;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A request as seen by a [`RequestMatcher`], independent of the HTTP
+/// framework serving it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedRequest {
+    method: String,
+    path: String,
+    headers: BTreeMap<String, String>,
+    body: Value,
+}
+
+impl NormalizedRequest {
+    /// A request without headers. `method` is upper-cased.
+    pub fn new(method: &str, path: &str, body: Value) -> Self {
+        Self {
+            method: method.to_ascii_uppercase(),
+            path: path.to_string(),
+            headers: BTreeMap::new(),
+            body,
+        }
+    }
+
+    /// Adds a header. Names are case-insensitive; a previous value of the
+    /// same header is replaced.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .insert(name.to_ascii_lowercase(), value.to_string());
+        self
+    }
+
+    /// Captures the method, path and headers of `http_req`, with `body`.
+    pub fn from_http(http_req: &MockRequest, body: Value) -> Self {
+        let mut request = Self::new(http_req.method().as_str(), http_req.uri().path(), body);
+        for (name, value) in http_req.headers() {
+            if let Ok(value) = value.to_str() {
+                request = request.with_header(name.as_str(), value);
+            }
+        }
+        request
+    }
+
+    /// HTTP method, upper-case, e.g. `POST`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Request path without the query string, e.g. `/v1/completions`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The value of header `name`, looked up case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Every header, keyed by lower-case name.
+    pub fn headers(&self) -> &BTreeMap<String, String> {
+        &self.headers
+    }
+
+    /// The parsed JSON body; `null` for requests without one.
+    pub fn body(&self) -> &Value {
+        &self.body
+    }
+
+    /// The `model` of the body, if any.
+    pub fn model(&self) -> Option<&str> {
+        self.body["model"].as_str()
+    }
+
+    /// The text prompts of the request: the completions `prompt` (one
+    /// entry per prompt of a batch), or the text content of every chat
+    /// message. Token-id prompts are not included.
+    pub fn prompts(&self) -> Vec<&str> {
+        prompts_of(&self.body)
+    }
+}
+
+/// The text prompts of a request body (see [`NormalizedRequest::prompts`]).
+pub(crate) fn prompts_of(body: &Value) -> Vec<&str> {
+    match &body["prompt"] {
+        Value::String(prompt) => return vec![prompt],
+        Value::Array(prompts) => return prompts.iter().filter_map(Value::as_str).collect(),
+        _ => {}
+    }
+
+    let Some(messages) = body["messages"].as_array() else {
+        return Vec::new();
+    };
+    messages
+        .iter()
+        .flat_map(|message| match &message["content"] {
+            Value::String(text) => vec![text.as_str()],
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Decides whether a rule applies to a request.
+///
+/// Implementations must be cheap to call: every rule's matchers run for
+/// every request until a rule matches. The trait is object safe, so
+/// matchers of different types can be attached to the same rule.
+pub trait RequestMatcher: Send + Sync {
+    /// Whether `request` meets the matcher's condition.
+    fn matches(&self, request: &NormalizedRequest) -> bool;
+
+    /// A short human readable description of the condition, used when
+    /// reporting rules and unmatched requests.
+    fn describe(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+impl<F> RequestMatcher for F
+where
+    F: Fn(&NormalizedRequest) -> bool + Send + Sync,
+{
+    fn matches(&self, request: &NormalizedRequest) -> bool {
+        self(request)
+    }
+
+    fn describe(&self) -> String {
+        "custom matcher".to_string()
+    }
+}
+
+/// The [`RequestMatcher`]s attached to a rule in code. They cannot be
+/// written in scenario files.
+#[derive(Clone, Default)]
+pub struct Matchers(Vec<Arc<dyn RequestMatcher>>);
+
+impl Matchers {
+    pub fn push(&mut self, matcher: impl RequestMatcher + 'static) {
+        self.0.push(Arc::new(matcher));
+    }
+
+    /// Whether every matcher accepts `request`; `true` when there are none.
+    pub fn matches(&self, request: &NormalizedRequest) -> bool {
+        self.0.iter().all(|matcher| matcher.matches(request))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn RequestMatcher> {
+        self.0.iter().map(|matcher| matcher.as_ref())
+    }
+}
+
+impl fmt::Debug for Matchers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|matcher| matcher.describe()))
+            .finish()
+    }
+}
+
+/// Matchers are compared by identity, as their conditions are opaque.
+impl PartialEq for Matchers {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct LongPrompt(usize);
+
+    impl RequestMatcher for LongPrompt {
+        fn matches(&self, request: &NormalizedRequest) -> bool {
+            request.prompts().iter().any(|prompt| prompt.len() > self.0)
+        }
+    }
+
+    #[test]
+    fn test_normalized_request() {
+        let request = NormalizedRequest::new(
+            "post",
+            "/v1/chat/completions",
+            json!({
+                "model": "gpt-4o",
+                "messages": [
+                    {"role": "system", "content": "Be brief."},
+                    {"role": "user", "content": [{"type": "text", "text": "Hi"}]},
+                ],
+            }),
+        )
+        .with_header("X-Team", "search");
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.header("x-team"), Some("search"));
+        assert_eq!(request.model(), Some("gpt-4o"));
+        assert_eq!(request.prompts(), vec!["Be brief.", "Hi"]);
+
+        let batch = NormalizedRequest::new("POST", "/v1/completions", json!({"prompt": ["a", 1]}));
+        assert_eq!(batch.prompts(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_matchers() {
+        let mut matchers = Matchers::default();
+        assert!(matchers.matches(&NormalizedRequest::new("GET", "/v1/models", Value::Null)));
+
+        matchers.push(LongPrompt(3));
+        matchers.push(|request: &NormalizedRequest| request.model() == Some("gpt-4"));
+        let request = |prompt: &str| {
+            NormalizedRequest::new(
+                "POST",
+                "/v1/completions",
+                json!({"model": "gpt-4", "prompt": prompt}),
+            )
+        };
+        assert!(matchers.matches(&request("hello")));
+        assert!(!matchers.matches(&request("hi")));
+
+        assert_eq!(
+            matchers.iter().next().unwrap().describe(),
+            std::any::type_name::<LongPrompt>()
+        );
+        assert_eq!(matchers.clone(), matchers);
+        assert_ne!(matchers, Matchers::default());
+    }
+}
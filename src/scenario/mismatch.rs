@@ -0,0 +1,141 @@
+//! Why a request does not meet the conditions of a [`RequestMatch`], for
+//! reports on requests no stub matched.
+
+use crate::scenario::{NormalizedRequest, RequestMatch};
+use serde_json::Value;
+use std::fmt;
+
+/// One condition of a [`RequestMatch`] that a request does not meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// What the condition is on, e.g. `model` or `header x-test-case`.
+    pub field: String,
+
+    /// What the condition expects, e.g. `"gpt-4"` or `to contain "refund"`.
+    pub expected: String,
+
+    /// What the request has instead.
+    pub actual: String,
+}
+
+impl Mismatch {
+    fn new(field: impl Into<String>, expected: String, actual: String) -> Self {
+        Self {
+            field: field.into(),
+            expected,
+            actual,
+        }
+    }
+}
+
+impl fmt::Display for Mismatch {
+    /// Formats the mismatch as `model: expected "gpt-4", got "gpt-4o"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expected {}, got {}", self.field, self.expected, self.actual)
+    }
+}
+
+/// A JSON string or `missing`, for reporting optional values.
+fn quoted(value: Option<&str>) -> String {
+    value.map_or_else(|| "missing".to_string(), |value| Value::from(value).to_string())
+}
+
+impl RequestMatch {
+    /// The conditions `request` does not meet; empty when it matches.
+    pub fn mismatches(&self, request: &NormalizedRequest) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        if let Some(path) = self.path.as_ref().filter(|path| *path != request.path()) {
+            mismatches.push(Mismatch::new("path", path.clone(), request.path().to_string()));
+        }
+        if let Some(model) = self.model.as_ref().filter(|model| request.model() != Some(model)) {
+            mismatches.push(Mismatch::new("model", quoted(Some(model)), quoted(request.model())));
+        }
+
+        let prompts = request.prompts();
+        let actual_prompts = || match prompts.as_slice() {
+            [] => "no prompt".to_string(),
+            [prompt] => quoted(Some(prompt)),
+            prompts => Value::from(prompts.to_vec()).to_string(),
+        };
+        if let Some(needle) = &self.prompt_contains {
+            if !prompts.iter().any(|prompt| prompt.contains(needle.as_str())) {
+                let expected = format!("to contain {}", quoted(Some(needle)));
+                mismatches.push(Mismatch::new("prompt", expected, actual_prompts()));
+            }
+        }
+        if let Some(pattern) = &self.prompt_matches {
+            if !prompts.iter().any(|prompt| pattern.is_match(prompt)) {
+                let expected = format!("to match /{}/", pattern);
+                mismatches.push(Mismatch::new("prompt", expected, actual_prompts()));
+            }
+        }
+
+        for (name, value) in &self.headers {
+            if request.header(name) != Some(value.as_str()) {
+                mismatches.push(Mismatch::new(
+                    format!("header {}", name.to_ascii_lowercase()),
+                    quoted(Some(value)),
+                    quoted(request.header(name)),
+                ));
+            }
+        }
+        for condition in self.json_path.iter().filter(|c| !c.matches(request.body())) {
+            let expected = match &condition.equals {
+                Some(value) => value.to_string(),
+                None => "to exist".to_string(),
+            };
+            let nodes = condition.path.query(request.body()).all();
+            let actual = match nodes.as_slice() {
+                [] => "missing".to_string(),
+                [node] => node.to_string(),
+                nodes => Value::from(nodes.iter().map(|node| (*node).clone()).collect::<Vec<_>>())
+                    .to_string(),
+            };
+            mismatches.push(Mismatch::new(condition.path.to_string(), expected, actual));
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::JsonPathMatch;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_mismatches() {
+        let when = RequestMatch {
+            model: Some("gpt-4".to_string()),
+            prompt_contains: Some("refund".to_string()),
+            headers: BTreeMap::from([("X-Test-Case".to_string(), "refunds".to_string())]),
+            json_path: vec![JsonPathMatch::equals("$.temperature", 0).unwrap()],
+            ..Default::default()
+        };
+        let request = NormalizedRequest::new(
+            "POST",
+            "/v1/completions",
+            json!({"model": "gpt-4o", "prompt": "Say hello", "temperature": 1}),
+        );
+        let mismatches: Vec<String> = when
+            .mismatches(&request)
+            .iter()
+            .map(Mismatch::to_string)
+            .collect();
+        assert_eq!(
+            mismatches,
+            vec![
+                r#"model: expected "gpt-4", got "gpt-4o""#,
+                r#"prompt: expected to contain "refund", got "Say hello""#,
+                r#"header x-test-case: expected "refunds", got missing"#,
+                "$.temperature: expected 0, got 1",
+            ]
+        );
+
+        let body = json!({"model": "gpt-4", "prompt": "A refund", "temperature": 0});
+        let request = NormalizedRequest::new("POST", "/v1/completions", body)
+            .with_header("X-Test-Case", "refunds");
+        assert!(when.mismatches(&request).is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+pub mod error_catalogue;
+pub mod file;
+pub mod json_path;
+pub mod matcher;
+pub mod mismatch;
+pub mod pattern;
+pub mod rule;
+pub mod sequence;
+pub mod states;
+pub use error_catalogue::{RateLimitKind, RateLimitUsage};
+pub use file::ScenarioFileError;
+pub use json_path::JsonPathMatch;
+pub use matcher::{Matchers, NormalizedRequest, RequestMatcher};
+pub use mismatch::Mismatch;
+pub use pattern::Pattern;
+pub use rule::{
+    apply_rules, matching_rule, CannedResponse, InjectedError, RequestMatch, ScenarioRule,
+    DEFAULT_SCENARIO,
+};
+pub use sequence::{ResponseStep, RuleCalls, SequenceEnd};
+pub use states::{ScenarioStates, STARTED};
@@ -0,0 +1,90 @@
+//! Regular expressions in scenario rules.
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A regular expression, written in scenario files as a string in the
+/// syntax of the [`regex`] crate, e.g. `"^Summarize the report of \d{4}-\d{2}-\d{2}"`.
+///
+/// Invalid expressions are rejected when the scenario is loaded.
+#[derive(Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    /// Whether the expression matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    /// The expression as written.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = regex::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Self::new(pattern)
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pattern({:?})", self.as_str())
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Patterns are equal when written identically.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_serde() {
+        let pattern: Pattern = serde_json::from_str(r#""report of \\d{4}-\\d{2}-\\d{2}""#).unwrap();
+        assert!(pattern.is_match("Summarize the report of 2024-06-01."));
+        assert!(!pattern.is_match("Summarize the report of yesterday."));
+        assert_eq!(
+            serde_json::to_string(&pattern).unwrap(),
+            r#""report of \\d{4}-\\d{2}-\\d{2}""#
+        );
+
+        let err = serde_json::from_str::<Pattern>(r#""(unclosed""#).unwrap_err();
+        assert!(err.to_string().contains("unclosed"));
+    }
+}
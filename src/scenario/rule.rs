@@ -0,0 +1,546 @@
+//! Declarative rules mapping requests to canned behavior.
+//!
+//! A rule pairs a [`RequestMatch`] with what should happen to matching
+//! requests: an added latency, a canned response served verbatim, or an
+//! injected API error. Rules are tried in order and the first match wins;
+//! requests matching no rule are served normally.
+//!
+//! Conditions beyond those of [`RequestMatch`] are added in code with
+//! [`ScenarioRule::matching`] (see [`RequestMatcher`]).
+
+use crate::models::{AsyncResource, ErrorResponse, LastError, OpenAIError};
+use crate::scenario::matcher::prompts_of;
+use crate::scenario::{
+    JsonPathMatch, Matchers, NormalizedRequest, Pattern, RequestMatcher, ResponseStep, RuleCalls,
+    ScenarioStates, SequenceEnd,
+};
+use crate::service::{body_response, json_response, MockResponse};
+use crate::templates::render_template;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Conditions a request must meet for a rule to apply. Every condition
+/// that is set must hold; an empty match applies to every request.
+///
+/// Header conditions let one shared mock serve different behavior to
+/// parallel test workers, each sending its own `X-Test-Case` header:
+///
+/// ```yaml
+/// rules:
+///   - when: { headers: { X-Test-Case: refund-denied } }
+///     error: { status: 403, message: Refunds are disabled }
+///   - when: { headers: { X-Test-Case: slow-upstream } }
+///     latency: 2s
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestMatch {
+    /// Exact request path, e.g. `/v1/completions`.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Exact `model` of the request body.
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Substring of the `prompt` (or of any prompt, for batched prompts)
+    /// or of the text of any chat message.
+    #[serde(default)]
+    pub prompt_contains: Option<String>,
+
+    /// Regular expression matching the `prompt` (or any prompt, for
+    /// batched prompts) or the text of any chat message. Unlike
+    /// `prompt_contains`, it tolerates parts that vary between requests,
+    /// such as timestamps.
+    #[serde(default)]
+    pub prompt_matches: Option<Pattern>,
+
+    /// Conditions on arbitrary fields of the JSON body, e.g. the name of
+    /// the first tool. Every condition must hold.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_path: Vec<JsonPathMatch>,
+
+    /// Exact values of request headers, keyed by header name. Names are
+    /// case-insensitive, e.g. `OpenAI-Organization` or `X-Test-Case`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl RequestMatch {
+    /// Whether a request to `path` with JSON `body` and no headers meets
+    /// every condition. Use the [`RequestMatcher`] implementation to match
+    /// header conditions too.
+    pub fn matches(&self, path: &str, body: &Value) -> bool {
+        self.headers.is_empty() && self.matches_body(path, body)
+    }
+
+    /// Whether the conditions other than `headers` hold.
+    fn matches_body(&self, path: &str, body: &Value) -> bool {
+        if self.path.as_ref().is_some_and(|expected| expected != path) {
+            return false;
+        }
+        if self
+            .model
+            .as_ref()
+            .is_some_and(|expected| body["model"].as_str() != Some(expected.as_str()))
+        {
+            return false;
+        }
+        if !self
+            .json_path
+            .iter()
+            .all(|condition| condition.matches(body))
+        {
+            return false;
+        }
+        if self.prompt_contains.is_none() && self.prompt_matches.is_none() {
+            return true;
+        }
+
+        let prompts = prompts_of(body);
+        if let Some(needle) = &self.prompt_contains {
+            if !prompts
+                .iter()
+                .any(|prompt| prompt.contains(needle.as_str()))
+            {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.prompt_matches {
+            if !prompts.iter().any(|prompt| pattern.is_match(prompt)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl RequestMatcher for RequestMatch {
+    fn matches(&self, request: &NormalizedRequest) -> bool {
+        self.headers
+            .iter()
+            .all(|(name, value)| request.header(name) == Some(value.as_str()))
+            && self.matches_body(request.path(), request.body())
+    }
+
+    fn describe(&self) -> String {
+        let mut conditions = Vec::new();
+        if let Some(path) = &self.path {
+            conditions.push(format!("path is {}", path));
+        }
+        if let Some(model) = &self.model {
+            conditions.push(format!("model is {}", model));
+        }
+        if let Some(needle) = &self.prompt_contains {
+            conditions.push(format!("prompt contains {:?}", needle));
+        }
+        if let Some(pattern) = &self.prompt_matches {
+            conditions.push(format!("prompt matches /{}/", pattern));
+        }
+        for (name, value) in &self.headers {
+            conditions.push(format!("header {} is {:?}", name, value));
+        }
+        for condition in &self.json_path {
+            conditions.push(match &condition.equals {
+                Some(value) => format!("{} == {}", condition.path, value),
+                None => format!("{} exists", condition.path),
+            });
+        }
+        if conditions.is_empty() {
+            "any request".to_string()
+        } else {
+            conditions.join(" and ")
+        }
+    }
+}
+
+/// A response served verbatim, or rendered per request when it is a
+/// template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CannedResponse {
+    /// HTTP status code.
+    #[serde(default = "default_status")]
+    pub status: u16,
+
+    /// JSON response body.
+    pub body: Value,
+
+    /// Render the strings of `body` as Handlebars templates with the
+    /// request as context (see [`templates`](crate::templates)).
+    #[serde(default)]
+    pub template: bool,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl CannedResponse {
+    pub fn new(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            body,
+            template: false,
+        }
+    }
+
+    /// A `200` response with asynchronous resource `id` in the `failed`
+    /// state, reporting `error`.
+    pub fn failed(resource: AsyncResource, id: &str, error: LastError) -> Self {
+        Self::new(200, resource.failed(id, error))
+    }
+
+    /// Marks the body as a template rendered per request.
+    pub fn template(mut self) -> Self {
+        self.template = true;
+        self
+    }
+
+    /// Builds the HTTP response to a request with JSON body `request`.
+    pub fn to_response(&self, request: &Value) -> MockResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        if !self.template {
+            return json_response(status, &self.body);
+        }
+
+        match render_template(&self.body.to_string(), request) {
+            Ok(body) => body_response(status, "application/json", body),
+            Err(e) => e.to_response(),
+        }
+    }
+}
+
+/// An API error returned in the standard OpenAI error envelope.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedError {
+    /// HTTP status code.
+    pub status: u16,
+
+    /// Human readable description of the error.
+    pub message: String,
+
+    /// Error `type`. Defaults to `server_error` for `5xx` statuses and
+    /// `invalid_request_error` otherwise.
+    #[serde(default, rename = "type")]
+    pub error_type: Option<String>,
+
+    /// Machine readable error code.
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// The request parameter the error refers to.
+    #[serde(default)]
+    pub param: Option<String>,
+}
+
+impl InjectedError {
+    /// The error with its status.
+    pub fn to_error_response(&self) -> ErrorResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let error_type = self.error_type.clone().unwrap_or_else(|| {
+            if status.is_server_error() {
+                "server_error".to_string()
+            } else {
+                "invalid_request_error".to_string()
+            }
+        });
+
+        ErrorResponse::new(
+            status,
+            OpenAIError {
+                message: self.message.clone(),
+                r#type: error_type,
+                param: self.param.clone(),
+                code: self.code.clone(),
+            },
+        )
+    }
+
+    /// The error envelope, e.g. for an error event of a stream.
+    pub fn to_json(&self) -> Value {
+        self.to_error_response().to_json()
+    }
+
+    /// The error response, formatted like the real API's: fields in the
+    /// order `message`, `type`, `param`, `code`, indented by four spaces.
+    pub fn to_response(&self) -> MockResponse {
+        self.to_error_response().to_response()
+    }
+}
+
+/// One scenario rule.
+///
+/// At most one of `respond` and `error` should be set. A rule with neither
+/// only adds its latency before the request is served normally. A
+/// non-empty `sequence` replaces both.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioRule {
+    /// Which requests the rule applies to.
+    #[serde(default)]
+    pub when: RequestMatch,
+
+    /// Extra delay before responding.
+    #[serde(default, with = "crate::config::duration::option")]
+    pub latency: Option<Duration>,
+
+    /// Canned response to serve.
+    #[serde(default)]
+    pub respond: Option<CannedResponse>,
+
+    /// Error to return.
+    #[serde(default)]
+    pub error: Option<InjectedError>,
+
+    /// Responses served to successive matching requests, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequence: Vec<ResponseStep>,
+
+    /// What happens once every step of `sequence` has been served.
+    #[serde(default)]
+    pub after_sequence: SequenceEnd,
+
+    /// Name of the scenario whose state `required_state` and `new_state`
+    /// refer to. Rules without one share the `default` scenario.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+
+    /// The rule only applies while its scenario is in this state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_state: Option<String>,
+
+    /// State the scenario moves to when the rule applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_state: Option<String>,
+
+    /// Conditions added in code, which must hold besides `when`.
+    #[serde(skip)]
+    pub matchers: Matchers,
+}
+
+impl ScenarioRule {
+    /// Adds a condition the request must meet besides `when`.
+    pub fn matching(mut self, matcher: impl RequestMatcher + 'static) -> Self {
+        self.matchers.push(matcher);
+        self
+    }
+
+    /// Whether the rule's conditions hold for `request`. Its scenario
+    /// state is not considered.
+    pub fn matches(&self, request: &NormalizedRequest) -> bool {
+        RequestMatcher::matches(&self.when, request) && self.matchers.matches(request)
+    }
+
+    /// The name of the rule's scenario.
+    pub fn scenario_name(&self) -> &str {
+        self.scenario.as_deref().unwrap_or(DEFAULT_SCENARIO)
+    }
+
+    /// The step of `sequence` serving the `call`-th matching request
+    /// (counting from zero), or `None` once the sequence is exhausted and
+    /// falls through.
+    pub fn sequence_step(&self, call: usize) -> Option<&ResponseStep> {
+        if self.sequence.is_empty() {
+            return None;
+        }
+        match self.after_sequence {
+            SequenceEnd::Repeat => self.sequence.get(call % self.sequence.len()),
+            SequenceEnd::FallThrough => self.sequence.get(call),
+        }
+    }
+
+    /// Waits for the rule's latency, then returns its response to a
+    /// request with JSON body `request`, or `None` if the request should be
+    /// served normally.
+    pub async fn apply(&self, request: &Value) -> Option<MockResponse> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        if let Some(error) = &self.error {
+            return Some(error.to_response());
+        }
+        self.respond
+            .as_ref()
+            .map(|respond| respond.to_response(request))
+    }
+
+    /// Like [`apply`](Self::apply), but serves `step` of the rule's
+    /// sequence.
+    async fn apply_step(&self, step: &ResponseStep, request: &Value) -> Option<MockResponse> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        step.to_response(request)
+    }
+}
+
+/// Serves `request` from the first rule that matches it, counting the
+/// calls of sequenced rules in `calls` and tracking scenario states in
+/// `states`.
+///
+/// Rules whose scenario is not in their required state, and rules whose
+/// sequence is exhausted and falls through, are skipped. Returns `None` if
+/// the request should be served normally.
+pub async fn apply_rules(
+    rules: &[ScenarioRule],
+    calls: &RuleCalls,
+    states: &ScenarioStates,
+    request: &NormalizedRequest,
+) -> Option<MockResponse> {
+    let body = request.body();
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule.matches(request) {
+            continue;
+        }
+        if rule
+            .required_state
+            .as_ref()
+            .is_some_and(|required| *required != states.get(rule.scenario_name()))
+        {
+            continue;
+        }
+
+        let step = if rule.sequence.is_empty() {
+            None
+        } else {
+            match rule.sequence_step(calls.next(index)) {
+                Some(step) => Some(step),
+                None => continue,
+            }
+        };
+        if let Some(state) = &rule.new_state {
+            states.set(rule.scenario_name(), state);
+        }
+        return match step {
+            Some(step) => rule.apply_step(step, body).await,
+            None => rule.apply(body).await,
+        };
+    }
+    None
+}
+
+/// Scenario of rules that do not name one.
+pub const DEFAULT_SCENARIO: &str = "default";
+
+/// Returns the first rule matching `request`.
+pub fn matching_rule<'a>(
+    rules: &'a [ScenarioRule],
+    request: &NormalizedRequest,
+) -> Option<&'a ScenarioRule> {
+    rules.iter().find(|rule| rule.matches(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_match() {
+        let body = json!({"model": "gpt-4", "prompt": ["hello", "what is the weather"]});
+
+        assert!(RequestMatch::default().matches("/v1/completions", &body));
+        let by_model = RequestMatch {
+            model: Some("gpt-4".to_string()),
+            ..Default::default()
+        };
+        assert!(by_model.matches("/v1/completions", &body));
+        assert!(!by_model.matches("/v1/completions", &json!({"model": "gpt-4o"})));
+
+        let by_prompt = RequestMatch {
+            path: Some("/v1/completions".to_string()),
+            prompt_contains: Some("weather".to_string()),
+            ..Default::default()
+        };
+        assert!(by_prompt.matches("/v1/completions", &body));
+        assert!(!by_prompt.matches("/v1/chat/completions", &body));
+        assert!(!by_prompt.matches("/v1/completions", &json!({"prompt": "sunny"})));
+
+        let by_pattern = RequestMatch {
+            prompt_matches: Some(Pattern::new(r"^Report for \d{4}-\d{2}-\d{2}:").unwrap()),
+            ..Default::default()
+        };
+        let chat = |text: &str| json!({"messages": [{"role": "user", "content": text}]});
+        assert!(by_pattern.matches("/v1/chat/completions", &chat("Report for 2024-06-01: ok")));
+        assert!(by_pattern.matches(
+            "/v1/completions",
+            &json!({"prompt": ["x", "Report for 2025-01-31: y"]})
+        ));
+        assert!(!by_pattern.matches("/v1/chat/completions", &chat("Report for today: ok")));
+        assert_eq!(
+            RequestMatcher::describe(&by_pattern),
+            r"prompt matches /^Report for \d{4}-\d{2}-\d{2}:/"
+        );
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = vec![
+            ScenarioRule {
+                when: RequestMatch {
+                    model: Some("gpt-4".to_string()),
+                    ..Default::default()
+                },
+                latency: Some(Duration::from_millis(5)),
+                ..Default::default()
+            },
+            ScenarioRule::default(),
+        ];
+
+        let request = |model: &str| {
+            NormalizedRequest::new("POST", "/v1/completions", json!({"model": model}))
+        };
+        let rule = matching_rule(&rules, &request("gpt-4"));
+        assert_eq!(rule, Some(&rules[0]));
+        let rule = matching_rule(&rules, &request("davinci"));
+        assert_eq!(rule, Some(&rules[1]));
+    }
+
+    #[test]
+    fn test_custom_matchers() {
+        let rule = ScenarioRule {
+            when: RequestMatch {
+                model: Some("gpt-4".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .matching(|request: &NormalizedRequest| request.header("x-tenant") == Some("acme"));
+        let by_header = RequestMatch {
+            headers: BTreeMap::from([("X-Test-Case".to_string(), "slow".to_string())]),
+            ..Default::default()
+        };
+
+        let request = NormalizedRequest::new("POST", "/v1/completions", json!({"model": "gpt-4"}));
+        assert!(!rule.matches(&request));
+        assert!(rule.matches(&request.clone().with_header("X-Tenant", "acme")));
+        assert_eq!(rule.when.describe(), "model is gpt-4");
+
+        assert!(!RequestMatcher::matches(&by_header, &request));
+        assert!(RequestMatcher::matches(
+            &by_header,
+            &request.clone().with_header("x-test-case", "slow")
+        ));
+        assert!(!by_header.matches("/v1/completions", request.body()));
+        assert_eq!(by_header.describe(), r#"header X-Test-Case is "slow""#);
+        assert_eq!(RequestMatch::default().describe(), "any request");
+    }
+
+    #[test]
+    fn test_sequence_steps() {
+        let step = |id: &str| ResponseStep::respond(CannedResponse::new(200, json!({"id": id})));
+        let mut rule = ScenarioRule {
+            sequence: vec![step("a"), step("b")],
+            ..Default::default()
+        };
+        assert_eq!(rule.sequence_step(0), Some(&step("a")));
+        assert_eq!(rule.sequence_step(3), Some(&step("b")));
+
+        rule.after_sequence = SequenceEnd::FallThrough;
+        assert_eq!(rule.sequence_step(1), Some(&step("b")));
+        assert_eq!(rule.sequence_step(2), None);
+        assert_eq!(ScenarioRule::default().sequence_step(0), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Sequenced responses: successive requests matching a rule get successive
+//! responses, e.g. to test retry and pagination loops.
+//!
+//! ```yaml
+//! rules:
+//!   - when: { prompt_contains: retry }
+//!     sequence:
+//!       - respond: { body: { id: first } }
+//!       - respond: { body: { id: second } }
+//!       - error: { status: 429, message: Rate limit reached }
+//!     after_sequence: fall_through
+//! ```
+
+use crate::scenario::{CannedResponse, InjectedError};
+use crate::service::MockResponse;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One response of a sequence. A step with neither `respond` nor `error`
+/// lets that request be served normally.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseStep {
+    /// Canned response to serve.
+    #[serde(default)]
+    pub respond: Option<CannedResponse>,
+
+    /// Error to return.
+    #[serde(default)]
+    pub error: Option<InjectedError>,
+}
+
+impl ResponseStep {
+    /// A step serving `response`.
+    pub fn respond(response: CannedResponse) -> Self {
+        Self {
+            respond: Some(response),
+            error: None,
+        }
+    }
+
+    /// A step returning `error`.
+    pub fn error(error: InjectedError) -> Self {
+        Self {
+            respond: None,
+            error: Some(error),
+        }
+    }
+
+    /// The step's response to a request with JSON body `request`, or
+    /// `None` if the request should be served normally.
+    pub fn to_response(&self, request: &Value) -> Option<MockResponse> {
+        if let Some(error) = &self.error {
+            return Some(error.to_response());
+        }
+        self.respond.as_ref().map(|respond| respond.to_response(request))
+    }
+}
+
+/// What happens once every step of a sequence has been served.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceEnd {
+    /// Start over from the first step.
+    #[default]
+    Repeat,
+
+    /// The rule no longer applies; later rules, or the normal behavior,
+    /// serve the request.
+    FallThrough,
+}
+
+/// Number of requests each sequenced rule has matched so far, keyed by the
+/// rule's position. Kept per mock instance.
+#[derive(Debug, Default)]
+pub struct RuleCalls {
+    counts: DashMap<usize, usize>,
+}
+
+impl RuleCalls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a request matching rule `rule`, returning how many requests
+    /// matched it before this one.
+    pub fn next(&self, rule: usize) -> usize {
+        let mut count = self.counts.entry(rule).or_default();
+        *count += 1;
+        *count - 1
+    }
+
+    /// Forgets every count, restarting all sequences.
+    pub fn clear(&self) {
+        self.counts.clear();
+    }
+}
@@ -0,0 +1,66 @@
+//! Named scenario states, in the style of WireMock's stateful scenarios.
+//!
+//! A rule can require its scenario to be in a given state and move it to a
+//! new state when it applies, so multi-step workflows (e.g. create a
+//! fine-tuning job, then poll until it is ready) can be scripted:
+//!
+//! ```yaml
+//! rules:
+//!   - when: { prompt_contains: create }
+//!     scenario: fine-tune
+//!     new_state: queued
+//!     respond: { body: { status: queued } }
+//!   - when: { prompt_contains: poll }
+//!     scenario: fine-tune
+//!     required_state: queued
+//!     new_state: succeeded
+//!     respond: { body: { status: running } }
+//!   - when: { prompt_contains: poll }
+//!     scenario: fine-tune
+//!     required_state: succeeded
+//!     respond: { body: { status: succeeded } }
+//! ```
+
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+
+/// The state every scenario starts in.
+pub const STARTED: &str = "Started";
+
+/// The current state of every scenario, kept per mock instance. Scenarios
+/// not yet transitioned are in [`STARTED`].
+#[derive(Debug, Default)]
+pub struct ScenarioStates {
+    states: DashMap<String, String>,
+}
+
+impl ScenarioStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current state of `scenario`.
+    pub fn get(&self, scenario: &str) -> String {
+        self.states
+            .get(scenario)
+            .map_or_else(|| STARTED.to_string(), |state| state.clone())
+    }
+
+    /// Moves `scenario` to `state`.
+    pub fn set(&self, scenario: &str, state: &str) {
+        self.states.insert(scenario.to_string(), state.to_string());
+    }
+
+    /// The state of every scenario that has left [`STARTED`].
+    pub fn all(&self) -> BTreeMap<String, String> {
+        self.states
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Moves every scenario back to [`STARTED`].
+    pub fn clear(&self) {
+        self.states.clear();
+    }
+}
@@ -0,0 +1,191 @@
+//! Socket binding for the standalone mock server.
+//!
+//! CI machines frequently have port clashes, so binding retries a range of
+//! ports instead of failing on the first conflict, and can listen on both
+//! IPv4 and IPv6 loopback at once.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::ops::RangeInclusive;
+
+/// How many ephemeral ports a dual-stack bind tries before giving up.
+const DUAL_STACK_ATTEMPTS: usize = 16;
+
+/// Which address family (or families) the server listens on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindAddress {
+    /// A single IPv4 or IPv6 address.
+    Single(IpAddr),
+
+    /// Both `127.0.0.1` and `::1`, on the same port. Falls back to IPv4
+    /// only if the host has no IPv6 loopback.
+    DualStack,
+}
+
+impl Default for BindAddress {
+    fn default() -> Self {
+        BindAddress::Single(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+}
+
+/// Where the mock server listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindConfig {
+    /// Address family selection.
+    pub address: BindAddress,
+
+    /// Ports to try, in order, until one is free. `0..=0` asks the OS for
+    /// an ephemeral port.
+    pub ports: RangeInclusive<u16>,
+}
+
+impl Default for BindConfig {
+    fn default() -> Self {
+        Self::ephemeral()
+    }
+}
+
+impl BindConfig {
+    /// Binds `127.0.0.1` on a port chosen by the OS.
+    pub fn ephemeral() -> Self {
+        Self {
+            address: BindAddress::default(),
+            ports: 0..=0,
+        }
+    }
+
+    /// Binds `127.0.0.1` on exactly `port`.
+    pub fn port(port: u16) -> Self {
+        Self::port_range(port..=port)
+    }
+
+    /// Binds `127.0.0.1` on the first free port in `ports`.
+    pub fn port_range(ports: RangeInclusive<u16>) -> Self {
+        Self {
+            address: BindAddress::default(),
+            ports,
+        }
+    }
+
+    /// Listens on a specific address instead of IPv4 loopback.
+    pub fn address(mut self, ip: IpAddr) -> Self {
+        self.address = BindAddress::Single(ip);
+        self
+    }
+
+    /// Listens on both IPv4 and IPv6 loopback.
+    pub fn dual_stack(mut self) -> Self {
+        self.address = BindAddress::DualStack;
+        self
+    }
+
+    /// Binds the configured sockets, retrying the port range on conflict.
+    ///
+    /// Returns one listener per address family. Fails with
+    /// `AddrInUse` only once every port in the range has been tried.
+    pub fn bind(&self) -> io::Result<Vec<TcpListener>> {
+        let mut last_error = None;
+
+        for port in self.ports.clone() {
+            match self.bind_port(port) {
+                Ok(listeners) => return Ok(listeners),
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!(
+                "no free port in {}..={}: {}",
+                self.ports.start(),
+                self.ports.end(),
+                last_error.map_or_else(|| "empty port range".to_string(), |e| e.to_string())
+            ),
+        ))
+    }
+
+    fn bind_port(&self, port: u16) -> io::Result<Vec<TcpListener>> {
+        match self.address {
+            BindAddress::Single(ip) => Ok(vec![TcpListener::bind(SocketAddr::new(ip, port))?]),
+            BindAddress::DualStack if port == 0 => {
+                // The ephemeral port the OS picks for IPv4 may be taken on
+                // IPv6; another one is likely free on both.
+                let mut result = Self::bind_dual_stack(port);
+                for _ in 1..DUAL_STACK_ATTEMPTS {
+                    match &result {
+                        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                            result = Self::bind_dual_stack(port);
+                        }
+                        _ => break,
+                    }
+                }
+                result
+            }
+            BindAddress::DualStack => Self::bind_dual_stack(port),
+        }
+    }
+
+    fn bind_dual_stack(port: u16) -> io::Result<Vec<TcpListener>> {
+        let v4 = TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))?;
+        // Reuse the port picked for IPv4 so both families share it.
+        let port = v4.local_addr()?.port();
+
+        match TcpListener::bind(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port)) {
+            Ok(v6) => Ok(vec![v4, v6]),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => Err(e),
+            Err(e) => {
+                log::warn!("IPv6 loopback unavailable ({}), listening on IPv4 only", e);
+                Ok(vec![v4])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ephemeral_bind() {
+        let listeners = BindConfig::ephemeral().bind().unwrap();
+        assert_eq!(listeners.len(), 1);
+        assert_ne!(listeners[0].local_addr().unwrap().port(), 0);
+    }
+
+    #[test]
+    fn test_port_range_skips_taken_ports() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let end = port.saturating_add(50);
+        if end == port {
+            return;
+        }
+
+        let listeners = BindConfig::port_range(port..=end).bind().unwrap();
+        let bound = listeners[0].local_addr().unwrap().port();
+        assert!(bound > port && bound <= end);
+    }
+
+    #[test]
+    fn test_exhausted_range_reports_addr_in_use() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let err = BindConfig::port(port).bind().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[test]
+    fn test_dual_stack_shares_port() {
+        let listeners = BindConfig::ephemeral().dual_stack().bind().unwrap();
+        let ports: Vec<u16> = listeners
+            .iter()
+            .map(|l| l.local_addr().unwrap().port())
+            .collect();
+        assert!(ports.iter().all(|p| *p == ports[0]));
+
+        let has_ipv6 = TcpListener::bind((Ipv6Addr::LOCALHOST, 0)).is_ok();
+        assert_eq!(listeners.len(), if has_ipv6 { 2 } else { 1 });
+    }
+}
@@ -0,0 +1,294 @@
+//! Programmatic configuration of a [`MockServer`].
+
+use crate::config::{
+    ChatReply, CorsConfig, Endpoint, GenerationStrategy, HighThroughputConfig, KeyProfile, Latency,
+    MockConfig, PromptResponse, UnmatchedRequests, WhenPromptContains,
+};
+use crate::fixtures::ResponseFixtures;
+use crate::generation::TextGenerator;
+use crate::hooks::{RequestSummary, ResponseSummary, StreamEndSummary};
+use crate::models::completion::CompletionRequest;
+use crate::models::ChatCompletionRequest;
+use crate::scenario::{CannedResponse, NormalizedRequest};
+use crate::mirror::MirrorSink;
+use crate::server::{BindConfig, MockServer, MockServerHandle, RuntimeConfig};
+use std::future::Future;
+use std::io;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// Builder for a [`MockServer`], created with [`MockServer::builder`].
+///
+/// ```no_run
+/// use openai_mock::config::{Endpoint, GenerationStrategy};
+/// use openai_mock::server::MockServer;
+/// use std::time::Duration;
+///
+/// let server = MockServer::builder()
+///     .endpoints([Endpoint::Completions])
+///     .latency(Duration::from_millis(50))
+///     .api_key("sk-test")
+///     .generation_strategy(GenerationStrategy::Fixed { text: "Hello!".to_string() })
+///     .start()
+///     .unwrap();
+/// let url = format!("{}/v1/completions", server.base_url());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockServerBuilder {
+    config: MockConfig,
+    bind: BindConfig,
+    runtime: RuntimeConfig,
+}
+
+impl MockServerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the whole configuration. Settings applied afterwards are
+    /// layered on top of it.
+    pub fn config(mut self, config: MockConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Replaces the bind settings.
+    pub fn bind(mut self, bind: BindConfig) -> Self {
+        self.bind = bind;
+        self
+    }
+
+    /// Replaces the worker and connection settings.
+    pub fn runtime(mut self, runtime: RuntimeConfig) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Serves connections on `workers` threads instead of one. Zero
+    /// workers make [`start`](Self::start) fail with `InvalidInput`.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.runtime.workers = Some(workers);
+        self
+    }
+
+    /// Keeps idle connections open for `keep_alive`; `Duration::ZERO`
+    /// closes every connection after its response.
+    pub fn connection_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.runtime.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Answers `408` to clients not sending request headers within
+    /// `timeout`.
+    pub fn client_request_timeout(mut self, timeout: Duration) -> Self {
+        self.runtime.client_request_timeout = Some(timeout);
+        self
+    }
+
+    /// Listens on exactly `port` instead of an ephemeral one.
+    pub fn port(mut self, port: u16) -> Self {
+        self.bind.ports = port..=port;
+        self
+    }
+
+    /// Listens on the first free port in `ports`.
+    pub fn port_range(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.bind.ports = ports;
+        self
+    }
+
+    /// Serves only `endpoints`; the others answer `404`.
+    pub fn endpoints(mut self, endpoints: impl IntoIterator<Item = Endpoint>) -> Self {
+        self.config = self.config.with_endpoints(endpoints);
+        self
+    }
+
+    /// Delays every response by `latency`, a fixed
+    /// [`Duration`] or a [`Latency`] distribution.
+    pub fn latency(mut self, latency: impl Into<Latency>) -> Self {
+        self.config = self.config.with_latency(latency);
+        self
+    }
+
+    /// Requires `key` as a bearer token. May be called several times to
+    /// accept several keys.
+    pub fn api_key(mut self, key: &str) -> Self {
+        self.config = self.config.with_api_key(key);
+        self
+    }
+
+    /// Accepts `key` as a bearer token, serving its requests as set by
+    /// `profile`.
+    pub fn key_profile(mut self, key: &str, profile: KeyProfile) -> Self {
+        self.config = self.config.with_key_profile(key, profile);
+        self
+    }
+
+    /// Sets how the completion text is produced.
+    pub fn generation_strategy(mut self, strategy: GenerationStrategy) -> Self {
+        self.config = self.config.with_generation_strategy(strategy);
+        self
+    }
+
+    /// Completes every completion request with `text` and answers every
+    /// chat completion request with an assistant message of `text`.
+    pub fn fixed_response(mut self, text: &str) -> Self {
+        self.config = self.config.with_fixed_response(text);
+        self
+    }
+
+    /// Answers chat completion requests no fixture matches with `reply`,
+    /// a message or tool calls.
+    pub fn chat_reply(mut self, reply: ChatReply) -> Self {
+        self.config = self.config.with_chat_reply(reply);
+        self
+    }
+
+    /// Produces the completion text with `generator` instead of the
+    /// generation strategy.
+    pub fn text_generator(mut self, generator: impl TextGenerator + 'static) -> Self {
+        self.config = self.config.with_text_generator(generator);
+        self
+    }
+
+    /// Starts mapping prompts containing `needle` to a fixed completion,
+    /// e.g. `builder.when_prompt_contains("refund").respond_with("...")`.
+    /// Mappings are consulted before the generation strategy, in the order
+    /// added.
+    pub fn when_prompt_contains(self, needle: &str) -> WhenPromptContains<MockServerBuilder> {
+        WhenPromptContains {
+            parent: self,
+            prompt_contains: needle.to_string(),
+        }
+    }
+
+    /// Serves matching requests from `fixtures`.
+    pub fn fixtures(mut self, fixtures: ResponseFixtures) -> Self {
+        self.config = self.config.with_fixtures(fixtures);
+        self
+    }
+
+    /// Answers cross-origin requests from browsers as configured by
+    /// `cors`.
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.config = self.config.with_cors(cors);
+        self
+    }
+
+    /// Mounts every route under `base_path`, e.g. `/openai` to serve
+    /// completions at `/openai/v1/completions`.
+    pub fn base_path(mut self, base_path: &str) -> Self {
+        self.config = self.config.with_base_path(base_path);
+        self
+    }
+
+    /// Rejects request bodies larger than `bytes` with a `413` error.
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.config = self.config.with_max_body_size(bytes);
+        self
+    }
+
+    /// Serves every completion with one precomputed response, for load
+    /// tests where the mock must answer as fast as possible.
+    pub fn high_throughput(mut self, high_throughput: HighThroughputConfig) -> Self {
+        self.config = self.config.with_high_throughput(high_throughput);
+        self
+    }
+
+    /// Sets what happens to requests no stub matches.
+    pub fn unmatched(mut self, behavior: UnmatchedRequests) -> Self {
+        self.config = self.config.with_unmatched(behavior);
+        self
+    }
+
+    /// Mirrors every incoming request to `sink`.
+    pub fn mirror(mut self, sink: MirrorSink) -> Self {
+        self.config = self.config.with_mirror(sink);
+        self
+    }
+
+    /// Runs `hook` before each request is served. If it returns a
+    /// response, the request is answered with it instead.
+    pub fn on_request<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(RequestSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<CannedResponse>> + Send + 'static,
+    {
+        self.config.hooks = self.config.hooks.on_request(hook);
+        self
+    }
+
+    /// Runs `hook` once each response is produced.
+    pub fn on_response<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ResponseSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.config.hooks = self.config.hooks.on_response(hook);
+        self
+    }
+
+    /// Runs `hook` when each streamed response ends, whether completed or
+    /// cancelled by the client.
+    pub fn on_stream_end<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(StreamEndSummary) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.config.hooks = self.config.hooks.on_stream_end(hook);
+        self
+    }
+
+    /// Answers every request to `endpoint` with the response `responder`
+    /// returns, instead of the fixture or generated one.
+    pub fn respond_with<F>(mut self, endpoint: Endpoint, responder: F) -> Self
+    where
+        F: Fn(&NormalizedRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.config.responders = self.config.responders.respond(endpoint, responder);
+        self
+    }
+
+    /// Answers every completions request with the response `responder`
+    /// returns for the parsed request, instead of the fixture or generated
+    /// one.
+    pub fn respond_to_completions<F>(mut self, responder: F) -> Self
+    where
+        F: Fn(&CompletionRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.config.responders = self.config.responders.completions(responder);
+        self
+    }
+
+    /// Answers every chat completions request with the response
+    /// `responder` returns for the parsed request, instead of the fixture
+    /// or configured chat reply.
+    pub fn respond_to_chat<F>(mut self, responder: F) -> Self
+    where
+        F: Fn(&ChatCompletionRequest) -> CannedResponse + Send + Sync + 'static,
+    {
+        self.config.responders = self.config.responders.chat(responder);
+        self
+    }
+
+    /// Starts the server on a background thread.
+    pub fn start(self) -> io::Result<MockServerHandle> {
+        MockServer::start_with_runtime(self.config, self.bind, self.runtime)
+    }
+}
+
+impl WhenPromptContains<MockServerBuilder> {
+    /// Completes every request whose prompt contains the substring with
+    /// `text`.
+    pub fn respond_with(mut self, text: &str) -> MockServerBuilder {
+        self.parent
+            .config
+            .generation
+            .prompt_responses
+            .push(PromptResponse {
+                prompt_contains: self.prompt_contains,
+                text: text.to_string(),
+            });
+        self.parent
+    }
+}
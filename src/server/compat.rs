@@ -0,0 +1,63 @@
+//! Ready-made actix applications, kept for code written against the
+//! original single-module API of the crate.
+//!
+//! New code should either run a [`MockServer`](crate::server::MockServer)
+//! or mount the routes into its own application with
+//! [`configure_all_routes_with`]; both expose every feature of the mock.
+
+use crate::config::MockConfig;
+use crate::routes::configure_all_routes_with;
+use crate::service::actix::cors_middleware;
+use crate::state::MockState;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, Error};
+
+/// Builds an application serving every mock route with the default
+/// configuration.
+///
+/// Each call creates its own [`MockState`], so when used as the factory of
+/// an `HttpServer`, every worker keeps its own request history.
+#[deprecated(
+    since = "0.2.0",
+    note = "use `create_mock_app_with`, `MockServer::builder()` or `configure_all_routes_with`"
+)]
+pub fn create_mock_app() -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    create_mock_app_with(None)
+}
+
+/// Like `create_mock_app`, using `config` instead of the default
+/// configuration when one is given.
+///
+/// The routes are wrapped in the same CORS handling as a
+/// [`MockServer`](crate::server::MockServer)'s; API keys are checked by
+/// the handlers themselves.
+pub fn create_mock_app_with(
+    config: Option<MockConfig>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let state = web::Data::new(MockState::new(config.unwrap_or_default()));
+    let cors_state = state.clone();
+    App::new()
+        .wrap(from_fn(move |req, next| {
+            let state = cors_state.clone();
+            async move { cors_middleware(state.config.cors.as_ref(), req, next).await }
+        }))
+        .configure(configure_all_routes_with(state))
+}
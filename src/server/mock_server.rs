@@ -0,0 +1,490 @@
+//! A self-contained mock server running on a background thread.
+
+use crate::config::{
+    Endpoint, MockConfig, ModelConfig, RouteConfig, UnmatchedRequests, UsageTier,
+};
+use crate::diff::{CassetteRequest, CassetteResponse, Interaction};
+use crate::expectations::{unmatched_report, Expectation, ExpectationBuilder, Operation};
+use crate::models::{ChatCompletionRequest, CompletionRequest};
+use crate::routes::configure_all_routes_with;
+use crate::server::{BindConfig, MockServerBuilder, RuntimeConfig};
+use crate::service::actix::cors_middleware;
+use crate::state::{MockState, MockStats, RecordedRequest};
+use actix_web::dev::ServerHandle;
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, HttpServer};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+/// One line written by [`MockServerHandle::dump_traffic`].
+#[derive(Serialize)]
+struct TrafficEntry {
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    interaction: Interaction,
+}
+
+/// Entry point for running the mock as a real HTTP server.
+///
+/// The server runs on its own thread with its own actix system, so it can
+/// be started from synchronous tests as well as from any async runtime.
+pub struct MockServer;
+
+impl MockServer {
+    /// Returns a builder for configuring and starting a server.
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::new()
+    }
+
+    /// Starts a mock server with the default configuration on a port of
+    /// `127.0.0.1` chosen by the OS, so servers started by tests running in
+    /// parallel never collide.
+    ///
+    /// The server stops when the returned handle is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server cannot be started; use
+    /// [`start_with`](Self::start_with) to handle the error.
+    pub fn start() -> MockServerHandle {
+        Self::start_with(MockConfig::default(), BindConfig::ephemeral())
+            .unwrap_or_else(|e| panic!("failed to start mock server: {}", e))
+    }
+
+    /// Starts a mock server using `config`, listening as described by
+    /// `bind`.
+    ///
+    /// Port conflicts are resolved by trying the rest of `bind.ports`; an
+    /// error is returned only when no port in the range is free.
+    pub fn start_with(config: MockConfig, bind: BindConfig) -> io::Result<MockServerHandle> {
+        Self::start_with_runtime(config, bind, RuntimeConfig::default())
+    }
+
+    /// Like [`start_with`](Self::start_with), serving connections as
+    /// described by `runtime`. Fails with `InvalidInput` if `runtime` asks
+    /// for zero workers.
+    pub fn start_with_runtime(
+        config: MockConfig,
+        bind: BindConfig,
+        runtime: RuntimeConfig,
+    ) -> io::Result<MockServerHandle> {
+        if runtime.workers == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the server needs at least one worker",
+            ));
+        }
+        let listeners = bind.bind()?;
+        let addrs = listeners
+            .iter()
+            .map(|listener| listener.local_addr())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let state = web::Data::new(MockState::new(config));
+        let server_state = state.clone();
+
+        let (handle_tx, handle_rx) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("openai-mock-server".to_string())
+            .spawn(move || {
+                actix_rt::System::new().block_on(async move {
+                    let mut server = HttpServer::new(move || {
+                        let state = server_state.clone();
+                        App::new()
+                            .wrap(from_fn(move |req, next| {
+                                let state = state.clone();
+                                async move {
+                                    cors_middleware(state.config.cors.as_ref(), req, next).await
+                                }
+                            }))
+                            .configure(configure_all_routes_with(server_state.clone()))
+                    })
+                    .workers(runtime.workers.unwrap_or(1));
+                    if let Some(keep_alive) = runtime.keep_alive {
+                        server = server.keep_alive(keep_alive);
+                    }
+                    if let Some(timeout) = runtime.client_request_timeout {
+                        server = server.client_request_timeout(timeout);
+                    }
+                    if let Some(timeout) = runtime.client_disconnect_timeout {
+                        server = server.client_disconnect_timeout(timeout);
+                    }
+                    if let Some(max) = runtime.max_connections {
+                        server = server.max_connections(max);
+                    }
+                    for listener in listeners {
+                        server = server.listen(listener)?;
+                    }
+
+                    let server = server.run();
+                    let _ = handle_tx.send(server.handle());
+                    server.await
+                })
+            })?;
+
+        match handle_rx.recv() {
+            Ok(server) => Ok(MockServerHandle {
+                addrs,
+                server,
+                thread: Some(thread),
+                state,
+            }),
+            // The thread exited before the server started; surface its error.
+            Err(_) => Err(match thread.join() {
+                Ok(Err(e)) => e,
+                _ => io::Error::other("mock server thread exited during startup"),
+            }),
+        }
+    }
+}
+
+/// Handle to a running [`MockServer`].
+///
+/// Dropping the handle stops the server, without waiting for requests in
+/// flight, and waits for its thread to exit; see [`shutdown`](Self::shutdown)
+/// to let them complete. When unmatched requests fail the test, dropping it
+/// also panics if any were received or an expectation is unmet, like
+/// [`verify`](Self::verify).
+pub struct MockServerHandle {
+    addrs: Vec<SocketAddr>,
+    server: ServerHandle,
+    thread: Option<JoinHandle<io::Result<()>>>,
+    state: web::Data<MockState>,
+}
+
+impl MockServerHandle {
+    /// Every socket address the server is listening on.
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// The primary socket address (IPv4 when listening dual-stack).
+    pub fn addr(&self) -> SocketAddr {
+        self.addrs[0]
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:49152`, followed by
+    /// the configured base path, if any: `http://127.0.0.1:49152/openai`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}{}", self.addr(), self.state.config.route_prefix())
+    }
+
+    /// URI of the server, e.g. `http://127.0.0.1:49152`; the same as
+    /// [`base_url`](Self::base_url).
+    pub fn uri(&self) -> String {
+        self.base_url()
+    }
+
+    /// Stops the server gracefully: it stops accepting connections and
+    /// waits for the requests in flight to complete, then its thread exits.
+    pub async fn shutdown(self) {
+        self.server.stop(true).await;
+    }
+
+    /// The state shared by the server's handlers.
+    pub fn state(&self) -> &MockState {
+        &self.state
+    }
+
+    /// A snapshot of the server's activity, including the active tokenizer
+    /// mode.
+    pub fn stats(&self) -> MockStats {
+        self.state.stats()
+    }
+
+    /// Overrides the latency, error rate or canned response of `endpoint`
+    /// while the server is running. Unset fields of `route` keep their
+    /// configured values; a previous override is replaced.
+    ///
+    /// The same can be done over HTTP with `PUT /__admin/routes/{endpoint}`.
+    pub fn set_route(&self, endpoint: Endpoint, route: RouteConfig) {
+        self.state.routes.set(endpoint, route);
+    }
+
+    /// Restores the configured settings of `endpoint`.
+    pub fn clear_route(&self, endpoint: Endpoint) {
+        self.state.routes.clear(endpoint);
+    }
+
+    /// Adds model `id` to this server, or changes its description, while
+    /// it is running: e.g. gives a model released after this crate its
+    /// context window and encoding. Other mock servers are unaffected.
+    pub fn register_model(&self, id: &str, model: ModelConfig) {
+        self.state.models.register(id, &model);
+    }
+
+    /// Moves API key `key` to usage `tier` while the server is running,
+    /// changing the limits advertised to it.
+    ///
+    /// The same can be done over HTTP with `PUT /__admin/keys/{key}/tier`.
+    pub fn set_key_tier(&self, key: &str, tier: UsageTier) {
+        self.state.key_tiers.set_tier(key, tier);
+    }
+
+    /// The simulated spend in USD of API key `key`, checked against the
+    /// budget of its profile.
+    pub fn key_spend(&self, key: &str) -> f64 {
+        self.state.key_spend.spent(key)
+    }
+
+    /// Sets the simulated spend of API key `key`, e.g. to exhaust its
+    /// budget without sending requests.
+    pub fn set_key_spend(&self, key: &str, usd: f64) {
+        self.state.key_spend.set(key, usd);
+    }
+
+    /// The current state of `scenario` (see
+    /// [`ScenarioRule::required_state`](crate::scenario::ScenarioRule::required_state)).
+    pub fn scenario_state(&self, scenario: &str) -> String {
+        self.state.scenario_states.get(scenario)
+    }
+
+    /// Moves `scenario` to `state`.
+    ///
+    /// The same can be done over HTTP with `PUT /__admin/scenarios/{scenario}`.
+    pub fn set_scenario_state(&self, scenario: &str, state: &str) {
+        self.state.scenario_states.set(scenario, state);
+    }
+
+    /// Returns the server to the state it started in, so consecutive test
+    /// cases can share one server without seeing each other's requests,
+    /// rate limit usage, scenario states or runtime settings (see
+    /// [`MockState::reset`]).
+    ///
+    /// The same can be done over HTTP with `POST /__mock/reset`.
+    pub fn reset(&self) {
+        self.state.reset();
+    }
+
+    /// Every request received so far, oldest first, including the partial
+    /// usage of streams the client cancelled.
+    pub fn received_requests(&self) -> Vec<RecordedRequest> {
+        self.state.history.all()
+    }
+
+    /// The requests received at `path`, e.g. `/v1/embeddings`, oldest
+    /// first.
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.state.history.filter(|record| record.path == path)
+    }
+
+    /// The requests received for `model`, on any endpoint, oldest first.
+    pub fn requests_for_model(&self, model: &str) -> Vec<RecordedRequest> {
+        self.state.history.filter(|record| record.model() == Some(model))
+    }
+
+    /// The `POST /v1/completions` requests received, oldest first.
+    pub fn completion_requests(&self) -> Vec<RecordedRequest> {
+        self.requests_to(Endpoint::Completions.path())
+    }
+
+    /// The `POST /v1/chat/completions` requests received, oldest first.
+    pub fn chat_requests(&self) -> Vec<RecordedRequest> {
+        self.requests_to("/v1/chat/completions")
+    }
+
+    /// The `POST /v1/embeddings` requests received, oldest first.
+    pub fn embedding_requests(&self) -> Vec<RecordedRequest> {
+        self.requests_to("/v1/embeddings")
+    }
+
+    /// The most recent request received, if any.
+    pub fn last_request(&self) -> Option<RecordedRequest> {
+        self.received_requests().pop()
+    }
+
+    /// The body of the most recent chat completion request, if any, as a
+    /// typed request. `None` when the body is not a valid chat completion
+    /// request.
+    pub fn last_chat_request(&self) -> Option<ChatCompletionRequest> {
+        let record = self.chat_requests().pop()?;
+        serde_json::from_value(record.body).ok()
+    }
+
+    /// The body of the most recent completion request, if any, as a typed
+    /// request. `None` when the body is not a valid completion request.
+    pub fn last_completion_request(&self) -> Option<CompletionRequest> {
+        let record = self.completion_requests().pop()?;
+        serde_json::from_value(record.body).ok()
+    }
+
+    /// Panics unless some request received so far, on any endpoint, used
+    /// `model`. The panic message lists the models that were used.
+    pub fn assert_model_used(&self, model: &str) {
+        let requests = self.received_requests();
+        let used: BTreeSet<&str> = requests.iter().filter_map(RecordedRequest::model).collect();
+        assert!(
+            used.contains(model),
+            "expected a request for model {:?}, but the models used were {:?}",
+            model,
+            used
+        );
+    }
+
+    /// Panics unless a `system` or `developer` message of the most recent
+    /// chat completion request contains `text`.
+    pub fn assert_system_prompt_contains(&self, text: &str) {
+        let request = self
+            .last_chat_request()
+            .expect("expected a chat completion request, but none was received");
+        let prompts = request.system_prompts();
+        assert!(
+            prompts.iter().any(|prompt| prompt.contains(text)),
+            "expected the system prompt to contain {:?}, but it was {:?}",
+            text,
+            prompts
+        );
+    }
+
+    /// Writes every request answered so far and its response to `path` as
+    /// JSON Lines, oldest first, for inspecting the traffic of a flaky
+    /// test. Each line is an [`Interaction`] of a
+    /// [`Cassette`](crate::diff::Cassette) with the time the request was
+    /// received, so the file loads as a cassette:
+    ///
+    /// ```json
+    /// {"timestamp": "2024-05-01T12:00:00Z", "request": {"method": "POST", "path": "/v1/completions", "headers": {}, "body": {}}, "response": {"status": 200, "body": {}}}
+    /// ```
+    ///
+    /// Requests still waiting for their response are left out; streamed
+    /// responses are written as far as they have been sent.
+    pub fn dump_traffic(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for record in self.received_requests() {
+            let Some(response) = record.response else {
+                continue;
+            };
+            let entry = TrafficEntry {
+                timestamp: record.timestamp,
+                interaction: Interaction {
+                    request: CassetteRequest {
+                        method: record.method,
+                        path: record.path,
+                        headers: record.headers,
+                        body: record.body,
+                    },
+                    response: CassetteResponse {
+                        status: response.status,
+                        body: response.body,
+                    },
+                },
+            };
+            serde_json::to_writer(&mut file, &entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
+
+    /// The requests no stub matched while unmatched requests fail the test
+    /// (see [`UnmatchedRequests::Fail`]), oldest first.
+    pub fn unexpected_requests(&self) -> Vec<RecordedRequest> {
+        self.state.history.unexpected()
+    }
+
+    /// Expects the system under test to call `operation`, at least once
+    /// unless the returned builder says otherwise:
+    ///
+    /// ```no_run
+    /// # use openai_mock::expectations::Operation;
+    /// # let mock = openai_mock::server::MockServer::builder().start().unwrap();
+    /// mock.expect(Operation::Embeddings).times(2);
+    /// mock.expect(Operation::Moderations).never();
+    /// ```
+    ///
+    /// Expectations are checked by [`verify`](Self::verify), and when the
+    /// handle is dropped while unmatched requests fail the test.
+    pub fn expect(&self, operation: impl Into<Operation>) -> ExpectationBuilder<'_> {
+        let index = self.state.expectations.add(Expectation::new(operation));
+        ExpectationBuilder::new(&self.state.expectations, index)
+    }
+
+    /// Expects calls matching `sequence` to be made in that order, e.g. a
+    /// moderation before every chat completion:
+    ///
+    /// ```no_run
+    /// # use openai_mock::expectations::Operation;
+    /// # let mock = openai_mock::server::MockServer::builder().start().unwrap();
+    /// mock.expect_in_order([Operation::Moderations, Operation::ChatCompletions]);
+    /// ```
+    ///
+    /// Other calls may come before, between and after them. Like other
+    /// expectations, the order is checked by [`verify`](Self::verify).
+    pub fn expect_in_order(&self, sequence: impl IntoIterator<Item = impl Into<Expectation>>) {
+        self.state
+            .expectations
+            .add_sequence(sequence.into_iter().map(Into::into).collect());
+    }
+
+    /// Panics with a report of the unmet expectations and the unexpected
+    /// requests, if there are any. Each unexpected request is listed with
+    /// the stub it came closest to matching and the conditions it failed
+    /// (see [`closest_stub`](crate::expectations::closest_stub)).
+    pub fn verify(&self) {
+        let unmet = self.state.expectations.verify(&self.received_requests());
+        let unexpected = self.unexpected_requests();
+        if !unmet.is_empty() || !unexpected.is_empty() {
+            panic!(
+                "{}",
+                verification_report(&self.state.config, &unmet, &unexpected)
+            );
+        }
+    }
+}
+
+/// Lists each unmet expectation, then the method, path and body of each
+/// unexpected request with the stub of `config` it came closest to
+/// matching.
+fn verification_report(
+    config: &MockConfig,
+    unmet: &[String],
+    unexpected: &[RecordedRequest],
+) -> String {
+    let mut sections = Vec::new();
+    if !unmet.is_empty() {
+        let mut section = format!("{} expectation(s) of the mock server unmet:", unmet.len());
+        for failure in unmet {
+            section.push_str(&format!("\n  {}", failure));
+        }
+        sections.push(section);
+    }
+    if !unexpected.is_empty() {
+        let mut section = format!(
+            "the mock server received {} unexpected request(s):",
+            unexpected.len()
+        );
+        for record in unexpected {
+            section.push_str(&format!("\n  #{} {} {}", record.id, record.method, record.path));
+            if !record.body.is_null() {
+                section.push_str(&format!(" {}", record.body));
+            }
+            for line in unmatched_report(config, &record.normalized()).lines() {
+                section.push_str(&format!("\n    {}", line));
+            }
+        }
+        sections.push(section);
+    }
+    sections.join("\n")
+}
+
+impl Drop for MockServerHandle {
+    fn drop(&mut self) {
+        // `stop` sends the command immediately; the returned future only
+        // waits for completion, which joining the thread covers.
+        drop(self.server.stop(false));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        // Don't turn a failing test's panic into an abort.
+        if self.state.config.unmatched == UnmatchedRequests::Fail && !std::thread::panicking() {
+            self.verify();
+        }
+    }
+}
@@ -0,0 +1,25 @@
+pub mod bind;
+#[cfg(feature = "actix-web")]
+pub mod builder;
+#[cfg(feature = "actix-web")]
+pub mod compat;
+#[cfg(feature = "actix-web")]
+pub mod mock_server;
+#[cfg(feature = "actix-web")]
+pub mod runtime;
+pub mod standalone;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub use bind::{BindAddress, BindConfig};
+#[cfg(feature = "actix-web")]
+pub use builder::MockServerBuilder;
+#[cfg(feature = "actix-web")]
+#[allow(deprecated)]
+pub use compat::{create_mock_app, create_mock_app_with};
+#[cfg(feature = "actix-web")]
+pub use mock_server::{MockServer, MockServerHandle};
+#[cfg(feature = "actix-web")]
+pub use runtime::RuntimeConfig;
+pub use standalone::{serve, serve_listener};
+#[cfg(feature = "tls")]
+pub use tls::{serve_listener_tls, serve_tls, TlsConfig, SELF_SIGNED_CERT_PEM};
@@ -0,0 +1,74 @@
+//! Worker and connection tuning of the actix-web server behind a
+//! [`MockServer`](crate::server::MockServer).
+//!
+//! The defaults suit tests: one worker and actix-web's connection
+//! settings. Client performance tests that need the mock to saturate the
+//! network raise the worker count and keep connections open longer.
+
+use std::time::Duration;
+
+/// How the server behind a [`MockServer`](crate::server::MockServer)
+/// serves connections. Settings left at `None` use actix-web's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Worker threads, each serving its own connections. One when `None`.
+    pub workers: Option<usize>,
+
+    /// How long idle connections are kept open for further requests;
+    /// `Duration::ZERO` closes every connection after its response.
+    /// Five seconds when `None`.
+    pub keep_alive: Option<Duration>,
+
+    /// How long a client may take to send the headers of a request before
+    /// it is answered `408`; `Duration::ZERO` waits forever. Five seconds
+    /// when `None`.
+    pub client_request_timeout: Option<Duration>,
+
+    /// How long a client may take to acknowledge the shutdown of a
+    /// connection before it is dropped; `Duration::ZERO` waits forever.
+    /// One second when `None`.
+    pub client_disconnect_timeout: Option<Duration>,
+
+    /// Largest number of connections each worker serves at once. 25,000
+    /// when `None`.
+    pub max_connections: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// One worker with actix-web's connection settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves connections on `workers` threads.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Keeps idle connections open for `keep_alive`.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Answers `408` to clients not sending request headers within
+    /// `timeout`.
+    pub fn client_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client_request_timeout = Some(timeout);
+        self
+    }
+
+    /// Drops connections whose client does not acknowledge their shutdown
+    /// within `timeout`.
+    pub fn client_disconnect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_disconnect_timeout = Some(timeout);
+        self
+    }
+
+    /// Serves at most `max` connections per worker at once.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+}
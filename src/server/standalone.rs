@@ -0,0 +1,69 @@
+//! Serving the mock directly on hyper, without a web framework.
+//!
+//! Unlike [`MockServer`](crate::server::MockServer), which needs the
+//! `actix-web` feature, these functions only depend on hyper and run on
+//! the caller's tokio runtime:
+//!
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! use openai_mock::config::MockConfig;
+//!
+//! openai_mock::serve("127.0.0.1:8000", MockConfig::default()).await
+//! # }
+//! ```
+
+use crate::config::MockConfig;
+use crate::service::MockService;
+use crate::state::MockState;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+/// Serves a mock configured with `config` on `addr` until the returned
+/// future is dropped.
+///
+/// # Errors
+///
+/// Returns an error when `addr` cannot be bound or accepting a connection
+/// fails.
+pub async fn serve(addr: impl ToSocketAddrs, config: MockConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    serve_listener(listener, Arc::new(MockState::new(config))).await
+}
+
+/// Serves the mock instance with `state` on the connections accepted by
+/// `listener`, each on its own task, until the returned future is
+/// dropped.
+///
+/// Keeping a clone of `state` lets the caller inspect the received
+/// requests or reconfigure the mock while it is running.
+///
+/// # Errors
+///
+/// Returns an error when accepting a connection fails.
+pub async fn serve_listener(listener: TcpListener, state: Arc<MockState>) -> io::Result<()> {
+    let service = MockService::from_state(state);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(serve_connection(stream, peer, service.clone()));
+    }
+}
+
+/// Serves the requests `peer` sends over `io` with `service`, until the
+/// connection is closed.
+pub(crate) async fn serve_connection<I>(io: I, peer: SocketAddr, service: MockService)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let connection = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .serve_connection(TokioIo::new(io), TowerToHyperService::new(service));
+    if let Err(e) = connection.await {
+        log::debug!("connection from {} failed: {}", peer, e);
+    }
+}
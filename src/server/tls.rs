@@ -0,0 +1,142 @@
+//! HTTPS for the standalone server, with the `tls` feature.
+//!
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! use openai_mock::config::MockConfig;
+//! use openai_mock::server::TlsConfig;
+//!
+//! openai_mock::serve_tls("127.0.0.1:8443", MockConfig::default(), TlsConfig::self_signed()).await
+//! # }
+//! ```
+
+use crate::config::MockConfig;
+use crate::server::standalone::serve_connection;
+use crate::service::MockService;
+use crate::state::MockState;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+/// The bundled self-signed certificate, in PEM format, valid for
+/// `localhost`, `127.0.0.1` and `::1`.
+///
+/// Clients of a server using [`TlsConfig::self_signed`] have to trust it,
+/// e.g. by adding it to their root certificates.
+pub const SELF_SIGNED_CERT_PEM: &str = include_str!("certs/localhost.crt");
+
+const SELF_SIGNED_KEY_PEM: &str = include_str!("certs/localhost.key");
+
+/// The certificate chain and private key the standalone server presents
+/// to clients.
+#[derive(Clone)]
+pub struct TlsConfig {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsConfig {
+    /// The bundled self-signed certificate, [`SELF_SIGNED_CERT_PEM`].
+    pub fn self_signed() -> Self {
+        Self::from_pem(SELF_SIGNED_CERT_PEM.as_bytes(), SELF_SIGNED_KEY_PEM.as_bytes())
+            .expect("the bundled certificate is valid")
+    }
+
+    /// A certificate chain and private key in PEM format, the server's
+    /// certificate first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error when either cannot be parsed or the
+    /// key does not match the certificate.
+    pub fn from_pem(cert_chain: &[u8], key: &[u8]) -> io::Result<Self> {
+        let cert_chain = CertificateDer::pem_slice_iter(cert_chain)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid_data)?;
+        let key = PrivateKeyDer::from_pem_slice(key).map_err(invalid_data)?;
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(invalid_data)?
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .map_err(invalid_data)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Self {
+            config: Arc::new(config),
+        })
+    }
+
+    /// Like [`from_pem`](Self::from_pem), reading the certificate chain
+    /// and private key from files.
+    pub fn from_pem_files(cert_chain: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_pem(&std::fs::read(cert_chain)?, &std::fs::read(key)?)
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig").finish_non_exhaustive()
+    }
+}
+
+fn invalid_data(e: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Like [`serve`](crate::server::serve), over HTTPS with `tls`.
+///
+/// # Errors
+///
+/// Returns an error when `addr` cannot be bound or accepting a connection
+/// fails.
+pub async fn serve_tls(addr: impl ToSocketAddrs, config: MockConfig, tls: TlsConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    serve_listener_tls(listener, Arc::new(MockState::new(config)), tls).await
+}
+
+/// Like [`serve_listener`](crate::server::serve_listener), over HTTPS with
+/// `tls`. Connections failing the TLS handshake are dropped.
+///
+/// # Errors
+///
+/// Returns an error when accepting a connection fails.
+pub async fn serve_listener_tls(
+    listener: TcpListener,
+    state: Arc<MockState>,
+    tls: TlsConfig,
+) -> io::Result<()> {
+    let service = MockService::from_state(state);
+    let acceptor = TlsAcceptor::from(tls.config);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let service = service.clone();
+        tokio::spawn(async move {
+            match acceptor.accept(stream).await {
+                Ok(stream) => serve_connection(stream, peer, service).await,
+                Err(e) => log::debug!("TLS handshake with {} failed: {}", peer, e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pem() {
+        TlsConfig::self_signed();
+
+        let err = TlsConfig::from_pem(b"not a certificate", SELF_SIGNED_KEY_PEM.as_bytes())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = TlsConfig::from_pem(SELF_SIGNED_CERT_PEM.as_bytes(), b"").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
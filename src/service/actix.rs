@@ -0,0 +1,169 @@
+//! Serves the endpoint handlers from actix-web, converting between its
+//! request and response types and those of the `http` crate.
+
+use crate::config::{AuthConfig, CorsConfig};
+use crate::handlers::authenticate;
+use crate::scenario::InjectedError;
+use crate::service::cors::{cors_headers, is_preflight, preflight_response};
+use crate::service::{
+    compress, strip_route_prefix, MockBody, MockRequest, MockResponse, RouteMatch,
+};
+use crate::state::MockState;
+use actix_web::body::{BodyStream, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpRequest, HttpResponse};
+use http::header::ACCEPT_ENCODING;
+use std::future::Future;
+use std::sync::Arc;
+
+/// The request `http_req` with `body`, routed to the actix resource that
+/// matched it.
+pub fn to_mock_request(http_req: &HttpRequest, body: web::Bytes) -> MockRequest {
+    let uri = http_req
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    let mut request = http::Request::builder()
+        .method(http_req.method().as_str())
+        .uri(uri);
+    for (name, value) in http_req.headers() {
+        request = request.header(name.as_str(), value.as_bytes());
+    }
+    let mut request = request
+        .body(body)
+        .unwrap_or_else(|_| http::Request::new(web::Bytes::new()));
+    request.extensions_mut().insert(RouteMatch {
+        pattern: http_req
+            .match_pattern()
+            .unwrap_or_else(|| http_req.path().to_string()),
+        params: http_req
+            .match_info()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    });
+    request
+}
+
+/// `response` as an actix response.
+pub fn to_actix_response(response: MockResponse) -> HttpResponse {
+    let (parts, body) = response.into_parts();
+    let status = StatusCode::from_u16(parts.status.as_u16()).unwrap_or(StatusCode::OK);
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in &parts.headers {
+        builder.append_header((name.as_str(), value.as_bytes()));
+    }
+    let body = match body {
+        MockBody::Full(bytes) => BoxBody::new(bytes),
+        MockBody::Stream(stream) => BoxBody::new(BodyStream::new(stream)),
+    };
+    builder.body(body)
+}
+
+/// Serves the actix request `http_req` with `handler`, one of the
+/// endpoint handlers of [`handlers`](crate::handlers), compressing the
+/// response as configured. The handler sees the request without the
+/// configured route prefix. Bodies larger than
+/// [`MockConfig::body_limit`](crate::config::MockConfig::body_limit) are
+/// rejected with an OpenAI-style `413` error.
+pub async fn serve_actix<F, Fut>(
+    http_req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+    handler: F,
+) -> HttpResponse
+where
+    F: FnOnce(Arc<MockState>, MockRequest) -> Fut,
+    Fut: Future<Output = MockResponse>,
+{
+    let state = state.into_inner();
+    let limit = state.config.body_limit();
+    let body = match payload.to_bytes_limited(limit).await {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => return e.as_response_error().error_response(),
+        Err(_) => return to_actix_response(InjectedError::request_too_large(limit).to_response()),
+    };
+    let request = to_mock_request(&http_req, body);
+    let Some(request) = strip_route_prefix(request, &state.config.route_prefix()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let accept_encoding = request.headers().get(ACCEPT_ENCODING).cloned();
+    let response = handler(state.clone(), request).await;
+    to_actix_response(compress(&state.config.compression, accept_encoding.as_ref(), response))
+}
+
+/// `headers` as a header map of the `http` crate.
+fn to_mock_headers(headers: &HeaderMap) -> http::HeaderMap {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = http::HeaderName::from_bytes(name.as_str().as_bytes()).ok()?;
+            let value = http::HeaderValue::from_bytes(value.as_bytes()).ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// Serves `req` with `next` if it carries one of the API keys `auth`
+/// accepts, and answers with the API's `401` error otherwise; for use with
+/// actix's `middleware::from_fn`, to authenticate routes of your own like
+/// the mock's:
+///
+/// ```
+/// use actix_web::middleware::from_fn;
+/// use actix_web::{web, App, HttpResponse};
+/// use openai_mock::config::MockConfig;
+/// use openai_mock::service::actix::auth_middleware;
+///
+/// let auth = MockConfig::default().with_api_key("sk-test").auth;
+/// let app = App::new()
+///     .wrap(from_fn(move |req, next| {
+///         let auth = auth.clone();
+///         async move { auth_middleware(&auth, req, next).await }
+///     }))
+///     .route("/v1/files", web::get().to(HttpResponse::Ok));
+/// ```
+pub async fn auth_middleware(
+    auth: &AuthConfig,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    if let Err(error) = authenticate(&to_mock_headers(req.headers()), auth) {
+        return Ok(req.into_response(to_actix_response(error.to_response())));
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Serves `req` with `next`, answering CORS preflight requests and adding
+/// CORS headers to the response as configured by `cors`; for use with
+/// actix's `middleware::from_fn`.
+pub async fn cors_middleware(
+    cors: Option<&CorsConfig>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(cors) = cors else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let headers = to_mock_headers(req.headers());
+    let method = http::Method::from_bytes(req.method().as_str().as_bytes()).unwrap_or_default();
+    if is_preflight(&method, &headers) {
+        let response = to_actix_response(preflight_response(cors, &headers));
+        return Ok(req.into_response(response));
+    }
+
+    let mut res = next.call(req).await?.map_into_boxed_body();
+    let added = cors_headers(cors, &headers, res.headers().keys().map(|name| name.as_str()));
+    for (name, value) in &added {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            res.headers_mut().append(name, value);
+        }
+    }
+    Ok(res)
+}
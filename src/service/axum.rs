@@ -0,0 +1,36 @@
+//! Serves a mock instance from axum.
+
+use crate::service::MockService;
+use crate::state::MockState;
+use axum::Router;
+use std::sync::Arc;
+
+/// A router serving every route of the mock instance with `state`, to be
+/// merged into or nested in an axum application.
+pub fn router(state: Arc<MockState>) -> Router {
+    Router::new().fallback_service(MockService::from_state(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MockConfig;
+    use http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower_service::Service;
+
+    #[tokio::test]
+    async fn test_router() {
+        let state = Arc::new(MockState::new(MockConfig::default()));
+        let mut app = Router::new().nest("/openai", router(state.clone()));
+        let request = Request::get("/openai/v1/models")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let models: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(models["object"], "list");
+        assert_eq!(state.history.len(), 1);
+    }
+}
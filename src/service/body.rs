@@ -0,0 +1,141 @@
+//! The body of responses produced by the mock.
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use http_body::{Body, Frame, SizeHint};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A response body: either complete, or a stream of chunks, e.g. the
+/// server-sent events of a streamed completion. A stream that yields an
+/// error makes the server drop the connection.
+pub enum MockBody {
+    Full(Bytes),
+    Stream(BoxStream<'static, io::Result<Bytes>>),
+}
+
+impl MockBody {
+    pub fn empty() -> Self {
+        MockBody::Full(Bytes::new())
+    }
+
+    /// A body sending the chunks of `stream` as they are produced.
+    pub fn from_stream(stream: impl Stream<Item = io::Result<Bytes>> + Send + 'static) -> Self {
+        MockBody::Stream(stream.boxed())
+    }
+
+    /// Whether the body is streamed.
+    pub fn is_stream(&self) -> bool {
+        matches!(self, MockBody::Stream(_))
+    }
+
+    /// Waits for the whole body.
+    ///
+    /// # Returns
+    ///
+    /// The error of the stream, if it yields one.
+    pub async fn to_bytes(self) -> io::Result<Bytes> {
+        match self {
+            MockBody::Full(bytes) => Ok(bytes),
+            MockBody::Stream(mut stream) => {
+                let mut body = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    body.extend_from_slice(&chunk?);
+                }
+                Ok(body.into())
+            }
+        }
+    }
+}
+
+impl Default for MockBody {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for MockBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockBody::Full(bytes) => f.debug_tuple("Full").field(bytes).finish(),
+            MockBody::Stream(_) => f.write_str("Stream"),
+        }
+    }
+}
+
+impl From<Bytes> for MockBody {
+    fn from(bytes: Bytes) -> Self {
+        MockBody::Full(bytes)
+    }
+}
+
+impl From<String> for MockBody {
+    fn from(text: String) -> Self {
+        MockBody::Full(text.into())
+    }
+}
+
+impl From<Vec<u8>> for MockBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        MockBody::Full(bytes.into())
+    }
+}
+
+impl From<&'static str> for MockBody {
+    fn from(text: &'static str) -> Self {
+        MockBody::Full(Bytes::from_static(text.as_bytes()))
+    }
+}
+
+impl Body for MockBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        match self.get_mut() {
+            MockBody::Full(bytes) if bytes.is_empty() => Poll::Ready(None),
+            MockBody::Full(bytes) => Poll::Ready(Some(Ok(Frame::data(std::mem::take(bytes))))),
+            MockBody::Stream(stream) => stream
+                .poll_next_unpin(cx)
+                .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        matches!(self, MockBody::Full(bytes) if bytes.is_empty())
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self {
+            MockBody::Full(bytes) => SizeHint::with_exact(bytes.len() as u64),
+            MockBody::Stream(_) => SizeHint::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use http_body_util::BodyExt;
+
+    #[tokio::test]
+    async fn test_collect() {
+        let body = MockBody::from("{}");
+        assert_eq!(body.size_hint().exact(), Some(2));
+        assert_eq!(body.collect().await.unwrap().to_bytes(), "{}");
+
+        let chunks = vec![Ok(Bytes::from("data: 1\n\n")), Ok(Bytes::from("data: 2\n\n"))];
+        let body = MockBody::from_stream(stream::iter(chunks));
+        assert!(body.is_stream());
+        assert_eq!(body.to_bytes().await.unwrap(), "data: 1\n\ndata: 2\n\n");
+
+        let failing = stream::iter(vec![Err(io::Error::other("reset"))]);
+        assert!(MockBody::from_stream(failing).to_bytes().await.is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//! Compression of response bodies, as configured by
+//! [`MockConfig::compression`](crate::config::MockConfig::compression).
+
+use crate::config::CompressionConfig;
+use crate::service::{MockBody, MockResponse};
+use brotli::CompressorWriter;
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use http::{HeaderValue, Response, StatusCode};
+use std::future;
+use std::io::{self, Write};
+
+/// A content coding the mock compresses responses with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    /// The name of the coding in `Content-Encoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// The coding preferred by `accept_encoding`, the value of an
+    /// `Accept-Encoding` header: the one with the highest quality, `br`
+    /// on a tie.
+    pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let (mut gzip, mut brotli, mut any) = (None, None, None);
+        for item in accept_encoding.split(',') {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(1.0, |q| q.trim().parse::<f32>().unwrap_or(0.0));
+            match coding.as_str() {
+                "gzip" | "x-gzip" => gzip = Some(quality),
+                "br" => brotli = Some(quality),
+                "*" => any = Some(quality),
+                _ => {}
+            }
+        }
+        let gzip = gzip.or(any).unwrap_or(0.0);
+        let brotli = brotli.or(any).unwrap_or(0.0);
+        if brotli > 0.0 && brotli >= gzip {
+            Some(Encoding::Brotli)
+        } else if gzip > 0.0 {
+            Some(Encoding::Gzip)
+        } else {
+            None
+        }
+    }
+}
+
+/// `response` compressed with the coding `accept_encoding` prefers, when
+/// `config` enables compression and the body is streamed or at least
+/// `config.min_size` bytes long.
+///
+/// Streamed bodies are compressed chunk by chunk, each flushed so clients
+/// can decode the events sent so far.
+pub fn compress(
+    config: &CompressionConfig,
+    accept_encoding: Option<&HeaderValue>,
+    response: MockResponse,
+) -> MockResponse {
+    let encoding = accept_encoding
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::negotiate);
+    let Some(encoding) = encoding.filter(|_| config.enabled) else {
+        return response;
+    };
+    if response.headers().contains_key(CONTENT_ENCODING)
+        || matches!(response.status(), StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match body {
+        MockBody::Full(bytes) if bytes.len() < config.min_size => {
+            return Response::from_parts(parts, MockBody::Full(bytes));
+        }
+        MockBody::Full(bytes) => match compress_bytes(encoding, &bytes) {
+            Ok(compressed) => MockBody::Full(compressed),
+            Err(_) => return Response::from_parts(parts, MockBody::Full(bytes)),
+        },
+        MockBody::Stream(body) => MockBody::from_stream(compress_stream(encoding, body)),
+    };
+    parts
+        .headers
+        .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+    Response::from_parts(parts, body)
+}
+
+fn compress_bytes(encoding: Encoding, bytes: &[u8]) -> io::Result<Bytes> {
+    let mut encoder = Encoder::new(encoding);
+    let mut compressed = encoder.write(bytes)?.to_vec();
+    compressed.extend_from_slice(&encoder.finish()?);
+    Ok(Bytes::from(compressed))
+}
+
+fn compress_stream(
+    encoding: Encoding,
+    body: BoxStream<'static, io::Result<Bytes>>,
+) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    stream::unfold(Some((body, Encoder::new(encoding))), |state| async move {
+        let (mut body, mut encoder) = state?;
+        Some(match body.next().await {
+            Some(Ok(chunk)) => (encoder.write(&chunk), Some((body, encoder))),
+            Some(Err(e)) => (Err(e), None),
+            None => (encoder.finish(), None),
+        })
+    })
+    // An empty chunk would end a chunked response early.
+    .filter(|chunk| future::ready(!matches!(chunk, Ok(chunk) if chunk.is_empty())))
+}
+
+/// An encoder writing to memory, whose output is taken after every write.
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+}
+
+impl Encoder {
+    fn new(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+            Encoding::Brotli => {
+                Encoder::Brotli(Box::new(CompressorWriter::new(Vec::new(), 4096, 5, 22)))
+            }
+        }
+    }
+
+    /// Compresses `chunk`, returning the output so far; it decodes to
+    /// everything written without what follows.
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(output)))
+    }
+
+    /// Ends the compressed stream, returning the remaining output.
+    fn finish(self) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Brotli(encoder) => encoder.into_inner(),
+        };
+        Ok(Bytes::from(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{body_response, empty_response};
+    use std::io::Read;
+
+    fn decode(encoding: &str, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        match encoding {
+            "gzip" => flate2::read::GzDecoder::new(bytes).read_to_string(&mut text),
+            "br" => brotli::Decompressor::new(bytes, 4096).read_to_string(&mut text),
+            _ => panic!("unexpected encoding {}", encoding),
+        }
+        .unwrap();
+        text
+    }
+
+    /// Decodes the start of a compressed stream.
+    fn decode_prefix(encoding: &str, bytes: &[u8]) -> String {
+        let mut text = Vec::new();
+        let mut buf = [0; 4096];
+        let mut reader: Box<dyn Read> = match encoding {
+            "gzip" => Box::new(flate2::read::GzDecoder::new(bytes)),
+            _ => Box::new(brotli::Decompressor::new(bytes, 4096)),
+        };
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            text.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Encoding::negotiate("gzip, deflate"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("*, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("identity"), None);
+        assert_eq!(Encoding::negotiate("gzip;q=0"), None);
+    }
+
+    #[tokio::test]
+    async fn test_compress() {
+        let config = CompressionConfig::default().min_size(16);
+        let text = "data: {\"text\": \"Hello\"}\n\n".repeat(8);
+        for encoding in ["gzip", "br"] {
+            let accept = HeaderValue::from_static(encoding);
+            let response = body_response(StatusCode::OK, "application/json", text.clone());
+            let response = compress(&config, Some(&accept), response);
+            assert_eq!(response.headers()[CONTENT_ENCODING], encoding);
+            let body = response.into_body().to_bytes().await.unwrap();
+            assert_eq!(decode(encoding, &body), text);
+
+            let chunks = vec![Ok(Bytes::from(text.clone())), Ok(Bytes::from("data: [DONE]\n\n"))];
+            let mut response = body_response(StatusCode::OK, "text/event-stream", "");
+            *response.body_mut() = MockBody::from_stream(stream::iter(chunks));
+            let response = compress(&config, Some(&accept), response);
+            let MockBody::Stream(body) = response.into_body() else {
+                panic!("expected a streamed body");
+            };
+            let chunks: Vec<Bytes> = body.map(Result::unwrap).collect().await;
+            // Each flushed chunk decodes to the events sent so far.
+            assert_eq!(decode_prefix(encoding, &chunks[0]), text);
+            assert_eq!(decode(encoding, &chunks.concat()), text.clone() + "data: [DONE]\n\n");
+        }
+
+        let accept = HeaderValue::from_static("gzip");
+        let small = body_response(StatusCode::OK, "text/plain", "Hi");
+        let small = compress(&config, Some(&accept), small);
+        assert!(!small.headers().contains_key(CONTENT_ENCODING));
+        let empty = compress(&config, Some(&accept), empty_response(StatusCode::NO_CONTENT));
+        assert!(!empty.headers().contains_key(CONTENT_ENCODING));
+        let response = body_response(StatusCode::OK, "application/json", text.clone());
+        let plain = compress(&CompressionConfig::disabled(), Some(&accept), response);
+        assert!(!plain.headers().contains_key(CONTENT_ENCODING));
+        let response = body_response(StatusCode::OK, "application/json", text);
+        assert!(!compress(&config, None, response).headers().contains_key(CONTENT_ENCODING));
+    }
+}
@@ -0,0 +1,178 @@
+//! Cross-origin resource sharing, as configured by
+//! [`MockConfig::cors`](crate::config::MockConfig::cors).
+
+use crate::config::CorsConfig;
+use crate::service::{empty_response, MockRequest, MockResponse};
+use crate::state::MockState;
+use http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Methods of the routes the mock serves.
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE";
+
+/// Whether a request with `method` and `headers` is a CORS preflight
+/// request.
+pub fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::OPTIONS
+        && headers.contains_key(ORIGIN)
+        && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// The answer to a preflight request with `headers`: `204` with the CORS
+/// headers when its origin is allowed, an empty `403` otherwise.
+pub fn preflight_response(cors: &CorsConfig, headers: &HeaderMap) -> MockResponse {
+    let Some(origin) = allowed_origin(cors, headers) else {
+        return empty_response(StatusCode::FORBIDDEN);
+    };
+    let mut response = empty_response(StatusCode::NO_CONTENT);
+    let out = response.headers_mut();
+    allow_origin(cors, origin, out);
+    out.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOWED_METHODS));
+    let allowed_headers = if cors.allowed_headers.is_empty() {
+        headers.get(ACCESS_CONTROL_REQUEST_HEADERS).cloned()
+    } else {
+        HeaderValue::from_str(&cors.allowed_headers.join(", ")).ok()
+    };
+    if let Some(allowed_headers) = allowed_headers {
+        out.insert(ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+    }
+    if let Some(max_age) = cors.max_age {
+        out.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+    }
+    out.append(
+        VARY,
+        HeaderValue::from_static("Access-Control-Request-Method, Access-Control-Request-Headers"),
+    );
+    response
+}
+
+/// The CORS headers to add to a response to a request with `headers`:
+/// none when the request has no allowed origin. Scripts may read every
+/// header named in `exposed`, the headers of the response.
+pub fn cors_headers<'a>(
+    cors: &CorsConfig,
+    headers: &HeaderMap,
+    exposed: impl IntoIterator<Item = &'a str>,
+) -> HeaderMap {
+    let mut out = HeaderMap::new();
+    let Some(origin) = allowed_origin(cors, headers) else {
+        return out;
+    };
+    allow_origin(cors, origin, &mut out);
+    let exposed = exposed.into_iter().collect::<Vec<_>>().join(", ");
+    if let Ok(exposed) = HeaderValue::from_str(&exposed) {
+        if !exposed.is_empty() {
+            out.insert(ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+        }
+    }
+    out
+}
+
+/// Serves `request` with `serve`, answering preflight requests and adding
+/// CORS headers to the response when the mock's configuration enables
+/// CORS.
+pub async fn serve_cors<F, Fut>(state: Arc<MockState>, request: MockRequest, serve: F) -> MockResponse
+where
+    F: FnOnce(Arc<MockState>, MockRequest) -> Fut,
+    Fut: Future<Output = MockResponse>,
+{
+    let Some(cors) = state.config.cors.clone() else {
+        return serve(state, request).await;
+    };
+    if is_preflight(request.method(), request.headers()) {
+        return preflight_response(&cors, request.headers());
+    }
+    let headers = request.headers().clone();
+    let mut response = serve(state, request).await;
+    let added = cors_headers(&cors, &headers, response.headers().keys().map(|name| name.as_str()));
+    for (name, value) in &added {
+        response.headers_mut().append(name, value.clone());
+    }
+    response
+}
+
+/// The `Origin` of a request with `headers`, if `cors` allows it.
+fn allowed_origin<'a>(cors: &CorsConfig, headers: &'a HeaderMap) -> Option<&'a HeaderValue> {
+    headers
+        .get(ORIGIN)
+        .filter(|origin| origin.to_str().is_ok_and(|origin| cors.allows_origin(origin)))
+}
+
+fn allow_origin(cors: &CorsConfig, origin: &HeaderValue, out: &mut HeaderMap) {
+    if cors.allowed_origins.is_empty() && !cors.allow_credentials {
+        out.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        return;
+    }
+    out.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+    out.append(VARY, HeaderValue::from_static("Origin"));
+    if cors.allow_credentials {
+        out.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_preflight_response() {
+        let request = headers(&[
+            ("origin", "http://localhost:3000"),
+            ("access-control-request-method", "POST"),
+            ("access-control-request-headers", "authorization, content-type"),
+        ]);
+        assert!(is_preflight(&Method::OPTIONS, &request));
+        assert!(!is_preflight(&Method::POST, &request));
+
+        let response = preflight_response(&CorsConfig::new(), &request);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let out = response.headers();
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_HEADERS], "authorization, content-type");
+        assert!(!out.contains_key(ACCESS_CONTROL_MAX_AGE));
+
+        let cors = CorsConfig::new()
+            .allow_origin("http://localhost:3000")
+            .allow_header("Authorization")
+            .max_age(Duration::from_secs(600));
+        let response = preflight_response(&cors, &request);
+        let out = response.headers();
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:3000");
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_HEADERS], "Authorization");
+        assert_eq!(out[ACCESS_CONTROL_MAX_AGE], "600");
+
+        let cors = CorsConfig::new().allow_origin("https://app.example.com");
+        let response = preflight_response(&cors, &request);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_cors_headers() {
+        let request = headers(&[("origin", "http://localhost:3000")]);
+        let cors = CorsConfig::new().allow_credentials();
+        let out = cors_headers(&cors, &request, ["content-type", "x-request-id"]);
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:3000");
+        assert_eq!(out[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(out[ACCESS_CONTROL_EXPOSE_HEADERS], "content-type, x-request-id");
+        assert_eq!(out[VARY], "Origin");
+
+        assert!(cors_headers(&cors, &HeaderMap::new(), ["content-type"]).is_empty());
+        let cors = CorsConfig::new().allow_origin("https://app.example.com");
+        assert!(cors_headers(&cors, &request, ["content-type"]).is_empty());
+    }
+}
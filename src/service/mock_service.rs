@@ -0,0 +1,85 @@
+//! The mock as a `tower::Service`, for embedding it in any hyper-based
+//! stack.
+
+use crate::config::MockConfig;
+use crate::scenario::InjectedError;
+use crate::service::{compress, dispatch, empty_response, serve_cors, MockBody};
+use crate::state::MockState;
+use futures::future::BoxFuture;
+use http::header::ACCEPT_ENCODING;
+use http::{Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// Serves every route of a mock instance (see [`dispatch`]) to requests
+/// with any body type, handling CORS and compressing responses as
+/// configured (see [`serve_cors`] and [`compress`]).
+/// Clones share the instance's state.
+///
+/// Bodies larger than [`MockConfig::body_limit`] are rejected with an
+/// OpenAI-style `413` error before the request is recorded.
+///
+/// With axum, mount it with `Router::fallback_service`; with hyper, wrap
+/// it in `hyper_util::service::TowerToHyperService`.
+#[derive(Debug, Clone)]
+pub struct MockService {
+    state: Arc<MockState>,
+}
+
+impl MockService {
+    /// A service for a new mock instance using `config`.
+    pub fn new(config: MockConfig) -> Self {
+        Self::from_state(Arc::new(MockState::new(config)))
+    }
+
+    /// A service for the mock instance with `state`, e.g. one also served
+    /// by another server.
+    pub fn from_state(state: Arc<MockState>) -> Self {
+        Self { state }
+    }
+
+    /// The state of the mock instance, for inspecting the requests it
+    /// received or changing its settings.
+    pub fn state(&self) -> &Arc<MockState> {
+        &self.state
+    }
+}
+
+impl<B> Service<Request<B>> for MockService
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = Response<MockBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let state = self.state.clone();
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let limit = state.config.body_limit();
+            let body = match Limited::new(body, limit).collect().await {
+                Ok(body) => body,
+                Err(e) if e.downcast_ref::<LengthLimitError>().is_some() => {
+                    return Ok(InjectedError::request_too_large(limit).to_response());
+                }
+                Err(_) => return Ok(empty_response(StatusCode::BAD_REQUEST)),
+            };
+            let request = Request::from_parts(parts, body.to_bytes());
+            let accept_encoding = request.headers().get(ACCEPT_ENCODING).cloned();
+            let response = serve_cors(state.clone(), request, dispatch).await;
+            Ok(compress(&state.config.compression, accept_encoding.as_ref(), response))
+        })
+    }
+}
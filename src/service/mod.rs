@@ -0,0 +1,35 @@
+//! The mock as plain async functions over the types of the `http` crate,
+//! independent of any web framework.
+//!
+//! Each endpoint handler of [`handlers`](crate::handlers) takes the
+//! instance's [`MockState`](crate::state::MockState) and a [`MockRequest`]
+//! and returns a [`MockResponse`]; [`dispatch`] routes a request to the
+//! right one. On top of them:
+//!
+//! - [`MockService`] is a `tower::Service`, for embedding the mock in any
+//!   hyper-based stack; [`serve`](crate::serve) runs it on hyper alone.
+//! - `actix`, with the `actix-web` feature, adapts the handlers to
+//!   actix-web; the routes of `routes` use it.
+//! - `axum`, with the `axum` feature, builds an axum `Router` from the
+//!   service.
+
+#[cfg(feature = "actix-web")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+pub mod body;
+pub mod compression;
+pub mod cors;
+pub mod mock_service;
+pub mod request;
+pub mod response;
+pub mod router;
+pub use body::MockBody;
+pub use compression::compress;
+pub use cors::serve_cors;
+pub use mock_service::MockService;
+pub use request::{
+    json_body, path_param, route_pattern, strip_route_prefix, MockRequest, RouteMatch,
+};
+pub use response::{body_response, empty_response, json_response, text_response, MockResponse};
+pub use router::{dispatch, route_patterns};
@@ -0,0 +1,158 @@
+//! Requests as seen by the endpoint handlers.
+
+use crate::service::{text_response, MockResponse};
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{Request, StatusCode};
+use serde::de::DeserializeOwned;
+
+/// A request with its whole body read.
+pub type MockRequest = Request<Bytes>;
+
+/// The route a request was routed to, stored in the extensions of the
+/// request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteMatch {
+    /// The route's pattern, e.g. `/v1/models/{model}`.
+    pub pattern: String,
+
+    /// The values of the pattern's parameters, e.g. `("model", "gpt-4")`.
+    pub params: Vec<(String, String)>,
+}
+
+impl RouteMatch {
+    /// Matches `path` against `pattern`, whose `{name}` segments match any
+    /// one segment.
+    pub fn new(pattern: &str, path: &str) -> Option<Self> {
+        let mut params = Vec::new();
+        let mut segments = path.split('/');
+        for expected in pattern.split('/') {
+            let segment = segments.next()?;
+            match expected.strip_prefix('{').and_then(|name| name.strip_suffix('}')) {
+                Some(_) if segment.is_empty() => return None,
+                Some(name) => params.push((name.to_string(), segment.to_string())),
+                None if expected != segment => return None,
+                None => {}
+            }
+        }
+        if segments.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            pattern: pattern.to_string(),
+            params,
+        })
+    }
+
+    /// The value of parameter `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The pattern of the route `request` was routed to, or its path when it
+/// was not routed.
+pub fn route_pattern(request: &MockRequest) -> String {
+    match request.extensions().get::<RouteMatch>() {
+        Some(route) => route.pattern.clone(),
+        None => request.uri().path().to_string(),
+    }
+}
+
+/// The value of parameter `name` of the route `request` was routed to.
+pub fn path_param<'a>(request: &'a MockRequest, name: &str) -> Option<&'a str> {
+    request.extensions().get::<RouteMatch>()?.param(name)
+}
+
+/// `request` with `prefix` removed from the start of its path and of the
+/// pattern of its route, or `None` when its path is not under `prefix`.
+/// An empty `prefix` leaves it unchanged.
+pub fn strip_route_prefix(mut request: MockRequest, prefix: &str) -> Option<MockRequest> {
+    if prefix.is_empty() {
+        return Some(request);
+    }
+    let path = request
+        .uri()
+        .path()
+        .strip_prefix(prefix)
+        .filter(|path| path.starts_with('/'))?;
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    *request.uri_mut() = path_and_query.parse().ok()?;
+    if let Some(route) = request.extensions_mut().get_mut::<RouteMatch>() {
+        if let Some(pattern) = route.pattern.strip_prefix(prefix) {
+            route.pattern = pattern.to_string();
+        }
+    }
+    Some(request)
+}
+
+/// Deserializes the JSON body of `request`.
+///
+/// # Returns
+///
+/// `Err` with a `400` response when the body is not JSON or does not
+/// deserialize. Bodies over the size limit are rejected before handlers
+/// see them (see [`MockConfig::max_body_size`](crate::config::MockConfig::max_body_size)).
+pub fn json_body<T: DeserializeOwned>(request: &MockRequest) -> Result<T, MockResponse> {
+    let is_json = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"));
+    if !is_json {
+        return Err(text_response(StatusCode::BAD_REQUEST, "Content type error"));
+    }
+
+    serde_json::from_slice(request.body()).map_err(|e| {
+        text_response(StatusCode::BAD_REQUEST, format!("Json deserialize error: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_route_match() {
+        let route = RouteMatch::new("/v1/models/{model}", "/v1/models/gpt-4").unwrap();
+        assert_eq!(route.param("model"), Some("gpt-4"));
+        assert!(RouteMatch::new("/v1/models/{model}", "/v1/models/").is_none());
+        assert!(RouteMatch::new("/v1/models/{model}", "/v1/models/a/b").is_none());
+        assert!(RouteMatch::new("/v1/models", "/v1/models").unwrap().params.is_empty());
+    }
+
+    #[test]
+    fn test_strip_route_prefix() {
+        let request = Request::get("/openai/v1/models?limit=1").body(Bytes::new()).unwrap();
+        let request = strip_route_prefix(request, "/openai").unwrap();
+        assert_eq!(request.uri(), "/v1/models?limit=1");
+        assert_eq!(strip_route_prefix(request, "").unwrap().uri(), "/v1/models?limit=1");
+
+        let request = Request::get("/openai-v1/models").body(Bytes::new()).unwrap();
+        assert!(strip_route_prefix(request, "/openai").is_none());
+        let request = Request::get("/v1/models").body(Bytes::new()).unwrap();
+        assert!(strip_route_prefix(request, "/openai").is_none());
+    }
+
+    #[test]
+    fn test_json_body() {
+        let request = Request::post("/v1/completions")
+            .header(CONTENT_TYPE, "application/json; charset=utf-8")
+            .body(Bytes::from(r#"{"model": "gpt-4"}"#))
+            .unwrap();
+        assert_eq!(json_body::<Value>(&request).unwrap(), json!({"model": "gpt-4"}));
+
+        let request = Request::post("/v1/completions").body(Bytes::from("{}")).unwrap();
+        let response = json_body::<Value>(&request).unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,44 @@
+//! Responses produced by the endpoint handlers.
+
+use crate::service::MockBody;
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::{Response, StatusCode};
+use serde::Serialize;
+
+/// A response of the mock.
+pub type MockResponse = Response<MockBody>;
+
+/// A response with `body` and the given content type.
+pub fn body_response(
+    status: StatusCode,
+    content_type: &'static str,
+    body: impl Into<MockBody>,
+) -> MockResponse {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+/// A response with `body` serialized as JSON, or an empty `500` response
+/// if it does not serialize.
+pub fn json_response(status: StatusCode, body: &impl Serialize) -> MockResponse {
+    match serde_json::to_vec(body) {
+        Ok(body) => body_response(status, "application/json", body),
+        Err(_) => empty_response(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// A plain text response.
+pub fn text_response(status: StatusCode, text: impl Into<String>) -> MockResponse {
+    body_response(status, "text/plain; charset=utf-8", text.into())
+}
+
+/// A response without a body.
+pub fn empty_response(status: StatusCode) -> MockResponse {
+    let mut response = Response::new(MockBody::empty());
+    *response.status_mut() = status;
+    response
+}
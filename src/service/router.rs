@@ -0,0 +1,95 @@
+//! Routes requests to the endpoint handlers, without a web framework.
+
+use crate::config::Endpoint;
+use crate::handlers::{
+    clear_faults, clear_requests, clear_route, completions, fixture, fixture_paths,
+    get_capabilities, get_config, get_faults, get_key_tier, list_models, list_requests,
+    list_routes, list_scenarios, patch_config, reset, retrieve_model, set_faults, set_key_tier,
+    set_route, set_scenario_state,
+};
+use crate::service::{empty_response, strip_route_prefix, MockRequest, MockResponse, RouteMatch};
+use crate::state::MockState;
+use http::{Method, StatusCode};
+use std::sync::Arc;
+
+/// Routes of the administrative and control APIs, which every mock
+/// serves.
+const CONTROL_ROUTES: [&str; 10] = [
+    "/__admin/capabilities",
+    "/__admin/routes",
+    "/__admin/routes/{endpoint}",
+    "/__admin/keys/{key}/tier",
+    "/__admin/scenarios",
+    "/__admin/scenarios/{scenario}",
+    "/__mock/config",
+    "/__mock/requests",
+    "/__mock/reset",
+    "/__mock/faults",
+];
+
+/// The pattern of every route `state` serves: the enabled endpoints, the
+/// endpoints only served from fixtures, and the control routes.
+pub fn route_patterns(state: &MockState) -> Vec<String> {
+    let mut patterns: Vec<String> = Endpoint::ALL
+        .iter()
+        .filter(|endpoint| state.config.is_endpoint_enabled(**endpoint))
+        .map(|endpoint| endpoint.path().to_string())
+        .collect();
+    patterns.extend(fixture_paths(state));
+    patterns.extend(CONTROL_ROUTES.iter().map(|pattern| pattern.to_string()));
+    patterns
+}
+
+/// Serves `request` with the handler of its route, as the routes
+/// registered by [`configure_all_routes_with`](crate::routes::configure_all_routes_with)
+/// do: `404` when no route matches the path, `405` when the route does not
+/// accept the method. Routes are mounted under the configured
+/// [`base_path`](crate::config::MockConfig::base_path), which is removed
+/// from the path before the request is handled.
+pub async fn dispatch(state: Arc<MockState>, request: MockRequest) -> MockResponse {
+    let Some(mut request) = strip_route_prefix(request, &state.config.route_prefix()) else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    let path = request.uri().path().to_string();
+    let Some(route) = route_patterns(&state)
+        .iter()
+        .find_map(|pattern| RouteMatch::new(pattern, &path))
+    else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    let pattern = route.pattern.clone();
+    request.extensions_mut().insert(route);
+
+    let method = request.method().clone();
+    match (pattern.as_str(), method) {
+        ("/v1/completions", Method::POST) => completions(state, request).await,
+        ("/v1/models", Method::GET) => list_models(state, request).await,
+        ("/v1/models/{model}", Method::GET) => retrieve_model(state, request).await,
+        ("/__admin/capabilities", Method::GET) => get_capabilities().await,
+        ("/__admin/routes", Method::GET) => list_routes(state, request).await,
+        ("/__admin/routes/{endpoint}", Method::PUT) => set_route(state, request).await,
+        ("/__admin/routes/{endpoint}", Method::DELETE) => clear_route(state, request).await,
+        ("/__admin/keys/{key}/tier", Method::GET) => get_key_tier(state, request).await,
+        ("/__admin/keys/{key}/tier", Method::PUT) => set_key_tier(state, request).await,
+        ("/__admin/scenarios", Method::GET) => list_scenarios(state, request).await,
+        ("/__admin/scenarios/{scenario}", Method::PUT) => {
+            set_scenario_state(state, request).await
+        }
+        ("/__mock/config", Method::GET) => get_config(state, request).await,
+        ("/__mock/config", Method::PATCH) => patch_config(state, request).await,
+        ("/__mock/requests", Method::GET) => list_requests(state, request).await,
+        ("/__mock/requests", Method::DELETE) => clear_requests(state, request).await,
+        ("/__mock/reset", Method::POST) => reset(state, request).await,
+        ("/__mock/faults", Method::GET) => get_faults(state, request).await,
+        ("/__mock/faults", Method::PUT) => set_faults(state, request).await,
+        ("/__mock/faults", Method::DELETE) => clear_faults(state, request).await,
+        (pattern, _) if !pattern.starts_with("/__") && !is_emulated(pattern) => {
+            fixture(state, request).await
+        }
+        _ => empty_response(StatusCode::METHOD_NOT_ALLOWED),
+    }
+}
+
+fn is_emulated(pattern: &str) -> bool {
+    Endpoint::ALL.iter().any(|endpoint| endpoint.path() == pattern)
+}
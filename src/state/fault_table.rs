@@ -0,0 +1,95 @@
+//! Fault injection settings that can be changed while the server is
+//! running.
+
+use crate::config::{FaultConfig, MockConfig};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// The configured [`FaultConfig`], optionally replaced at runtime, and
+/// the window injected rate limits are counted in.
+#[derive(Debug, Default)]
+pub struct FaultTable {
+    configured: FaultConfig,
+    replaced: RwLock<Option<FaultConfig>>,
+    window: Mutex<Option<Window>>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    requests: u32,
+}
+
+impl FaultTable {
+    /// Builds the table from the `faults` of `config`.
+    pub fn from_config(config: &MockConfig) -> Self {
+        Self {
+            configured: config.faults.clone(),
+            replaced: RwLock::new(None),
+            window: Mutex::new(None),
+        }
+    }
+
+    /// The fault settings in effect.
+    pub fn get(&self) -> FaultConfig {
+        self.replaced
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| self.configured.clone())
+    }
+
+    /// Replaces the configured fault settings, starting the rate limit
+    /// window afresh.
+    pub fn set(&self, faults: FaultConfig) {
+        *self.replaced.write().unwrap() = Some(faults);
+        *self.window.lock().unwrap() = None;
+    }
+
+    /// Restores the configured fault settings, starting the rate limit
+    /// window afresh.
+    pub fn clear(&self) {
+        *self.replaced.write().unwrap() = None;
+        *self.window.lock().unwrap() = None;
+    }
+
+    /// Counts a request against the injected rate limit's `window`,
+    /// returning the requests made in it, including this one, and the time
+    /// until it resets.
+    pub fn count_request(&self, window: Duration) -> (u32, Duration) {
+        let mut current = self.window.lock().unwrap();
+        let current = match current.as_mut() {
+            Some(current) if current.started.elapsed() < window => current,
+            _ => current.insert(Window {
+                started: Instant::now(),
+                requests: 0,
+            }),
+        };
+        current.requests += 1;
+        (current.requests, window.saturating_sub(current.started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::StreamFault;
+
+    #[test]
+    fn test_replace_and_restore() {
+        let faults = FaultTable::from_config(&MockConfig::default().with_error_rate(0.5));
+        assert_eq!(faults.get().error_rate, 0.5);
+
+        let replaced = FaultConfig {
+            stream: Some(StreamFault::ErrorEvent { after_chunks: 1 }),
+            error_rate: 0.0,
+            ..Default::default()
+        };
+        faults.set(replaced.clone());
+        assert_eq!(faults.get(), replaced);
+
+        faults.clear();
+        assert_eq!(faults.get().error_rate, 0.5);
+        assert_eq!(faults.get().stream, None);
+    }
+}
@@ -0,0 +1,294 @@
+//! Request history recorded by the mock server.
+
+use crate::models::completion::Usage;
+use crate::scenario::NormalizedRequest;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What ultimately happened to a recorded request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RequestOutcome {
+    /// The response (or stream) is still being produced.
+    InProgress,
+
+    /// The full response was delivered.
+    Completed,
+
+    /// The client disconnected before a streamed response finished.
+    Cancelled,
+
+    /// The request was rejected with a non-success status.
+    Failed { status: u16 },
+}
+
+/// The response the mock sent to a recorded request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code.
+    pub status: u16,
+
+    /// The response body. Bodies that are not JSON (e.g. event streams)
+    /// are stored as strings; streams grow as their chunks are sent.
+    #[serde(default)]
+    pub body: Value,
+
+    /// When the response was sent.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A single request received by the mock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// Sequence number, starting at 0 for the first request.
+    pub id: usize,
+
+    /// HTTP method, e.g. `POST`.
+    pub method: String,
+
+    /// Request path, e.g. `/v1/completions`.
+    pub path: String,
+
+    /// Request headers, keyed by lowercase name.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// The parsed request body.
+    pub body: Value,
+
+    /// When the request was received.
+    pub timestamp: DateTime<Utc>,
+
+    /// Token usage attributed to the request. For cancelled streams this is
+    /// the partial usage up to the point the client disconnected.
+    pub usage: Option<Usage>,
+
+    /// What happened to the request.
+    pub outcome: RequestOutcome,
+
+    /// Whether no stub matched the request while unmatched requests fail
+    /// the test (see [`UnmatchedRequests::Fail`](crate::config::UnmatchedRequests::Fail)).
+    #[serde(default)]
+    pub unexpected: bool,
+
+    /// The response sent, once the mock has answered.
+    #[serde(default)]
+    pub response: Option<RecordedResponse>,
+}
+
+impl RecordedRequest {
+    /// The value of header `name`, which is matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// The `model` of the request body, if it names one.
+    pub fn model(&self) -> Option<&str> {
+        self.body["model"].as_str()
+    }
+
+    /// The request as seen by a [`RequestMatcher`](crate::scenario::RequestMatcher).
+    pub fn normalized(&self) -> NormalizedRequest {
+        let mut request = NormalizedRequest::new(&self.method, &self.path, self.body.clone());
+        for (name, value) in &self.headers {
+            request = request.with_header(name, value);
+        }
+        request
+    }
+}
+
+/// Thread-safe, append-only log of received requests.
+///
+/// Records are kept in a sharded map keyed by id, so requests updating
+/// their own records while others are served do not contend; listings
+/// sort them back into arrival order.
+#[derive(Debug, Default)]
+pub struct RequestHistory {
+    records: DashMap<usize, RecordedRequest>,
+    /// Id of the next request. Ids keep increasing across
+    /// [`clear`](Self::clear), so requests still in flight when the
+    /// history is cleared cannot update a newer record.
+    next_id: AtomicUsize,
+}
+
+impl RequestHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a new in-progress record and returns its id.
+    pub fn record(
+        &self,
+        method: &str,
+        path: &str,
+        headers: BTreeMap<String, String>,
+        body: Value,
+    ) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let record = RecordedRequest {
+            id,
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            body,
+            timestamp: Utc::now(),
+            usage: None,
+            outcome: RequestOutcome::InProgress,
+            unexpected: false,
+            response: None,
+        };
+        self.records.insert(id, record);
+        id
+    }
+
+    /// Sets the outcome and usage of a previously recorded request.
+    pub fn finish(&self, id: usize, outcome: RequestOutcome, usage: Option<Usage>) {
+        if let Some(mut record) = self.records.get_mut(&id) {
+            record.outcome = outcome;
+            if usage.is_some() {
+                record.usage = usage;
+            }
+        }
+    }
+
+    /// Records the response sent to a previously recorded request.
+    pub fn respond(&self, id: usize, response: RecordedResponse) {
+        if let Some(mut record) = self.records.get_mut(&id) {
+            record.response = Some(response);
+        }
+    }
+
+    /// Appends `chunk` to the body of a streamed response recorded with
+    /// [`respond`](Self::respond).
+    pub fn extend_response(&self, id: usize, chunk: &str) {
+        let Some(mut record) = self.records.get_mut(&id) else {
+            return;
+        };
+        let Some(response) = record.response.as_mut() else {
+            return;
+        };
+        match &mut response.body {
+            Value::String(body) => body.push_str(chunk),
+            body => *body = Value::from(chunk),
+        }
+    }
+
+    /// Flags a previously recorded request as unexpected.
+    pub fn mark_unexpected(&self, id: usize) {
+        if let Some(mut record) = self.records.get_mut(&id) {
+            record.unexpected = true;
+        }
+    }
+
+    /// Returns the requests flagged as unexpected, oldest first.
+    pub fn unexpected(&self) -> Vec<RecordedRequest> {
+        self.filter(|record| record.unexpected)
+    }
+
+    /// Returns a snapshot of every recorded request, oldest first.
+    pub fn all(&self) -> Vec<RecordedRequest> {
+        self.filter(|_| true)
+    }
+
+    /// Returns the recorded requests `predicate` accepts, oldest first.
+    pub fn filter(&self, predicate: impl Fn(&RecordedRequest) -> bool) -> Vec<RecordedRequest> {
+        let mut records: Vec<RecordedRequest> = self
+            .records
+            .iter()
+            .filter(|record| predicate(record))
+            .map(|record| record.clone())
+            .collect();
+        records.sort_unstable_by_key(|record| record.id);
+        records
+    }
+
+    /// Number of requests recorded.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no request has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the recorded request with the given id.
+    pub fn get(&self, id: usize) -> Option<RecordedRequest> {
+        self.records.get(&id).map(|record| record.clone())
+    }
+
+    /// Forgets every recorded request. Ids of later requests continue from
+    /// where the cleared ones stopped.
+    pub fn clear(&self) {
+        self.records.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_and_filter() {
+        let history = RequestHistory::new();
+        let headers = BTreeMap::from([("x-trace-id".to_string(), "abc".to_string())]);
+        let id = history.record("POST", "/v1/completions", headers, json!({"model": "gpt-4"}));
+        history.record("GET", "/v1/models", BTreeMap::new(), Value::Null);
+
+        let record = history.get(id).unwrap();
+        assert_eq!(record.header("X-Trace-Id"), Some("abc"));
+        assert_eq!(record.model(), Some("gpt-4"));
+
+        let gets = history.filter(|record| record.method == "GET");
+        assert_eq!(gets.len(), 1);
+        assert_eq!(gets[0].path, "/v1/models");
+    }
+
+    #[test]
+    fn test_streamed_response() {
+        let history = RequestHistory::new();
+        let id = history.record("POST", "/v1/completions", BTreeMap::new(), Value::Null);
+        history.respond(
+            id,
+            RecordedResponse {
+                status: 200,
+                body: Value::Null,
+                timestamp: Utc::now(),
+            },
+        );
+        history.extend_response(id, "data: {}\n\n");
+        history.extend_response(id, "data: [DONE]\n\n");
+
+        let response = history.get(id).unwrap().response.unwrap();
+        assert_eq!(response.body, json!("data: {}\n\ndata: [DONE]\n\n"));
+    }
+
+    #[test]
+    fn test_concurrent_records() {
+        let history = RequestHistory::new();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..50 {
+                        let id =
+                            history.record("POST", "/v1/completions", BTreeMap::new(), Value::Null);
+                        history.finish(id, RequestOutcome::Completed, None);
+                    }
+                });
+            }
+        });
+        let records = history.all();
+        assert_eq!(records.len(), 200);
+        assert!(records.windows(2).all(|pair| pair[0].id < pair[1].id));
+        assert!(records.iter().all(|record| record.outcome == RequestOutcome::Completed));
+
+        history.clear();
+        assert!(history.is_empty());
+        assert_eq!(history.record("GET", "/v1/models", BTreeMap::new(), Value::Null), 200);
+    }
+}
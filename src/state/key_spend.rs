@@ -0,0 +1,60 @@
+//! Simulated spend of API keys, checked against the budgets of their
+//! profiles.
+
+use dashmap::DashMap;
+
+/// The simulated spend in USD of every API key since the instance started
+/// or was last reset. Keys are sharded, so charging different keys does
+/// not contend.
+#[derive(Debug, Default)]
+pub struct KeySpend {
+    spent: DashMap<String, f64>,
+}
+
+impl KeySpend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `usd` to the spend of `key`, returning its new total.
+    pub fn charge(&self, key: &str, usd: f64) -> f64 {
+        let mut total = self.spent.entry(key.to_string()).or_insert(0.0);
+        *total += usd;
+        *total
+    }
+
+    /// The spend of `key` so far.
+    pub fn spent(&self, key: &str) -> f64 {
+        self.spent.get(key).map_or(0.0, |spent| *spent)
+    }
+
+    /// Sets the spend of `key`, e.g. to start a test with a nearly
+    /// exhausted budget.
+    pub fn set(&self, key: &str, usd: f64) {
+        self.spent.insert(key.to_string(), usd);
+    }
+
+    /// Forgets the spend of every key.
+    pub fn clear(&self) {
+        self.spent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charge() {
+        let spend = KeySpend::new();
+        assert_eq!(spend.spent("sk-a"), 0.0);
+        spend.charge("sk-a", 0.25);
+        assert_eq!(spend.charge("sk-a", 0.5), 0.75);
+        assert_eq!(spend.spent("sk-b"), 0.0);
+
+        spend.set("sk-b", 2.0);
+        assert_eq!(spend.spent("sk-b"), 2.0);
+        spend.clear();
+        assert_eq!(spend.spent("sk-a"), 0.0);
+    }
+}
@@ -0,0 +1,133 @@
+//! Usage tiers of API keys and the rate limit windows they are measured
+//! against.
+
+use crate::config::{MockConfig, UsageTier};
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Length of a rate limit window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Usage of one API key in the current window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowUsage {
+    /// Requests made in the window, including the current one.
+    pub requests: u32,
+
+    /// Tokens used in the window, including those of the current request.
+    pub tokens: u32,
+
+    /// Time until the window resets.
+    pub reset_in: Duration,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    requests: u32,
+    tokens: u32,
+}
+
+/// The usage tier of every API key, which can be changed at runtime, and
+/// each key's usage in the current one-minute window. Keys are sharded,
+/// so requests with different keys do not contend.
+#[derive(Debug, Default)]
+pub struct KeyTiers {
+    configured: HashMap<String, UsageTier>,
+    tiers: DashMap<String, UsageTier>,
+    default_tier: Option<UsageTier>,
+    windows: DashMap<String, Window>,
+}
+
+impl KeyTiers {
+    /// Builds the tiers configured in `config.auth`, including those of
+    /// key profiles.
+    pub fn from_config(config: &MockConfig) -> Self {
+        let mut configured = config.auth.tiers.clone();
+        for (key, profile) in &config.auth.profiles {
+            if let Some(tier) = profile.tier {
+                configured.insert(key.clone(), tier);
+            }
+        }
+        Self {
+            tiers: configured.clone().into_iter().collect(),
+            configured,
+            default_tier: config.auth.default_tier,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// The tier of `key`, if it has one.
+    pub fn tier(&self, key: &str) -> Option<UsageTier> {
+        self.tiers
+            .get(key)
+            .map(|tier| *tier)
+            .or(self.default_tier)
+    }
+
+    /// Moves `key` to `tier`. Its usage in the current window is kept.
+    pub fn set_tier(&self, key: &str, tier: UsageTier) {
+        self.tiers.insert(key.to_string(), tier);
+    }
+
+    /// Counts a request with `key` that used `tokens`, returning the key's
+    /// usage in the current window.
+    pub fn record(&self, key: &str, tokens: u32) -> WindowUsage {
+        let mut window = self.windows.entry(key.to_string()).or_insert_with(|| Window {
+            started: Instant::now(),
+            requests: 0,
+            tokens: 0,
+        });
+        if window.started.elapsed() >= WINDOW {
+            *window = Window {
+                started: Instant::now(),
+                requests: 0,
+                tokens: 0,
+            };
+        }
+
+        window.requests += 1;
+        window.tokens = window.tokens.saturating_add(tokens);
+        WindowUsage {
+            requests: window.requests,
+            tokens: window.tokens,
+            reset_in: WINDOW.saturating_sub(window.started.elapsed()),
+        }
+    }
+
+    /// Restores the configured tiers and starts every key's window afresh.
+    pub fn reset(&self) {
+        self.tiers.clear();
+        for (key, tier) in &self.configured {
+            self.tiers.insert(key.clone(), *tier);
+        }
+        self.windows.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiers_and_windows() {
+        let config = MockConfig::default().with_key_tier("sk-free", UsageTier::Free);
+        let tiers = KeyTiers::from_config(&config);
+        assert_eq!(tiers.tier("sk-free"), Some(UsageTier::Free));
+        assert_eq!(tiers.tier("sk-other"), None);
+
+        tiers.set_tier("sk-free", UsageTier::Tier3);
+        assert_eq!(tiers.tier("sk-free"), Some(UsageTier::Tier3));
+
+        tiers.record("sk-free", 10);
+        let usage = tiers.record("sk-free", 5);
+        assert_eq!((usage.requests, usage.tokens), (2, 15));
+        assert!(usage.reset_in <= WINDOW);
+        assert_eq!(tiers.record("sk-other", 0).requests, 1);
+
+        tiers.reset();
+        assert_eq!(tiers.tier("sk-free"), Some(UsageTier::Free));
+        assert_eq!(tiers.record("sk-free", 0).requests, 1);
+    }
+}
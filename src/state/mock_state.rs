@@ -0,0 +1,116 @@
+//! State shared by every handler of one mock instance.
+
+use crate::config::MockConfig;
+use crate::expectations::Expectations;
+use crate::scenario::{RuleCalls, ScenarioStates};
+use crate::state::{
+    FaultTable, KeySpend, KeyTiers, MockStats, ModelRegistry, RequestHistory, RouteTable,
+};
+use crate::streaming::StreamScheduler;
+use crate::templates::PrecomputedResponse;
+use crate::utils::token_counting::tokenizer_mode;
+use std::sync::Arc;
+
+/// Per-instance state: the configuration plus everything recorded while
+/// serving requests.
+///
+/// Handlers receive it as an `Arc<MockState>`, from `serve_actix`,
+/// `MockService` or `dispatch`; each mock server owns its own instance so
+/// servers never share history. Everything a request can
+/// change lives here rather than in globals, so any number of mocks can
+/// run in one process; the only process-wide values are the compiled
+/// template helpers, the loaded tokenizers and whether a tokenizer had to
+/// fall back to approximate counts.
+#[derive(Debug)]
+pub struct MockState {
+    /// Behavior configuration.
+    pub config: MockConfig,
+
+    /// Per-model behavior, derived from the configuration, including models
+    /// registered at runtime.
+    pub models: ModelRegistry,
+
+    /// Per-endpoint settings, including overrides applied at runtime.
+    pub routes: RouteTable,
+
+    /// Fault injection settings, including replacements applied at
+    /// runtime.
+    pub faults: FaultTable,
+
+    /// Usage tier and rate limit window of each API key.
+    pub key_tiers: KeyTiers,
+
+    /// Simulated spend of each API key.
+    pub key_spend: KeySpend,
+
+    /// Requests matched by each sequenced scenario rule.
+    pub rule_calls: RuleCalls,
+
+    /// Current state of each scenario.
+    pub scenario_states: ScenarioStates,
+
+    /// Every request received so far.
+    pub history: RequestHistory,
+
+    /// Calls the test expects, verified against `history`.
+    pub expectations: Expectations,
+
+    /// Admission and interleaving of concurrent streams.
+    pub streams: Arc<StreamScheduler>,
+
+    /// The completion served to every request in high-throughput mode,
+    /// computed from the configuration.
+    pub precomputed: Option<PrecomputedResponse>,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self::new(MockConfig::default())
+    }
+}
+
+impl MockState {
+    pub fn new(config: MockConfig) -> Self {
+        Self {
+            models: ModelRegistry::from_config(&config),
+            routes: RouteTable::from_config(&config),
+            faults: FaultTable::from_config(&config),
+            key_tiers: KeyTiers::from_config(&config),
+            key_spend: KeySpend::new(),
+            precomputed: config.high_throughput.as_ref().map(PrecomputedResponse::new),
+            config,
+            rule_calls: RuleCalls::new(),
+            scenario_states: ScenarioStates::new(),
+            history: RequestHistory::new(),
+            expectations: Expectations::new(),
+            streams: Arc::new(StreamScheduler::new()),
+        }
+    }
+
+    /// Returns the instance to the state it started in: forgets every
+    /// recorded request and expectation, rewinds sequences and scenarios,
+    /// empties the rate limit windows and key budgets, and drops the route,
+    /// fault and key tier settings and the models registered at runtime.
+    ///
+    /// Streams still in progress are not interrupted.
+    pub fn reset(&self) {
+        self.history.clear();
+        self.expectations.clear();
+        self.rule_calls.clear();
+        self.scenario_states.clear();
+        self.key_tiers.reset();
+        self.key_spend.clear();
+        self.routes.clear_all();
+        self.faults.clear();
+        self.models.clear_registered();
+    }
+
+    /// A snapshot of the instance's activity.
+    pub fn stats(&self) -> MockStats {
+        MockStats {
+            requests: self.history.len(),
+            active_streams: self.streams.active(),
+            tokenizer_mode: tokenizer_mode(),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+pub mod fault_table;
+pub mod history;
+pub mod key_spend;
+pub mod key_tiers;
+pub mod mock_state;
+pub mod model_registry;
+pub mod route_table;
+pub mod stats;
+pub use fault_table::FaultTable;
+pub use history::{RecordedRequest, RecordedResponse, RequestHistory, RequestOutcome};
+pub use key_spend::KeySpend;
+pub use key_tiers::{KeyTiers, WindowUsage};
+pub use mock_state::MockState;
+pub use model_registry::{ModelRegistry, ModelSpec};
+pub use route_table::RouteTable;
+pub use stats::MockStats;
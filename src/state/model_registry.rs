@@ -0,0 +1,375 @@
+//! Per-model behavior of a mock instance.
+//!
+//! Every model id the mock knows is described once, here: its context
+//! window, tokenizer encoding, latency, supported endpoints and how its text
+//! is generated. Handlers look models up instead of hardcoding behavior, and
+//! `GET /v1/models` lists the registry.
+//!
+//! Besides real OpenAI model ids, the registry contains a small zoo of fake
+//! models (`mock-tiny`, `mock-smart`, `mock-verbose`) with deterministic,
+//! clearly distinct styles, for testing model-routing logic without
+//! depending on the behavior of real model names.
+
+use crate::config::{
+    Endpoint, GenerationConfig, GenerationStrategy, Latency, MockConfig, ModelConfig,
+};
+use crate::generation::TextGenerator;
+use crate::models::Model;
+use crate::utils::context_window_registry;
+use crate::utils::token_counting::Encoding;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Context window assumed for model ids neither registered nor found in the
+/// built-in [`context_window_registry`].
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 4096;
+
+/// Simulated price in USD per 1000 tokens of models without one.
+pub const DEFAULT_PRICE_PER_1K_TOKENS: f64 = 0.002;
+
+/// Built-in models: id, creation timestamp and owner. Their context
+/// windows come from the [`context_window_registry`].
+const BUILTIN_MODELS: [(&str, u64, &str); 10] = [
+    ("gpt-4o", 1715367049, "system"),
+    ("gpt-4o-mini", 1721172741, "system"),
+    ("gpt-4-turbo", 1712361441, "system"),
+    ("gpt-4", 1687882411, "openai"),
+    ("gpt-3.5-turbo", 1677610602, "openai"),
+    ("gpt-3.5-turbo-instruct", 1692901427, "system"),
+    ("davinci-002", 1692634301, "system"),
+    ("babbage-002", 1692634615, "system"),
+    ("o1", 1734375816, "system"),
+    ("text-embedding-ada-002", 1671217299, "openai-internal"),
+];
+
+/// Owner of the fake models reported by `/v1/models`.
+pub const FAKE_MODEL_OWNER: &str = "openai-mock";
+
+/// Built-in fake models: id, context window, latency in milliseconds and
+/// the text every completion produces. Smaller models answer faster and
+/// more tersely.
+const FAKE_MODELS: [(&str, u32, u64, &str); 3] = [
+    ("mock-tiny", 2_048, 10, "OK."),
+    (
+        "mock-smart",
+        32_768,
+        40,
+        "The request is clear. Here is a short, direct answer that addresses it.",
+    ),
+    (
+        "mock-verbose",
+        128_000,
+        25,
+        "Thank you for your question! Let me walk through this carefully, step by step, so \
+         that nothing important is left out. First, it helps to restate what is being asked \
+         and why it matters. Second, there are several angles worth considering, each with \
+         its own trade-offs. Finally, putting all of this together, the answer depends on \
+         the details, and I hope this thorough explanation proves useful.",
+    ),
+];
+
+/// Everything the mock needs to know about one model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSpec {
+    /// The model identifier.
+    pub id: String,
+
+    /// Unix timestamp reported by `/v1/models`.
+    pub created: u64,
+
+    /// Owner reported by `/v1/models`.
+    pub owned_by: String,
+
+    /// Maximum number of prompt plus completion tokens.
+    pub context_window: u32,
+
+    /// Tokenizer encoding.
+    pub encoding: Encoding,
+
+    /// Delay before responding; the endpoint's latency when `None`.
+    pub latency: Option<Latency>,
+
+    /// Fraction of requests that fail with a server error; the endpoint's
+    /// error rate when `None`.
+    pub error_rate: Option<f64>,
+
+    /// Endpoints the model may be used with; every endpoint when `None`.
+    pub endpoints: Option<BTreeSet<Endpoint>>,
+
+    /// How completion text is produced; the global generator or strategy
+    /// when `None`.
+    pub generation: Option<GenerationStrategy>,
+
+    /// Simulated price in USD per 1000 tokens.
+    pub price_per_1k_tokens: f64,
+}
+
+impl ModelSpec {
+    /// The description of `id` before any per-model override.
+    fn base(id: &str) -> Self {
+        if let Some(spec) = Self::fake(id) {
+            return spec;
+        }
+
+        let builtin = BUILTIN_MODELS.iter().find(|(builtin, ..)| *builtin == id);
+        let (created, owned_by) = match builtin {
+            Some((_, created, owned_by)) => (*created, *owned_by),
+            None => (0, "user"),
+        };
+
+        Self {
+            id: id.to_string(),
+            created,
+            owned_by: owned_by.to_string(),
+            context_window: context_window(id),
+            encoding: Encoding::for_model(id),
+            latency: None,
+            error_rate: None,
+            endpoints: None,
+            generation: None,
+            price_per_1k_tokens: DEFAULT_PRICE_PER_1K_TOKENS,
+        }
+    }
+
+    /// The description of fake model `id`. Its latency and text are part
+    /// of its style, so they do not follow the global settings.
+    fn fake(id: &str) -> Option<Self> {
+        let (_, context_window, latency_ms, text) =
+            FAKE_MODELS.iter().find(|(fake, ..)| *fake == id)?;
+        Some(Self {
+            id: id.to_string(),
+            created: 0,
+            owned_by: FAKE_MODEL_OWNER.to_string(),
+            context_window: *context_window,
+            encoding: Encoding::Cl100kBase,
+            latency: Some(Latency::fixed(Duration::from_millis(*latency_ms))),
+            error_rate: None,
+            endpoints: Some(BTreeSet::from([Endpoint::Completions])),
+            generation: Some(GenerationStrategy::Fixed {
+                text: text.to_string(),
+            }),
+            price_per_1k_tokens: DEFAULT_PRICE_PER_1K_TOKENS,
+        })
+    }
+
+    /// Replaces the fields `overrides` sets.
+    fn apply(&mut self, overrides: &ModelConfig) {
+        if let Some(owned_by) = &overrides.owned_by {
+            self.owned_by = owned_by.clone();
+        }
+        if let Some(context_window) = overrides.context_window {
+            self.context_window = context_window;
+        }
+        if let Some(encoding) = overrides.encoding {
+            self.encoding = encoding;
+        }
+        if let Some(latency) = &overrides.latency {
+            self.latency = Some(latency.clone());
+        }
+        if let Some(error_rate) = overrides.error_rate {
+            self.error_rate = Some(error_rate);
+        }
+        if let Some(endpoints) = &overrides.endpoints {
+            self.endpoints = Some(endpoints.clone());
+        }
+        if let Some(generation) = &overrides.generation {
+            self.generation = Some(generation.clone());
+        }
+        if let Some(price) = overrides.price_per_1k_tokens {
+            self.price_per_1k_tokens = price;
+        }
+    }
+
+    /// Whether the model may be used with `endpoint`.
+    pub fn supports(&self, endpoint: Endpoint) -> bool {
+        self.endpoints
+            .as_ref()
+            .is_none_or(|endpoints| endpoints.contains(&endpoint))
+    }
+
+    /// What produces the model's completion text: its own strategy, or
+    /// else the generator or strategy of `config`.
+    pub fn text_generator<'a>(&'a self, config: &'a GenerationConfig) -> &'a dyn TextGenerator {
+        match (&self.generation, &config.generator) {
+            (Some(strategy), _) => strategy,
+            (None, Some(generator)) => generator,
+            (None, None) => &config.strategy,
+        }
+    }
+
+    /// The simulated price in USD of `tokens` tokens.
+    pub fn cost(&self, tokens: u32) -> f64 {
+        tokens as f64 * self.price_per_1k_tokens / 1000.0
+    }
+
+    /// The model as listed by `/v1/models`.
+    pub fn to_model(&self) -> Model {
+        Model {
+            id: self.id.clone(),
+            object: "model".to_string(),
+            created: self.created,
+            owned_by: self.owned_by.clone(),
+        }
+    }
+}
+
+/// The built-in context window of `id`, or [`DEFAULT_CONTEXT_WINDOW`].
+fn context_window(id: &str) -> u32 {
+    context_window_registry::lookup(id).unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// The models of one mock instance: the built-in ones plus those added or
+/// overridden by `MockConfig::models`, and by
+/// [`register`](Self::register) while the server is running.
+#[derive(Debug)]
+pub struct ModelRegistry {
+    configured: BTreeMap<String, ModelSpec>,
+    registered: RwLock<BTreeMap<String, ModelSpec>>,
+    /// Template for model ids that are not registered.
+    fallback: ModelSpec,
+}
+
+impl ModelRegistry {
+    /// Builds the registry for `config`.
+    pub fn from_config(config: &MockConfig) -> Self {
+        let mut configured: BTreeMap<String, ModelSpec> = BUILTIN_MODELS
+            .iter()
+            .map(|(id, ..)| *id)
+            .chain(FAKE_MODELS.iter().map(|(id, ..)| *id))
+            .map(|id| (id.to_string(), ModelSpec::base(id)))
+            .collect();
+
+        for (id, overrides) in &config.models {
+            configured
+                .entry(id.clone())
+                .or_insert_with(|| ModelSpec::base(id))
+                .apply(overrides);
+        }
+
+        Self {
+            configured,
+            registered: RwLock::new(BTreeMap::new()),
+            fallback: ModelSpec::base(""),
+        }
+    }
+
+    /// Adds model `id`, or changes its description, for this instance
+    /// only. Unset fields of `overrides` keep their current values.
+    pub fn register(&self, id: &str, overrides: &ModelConfig) {
+        let mut spec = self.get(id).unwrap_or_else(|| ModelSpec::base(id));
+        spec.apply(overrides);
+        self.registered.write().unwrap().insert(id.to_string(), spec);
+    }
+
+    /// Forgets every model registered with [`register`](Self::register).
+    pub fn clear_registered(&self) {
+        self.registered.write().unwrap().clear();
+    }
+
+    /// The registered model `id`.
+    pub fn get(&self, id: &str) -> Option<ModelSpec> {
+        match self.registered.read().unwrap().get(id) {
+            Some(spec) => Some(spec.clone()),
+            None => self.configured.get(id).cloned(),
+        }
+    }
+
+    /// The registered model `id`, or a description built from the global
+    /// settings if `id` is unknown. Unknown models are served like any
+    /// other, so tests are free to use made-up ids.
+    pub fn resolve(&self, id: &str) -> ModelSpec {
+        self.get(id).unwrap_or_else(|| ModelSpec {
+            id: id.to_string(),
+            context_window: context_window(id),
+            encoding: Encoding::for_model(id),
+            ..self.fallback.clone()
+        })
+    }
+
+    /// Every registered model, ordered by id.
+    pub fn models(&self) -> Vec<ModelSpec> {
+        let mut models = self.configured.clone();
+        models.extend(self.registered.read().unwrap().clone());
+        models.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_builtin_and_overridden_models() {
+        let config = MockConfig::default()
+            .with_latency(Duration::from_millis(10))
+            .with_model("gpt-4o", ModelConfig::new().latency(Duration::from_millis(300)))
+            .with_model(
+                "ft:custom",
+                ModelConfig::new().endpoints([Endpoint::ListModels]),
+            );
+        let registry = ModelRegistry::from_config(&config);
+
+        let gpt4o = registry.get("gpt-4o").unwrap();
+        assert_eq!(gpt4o.encoding, Encoding::O200kBase);
+        assert_eq!(gpt4o.context_window, 128_000);
+        assert_eq!(gpt4o.latency, Some(Latency::fixed(Duration::from_millis(300))));
+        assert_eq!(registry.get("gpt-4").unwrap().latency, None);
+
+        let custom = registry.get("ft:custom").unwrap();
+        assert!(!custom.supports(Endpoint::Completions));
+        assert_eq!(custom.owned_by, "user");
+
+        let unknown = registry.resolve("made-up-model");
+        assert_eq!(unknown.context_window, DEFAULT_CONTEXT_WINDOW);
+        assert_eq!(registry.resolve("gpt-4o-2024-08-06").context_window, 128_000);
+        assert!(unknown.supports(Endpoint::Completions));
+        assert!(registry.get("made-up-model").is_none());
+    }
+
+    #[test]
+    fn test_fake_models() {
+        let registry = ModelRegistry::from_config(
+            &MockConfig::default()
+                .with_model("mock-tiny", ModelConfig::new().latency(Duration::ZERO)),
+        );
+        let text = |id: &str| match &registry.get(id).unwrap().generation {
+            Some(GenerationStrategy::Fixed { text }) => text.len(),
+            other => panic!("unexpected strategy {:?}", other),
+        };
+        assert!(text("mock-tiny") < text("mock-smart"));
+        assert!(text("mock-smart") < text("mock-verbose"));
+
+        let smart = registry.get("mock-smart").unwrap();
+        assert_eq!(smart.owned_by, FAKE_MODEL_OWNER);
+        assert_eq!(smart.latency, Some(Latency::fixed(Duration::from_millis(40))));
+        assert_eq!(
+            registry.get("mock-tiny").unwrap().latency,
+            Some(Latency::fixed(Duration::ZERO))
+        );
+    }
+
+    #[test]
+    fn test_registered_models() {
+        let registry = ModelRegistry::from_config(&MockConfig::default());
+        registry.register("acme-large", &ModelConfig::new().context_window(65_536));
+        registry.register("gpt-4o", &ModelConfig::new().latency(Duration::from_millis(5)));
+
+        let acme = registry.resolve("acme-large");
+        assert_eq!(acme.context_window, 65_536);
+        assert_eq!(acme.owned_by, "user");
+        let gpt4o = registry.get("gpt-4o").unwrap();
+        assert_eq!(gpt4o.context_window, 128_000);
+        assert_eq!(gpt4o.latency, Some(Latency::fixed(Duration::from_millis(5))));
+        assert!(registry.models().iter().any(|model| model.id == "acme-large"));
+
+        // Other instances are unaffected.
+        let other = ModelRegistry::from_config(&MockConfig::default());
+        assert_eq!(other.resolve("acme-large").context_window, DEFAULT_CONTEXT_WINDOW);
+
+        registry.clear_registered();
+        assert_eq!(registry.resolve("acme-large").context_window, DEFAULT_CONTEXT_WINDOW);
+        assert_eq!(registry.get("gpt-4o").unwrap().latency, None);
+    }
+}
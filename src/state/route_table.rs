@@ -0,0 +1,84 @@
+//! Per-endpoint settings that can be changed while the server is running.
+
+use crate::config::{Endpoint, MockConfig, RouteConfig};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// The settings of every endpoint: those of the configuration, plus
+/// overrides applied at runtime.
+///
+/// Overrides let a test flip a dependency from healthy to degraded (and
+/// back) mid-scenario without restarting the server.
+#[derive(Debug, Default)]
+pub struct RouteTable {
+    configured: BTreeMap<Endpoint, RouteConfig>,
+    overrides: RwLock<BTreeMap<Endpoint, RouteConfig>>,
+}
+
+impl RouteTable {
+    /// Builds the table from the `routes` of `config`, without overrides.
+    pub fn from_config(config: &MockConfig) -> Self {
+        Self {
+            configured: config.routes.clone(),
+            overrides: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// The effective settings of `endpoint`: its override layered over its
+    /// configured settings.
+    pub fn get(&self, endpoint: Endpoint) -> RouteConfig {
+        let configured = self.configured.get(&endpoint).cloned().unwrap_or_default();
+        match self.overrides.read().unwrap().get(&endpoint) {
+            Some(route) => route.layered_over(&configured),
+            None => configured,
+        }
+    }
+
+    /// The effective settings of every endpoint.
+    pub fn all(&self) -> BTreeMap<Endpoint, RouteConfig> {
+        Endpoint::ALL
+            .into_iter()
+            .map(|endpoint| (endpoint, self.get(endpoint)))
+            .collect()
+    }
+
+    /// Overrides the settings of `endpoint`. Unset fields keep their
+    /// configured values; a previous override is replaced.
+    pub fn set(&self, endpoint: Endpoint, route: RouteConfig) {
+        self.overrides.write().unwrap().insert(endpoint, route);
+    }
+
+    /// Removes the override of `endpoint`, restoring its configured
+    /// settings.
+    pub fn clear(&self, endpoint: Endpoint) {
+        self.overrides.write().unwrap().remove(&endpoint);
+    }
+
+    /// Removes every override.
+    pub fn clear_all(&self) {
+        self.overrides.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Latency;
+    use std::time::Duration;
+
+    #[test]
+    fn test_overrides_layer_over_config() {
+        let config = MockConfig::default().with_route_latency(Endpoint::Completions, Duration::from_millis(5));
+        let routes = RouteTable::from_config(&config);
+        assert_eq!(routes.get(Endpoint::Completions).error_rate, None);
+
+        routes.set(Endpoint::Completions, RouteConfig::new().error_rate(1.0));
+        let route = routes.get(Endpoint::Completions);
+        assert_eq!(route.error_rate, Some(1.0));
+        assert_eq!(route.latency, Some(Latency::fixed(Duration::from_millis(5))));
+        assert_eq!(routes.get(Endpoint::ListModels), RouteConfig::default());
+
+        routes.clear(Endpoint::Completions);
+        assert_eq!(routes.get(Endpoint::Completions), config.routes[&Endpoint::Completions]);
+    }
+}
@@ -0,0 +1,18 @@
+//! Point-in-time statistics about a mock instance.
+
+use crate::utils::token_counting::TokenizerMode;
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of a mock instance's activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockStats {
+    /// Requests received so far.
+    pub requests: usize,
+
+    /// Streams currently being served.
+    pub active_streams: usize,
+
+    /// Whether token counts come from the real BPE encodings or from the
+    /// approximate fallback used when the encoding data is unavailable.
+    pub tokenizer_mode: TokenizerMode,
+}
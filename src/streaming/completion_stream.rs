@@ -0,0 +1,152 @@
+//! Splits a completion response into the chunks sent when `stream: true`.
+
+use crate::config::ChunkGranularity;
+use crate::models::completion::{Choice, CompletionChunk, CompletionResponse};
+use crate::streaming::sse::{sse_data, SseEvent};
+use crate::utils::token_counting::TokenCounter;
+use std::iter;
+
+/// Converts a full completion response into streamed chunks.
+///
+/// Each choice's text is emitted in chunks of `granularity` (one token per
+/// chunk, like the real API, by default), followed by a final chunk for
+/// that choice carrying its `finish_reason`, mirroring the real API's
+/// streaming format. Tokens are split with `token_counter`.
+pub fn completion_chunks(
+    response: &CompletionResponse,
+    token_counter: &TokenCounter,
+    granularity: ChunkGranularity,
+) -> Vec<CompletionChunk> {
+    let template = chunk_template(response);
+    response
+        .choices
+        .iter()
+        .flat_map(|choice| choice_chunks(&template, choice, token_counter, granularity))
+        .map(|(chunk, _)| chunk)
+        .collect()
+}
+
+/// Encodes the chunks of a completion response as SSE events, counting the
+/// completion tokens each chunk carries.
+///
+/// Events are encoded as they are consumed and the tokens of each choice
+/// are only split when its first event is reached, so the events of a
+/// long response never all exist at once.
+pub fn completion_events(
+    response: CompletionResponse,
+    token_counter: TokenCounter,
+    granularity: ChunkGranularity,
+) -> impl Iterator<Item = SseEvent> + Send + 'static {
+    let template = chunk_template(&response);
+    response
+        .choices
+        .into_iter()
+        .flat_map(move |choice| choice_chunks(&template, &choice, &token_counter, granularity))
+        .map(|(chunk, tokens)| SseEvent::new(sse_data(&chunk), tokens))
+}
+
+/// The chunks of one choice, each with the number of tokens it carries.
+/// They are built from `template` as they are consumed.
+fn choice_chunks(
+    template: &CompletionChunk,
+    choice: &Choice,
+    token_counter: &TokenCounter,
+    granularity: ChunkGranularity,
+) -> impl Iterator<Item = (CompletionChunk, u32)> + Send + 'static {
+    let template = template.clone();
+    let index = choice.index;
+    let pieces = token_counter.split_tokens(&choice.text);
+
+    group_tokens(pieces, granularity)
+        .into_iter()
+        .map(|(text, tokens)| (text, None, tokens))
+        .chain(iter::once((String::new(), choice.finish_reason.clone(), 0)))
+        .map(move |(text, finish_reason, tokens)| {
+            let choice = Choice {
+                text,
+                index,
+                logprobs: None,
+                finish_reason,
+            };
+            (CompletionChunk { choices: vec![choice], ..template.clone() }, tokens)
+        })
+}
+
+/// Joins consecutive tokens into the chunks of `granularity`, returning
+/// each chunk's text and token count.
+fn group_tokens(pieces: Vec<String>, granularity: ChunkGranularity) -> Vec<(String, u32)> {
+    let mut groups: Vec<(String, u32)> = Vec::new();
+    let mut current = String::new();
+    let mut tokens = 0;
+
+    for piece in pieces {
+        let starts_word = piece.starts_with(char::is_whitespace);
+        if granularity == ChunkGranularity::Word && starts_word && tokens > 0 {
+            groups.push((std::mem::take(&mut current), tokens));
+            tokens = 0;
+        }
+
+        current.push_str(&piece);
+        tokens += 1;
+
+        let complete = match granularity {
+            ChunkGranularity::Token => true,
+            ChunkGranularity::Word => false,
+            ChunkGranularity::Sentence => {
+                piece.contains('\n') || piece.trim_end().ends_with(['.', '!', '?'])
+            }
+            ChunkGranularity::Tokens(count) => tokens as usize >= count.max(1),
+        };
+        if complete {
+            groups.push((std::mem::take(&mut current), tokens));
+            tokens = 0;
+        }
+    }
+    if tokens > 0 {
+        groups.push((current, tokens));
+    }
+
+    groups
+}
+
+/// A chunk of `response` without choices.
+fn chunk_template(response: &CompletionResponse) -> CompletionChunk {
+    CompletionChunk {
+        id: response.id.clone(),
+        object: response.object.clone(),
+        created: response.created,
+        model: response.model.clone(),
+        choices: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(pieces: &[&str], granularity: ChunkGranularity) -> Vec<(String, u32)> {
+        group_tokens(pieces.iter().map(|piece| piece.to_string()).collect(), granularity)
+    }
+
+    #[test]
+    fn test_group_tokens() {
+        let pieces = ["Hel", "lo", " wor", "ld", ".", " How", " are", " you", "?"];
+        assert_eq!(group(&pieces, ChunkGranularity::Token).len(), pieces.len());
+
+        let words = group(&pieces, ChunkGranularity::Word);
+        let texts: Vec<&str> = words.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["Hello", " world.", " How", " are", " you?"]);
+        assert_eq!(words[1].1, 3);
+
+        let sentences = group(&pieces, ChunkGranularity::Sentence);
+        assert_eq!(
+            sentences,
+            vec![("Hello world.".to_string(), 5), (" How are you?".to_string(), 4)]
+        );
+
+        let fours = group(&pieces, ChunkGranularity::Tokens(4));
+        let counts: Vec<u32> = fours.iter().map(|(_, tokens)| *tokens).collect();
+        assert_eq!(counts, vec![4, 4, 1]);
+        assert_eq!(group(&pieces, ChunkGranularity::Tokens(0)).len(), pieces.len());
+    }
+}
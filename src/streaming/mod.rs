@@ -0,0 +1,6 @@
+pub mod sse;
+pub mod completion_stream;
+pub mod scheduler;
+pub use sse::{sse_comment, sse_data, sse_done, sse_response, SseEvent, StreamEnd, StreamOptions};
+pub use completion_stream::{completion_chunks, completion_events};
+pub use scheduler::{StreamPermit, StreamScheduler};
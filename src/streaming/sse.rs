@@ -0,0 +1,263 @@
+//! Server-sent events (SSE) encoding for streamed responses.
+
+use crate::faults::StreamFault;
+use crate::scenario::InjectedError;
+use crate::streaming::StreamPermit;
+use crate::service::{body_response, MockBody, MockResponse};
+use bytes::Bytes;
+use http::header::{HeaderValue, CACHE_CONTROL};
+use http::StatusCode;
+use futures::stream;
+use serde::Serialize;
+use std::io;
+use std::iter::{self, Peekable};
+use std::time::Duration;
+
+/// Encodes a payload as a single SSE `data:` event.
+pub fn sse_data<T: Serialize>(payload: &T) -> Bytes {
+    let json = serde_json::to_string(payload).unwrap_or_else(|_| "{}".to_string());
+    Bytes::from(format!("data: {}\n\n", json))
+}
+
+/// Encodes an SSE comment line (e.g. `: keep-alive`), which clients must
+/// ignore.
+pub fn sse_comment(text: &str) -> Bytes {
+    Bytes::from(format!(": {}\n\n", text))
+}
+
+/// The terminating `data: [DONE]` event.
+pub fn sse_done() -> Bytes {
+    Bytes::from_static(b"data: [DONE]\n\n")
+}
+
+/// A pre-encoded SSE event together with the number of completion tokens
+/// it delivers, used for usage accounting.
+#[derive(Debug, Clone)]
+pub struct SseEvent {
+    pub data: Bytes,
+    pub tokens: u32,
+}
+
+impl SseEvent {
+    pub fn new(data: Bytes, tokens: u32) -> Self {
+        Self { data, tokens }
+    }
+}
+
+/// How a streamed body ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamEnd {
+    /// `true` if every event was sent, `false` if the client disconnected
+    /// first.
+    pub completed: bool,
+
+    /// Completion tokens delivered before the stream ended.
+    pub tokens_sent: u32,
+}
+
+/// Callback invoked exactly once when a streamed body ends.
+pub type StreamEndCallback = Box<dyn FnOnce(StreamEnd) + Send>;
+
+/// Options controlling how events are streamed.
+#[derive(Default)]
+pub struct StreamOptions {
+    /// Failure injected partway through the stream.
+    pub fault: Option<StreamFault>,
+
+    /// Delay between consecutive events.
+    pub chunk_delay: Duration,
+
+    /// Emit a `: keep-alive` comment whenever the wait for the next event
+    /// exceeds this interval.
+    pub keep_alive: Option<Duration>,
+
+    /// Called when the stream completes or the client disconnects.
+    pub on_end: Option<StreamEndCallback>,
+
+    /// Concurrency slot held for the lifetime of the stream.
+    pub permit: Option<StreamPermit>,
+
+    /// Wait for a fair turn (see [`StreamPermit::turn`]) before each
+    /// event. Requires `permit`.
+    pub fair_scheduling: bool,
+}
+
+/// Tracks progress of a body and reports it when dropped, which happens
+/// both when the stream is exhausted and when the server drops it because
+/// the client went away.
+struct EndGuard {
+    on_end: Option<StreamEndCallback>,
+    tokens_sent: u32,
+    completed: bool,
+}
+
+impl Drop for EndGuard {
+    fn drop(&mut self) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(StreamEnd {
+                completed: self.completed,
+                tokens_sent: self.tokens_sent,
+            });
+        }
+    }
+}
+
+/// The items of a body: each chunk (or the error ending the body) with
+/// the number of completion tokens it delivers.
+type BodyItems = Peekable<Box<dyn Iterator<Item = (io::Result<Bytes>, u32)> + Send>>;
+
+/// State threaded through the lazily produced body.
+struct BodyState {
+    items: BodyItems,
+    guard: EndGuard,
+    permit: Option<StreamPermit>,
+    /// Time left to wait before the next item is due.
+    wait: Duration,
+}
+
+/// The events of a stream interrupted by a fault: the first
+/// `after_chunks` events, then the failure.
+struct FaultedEvents<I> {
+    events: I,
+    remaining: usize,
+    fault: Option<StreamFault>,
+}
+
+impl<I: Iterator<Item = SseEvent>> Iterator for FaultedEvents<I> {
+    type Item = (io::Result<Bytes>, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            if let Some(event) = self.events.next() {
+                return Some((Ok(event.data), event.tokens));
+            }
+        }
+        match self.fault.take()? {
+            StreamFault::ErrorEvent { .. } => {
+                Some((Ok(sse_data(&InjectedError::server_error().to_json())), 0))
+            }
+            StreamFault::ServerError { .. } => Some((
+                Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "injected mid-stream server error",
+                )),
+                0,
+            )),
+            StreamFault::Truncate { mid_json, .. } => {
+                let next = self.events.next().filter(|_| mid_json)?;
+                Some((Ok(next.data.slice(..next.data.len() / 2)), 0))
+            }
+        }
+    }
+}
+
+/// Builds a `text/event-stream` response from pre-encoded events.
+///
+/// Events are taken from `events` only as the body is sent, so a long
+/// stream is never held in memory at once, and when the client
+/// disconnects no further events are produced. The `[DONE]` terminator is
+/// appended automatically unless `options.fault` interrupts the stream
+/// first.
+pub fn sse_response<I>(events: I, options: StreamOptions) -> MockResponse
+where
+    I: IntoIterator<Item = SseEvent>,
+    I::IntoIter: Send + 'static,
+{
+    let events = events.into_iter();
+    let items: Box<dyn Iterator<Item = (io::Result<Bytes>, u32)> + Send> = match options.fault {
+        None => Box::new(
+            events
+                .map(|event| (Ok(event.data), event.tokens))
+                .chain(iter::once((Ok(sse_done()), 0))),
+        ),
+        Some(fault) => Box::new(FaultedEvents {
+            events,
+            remaining: fault.after_chunks(),
+            fault: Some(fault),
+        }),
+    };
+    let mut items = items.peekable();
+
+    let guard = EndGuard {
+        on_end: options.on_end,
+        tokens_sent: 0,
+        completed: items.peek().is_none(),
+    };
+    let delay = options.chunk_delay;
+    let keep_alive = options.keep_alive.filter(|interval| !interval.is_zero());
+    let fair = options.fair_scheduling;
+    let state = BodyState {
+        items,
+        guard,
+        permit: options.permit,
+        wait: Duration::ZERO,
+    };
+
+    let body = stream::unfold(state, move |mut state| async move {
+        state.items.peek()?;
+
+        // Long gaps are broken up by keep-alive comments.
+        if let Some(interval) = keep_alive.filter(|interval| state.wait > *interval) {
+            tokio::time::sleep(interval).await;
+            state.wait -= interval;
+            return Some((Ok(sse_comment("keep-alive")), state));
+        }
+        if !state.wait.is_zero() {
+            tokio::time::sleep(state.wait).await;
+        }
+        state.wait = delay;
+
+        if let Some(permit) = state.permit.as_ref().filter(|_| fair) {
+            permit.turn().await;
+        }
+
+        let (item, tokens) = state.items.next()?;
+        state.guard.tokens_sent += tokens;
+        // An injected error ends the body; it is not a client disconnect.
+        if item.is_err() || state.items.peek().is_none() {
+            state.guard.completed = true;
+        }
+        Some((item, state))
+    });
+
+    let mut response =
+        body_response(StatusCode::OK, "text/event-stream", MockBody::from_stream(body));
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn endless_events() -> impl Iterator<Item = SseEvent> + Send + 'static {
+        iter::repeat_with(|| SseEvent::new(Bytes::from_static(b"data: {}\n\n"), 1))
+    }
+
+    #[tokio::test]
+    async fn test_events_are_produced_lazily() {
+        // An endless source of events only works if they are consumed as
+        // the body is sent.
+        let response = sse_response(endless_events(), StreamOptions::default());
+        let MockBody::Stream(body) = response.into_body() else {
+            panic!("expected a streamed body");
+        };
+        let chunks: Vec<_> = body.take(3).map(Result::unwrap).collect().await;
+        assert_eq!(chunks.len(), 3);
+
+        let options = StreamOptions {
+            fault: Some(StreamFault::ErrorEvent { after_chunks: 2 }),
+            ..StreamOptions::default()
+        };
+        let response = sse_response(endless_events(), options);
+        let body = response.into_body().to_bytes().await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(body.matches("data: {}").count(), 2);
+        assert!(body.contains("\"error\""));
+        assert!(!body.contains("[DONE]"));
+    }
+}
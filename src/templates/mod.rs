@@ -0,0 +1,4 @@
+pub mod precomputed;
+pub mod response_template;
+pub use precomputed::PrecomputedResponse;
+pub use response_template::{render_template, validate_template, TemplateError};
@@ -0,0 +1,189 @@
+//! Completion responses computed once and served with only their `id`
+//! and `created` fields changed, as configured by
+//! [`MockConfig::high_throughput`](crate::config::MockConfig::high_throughput).
+
+use crate::config::{ChunkGranularity, HighThroughputConfig};
+use crate::models::completion::{Choice, CompletionResponse, Usage};
+use crate::service::{body_response, MockResponse};
+use crate::streaming::{completion_chunks, sse_done};
+use crate::utils::token_counting::TokenCounter;
+use crate::utils::utils::{generate_uuid, get_current_timestamp};
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{HeaderValue, CACHE_CONTROL};
+use http::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Stand-ins for the `id` and `created` values while the bodies are
+/// serialized. They are found with their quotes, which a string in the
+/// body cannot contain unescaped.
+const ID_PLACEHOLDER: &str = "__precomputed_id__";
+const CREATED_PLACEHOLDER: &str = "__precomputed_created__";
+
+/// The JSON and streamed bodies of one completion, ready to be served.
+#[derive(Debug, Clone)]
+pub struct PrecomputedResponse {
+    json: BodyTemplate,
+    events: BodyTemplate,
+}
+
+impl PrecomputedResponse {
+    /// Computes the response `config` describes, tokenizing its text once.
+    pub fn new(config: &HighThroughputConfig) -> Self {
+        let token_counter = TokenCounter::for_model(&config.model);
+        let completion_tokens = token_counter.count_tokens(&config.text);
+        let response = CompletionResponse {
+            id: String::new(),
+            object: "text_completion".to_string(),
+            created: 0,
+            model: config.model.clone(),
+            choices: vec![Choice {
+                text: config.text.clone(),
+                index: 0,
+                logprobs: None,
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: Usage {
+                prompt_tokens: 0,
+                completion_tokens,
+                total_tokens: completion_tokens,
+            },
+        };
+
+        let mut events = Vec::new();
+        for chunk in completion_chunks(&response, &token_counter, ChunkGranularity::default()) {
+            events.extend_from_slice(b"data: ");
+            events.extend_from_slice(&with_placeholders(&chunk));
+            events.extend_from_slice(b"\n\n");
+        }
+        events.extend_from_slice(&sse_done());
+        Self {
+            json: BodyTemplate::parse(&with_placeholders(&response)),
+            events: BodyTemplate::parse(&events),
+        }
+    }
+
+    /// The response with a fresh `id` and the current time: the JSON body,
+    /// or every SSE event at once when `stream` is set.
+    pub fn respond(&self, stream: bool) -> MockResponse {
+        let id = format!("\"cmpl-mock-id-{}\"", generate_uuid());
+        let created = get_current_timestamp().timestamp().to_string();
+        if !stream {
+            let body = self.json.render(&id, &created);
+            return body_response(StatusCode::OK, "application/json", body);
+        }
+        let mut response = body_response(
+            StatusCode::OK,
+            "text/event-stream",
+            self.events.render(&id, &created),
+        );
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+}
+
+/// `payload` as JSON, with the placeholders as its `id` and `created`.
+fn with_placeholders(payload: &impl Serialize) -> Vec<u8> {
+    let mut value = serde_json::to_value(payload).unwrap_or_default();
+    if let Value::Object(fields) = &mut value {
+        fields.insert("id".to_string(), Value::from(ID_PLACEHOLDER));
+        fields.insert("created".to_string(), Value::from(CREATED_PLACEHOLDER));
+    }
+    serde_json::to_vec(&value).unwrap_or_default()
+}
+
+/// A body split at its placeholders.
+#[derive(Debug, Clone)]
+struct BodyTemplate {
+    parts: Vec<Part>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(Bytes),
+    Id,
+    Created,
+}
+
+impl BodyTemplate {
+    fn parse(body: &[u8]) -> Self {
+        let id = format!("\"{}\"", ID_PLACEHOLDER);
+        let created = format!("\"{}\"", CREATED_PLACEHOLDER);
+        let mut parts = Vec::new();
+        let mut len = 0;
+        let mut rest = body;
+        while !rest.is_empty() {
+            let next = [(id.as_bytes(), Part::Id), (created.as_bytes(), Part::Created)]
+                .into_iter()
+                .filter_map(|(needle, part)| Some((find(rest, needle)?, needle.len(), part)))
+                .min_by_key(|(at, _, _)| *at);
+            let Some((at, needle_len, part)) = next else {
+                break;
+            };
+            parts.push(Part::Literal(Bytes::copy_from_slice(&rest[..at])));
+            parts.push(part);
+            len += at;
+            rest = &rest[at + needle_len..];
+        }
+        parts.push(Part::Literal(Bytes::copy_from_slice(rest)));
+        len += rest.len();
+        Self { parts, len }
+    }
+
+    fn render(&self, id: &str, created: &str) -> Bytes {
+        let capacity = self.len + self.parts.len() * id.len().max(created.len());
+        let mut body = BytesMut::with_capacity(capacity);
+        for part in &self.parts {
+            match part {
+                Part::Literal(bytes) => body.put_slice(bytes),
+                Part::Id => body.put_slice(id.as_bytes()),
+                Part::Created => body.put_slice(created.as_bytes()),
+            }
+        }
+        body.freeze()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_respond() {
+        let config = HighThroughputConfig::new().text("Say \"__precomputed_id__\" twice.");
+        let precomputed = PrecomputedResponse::new(&config);
+
+        let body = precomputed.respond(false).into_body().to_bytes().await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["id"].as_str().unwrap().starts_with("cmpl-mock-id-"));
+        assert!(body["created"].as_u64().unwrap() > 0);
+        assert_eq!(body["choices"][0]["text"], config.text);
+        assert!(body["usage"]["completion_tokens"].as_u64().unwrap() > 0);
+
+        let response = precomputed.respond(true);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let body = response.into_body().to_bytes().await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.ends_with("data: [DONE]\n\n"));
+        let chunks: Vec<Value> = body
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert!(chunks.len() > 1);
+        let text: String = chunks
+            .iter()
+            .map(|chunk| chunk["choices"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(text, config.text);
+        assert!(chunks.iter().all(|chunk| chunk["id"] == chunks[0]["id"]));
+    }
+}
@@ -0,0 +1,221 @@
+//! Handlebars templating of canned responses.
+//!
+//! Templates are rendered per request, so one fixture can serve many
+//! parameterized cases. The request's JSON body is the template context,
+//! and three helpers are available:
+//!
+//! ```text
+//! {
+//!   "id": "chatcmpl-{{uuid}}",
+//!   "created": {{timestamp}},
+//!   "model": "{{model}}",
+//!   "choices": [{"message": {"role": "assistant", "content": "You said: {{messages.[0].content}}"}}]
+//! }
+//! ```
+//!
+//! - `{{uuid}}` renders a random UUID.
+//! - `{{timestamp}}` renders the current Unix timestamp in seconds.
+//! - `{{usage "text"}}` renders a `usage` object for a response of
+//!   `text`, counting the prompt tokens of the request's `messages` (with
+//!   the per-message overheads of its `model`) or `prompt`.
+//!
+//! Values are escaped for use inside JSON strings; use triple braces
+//! (`{{{field}}}`) to insert a value unescaped. Missing fields render as
+//! empty strings.
+
+use crate::models::completion::Usage;
+use crate::models::{ErrorResponse, OpenAIError};
+use crate::utils::token_counting::TokenCounter;
+use crate::utils::utils::{generate_uuid, get_current_timestamp};
+use crate::validators::{chat_messages, prompt_token_counts};
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError, Template,
+};
+use crate::service::MockResponse;
+use http::StatusCode;
+use serde_json::Value;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// A template that could not be compiled or rendered.
+#[derive(Debug)]
+pub enum TemplateError {
+    /// The template is not valid Handlebars.
+    Syntax(handlebars::TemplateError),
+
+    /// Rendering the template failed.
+    Render(RenderError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Syntax(e) => write!(f, "invalid template: {}", e),
+            TemplateError::Render(e) => write!(f, "cannot render template: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl TemplateError {
+    /// The `500` response served when a canned response cannot be
+    /// rendered.
+    pub fn to_response(&self) -> MockResponse {
+        ErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            OpenAIError::server_error(format!("The mock failed to render a response template: {}", self)),
+        )
+        .to_response()
+    }
+}
+
+fn uuid_helper(
+    _: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&generate_uuid())?;
+    Ok(())
+}
+
+fn timestamp_helper(
+    _: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&get_current_timestamp().timestamp().to_string())?;
+    Ok(())
+}
+
+/// Model whose tokenizer counts the usage of requests without a `model`.
+const DEFAULT_USAGE_MODEL: &str = "gpt-4o-mini";
+
+fn usage_helper(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let completion = h.param(0).and_then(|p| p.value().as_str()).unwrap_or_default();
+    let request = ctx.data();
+    let counter = TokenCounter::for_model(request["model"].as_str().unwrap_or(DEFAULT_USAGE_MODEL));
+    let prompt_tokens = match request.get("messages") {
+        Some(messages) => counter.count_messages_tokens(&chat_messages(messages)),
+        None => prompt_token_counts(request.get("prompt"), &counter).iter().sum(),
+    };
+    let usage = Usage::new(prompt_tokens, counter.count_tokens(completion));
+    out.write(&serde_json::to_string(&usage).unwrap_or_default())?;
+    Ok(())
+}
+
+/// Escapes a value for use inside a JSON string literal.
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn registry() -> &'static Handlebars<'static> {
+    static REGISTRY: OnceLock<Handlebars<'static>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(escape_json);
+        registry.register_helper("uuid", Box::new(uuid_helper));
+        registry.register_helper("timestamp", Box::new(timestamp_helper));
+        registry.register_helper("usage", Box::new(usage_helper));
+        registry
+    })
+}
+
+/// Checks that `template` is valid Handlebars, so broken templates are
+/// caught when they are loaded rather than when they are served.
+pub fn validate_template(template: &str) -> Result<(), TemplateError> {
+    Template::compile(template)
+        .map(|_| ())
+        .map_err(TemplateError::Syntax)
+}
+
+/// Renders `template` with the JSON body of a `request` as its context.
+pub fn render_template(template: &str, request: &Value) -> Result<String, TemplateError> {
+    validate_template(template)?;
+    registry()
+        .render_template(template, request)
+        .map_err(TemplateError::Render)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_request_fields() {
+        let request = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "say \"hi\""}],
+        });
+        let rendered = render_template(
+            r#"{"model": "{{model}}", "echo": "{{messages.[0].content}}", "missing": "{{nope}}"}"#,
+            &request,
+        )
+        .unwrap();
+        let rendered: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(rendered, json!({"model": "gpt-4o", "echo": "say \"hi\"", "missing": ""}));
+    }
+
+    #[test]
+    fn test_builtin_helpers() {
+        let rendered = render_template(r#"{"id": "{{uuid}}", "created": {{timestamp}}}"#, &Value::Null).unwrap();
+        let rendered: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(rendered["id"].as_str().unwrap().len(), 36);
+        assert!(rendered["created"].as_i64().unwrap() > 1_700_000_000);
+
+        let other = render_template("{{uuid}}", &Value::Null).unwrap();
+        assert_ne!(rendered["id"], other);
+    }
+
+    #[test]
+    fn test_usage_helper() {
+        let counter = TokenCounter::for_model("gpt-4o");
+        let request = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "name": "ada", "content": "Hello there"},
+            ],
+        });
+        let rendered = render_template(r#"{"usage": {{usage "Hi!"}}}"#, &request).unwrap();
+        let rendered: Value = serde_json::from_str(&rendered).unwrap();
+        let prompt_tokens = counter.count_messages_tokens(&chat_messages(&request["messages"]));
+        assert_eq!(rendered["usage"]["prompt_tokens"], prompt_tokens);
+        assert_eq!(rendered["usage"]["completion_tokens"], counter.count_tokens("Hi!"));
+        assert_eq!(
+            rendered["usage"]["total_tokens"],
+            prompt_tokens + counter.count_tokens("Hi!")
+        );
+
+        let request = json!({"model": "gpt-3.5-turbo-instruct", "prompt": ["one", "two three"]});
+        let rendered = render_template("{{usage \"\"}}", &request).unwrap();
+        let rendered: Value = serde_json::from_str(&rendered).unwrap();
+        let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+        assert_eq!(
+            rendered["prompt_tokens"],
+            counter.count_tokens("one") + counter.count_tokens("two three")
+        );
+        assert_eq!(rendered["completion_tokens"], 0);
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        assert!(validate_template("{{#if model}}unterminated").is_err());
+        assert!(matches!(
+            render_template("{{#if model}}unterminated", &Value::Null),
+            Err(TemplateError::Syntax(_))
+        ));
+    }
+}
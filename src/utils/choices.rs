@@ -1,7 +1,9 @@
 use crate::models::completion::Choice;
 use crate::utils::token_counting::TokenCounter;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use rand::{thread_rng, Rng};
+use rayon::prelude::*;
 use crate::models::completion::Logprobs;
 
 
@@ -21,117 +23,355 @@ impl Choice {
         }
     }
 
-    fn generate_mock_logprobs(&self, text: &str, logprobs_n: u32) -> Logprobs {
-        let mut rng = thread_rng();
+    /// Sets the choice's text to the echoed `prompt`, if any.
+    ///
+    /// The echoed prompt does not count toward `max_tokens`, so it is never
+    /// cut; the finish reason is left to [`append_completion`].
+    pub fn generate_text(&mut self, prompt: &str, echo: bool) {
+        self.text = if echo {
+            prompt.to_string()
+        } else {
+            String::new()
+        };
+    }
+}
 
-        // Split text into mock tokens (simple word-based splitting for mock data)
-        let tokens: Vec<String> = text
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
+/// Mock log probabilities for the tokens of `text`, as split by
+/// `token_counter`, each with `logprobs_n` alternatives.
+fn generate_mock_logprobs(text: &str, logprobs_n: u32, token_counter: &TokenCounter) -> Logprobs {
+    let tokens = token_counter.split_tokens(text);
+    let mut text_offset = Vec::with_capacity(tokens.len());
+    let mut offset = 0;
+    for token in &tokens {
+        text_offset.push(offset);
+        offset += token.len();
+    }
+    sample_logprobs(tokens, text_offset, logprobs_n, token_counter.candidate_tokens())
+}
 
-        let mut current_offset = 0;
-        let mut text_offset: Vec<usize> = Vec::new();
+/// Random log probabilities for `tokens`, starting at `text_offset` in the
+/// text. Each token is the most likely of its top `logprobs_n`, the others
+/// drawn from `candidates`.
+///
+/// Like a model's, most tokens are picked with high confidence and a few
+/// are not, and the probabilities of a position's alternatives never add
+/// up to more than one.
+fn sample_logprobs(
+    tokens: Vec<String>,
+    text_offset: Vec<usize>,
+    logprobs_n: u32,
+    candidates: &'static [String],
+) -> Logprobs {
+    let mut rng = thread_rng();
+    let mut token_logprobs = Vec::with_capacity(tokens.len());
+    let mut top_logprobs = Vec::with_capacity(tokens.len());
+    for token in &tokens {
+        // Squaring a uniform draw skews it toward certainty: a median
+        // probability near one half, and one token in ten below 0.09.
+        let doubt: f32 = rng.gen();
+        let logprob = -3.0 * doubt * doubt;
+        let probability = logprob.exp();
+        token_logprobs.push(logprob);
 
-        // Generate text offsets
-        for token in &tokens {
-            text_offset.push(current_offset);
-            current_offset += token.len() + 1; // +1 for space
+        let mut top = HashMap::with_capacity(logprobs_n as usize);
+        if logprobs_n > 0 {
+            top.insert(Cow::Owned(token.clone()), logprob);
         }
-
-        // Generate mock token logprobs
-        let token_logprobs: Vec<f32> = (0..tokens.len())
-            .map(|_| -rng.gen_range(0.0..5.0))
-            .collect();
-
-        // Generate top logprobs for each token
-        let top_logprobs: Vec<HashMap<String, f32>> = tokens
-            .iter()
-            .map(|_| {
-                let mut map = HashMap::new();
-                for _ in 0..logprobs_n {
-                    let mock_token = format!("token_{}", rng.gen_range(0..100));
-                    let mock_logprob = -rng.gen_range(0.0..10.0);
-                    map.insert(mock_token, mock_logprob);
-                }
-                map
-            })
-            .collect();
-
-        Logprobs {
-            tokens,
-            token_logprobs,
-            text_offset,
-            top_logprobs,
+        let alternatives = if candidates.is_empty() { 0 } else { logprobs_n.saturating_sub(1) };
+        // Each alternative takes a share of the probability left over, and
+        // is less likely than the token itself.
+        let mut remaining = 1.0 - probability;
+        for _ in 0..alternatives {
+            let share = remaining * rng.gen_range(0.2..0.6);
+            let candidate = candidates[rng.gen_range(0..candidates.len())].as_str();
+            top.entry(Cow::Borrowed(candidate))
+                .or_insert_with(|| share.min(probability * 0.99).max(f32::MIN_POSITIVE).ln());
+            remaining -= share;
         }
+        top_logprobs.push(top);
     }
 
-    pub fn generate_text(
-        &mut self,
-        prompt: &str,
-        stop_sequences: &[String],
-        max_tokens: u32,
-        echo: bool,
-        logprobs_n: Option<u32>,
-        model: &str
-    ) {
-        let mut generated = if echo {
-            prompt.to_string()
-        } else {
-            String::new()
-        };
+    Logprobs {
+        tokens,
+        token_logprobs,
+        text_offset,
+        top_logprobs,
+    }
+}
 
-        // Check for stop sequences
-        for stop_seq in stop_sequences {
-            if generated.contains(stop_seq) {
-                self.finish_reason = Some("stop".to_string());
-                generated = generated.split(stop_seq).next().unwrap_or("").to_string();
-                self.text = generated;
-                return;
-            }
-        }
-        let token_counter = TokenCounter::new(&model);
-        match token_counter {
-            Ok(token_counter) => {
-                // More robust token count estimation
-                let estimated_tokens = token_counter.count_tokens(&generated);
-                if estimated_tokens >= max_tokens {
-            self.finish_reason = Some("length".to_string());
-            self.text = token_counter.truncate_to_tokens(&generated, max_tokens);
-                    return;
-                }
-            },
-            Err(e) => {
-                eprintln!("Error creating token counter: {}", e);
+/// Smallest number of choices that are generated and given logprobs on
+/// rayon's thread pool rather than one choice after the other.
+pub const PARALLEL_CHOICES: usize = 8;
+
+/// Creates `n` choices for `prompt`, with indices `0..n` in order, holding
+/// the echoed prompt if `echo` is set.
+pub fn create_choices(n: i32, prompt: &str, echo: bool) -> Vec<Choice> {
+    let mut first = Choice::new(0, String::new(), echo, prompt);
+    first.generate_text(prompt, echo);
+    (0..n)
+        .map(|index| Choice {
+            index,
+            ..first.clone()
+        })
+        .collect()
+}
+
+/// Gives every choice mock logprobs for the tokens of its final text, echoed
+/// prompt included, with `logprobs_n` alternatives each.
+///
+/// Each choice samples its own values; a text shared with the previous
+/// choice is only tokenized once. From [`PARALLEL_CHOICES`] choices on,
+/// they are sampled concurrently.
+pub fn add_logprobs(choices: &mut [Choice], logprobs_n: u32, token_counter: &TokenCounter) {
+    let mut tokenized: Vec<(Vec<String>, Vec<usize>)> = Vec::with_capacity(choices.len());
+    for (i, choice) in choices.iter().enumerate() {
+        let tokens = match i.checked_sub(1) {
+            Some(previous) if choices[previous].text == choice.text => tokenized[previous].clone(),
+            _ => {
+                let Logprobs { tokens, text_offset, .. } =
+                    generate_mock_logprobs(&choice.text, 0, token_counter);
+                (tokens, text_offset)
             }
-        }
+        };
+        tokenized.push(tokens);
+    }
+
+    let candidates = token_counter.candidate_tokens();
+    let sample = |(choice, (tokens, text_offset)): (&mut Choice, (Vec<String>, Vec<usize>))| {
+        choice.logprobs = Some(sample_logprobs(tokens, text_offset, logprobs_n, candidates));
+    };
+    if choices.len() >= PARALLEL_CHOICES {
+        choices.par_iter_mut().zip(tokenized).for_each(sample);
+    } else {
+        choices.iter_mut().zip(tokenized).for_each(sample);
+    }
+}
 
-        self.text = generated;
-        self.finish_reason = Some("content".to_string());
+/// Most times a choice is generated again to make its text distinct.
+const MAX_DISTINCT_ATTEMPTS: usize = 8;
 
-        // Generate logprobs if requested
-        if let Some(n) = logprobs_n {
-            self.logprobs = Some(self.generate_mock_logprobs(&self.text, n));
+/// Ensures no two choices share the same text, so clients that deduplicate
+/// choices see `n` distinct candidates.
+///
+/// A choice whose text repeats an earlier one is replaced by
+/// `regenerate(choice, attempt)`, for attempts 1, 2, ... until it is
+/// distinct. Regenerated choices are cut like any other, so they respect
+/// `max_tokens`. After 8 attempts the choice is
+/// left as it is, for limits that leave no room for distinct texts.
+pub fn make_choices_distinct(
+    choices: &mut [Choice],
+    mut regenerate: impl FnMut(&mut Choice, usize),
+) {
+    let mut seen = HashSet::new();
+
+    for choice in choices.iter_mut() {
+        let mut attempt = 0;
+        while seen.contains(&choice.text) && attempt < MAX_DISTINCT_ATTEMPTS {
+            attempt += 1;
+            regenerate(choice, attempt);
         }
+        seen.insert(choice.text.clone());
     }
 }
 
-pub fn create_choices(
-    n: i32,
-    prompt: &str,
+/// Appends a completion `text` to every choice, after any echoed prompt.
+///
+/// The text is cut before the first of `stop_sequences` it contains, in
+/// which case the finish reason is `stop`. What remains is cut to exactly
+/// `max_tokens` tokens, in which case the finish reason is `length`;
+/// otherwise it is `stop`. The echoed prompt never counts toward the limit.
+pub fn append_completion(
+    choices: &mut [Choice],
+    text: &str,
+    max_tokens: u32,
     stop_sequences: &[String],
+    counter: &TokenCounter,
+) {
+    let (completion, finish_reason) = cut_completion(text, max_tokens, stop_sequences, counter);
+    for choice in choices.iter_mut() {
+        choice.text.push_str(&completion);
+        choice.finish_reason = Some(finish_reason.to_string());
+    }
+}
+
+/// Appends the best of `candidates` completions to the choices, one each,
+/// and returns the tokens of those left over, which the API bills too.
+///
+/// Candidates are cut like in [`append_completion`]. Being mock text, they
+/// are ranked only by whether they finished on their own: those cut to
+/// `max_tokens` come last, otherwise candidates keep their order.
+pub fn append_best_of(
+    choices: &mut [Choice],
+    candidates: &[String],
     max_tokens: u32,
-    echo: bool,
-    logprobs: Option<u32>,
-    model: &str
-) -> Vec<Choice> {
-    let mut choices = Vec::with_capacity(n as usize);
+    stop_sequences: &[String],
+    counter: &TokenCounter,
+) -> u32 {
+    let mut candidates: Vec<_> = candidates
+        .iter()
+        .map(|text| cut_completion(text, max_tokens, stop_sequences, counter))
+        .collect();
+    candidates.sort_by_key(|(_, finish_reason)| *finish_reason == "length");
 
-    for i in 0..n {
-        let mut choice = Choice::new(i, String::new(), echo, prompt);
-        choice.generate_text(prompt, stop_sequences, max_tokens, echo, logprobs, model);
-        choices.push(choice);
+    let mut candidates = candidates.into_iter();
+    for (choice, (completion, finish_reason)) in choices.iter_mut().zip(candidates.by_ref()) {
+        choice.text.push_str(&completion);
+        choice.finish_reason = Some(finish_reason.to_string());
+    }
+    candidates
+        .map(|(completion, _)| counter.count_tokens(&completion))
+        .sum()
+}
+
+/// `text` cut as [`append_completion`] does, with its finish reason.
+fn cut_completion(
+    text: &str,
+    max_tokens: u32,
+    stop_sequences: &[String],
+    counter: &TokenCounter,
+) -> (String, &'static str) {
+    let text = cut_at_stop(text, stop_sequences);
+    if counter.count_tokens(text) > max_tokens {
+        (counter.truncate_to_tokens(text, max_tokens), "length")
+    } else {
+        (text.to_string(), "stop")
+    }
+}
+
+/// `text` up to the earliest occurrence of any of `stop_sequences`, the
+/// sequence excluded.
+fn cut_at_stop<'a>(text: &'a str, stop_sequences: &[String]) -> &'a str {
+    let end = stop_sequences
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| text.find(stop.as_str()))
+        .min()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_choices() {
+        let counter = TokenCounter::approximate();
+        for n in [1, PARALLEL_CHOICES - 1, 50] {
+            let mut choices = create_choices(n as i32, "Say hi", true);
+            assert_eq!(choices.len(), n);
+            add_logprobs(&mut choices, 2, &counter);
+            for (i, choice) in choices.iter().enumerate() {
+                assert_eq!(choice.index, i as i32);
+                assert_eq!(choice.text, "Say hi");
+                assert!(choice.logprobs.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_mock_logprobs() {
+        let counter = TokenCounter::approximate();
+        let logprobs = generate_mock_logprobs("Hello wonderful world", 3, &counter);
+        assert_eq!(logprobs.tokens, counter.split_tokens("Hello wonderful world"));
+        assert_eq!(logprobs.text_offset[..3], [0, 4, 5]);
+        assert_eq!(logprobs.token_logprobs.len(), logprobs.tokens.len());
+        for (i, top) in logprobs.top_logprobs.iter().enumerate() {
+            assert!((1..=3).contains(&top.len()));
+            // The sampled token is the most likely alternative.
+            let logprob = logprobs.token_logprobs[i];
+            assert_eq!(top[logprobs.tokens[i].as_str()], logprob);
+            assert!(top.values().all(|alternative| *alternative <= logprob));
+        }
+
+        for top in &logprobs.top_logprobs {
+            let total: f32 = top.values().map(|logprob| logprob.exp()).sum();
+            assert!(total <= 1.0 + 1e-4, "{:?}", top);
+        }
+
+        let logprobs = generate_mock_logprobs("Hello", 0, &counter);
+        assert!(logprobs.top_logprobs.iter().all(HashMap::is_empty));
+    }
+
+    #[test]
+    fn test_append_completion_stops() {
+        let counter = TokenCounter::approximate();
+        let stops = ["\n\n".to_string(), "END".to_string()];
+        let mut choices = [Choice::new(0, String::new(), true, "Q:")];
+        append_completion(&mut choices, " one two END three\n\nfour", 16, &stops, &counter);
+        assert_eq!(choices[0].text, "Q: one two ");
+        assert_eq!(choices[0].finish_reason.as_deref(), Some("stop"));
+
+        // The earliest stop sequence wins, wherever it is listed.
+        assert_eq!(cut_at_stop("a\n\nb END", &stops), "a");
+        assert_eq!(cut_at_stop("a END", &[String::new()]), "a END");
+
+        // Text before the stop sequence may still run out of tokens.
+        let mut choices = [Choice::new(0, String::new(), false, "")];
+        append_completion(&mut choices, " one two three END", 2, &stops, &counter);
+        assert_eq!(choices[0].text, " one two");
+        assert_eq!(choices[0].finish_reason.as_deref(), Some("length"));
+    }
+
+    #[test]
+    fn test_append_best_of() {
+        let counter = TokenCounter::approximate();
+        let candidates = [" one two three".to_string(), " one".to_string(), " two".to_string()];
+        let mut choices = [
+            Choice::new(0, String::new(), false, ""),
+            Choice::new(1, String::new(), false, ""),
+        ];
+        let leftover = append_best_of(&mut choices, &candidates, 2, &[], &counter);
+        assert_eq!(choices[0].text, " one");
+        assert_eq!(choices[1].text, " two");
+        assert!(choices.iter().all(|choice| choice.finish_reason.as_deref() == Some("stop")));
+        // The candidate cut to two tokens is billed all the same.
+        assert_eq!(leftover, 2);
+    }
+
+    #[test]
+    fn test_make_choices_distinct() {
+        let counter = TokenCounter::approximate();
+        let mut choices = create_choices(3, "", false);
+        append_completion(&mut choices, " same text", 3, &[], &counter);
+        let mut attempts = Vec::new();
+        make_choices_distinct(&mut choices, |choice, attempt| {
+            attempts.push((choice.index, attempt));
+            let text = match attempt {
+                1 => " same text".to_string(),
+                _ => format!(" choice {} of many, many more", choice.index),
+            };
+            let mut fresh = Choice::new(choice.index, String::new(), false, "");
+            append_completion(std::slice::from_mut(&mut fresh), &text, 3, &[], &counter);
+            *choice = fresh;
+        });
+        assert_eq!(attempts, [(1, 1), (1, 2), (2, 1), (2, 2)]);
+        assert!(choices[1].text != choices[0].text && choices[2].text != choices[1].text);
+        // The replacements are cut like the originals.
+        assert_eq!(counter.count_tokens(&choices[2].text), 3);
+        assert_eq!(choices[2].finish_reason.as_deref(), Some("length"));
+
+        // A regenerated text that never differs is given up on.
+        let mut choices = create_choices(2, "", false);
+        make_choices_distinct(&mut choices, |_, _| {});
+        assert_eq!(choices[0].text, choices[1].text);
     }
 
-    choices
+    #[test]
+    fn test_add_logprobs() {
+        let counter = TokenCounter::approximate();
+        let mut choices = create_choices(2, "Q:", true);
+        append_completion(&mut choices[..1], " yes", 16, &[], &counter);
+        append_completion(&mut choices[1..], " no way", 16, &[], &counter);
+        add_logprobs(&mut choices, 1, &counter);
+
+        // Logprobs cover the completion as well as the echoed prompt.
+        for choice in &choices {
+            let logprobs = choice.logprobs.as_ref().unwrap();
+            assert_eq!(logprobs.tokens.concat(), choice.text);
+            for (token, offset) in logprobs.tokens.iter().zip(&logprobs.text_offset) {
+                assert!(choice.text[*offset..].starts_with(token.as_str()));
+            }
+        }
+    }
 }
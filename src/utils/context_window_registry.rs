@@ -0,0 +1,156 @@
+//! How many tokens fit in the context window of each model id.
+//!
+//! Like the [`encoding_registry`](crate::utils::encoding_registry), model
+//! ids are looked up exactly first, then by their longest registered
+//! prefix, and fine-tuned ids use the window of their base model. Models
+//! neither registered here nor configured in `MockConfig::models` get
+//! [`DEFAULT_CONTEXT_WINDOW`](crate::state::model_registry::DEFAULT_CONTEXT_WINDOW)
+//! tokens.
+//!
+//! The built-in table is shared by the whole process and never changes;
+//! other models get their window per mock instance, from `context_window`
+//! in `MockConfig::models` or
+//! [`MockServerHandle::register_model`](crate::server::MockServerHandle::register_model).
+//!
+//! ```
+//! use openai_mock::utils::context_window_registry::lookup;
+//!
+//! assert_eq!(lookup("gpt-4o-2024-08-06"), Some(128_000));
+//! assert_eq!(lookup("acme-large"), None);
+//! ```
+
+use crate::utils::encoding_registry::base_model;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Model ids with a known context window.
+const BUILTIN_MODELS: [(&str, u32); 30] = [
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4.5-preview", 128_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4o-mini", 128_000),
+    ("chatgpt-4o-latest", 128_000),
+    ("o1", 200_000),
+    ("o1-2024-12-17", 200_000),
+    ("o1-mini", 128_000),
+    ("o1-preview", 128_000),
+    ("o3", 200_000),
+    ("o3-mini", 200_000),
+    ("o4-mini", 200_000),
+    ("gpt-4", 8_192),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-turbo-preview", 128_000),
+    ("gpt-4-1106-preview", 128_000),
+    ("gpt-4-0125-preview", 128_000),
+    ("gpt-3.5-turbo", 16_385),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-35-turbo", 16_385),
+    ("davinci-002", 16_384),
+    ("babbage-002", 16_384),
+    ("text-embedding-ada-002", 8_191),
+    ("text-embedding-3-small", 8_191),
+    ("text-embedding-3-large", 8_191),
+    ("text-davinci-003", 4_097),
+    ("text-davinci-002", 4_097),
+    ("code-davinci-002", 8_001),
+    ("code-cushman-001", 2_048),
+];
+
+/// Model id prefixes with a known context window.
+const BUILTIN_PREFIXES: [(&str, u32); 14] = [
+    ("gpt-4.1-", 1_047_576),
+    ("gpt-4.5-", 128_000),
+    ("gpt-4o-", 128_000),
+    ("chatgpt-4o-", 128_000),
+    ("o1-", 128_000),
+    ("o3-", 200_000),
+    ("o4-", 200_000),
+    ("gpt-4-turbo-", 128_000),
+    ("gpt-4-32k-", 32_768),
+    ("gpt-4-", 8_192),
+    ("gpt-3.5-turbo-", 16_385),
+    ("gpt-3.5-turbo-instruct-", 4_096),
+    ("gpt-35-turbo-", 16_385),
+    ("text-embedding-", 8_191),
+];
+
+/// Context windows by exact model id and by model id prefix.
+#[derive(Debug, Clone, Default)]
+pub struct ContextWindowRegistry {
+    models: HashMap<String, u32>,
+    prefixes: Vec<(String, u32)>,
+}
+
+impl ContextWindowRegistry {
+    /// The context windows of the models known when this crate was
+    /// released.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        for (model, context_window) in BUILTIN_MODELS {
+            registry.register(model, context_window);
+        }
+        for (prefix, context_window) in BUILTIN_PREFIXES {
+            registry.register_prefix(prefix, context_window);
+        }
+        registry
+    }
+
+    /// Gives model id `model` a window of `context_window` tokens.
+    pub fn register(&mut self, model: &str, context_window: u32) {
+        self.models.insert(model.to_string(), context_window);
+    }
+
+    /// Gives every model id starting with `prefix` a window of
+    /// `context_window` tokens, unless the id or a longer prefix of it is
+    /// registered too.
+    pub fn register_prefix(&mut self, prefix: &str, context_window: u32) {
+        self.prefixes.retain(|(registered, _)| registered != prefix);
+        self.prefixes.push((prefix.to_string(), context_window));
+    }
+
+    /// The context window of `model`, if it or a prefix of it is
+    /// registered.
+    pub fn lookup(&self, model: &str) -> Option<u32> {
+        let model = base_model(model);
+        if let Some(context_window) = self.models.get(model) {
+            return Some(*context_window);
+        }
+        self.prefixes
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, context_window)| *context_window)
+    }
+}
+
+/// The built-in context window of `model`, if any.
+pub fn lookup(model: &str) -> Option<u32> {
+    static BUILTIN: OnceLock<ContextWindowRegistry> = OnceLock::new();
+    BUILTIN.get_or_init(ContextWindowRegistry::builtin).lookup(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let registry = ContextWindowRegistry::builtin();
+        assert_eq!(registry.lookup("gpt-4o-2024-08-06"), Some(128_000));
+        assert_eq!(registry.lookup("gpt-4.1-nano"), Some(1_047_576));
+        assert_eq!(registry.lookup("gpt-4-0613"), Some(8_192));
+        assert_eq!(registry.lookup("gpt-4-turbo-2024-04-09"), Some(128_000));
+        assert_eq!(registry.lookup("gpt-4-1106-preview"), Some(128_000));
+        assert_eq!(registry.lookup("gpt-3.5-turbo-0125"), Some(16_385));
+        assert_eq!(registry.lookup("gpt-3.5-turbo-instruct-0914"), Some(4_096));
+        assert_eq!(registry.lookup("ft:gpt-4o-mini-2024-07-18:acme::abc123"), Some(128_000));
+        assert_eq!(registry.lookup("made-up-model"), None);
+
+        let mut registry = ContextWindowRegistry::builtin();
+        registry.register("acme-large", 65_536);
+        registry.register_prefix("acme-", 2_048);
+        assert_eq!(registry.lookup("acme-large"), Some(65_536));
+        assert_eq!(registry.lookup("acme-small"), Some(2_048));
+    }
+}
@@ -0,0 +1,172 @@
+//! Which BPE encoding each model id uses.
+//!
+//! Model ids are looked up exactly first, then by their longest registered
+//! prefix, so dated snapshots and variants (`gpt-4o-2024-08-06`,
+//! `gpt-4o-audio-preview`) use the encoding of their family. Fine-tuned
+//! ids (`ft:gpt-4o-mini:org::id`) use the encoding of their base model.
+//!
+//! The built-in table is shared by the whole process and never changes.
+//! Models released after this crate get their encoding per mock instance,
+//! from `encoding` in `MockConfig::models` or
+//! [`MockServerHandle::register_model`](crate::server::MockServerHandle::register_model):
+//!
+//! ```
+//! use openai_mock::config::{MockConfig, ModelConfig};
+//! use openai_mock::state::MockState;
+//! use openai_mock::utils::encoding_registry::lookup;
+//! use openai_mock::utils::token_counting::Encoding;
+//!
+//! assert_eq!(lookup("gpt-4o-2024-08-06"), Some(Encoding::O200kBase));
+//! assert_eq!(lookup("gpt-7-mini"), None);
+//!
+//! let state = MockState::new(MockConfig::default());
+//! state
+//!     .models
+//!     .register("gpt-7-mini", &ModelConfig::new().encoding(Encoding::O200kBase));
+//! assert_eq!(state.models.resolve("gpt-7-mini").encoding, Encoding::O200kBase);
+//! ```
+
+use crate::utils::token_counting::Encoding;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Model ids with a known encoding.
+const BUILTIN_MODELS: [(&str, Encoding); 29] = [
+    ("gpt-4.1", Encoding::O200kBase),
+    ("gpt-4.5-preview", Encoding::O200kBase),
+    ("gpt-4o", Encoding::O200kBase),
+    ("gpt-4o-mini", Encoding::O200kBase),
+    ("chatgpt-4o-latest", Encoding::O200kBase),
+    ("o1", Encoding::O200kBase),
+    ("o1-mini", Encoding::O200kBase),
+    ("o1-preview", Encoding::O200kBase),
+    ("o3", Encoding::O200kBase),
+    ("o3-mini", Encoding::O200kBase),
+    ("o4-mini", Encoding::O200kBase),
+    ("gpt-4", Encoding::Cl100kBase),
+    ("gpt-4-turbo", Encoding::Cl100kBase),
+    ("gpt-3.5-turbo", Encoding::Cl100kBase),
+    ("gpt-3.5-turbo-instruct", Encoding::Cl100kBase),
+    ("gpt-35-turbo", Encoding::Cl100kBase),
+    ("davinci-002", Encoding::Cl100kBase),
+    ("babbage-002", Encoding::Cl100kBase),
+    ("text-embedding-ada-002", Encoding::Cl100kBase),
+    ("text-embedding-3-small", Encoding::Cl100kBase),
+    ("text-embedding-3-large", Encoding::Cl100kBase),
+    ("text-davinci-003", Encoding::P50kBase),
+    ("text-davinci-002", Encoding::P50kBase),
+    ("code-davinci-002", Encoding::P50kBase),
+    ("code-davinci-001", Encoding::P50kBase),
+    ("code-cushman-002", Encoding::P50kBase),
+    ("code-cushman-001", Encoding::P50kBase),
+    ("davinci-codex", Encoding::P50kBase),
+    ("cushman-codex", Encoding::P50kBase),
+];
+
+/// Model id prefixes with a known encoding.
+const BUILTIN_PREFIXES: [(&str, Encoding); 11] = [
+    ("gpt-4.1-", Encoding::O200kBase),
+    ("gpt-4.5-", Encoding::O200kBase),
+    ("gpt-4o-", Encoding::O200kBase),
+    ("chatgpt-4o-", Encoding::O200kBase),
+    ("o1-", Encoding::O200kBase),
+    ("o3-", Encoding::O200kBase),
+    ("o4-", Encoding::O200kBase),
+    ("gpt-4-", Encoding::Cl100kBase),
+    ("gpt-3.5-turbo-", Encoding::Cl100kBase),
+    ("gpt-35-turbo-", Encoding::Cl100kBase),
+    ("text-embedding-", Encoding::Cl100kBase),
+];
+
+/// Encodings by exact model id and by model id prefix.
+#[derive(Debug, Clone, Default)]
+pub struct EncodingRegistry {
+    models: HashMap<String, Encoding>,
+    prefixes: Vec<(String, Encoding)>,
+}
+
+impl EncodingRegistry {
+    /// The encodings of the models known when this crate was released.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        for (model, encoding) in BUILTIN_MODELS {
+            registry.register(model, encoding);
+        }
+        for (prefix, encoding) in BUILTIN_PREFIXES {
+            registry.register_prefix(prefix, encoding);
+        }
+        registry
+    }
+
+    /// Maps model id `model` to `encoding`.
+    pub fn register(&mut self, model: &str, encoding: Encoding) {
+        self.models.insert(model.to_string(), encoding);
+    }
+
+    /// Maps every model id starting with `prefix` to `encoding`, unless the
+    /// id or a longer prefix of it is registered too.
+    pub fn register_prefix(&mut self, prefix: &str, encoding: Encoding) {
+        self.prefixes.retain(|(registered, _)| registered != prefix);
+        self.prefixes.push((prefix.to_string(), encoding));
+    }
+
+    /// The encoding of `model`, if it or a prefix of it is registered.
+    pub fn lookup(&self, model: &str) -> Option<Encoding> {
+        let model = base_model(model);
+        if let Some(encoding) = self.models.get(model) {
+            return Some(*encoding);
+        }
+        self.prefixes
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, encoding)| *encoding)
+    }
+}
+
+/// The base model of fine-tuned id `ft:<base>:<org>::<id>`, or `model`
+/// itself.
+pub(crate) fn base_model(model: &str) -> &str {
+    match model.strip_prefix("ft:") {
+        Some(rest) => rest.split(':').next().unwrap_or(rest),
+        None => model,
+    }
+}
+
+/// The built-in encoding of `model`, if any.
+pub fn lookup(model: &str) -> Option<Encoding> {
+    static BUILTIN: OnceLock<EncodingRegistry> = OnceLock::new();
+    BUILTIN.get_or_init(EncodingRegistry::builtin).lookup(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let registry = EncodingRegistry::builtin();
+        assert_eq!(registry.lookup("gpt-4o"), Some(Encoding::O200kBase));
+        assert_eq!(registry.lookup("gpt-4o-audio-preview"), Some(Encoding::O200kBase));
+        assert_eq!(registry.lookup("gpt-4.1-nano"), Some(Encoding::O200kBase));
+        assert_eq!(registry.lookup("o3-mini-2025-01-31"), Some(Encoding::O200kBase));
+        assert_eq!(registry.lookup("gpt-4-0613"), Some(Encoding::Cl100kBase));
+        assert_eq!(registry.lookup("text-embedding-3-small"), Some(Encoding::Cl100kBase));
+        assert_eq!(registry.lookup("text-davinci-003"), Some(Encoding::P50kBase));
+        assert_eq!(
+            registry.lookup("ft:gpt-4o-mini-2024-07-18:acme::abc123"),
+            Some(Encoding::O200kBase)
+        );
+        assert_eq!(registry.lookup("made-up-model"), None);
+
+        let mut registry = EncodingRegistry::builtin();
+        registry.register_prefix("gpt-4-", Encoding::O200kBase);
+        registry.register("gpt-4-0613", Encoding::P50kBase);
+        assert_eq!(registry.lookup("gpt-4-turbo-preview"), Some(Encoding::O200kBase));
+        assert_eq!(registry.lookup("gpt-4-0613"), Some(Encoding::P50kBase));
+        // Longer prefixes win.
+        registry.register_prefix("gpt-4o-audio-", Encoding::Cl100kBase);
+        assert_eq!(registry.lookup("gpt-4o-audio-preview"), Some(Encoding::Cl100kBase));
+        assert_eq!(registry.lookup("gpt-4o-2024-08-06"), Some(Encoding::O200kBase));
+    }
+}
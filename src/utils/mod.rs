@@ -1,5 +1,8 @@
 pub mod choices;
+pub mod context_window_registry;
+pub mod encoding_registry;
 pub mod token_counting;
+#[allow(clippy::module_inception)]
 pub mod utils;
 
 pub use choices::*;
@@ -1,68 +1,577 @@
+#[cfg(feature = "tiktoken")]
 use tiktoken_rs::{cl100k_base, p50k_base, o200k_base};
+#[cfg(feature = "tiktoken")]
+use tiktoken_rs::CoreBPE;
+use crate::models::chat::ChatCompletionMessage;
 use crate::models::completion::Usage;
+use crate::utils::encoding_registry;
+use dashmap::DashSet;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
+/// Average number of characters per token assumed by the approximate
+/// tokenizer, matching OpenAI's rule of thumb for English text.
+const APPROXIMATE_CHARS_PER_TOKEN: usize = 4;
+
+/// Ids of the tokens offered as alternatives in mock logprobs: common
+/// words and subwords, past the single-byte tokens every encoding starts
+/// with.
+const CANDIDATE_IDS: std::ops::Range<u32> = 256..1280;
+
+/// Alternatives offered in mock logprobs by the approximate tokenizer.
+const APPROXIMATE_CANDIDATES: [&str; 16] = [
+    " the", " a", " and", " to", " of", " in", " is", " it", " that", " for", " on", " with",
+    ".", ",", " you", " I",
+];
+
+/// Stands in for `tiktoken_rs::CoreBPE` when the `tiktoken` feature is
+/// disabled. No value of it exists, so every counter is approximate.
+#[cfg(not(feature = "tiktoken"))]
+#[derive(Debug)]
+enum CoreBPE {}
+
+#[cfg(not(feature = "tiktoken"))]
+impl CoreBPE {
+    fn encode_with_special_tokens(&self, _: &str) -> Vec<u32> {
+        match *self {}
+    }
+
+    fn decode(&self, _: Vec<u32>) -> Result<String, std::fmt::Error> {
+        match *self {}
+    }
+
+    fn _decode_native_and_split(&self, _: Vec<u32>) -> std::iter::Empty<Vec<u8>> {
+        match *self {}
+    }
+}
+
+/// Set once any `TokenCounter::for_model` call has fallen back to the
+/// approximate tokenizer.
+static FELL_BACK: AtomicBool = AtomicBool::new(false);
+
+/// Which tokenizer a `TokenCounter` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerMode {
+    /// The model's real BPE encoding; counts match the API exactly.
+    Bpe,
+
+    /// A character-based estimate, used when the BPE data could not be
+    /// loaded or the `tiktoken` feature is disabled. Counts are close to,
+    /// but not exactly, the API's.
+    Approximate,
+}
+
+/// The tokenizer mode in effect for this process: `Approximate` without
+/// the `tiktoken` feature or once any counter had to fall back, `Bpe`
+/// otherwise.
+pub fn tokenizer_mode() -> TokenizerMode {
+    if cfg!(not(feature = "tiktoken")) || FELL_BACK.load(Ordering::Relaxed) {
+        TokenizerMode::Approximate
+    } else {
+        TokenizerMode::Bpe
+    }
+}
+
+/// The parts of a chat message that count towards a request's prompt
+/// tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChatMessage {
+    /// `system`, `developer`, `user`, `assistant` or `tool`.
     pub role: String,
+
+    /// The text of the message.
     pub content: String,
+
+    /// Optional name of the participant.
+    pub name: Option<String>,
+}
+
+impl ChatMessage {
+    /// A message from `role` with text `content` and no name.
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            name: None,
+        }
+    }
+}
+
+impl From<&ChatCompletionMessage> for ChatMessage {
+    fn from(message: &ChatCompletionMessage) -> Self {
+        Self {
+            role: message.role.clone(),
+            content: message.text(),
+            name: message.name.clone(),
+        }
+    }
+}
+
+/// Tokens the chat format adds around the messages of a request, on top
+/// of their role, content and name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageOverhead {
+    /// Tokens added for every message.
+    pub per_message: u32,
+
+    /// Tokens added for a message with a `name`. Negative for models where
+    /// the name replaces the role.
+    pub per_name: i32,
+
+    /// Tokens priming the assistant's reply, added once per request.
+    pub reply_priming: u32,
+}
+
+impl Default for MessageOverhead {
+    fn default() -> Self {
+        Self {
+            per_message: 3,
+            per_name: 1,
+            reply_priming: 3,
+        }
+    }
+}
+
+impl MessageOverhead {
+    /// The overheads `model` is documented to add. `gpt-3.5-turbo-0301`
+    /// wraps each message in one more token and drops the role of named
+    /// messages; every later chat model uses the defaults.
+    pub fn for_model(model: &str) -> Self {
+        match model {
+            "gpt-3.5-turbo-0301" | "gpt-35-turbo-0301" => Self {
+                per_message: 4,
+                per_name: -1,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
 }
 
+/// Counts tokens the way the mock does when it fills in `usage`, so tests
+/// can compute the counts they expect.
+///
+/// ```
+/// use openai_mock::{ChatMessage, TokenCounter};
+///
+/// let counter = TokenCounter::for_model("gpt-4o-mini");
+/// let usage = counter.calculate_usage("Say hello", "Hello!");
+/// assert_eq!(usage.total_tokens, usage.prompt_tokens + usage.completion_tokens);
+///
+/// let usage = counter.calculate_chat_usage(&[ChatMessage::new("user", "Say hello")], "Hello!");
+/// assert_eq!(usage.prompt_tokens, counter.count_tokens("user") + counter.count_tokens("Say hello") + 6);
+/// ```
+#[derive(Clone)]
 pub struct TokenCounter {
-    encoding: tiktoken_rs::CoreBPE,
+    encoding: Option<&'static CoreBPE>,
+    candidates: &'static [String],
+    message_overhead: MessageOverhead,
+}
+
+/// A BPE encoding used by OpenAI models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// GPT-4o and o-series models.
+    O200kBase,
+
+    /// GPT-4, GPT-3.5 and the embedding models.
+    Cl100kBase,
+
+    /// Legacy `text-davinci` models.
+    P50kBase,
+}
+
+impl Encoding {
+    /// The built-in encoding of `model` from the
+    /// [`encoding_registry`], `cl100k_base`
+    /// (with a warning) for any other. Mock instances take the encodings
+    /// of their own models from their
+    /// [`ModelRegistry`](crate::state::ModelRegistry).
+    pub fn for_model(model: &str) -> Self {
+        /// Unknown models already warned about, bounded so arbitrary ids
+        /// cannot grow it forever.
+        static WARNED: OnceLock<DashSet<String>> = OnceLock::new();
+        const MAX_WARNED: usize = 256;
+
+        encoding_registry::lookup(model).unwrap_or_else(|| {
+            let warned = WARNED.get_or_init(DashSet::new);
+            if warned.len() < MAX_WARNED && warned.insert(model.to_string()) {
+                log::warn!(
+                    "no encoding registered for model {:?}; counting its tokens with cl100k_base",
+                    model
+                );
+            }
+            Encoding::Cl100kBase
+        })
+    }
+
+    /// The encoder, loaded on first use and shared by every counter since
+    /// building it takes tens of milliseconds.
+    #[cfg(feature = "tiktoken")]
+    fn load(&self) -> Result<&'static CoreBPE, Box<dyn std::error::Error>> {
+        type Loaded = OnceLock<Result<CoreBPE, String>>;
+        static O200K_BASE: Loaded = OnceLock::new();
+        static CL100K_BASE: Loaded = OnceLock::new();
+        static P50K_BASE: Loaded = OnceLock::new();
+
+        let loaded = match self {
+            Encoding::O200kBase => O200K_BASE.get_or_init(|| o200k_base().map_err(|e| e.to_string())),
+            Encoding::Cl100kBase => {
+                CL100K_BASE.get_or_init(|| cl100k_base().map_err(|e| e.to_string()))
+            }
+            Encoding::P50kBase => P50K_BASE.get_or_init(|| p50k_base().map_err(|e| e.to_string())),
+        };
+        loaded.as_ref().map_err(|e| e.clone().into())
+    }
+
+    /// Fails: the encoders are only available with the `tiktoken` feature.
+    #[cfg(not(feature = "tiktoken"))]
+    fn load(&self) -> Result<&'static CoreBPE, Box<dyn std::error::Error>> {
+        Err("openai-mock was built without the `tiktoken` feature".into())
+    }
+
+    /// The text of the [`CANDIDATE_IDS`] tokens of the encoding, decoded
+    /// on first use.
+    fn candidates(&self, bpe: &CoreBPE) -> &'static [String] {
+        static CANDIDATES: [OnceLock<Vec<String>>; 3] =
+            [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+
+        CANDIDATES[*self as usize].get_or_init(|| {
+            CANDIDATE_IDS
+                .filter_map(|id| bpe.decode(vec![id]).ok())
+                .filter(|text| !text.trim().is_empty())
+                .collect()
+        })
+    }
 }
 
 impl TokenCounter {
+    /// Creates a counter for `model`, failing if the BPE data of its
+    /// encoding cannot be loaded.
     pub fn new(model: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let encoding = match model {
-            "gpt-4" | "gpt-3.5-turbo" | "text-embedding-ada-002" => {
-                cl100k_base()?
-            },
-            "gpt-4o" | "gpt-4o-mini" => {
-                o200k_base()?
-            },
-            "text-davinci-002" | "text-davinci-003" => {
-                p50k_base()?
-            },
-            _ => cl100k_base()? // default to cl100k_base
-        };
+        Ok(Self::with_encoding(Encoding::for_model(model))?
+            .with_message_overhead(MessageOverhead::for_model(model)))
+    }
+
+    /// Creates a counter using `encoding`.
+    pub fn with_encoding(encoding: Encoding) -> Result<Self, Box<dyn std::error::Error>> {
+        let bpe = encoding.load()?;
+        Ok(Self {
+            encoding: Some(bpe),
+            candidates: encoding.candidates(bpe),
+            message_overhead: MessageOverhead::default(),
+        })
+    }
+
+    /// Creates a counter for `model`, falling back to the approximate
+    /// tokenizer (with a warning) if the BPE data cannot be loaded, e.g.
+    /// when offline.
+    pub fn for_model(model: &str) -> Self {
+        Self::for_encoding(Encoding::for_model(model))
+            .with_message_overhead(MessageOverhead::for_model(model))
+    }
+
+    /// Creates a counter using `encoding`, falling back to the approximate
+    /// tokenizer (with a warning) if its BPE data cannot be loaded. Without
+    /// the `tiktoken` feature, the counter is always approximate.
+    pub fn for_encoding(encoding: Encoding) -> Self {
+        if cfg!(not(feature = "tiktoken")) {
+            return Self::approximate();
+        }
+        match Self::with_encoding(encoding) {
+            Ok(counter) => counter,
+            Err(e) => {
+                if !FELL_BACK.swap(true, Ordering::Relaxed) {
+                    log::warn!(
+                        "failed to load BPE data for {:?} ({}); token counts are approximate",
+                        encoding,
+                        e
+                    );
+                }
+                Self::approximate()
+            }
+        }
+    }
+
+    /// Creates a counter using the approximate tokenizer.
+    pub fn approximate() -> Self {
+        static CANDIDATES: OnceLock<Vec<String>> = OnceLock::new();
+        let candidates =
+            CANDIDATES.get_or_init(|| APPROXIMATE_CANDIDATES.map(str::to_string).to_vec());
+        Self {
+            encoding: None,
+            candidates,
+            message_overhead: MessageOverhead::default(),
+        }
+    }
 
-        Ok(Self { encoding })
+    /// Counts chat messages with `overhead` instead of the default
+    /// overheads.
+    pub fn with_message_overhead(mut self, overhead: MessageOverhead) -> Self {
+        self.message_overhead = overhead;
+        self
     }
 
+    /// Which tokenizer this counter uses.
+    pub fn mode(&self) -> TokenizerMode {
+        match self.encoding {
+            Some(_) => TokenizerMode::Bpe,
+            None => TokenizerMode::Approximate,
+        }
+    }
+
+    /// Common tokens of the encoding, offered as the alternatives of each
+    /// token in mock logprobs.
+    pub fn candidate_tokens(&self) -> &'static [String] {
+        self.candidates
+    }
+
+    /// Counts the tokens of `text`.
     pub fn count_tokens(&self, text: &str) -> u32 {
-        self.encoding.encode_with_special_tokens(text).len() as u32
+        match &self.encoding {
+            Some(encoding) => encoding.encode_with_special_tokens(text).len() as u32,
+            None => approximate_pieces(text).len() as u32,
+        }
     }
 
+    /// Counts the prompt tokens of a chat request with `messages`: their
+    /// role, content and name, plus the overheads of the counter's model.
     pub fn count_messages_tokens(&self, messages: &[ChatMessage]) -> u32 {
-        // Add 3 tokens for each message for ChatML format
-        let per_message_tokens = 3;
+        let overhead = self.message_overhead;
+        let tokens: i64 = messages
+            .iter()
+            .map(|msg| {
+                let mut tokens = i64::from(overhead.per_message)
+                    + i64::from(self.count_tokens(&msg.role))
+                    + i64::from(self.count_tokens(&msg.content));
+                if let Some(name) = &msg.name {
+                    tokens += i64::from(self.count_tokens(name)) + i64::from(overhead.per_name);
+                }
+                tokens
+            })
+            .sum();
+        (tokens + i64::from(overhead.reply_priming)).clamp(0, i64::from(u32::MAX)) as u32
+    }
+
+    /// The text of token `ids` of the counter's encoding. IDs the encoding
+    /// does not know are skipped; the approximate tokenizer, which has no
+    /// vocabulary, decodes everything to nothing.
+    pub fn decode(&self, ids: &[u32]) -> String {
+        let Some(encoding) = &self.encoding else {
+            return String::new();
+        };
+        if let Ok(text) = encoding.decode(ids.to_vec()) {
+            return text;
+        }
 
-        messages.iter().map(|msg| {
-            self.count_tokens(&msg.content) +
-            self.count_tokens(&msg.role) +
-            per_message_tokens
-        }).sum()
+        // Decode the IDs in the shortest runs forming valid UTF-8 (a
+        // character may be split across up to four tokens), skipping IDs
+        // no run starting with them decodes.
+        let mut text = String::new();
+        let mut start = 0;
+        while start < ids.len() {
+            let run = (start + 1..=(start + 4).min(ids.len()))
+                .find_map(|end| Some((end, encoding.decode(ids[start..end].to_vec()).ok()?)));
+            match run {
+                Some((end, piece)) => {
+                    text.push_str(&piece);
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        text
     }
 
-    /// Creates a Usage struct with token counts for prompt and completion
+    /// The usage of a completion request for `prompt` answered with
+    /// `completion`.
     pub fn calculate_usage(&self, prompt: &str, completion: &str) -> Usage {
-        let prompt_tokens = self.count_tokens(prompt);
-        let completion_tokens = self.count_tokens(completion);
+        Usage::new(self.count_tokens(prompt), self.count_tokens(completion))
+    }
 
-        Usage {
-            prompt_tokens,
-            completion_tokens,
-            total_tokens: prompt_tokens + completion_tokens,
+    /// The usage of a chat request with `messages` answered with
+    /// `completion`.
+    pub fn calculate_chat_usage(&self, messages: &[ChatMessage], completion: &str) -> Usage {
+        Usage::new(self.count_messages_tokens(messages), self.count_tokens(completion))
+    }
+
+    /// Splits text into the pieces produced by the encoder, one per token.
+    ///
+    /// Tokens that only form valid UTF-8 together (e.g. a multi-byte
+    /// character split across two tokens) are merged into a single piece,
+    /// so every returned string is valid on its own.
+    pub fn split_tokens(&self, text: &str) -> Vec<String> {
+        let Some(encoding) = &self.encoding else {
+            return approximate_pieces(text).into_iter().map(str::to_string).collect();
+        };
+
+        let tokens = encoding.encode_with_special_tokens(text);
+        let mut pieces = Vec::with_capacity(tokens.len());
+        let mut pending: Vec<u8> = Vec::new();
+
+        for bytes in encoding._decode_native_and_split(tokens) {
+            pending.extend_from_slice(&bytes);
+            if let Ok(piece) = std::str::from_utf8(&pending) {
+                pieces.push(piece.to_string());
+                pending.clear();
+            }
+        }
+        if !pending.is_empty() {
+            pieces.push(String::from_utf8_lossy(&pending).into_owned());
         }
+
+        pieces
     }
 
-    /// Truncates text to approximately fit within max_tokens
+    /// The longest prefix of `text` made of at most its first `max_tokens`
+    /// tokens. A character split across the last kept token and the next
+    /// is dropped whole, so the result is always a prefix of `text`.
     pub fn truncate_to_tokens(&self, text: &str, max_tokens: u32) -> String {
-        let tokens = self.encoding.encode_with_special_tokens(text);
+        let Some(encoding) = &self.encoding else {
+            return approximate_pieces(text)
+                .into_iter()
+                .take(max_tokens as usize)
+                .collect();
+        };
+
+        let mut tokens = encoding.encode_with_special_tokens(text);
         if tokens.len() as u32 <= max_tokens {
             return text.to_string();
         }
+        tokens.truncate(max_tokens as usize);
+
+        // The tokens' bytes are a prefix of the text's; cut it at the last
+        // character boundary they reach.
+        let mut end: usize = encoding
+            ._decode_native_and_split(tokens)
+            .map(|bytes| bytes.len())
+            .sum();
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text[..end].to_string()
+    }
+}
+
+/// The usage of a completion request to `model` for `prompt` answered with
+/// `completion`, counted with [`TokenCounter::for_model`].
+pub fn calculate_usage(model: &str, prompt: &str, completion: &str) -> Usage {
+    TokenCounter::for_model(model).calculate_usage(prompt, completion)
+}
+
+/// Splits text the way the approximate tokenizer counts it: each word
+/// together with its leading whitespace, with long words cut into pieces of
+/// `APPROXIMATE_CHARS_PER_TOKEN` characters.
+fn approximate_pieces(text: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    let mut in_word = false;
+
+    for (i, c) in text.char_indices() {
+        let boundary = (c.is_whitespace() && in_word) || chars == APPROXIMATE_CHARS_PER_TOKEN;
+        if boundary && i > start {
+            pieces.push(&text[start..i]);
+            start = i;
+            chars = 0;
+            in_word = false;
+        }
+        if !c.is_whitespace() {
+            in_word = true;
+        }
+        chars += 1;
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximate_tokenizer() {
+        let counter = TokenCounter::approximate();
+        assert_eq!(counter.mode(), TokenizerMode::Approximate);
+
+        let text = "Hello wonderful world";
+        assert_eq!(counter.split_tokens(text), vec!["Hell", "o", " won", "derf", "ul", " wor", "ld"]);
+        assert_eq!(counter.count_tokens(text), 7);
+        assert_eq!(counter.split_tokens(text).concat(), text);
+        assert_eq!(counter.truncate_to_tokens(text, 3), "Hello won");
+        assert_eq!(counter.count_tokens(""), 0);
+
+        // Multi-byte characters are never split.
+        assert_eq!(counter.split_tokens("héllo").concat(), "héllo");
+    }
+
+    #[test]
+    fn test_message_overheads() {
+        let messages = [
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You are a helpful assistant.".to_string(),
+                name: None,
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: "New synergies will help drive top-line growth.".to_string(),
+                name: Some("example_user".to_string()),
+            },
+        ];
+        let text = |counter: &TokenCounter| -> u32 {
+            messages
+                .iter()
+                .map(|msg| {
+                    counter.count_tokens(&msg.role)
+                        + counter.count_tokens(&msg.content)
+                        + msg.name.as_deref().map_or(0, |name| counter.count_tokens(name))
+                })
+                .sum()
+        };
+        let counter = TokenCounter::for_model("gpt-4o");
+        assert_eq!(counter.count_messages_tokens(&messages), text(&counter) + 2 * 3 + 1 + 3);
+        assert_eq!(counter.count_messages_tokens(&[]), 3);
+
+        let legacy = TokenCounter::for_model("gpt-3.5-turbo-0301");
+        assert_eq!(legacy.count_messages_tokens(&messages), text(&legacy) + 2 * 4 - 1 + 3);
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_truncate_multibyte() {
+        for model in ["gpt-4o", "gpt-4", "text-davinci-003"] {
+            let counter = TokenCounter::for_model(model);
+            for text in ["🦀 crabs 🦀🦀 everywhere 🎉", "東京特許許可局の許可", "naïve café 😀"] {
+                let tokens = counter.count_tokens(text);
+                let mut previous = String::new();
+                for max_tokens in 0..=tokens {
+                    let truncated = counter.truncate_to_tokens(text, max_tokens);
+                    assert!(text.starts_with(&truncated), "{:?} of {:?}", truncated, text);
+                    assert!(!truncated.contains('\u{FFFD}'));
+                    assert!(counter.count_tokens(&truncated) <= max_tokens);
+                    assert!(truncated.len() >= previous.len());
+                    previous = truncated;
+                }
+                assert_eq!(previous, text);
+            }
+        }
+    }
 
-        let truncated_tokens = tokens[..max_tokens as usize].to_vec();
-        self.encoding.decode(truncated_tokens).unwrap()
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_decode() {
+        let counter = TokenCounter::for_model("gpt-3.5-turbo-instruct");
+        assert_eq!(counter.decode(&[9906, 1917]), "Hello world");
+        // Unknown IDs are skipped.
+        assert_eq!(counter.decode(&[9906, u32::MAX, 1917]), "Hello world");
+        assert_eq!(counter.decode(&[]), "");
+        assert_eq!(TokenCounter::approximate().decode(&[9906, 1917]), "");
     }
 }
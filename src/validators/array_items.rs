@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A validation failure tied to a single item of an array field.
+///
+/// `param` carries the indexed path of the offending item (e.g.
+/// `prompt[3]` or `prompt[1][0]`), matching the real API's error paths so
+/// clients can map failures back to the items they sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemError {
+    /// Indexed path of the offending item.
+    pub param: String,
+
+    /// Human readable description of the problem.
+    pub message: String,
+
+    /// Machine readable error code.
+    pub code: String,
+}
+
+impl ItemError {
+    pub fn new(param: &str, message: &str, code: &str) -> Self {
+        Self {
+            param: param.to_string(),
+            message: message.to_string(),
+            code: code.to_string(),
+        }
+    }
+}
+
+/// The shape an array of inputs is expected to have, decided by its first
+/// element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Text,
+    TokenId,
+    TokenArray,
+}
+
+impl ItemKind {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(_) => Some(ItemKind::Text),
+            Value::Number(_) => Some(ItemKind::TokenId),
+            Value::Array(_) => Some(ItemKind::TokenArray),
+            _ => None,
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            ItemKind::Text => "a string",
+            ItemKind::TokenId => "an integer",
+            ItemKind::TokenArray => "an array of integers",
+        }
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn invalid_type(param: &str, expected: &str, value: &Value) -> ItemError {
+    ItemError::new(
+        param,
+        &format!(
+            "Invalid type for '{}': expected {}, but got {} instead.",
+            param,
+            expected,
+            json_type_name(value)
+        ),
+        "invalid_type",
+    )
+}
+
+fn is_token_id(value: &Value) -> bool {
+    value.as_u64().is_some_and(|id| id <= u32::MAX as u64)
+}
+
+/// Validates a multi-item input field such as `prompt` or `input`.
+///
+/// The field may be a string, an array of strings, an array of token ids,
+/// or an array of token-id arrays. Every offending item is reported, not
+/// just the first, each with its indexed `param` path.
+pub fn validate_array_items(param: &str, value: Option<&Value>) -> Result<(), Vec<ItemError>> {
+    let items = match value {
+        None | Some(Value::Null) | Some(Value::String(_)) => return Ok(()),
+        Some(Value::Array(items)) => items,
+        Some(other) => {
+            return Err(vec![invalid_type(
+                param,
+                "a string or an array",
+                other,
+            )])
+        }
+    };
+
+    if items.is_empty() {
+        return Err(vec![ItemError::new(
+            param,
+            &format!(
+                "Invalid '{}': empty array. Expected an array with minimum length 1, but got an empty array instead.",
+                param
+            ),
+            "empty_array",
+        )]);
+    }
+
+    let kind = ItemKind::of(&items[0]);
+    let mut errors = Vec::new();
+
+    for (i, item) in items.iter().enumerate() {
+        let item_param = format!("{}[{}]", param, i);
+        let Some(kind) = kind else {
+            errors.push(invalid_type(&item_param, "a string, an integer or an array", item));
+            continue;
+        };
+
+        match (kind, item) {
+            (ItemKind::Text, Value::String(_)) => {}
+            (ItemKind::TokenId, value) if is_token_id(value) => {}
+            (ItemKind::TokenArray, Value::Array(tokens)) => {
+                if tokens.is_empty() {
+                    errors.push(ItemError::new(
+                        &item_param,
+                        &format!(
+                            "Invalid '{}': empty array. Expected an array with minimum length 1, but got an empty array instead.",
+                            item_param
+                        ),
+                        "empty_array",
+                    ));
+                }
+                for (j, token) in tokens.iter().enumerate() {
+                    if !is_token_id(token) {
+                        errors.push(invalid_type(
+                            &format!("{}[{}]", item_param, j),
+                            "a non-negative integer token id",
+                            token,
+                        ));
+                    }
+                }
+            }
+            (kind, value) => errors.push(invalid_type(&item_param, kind.expected(), value)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Validates the completions `prompt` field.
+pub fn validate_prompt(prompt: Option<&Value>) -> Result<(), Vec<ItemError>> {
+    validate_array_items("prompt", prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_valid_prompts() {
+        assert!(validate_prompt(None).is_ok());
+        assert!(validate_prompt(Some(&json!("hello"))).is_ok());
+        assert!(validate_prompt(Some(&json!(["a", "b"]))).is_ok());
+        assert!(validate_prompt(Some(&json!([1, 2, 3]))).is_ok());
+        assert!(validate_prompt(Some(&json!([[1, 2], [3]]))).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_invalid_item() {
+        let errors = validate_prompt(Some(&json!(["a", 1, "c", {"x": 1}]))).unwrap_err();
+        let params: Vec<&str> = errors.iter().map(|e| e.param.as_str()).collect();
+        assert_eq!(params, vec!["prompt[1]", "prompt[3]"]);
+        assert_eq!(
+            errors[1].message,
+            "Invalid type for 'prompt[3]': expected a string, but got an object instead."
+        );
+    }
+
+    #[test]
+    fn test_nested_token_arrays() {
+        let errors = validate_prompt(Some(&json!([[1, -2], []]))).unwrap_err();
+        let params: Vec<&str> = errors.iter().map(|e| e.param.as_str()).collect();
+        assert_eq!(params, vec!["prompt[0][1]", "prompt[1]"]);
+        assert_eq!(errors[1].code, "empty_array");
+    }
+
+    #[test]
+    fn test_invalid_top_level() {
+        let errors = validate_prompt(Some(&json!([]))).unwrap_err();
+        assert_eq!(errors[0].param, "prompt");
+
+        let errors = validate_array_items("input", Some(&json!(true))).unwrap_err();
+        assert_eq!(errors[0].param, "input");
+        assert_eq!(errors[0].code, "invalid_type");
+    }
+}
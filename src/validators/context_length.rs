@@ -0,0 +1,169 @@
+use crate::scenario::InjectedError;
+use crate::utils::token_counting::{ChatMessage, TokenCounter};
+use serde_json::Value;
+
+/// Checks that every prompt of a completion request, plus `max_tokens`,
+/// fits in `context_window` tokens.
+///
+/// Each prompt of a batch is checked on its own, as the real API does.
+/// Text prompts are counted with `counter`; token ID prompts count one
+/// token per ID.
+///
+/// # Returns
+///
+/// `Err` with the `400 context_length_exceeded` error of the first prompt
+/// that does not fit.
+pub fn validate_context_length(
+    prompt: Option<&Value>,
+    max_tokens: u32,
+    context_window: u32,
+    counter: &TokenCounter,
+) -> Result<(), InjectedError> {
+    check_prompt_tokens(&prompt_token_counts(prompt, counter), max_tokens, context_window)
+}
+
+/// Like [`validate_context_length`], for prompts already counted with
+/// [`prompt_token_counts`].
+pub fn check_prompt_tokens(
+    counts: &[u32],
+    max_tokens: u32,
+    context_window: u32,
+) -> Result<(), InjectedError> {
+    match counts
+        .iter()
+        .find(|&&prompt_tokens| prompt_tokens.saturating_add(max_tokens) > context_window)
+    {
+        Some(&prompt_tokens) => Err(InjectedError::context_length_exceeded(
+            context_window,
+            prompt_tokens,
+            max_tokens,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The number of tokens of each prompt of a completion request. Text
+/// prompts are counted with `counter`; token ID prompts count one token
+/// per ID.
+pub fn prompt_token_counts(prompt: Option<&Value>, counter: &TokenCounter) -> Vec<u32> {
+    match prompt {
+        Some(Value::String(text)) => vec![counter.count_tokens(text)],
+        Some(Value::Array(items)) if items.iter().all(Value::is_number) => vec![items.len() as u32],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(text) => counter.count_tokens(text),
+                Value::Array(ids) => ids.len() as u32,
+                _ => 0,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Checks that chat `messages`, plus `max_tokens` if set, fit in
+/// `context_window` tokens. Only the text content of messages is counted.
+///
+/// # Returns
+///
+/// `Err` with the `400 context_length_exceeded` error of the real API.
+pub fn validate_messages_context_length(
+    messages: &Value,
+    max_tokens: Option<u32>,
+    context_window: u32,
+    counter: &TokenCounter,
+) -> Result<(), InjectedError> {
+    let message_tokens = counter.count_messages_tokens(&chat_messages(messages));
+
+    match max_tokens {
+        None if message_tokens > context_window => Err(
+            InjectedError::messages_context_length_exceeded(context_window, message_tokens),
+        ),
+        Some(max_tokens) if message_tokens.saturating_add(max_tokens) > context_window => Err(
+            InjectedError::chat_context_length_exceeded(context_window, message_tokens, max_tokens),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// The role, text content and name of each of the chat `messages` of a
+/// request body.
+pub fn chat_messages(messages: &Value) -> Vec<ChatMessage> {
+    messages
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|message| ChatMessage {
+            role: message["role"].as_str().unwrap_or_default().to_string(),
+            content: text_content(&message["content"]),
+            name: message["name"].as_str().map(str::to_string),
+        })
+        .collect()
+}
+
+/// The text of a message's `content`, a string or an array of parts.
+fn text_content(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_context_length() {
+        let counter = TokenCounter::approximate();
+        let long = "one two three four five six";
+        let tokens = counter.count_tokens(long);
+        let prompt = json!(["short", long]);
+        assert!(validate_context_length(Some(&prompt), 4, tokens + 4, &counter).is_ok());
+
+        let error = validate_context_length(Some(&prompt), 5, tokens + 4, &counter).unwrap_err();
+        assert_eq!(error.code.as_deref(), Some("context_length_exceeded"));
+        assert!(error.message.contains(&format!(
+            "however you requested {} tokens ({} in your prompt; 5 for the completion)",
+            tokens + 5,
+            tokens
+        )));
+
+        let ids = json!([1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(validate_context_length(Some(&ids), 3, 10, &counter).is_err());
+    }
+
+    #[test]
+    fn test_messages_context_length() {
+        let counter = TokenCounter::approximate();
+        let messages =
+            json!([{"role": "user", "content": [{"type": "text", "text": "one two three"}]}]);
+        let tokens = counter.count_messages_tokens(&[ChatMessage {
+            role: "user".to_string(),
+            content: "one two three".to_string(),
+            name: None,
+        }]);
+        assert!(validate_messages_context_length(&messages, None, tokens, &counter).is_ok());
+
+        let error =
+            validate_messages_context_length(&messages, None, tokens - 1, &counter).unwrap_err();
+        assert!(error
+            .message
+            .contains(&format!("your messages resulted in {} tokens", tokens)));
+
+        let error = validate_messages_context_length(&messages, Some(10), tokens + 9, &counter)
+            .unwrap_err();
+        assert!(error.message.contains(&format!(
+            "you requested {} tokens ({} in the messages, 10 in the completion)",
+            tokens + 10,
+            tokens
+        )));
+    }
+}
@@ -1,6 +1,13 @@
 mod validation_error;
 mod req_required_fields;
 mod optional_fields;
+mod array_items;
+mod context_length;
 pub use validation_error::ValidationError;
 pub use req_required_fields::validate_required_fields;
-pub use optional_fields::*;
\ No newline at end of file
+pub use optional_fields::*;
+pub use array_items::{ItemError, validate_array_items, validate_prompt};
+pub use context_length::{
+    chat_messages, check_prompt_tokens, prompt_token_counts, validate_context_length,
+    validate_messages_context_length,
+};
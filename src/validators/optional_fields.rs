@@ -1,70 +1,115 @@
-use serde::{Serialize, Deserialize};
+use crate::validators::ValidationError;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
-pub fn validate_temperature(temperature: Option<f32>) -> Result<(), String> {
+/// Checks that `value` of `param` is within `min..=max`, failing with
+/// `message` and the API's `decimal_below_min_value` or
+/// `decimal_above_max_value` code otherwise.
+fn check_decimal_range(param: &str, value: f32, min: f32, max: f32, message: String) -> Result<(), ValidationError> {
+    if (min..=max).contains(&value) {
+        return Ok(());
+    }
+    let error = ValidationError::invalid(param, message);
+    Err(if value < min {
+        error.with_code("decimal_below_min_value")
+    } else if value > max {
+        error.with_code("decimal_above_max_value")
+    } else {
+        error
+    })
+}
+
+/// Checks that integer `value` of `param` is at least `min`, failing with
+/// `message` and the API's `integer_below_min_value` code otherwise.
+fn check_integer_min(param: &str, value: i64, min: i64, message: String) -> Result<(), ValidationError> {
+    if value >= min {
+        return Ok(());
+    }
+    Err(ValidationError::invalid(param, message).with_code("integer_below_min_value"))
+}
+
+/// Checks that integer `value` of `param` is at most `max`, failing with
+/// `message` and the API's `integer_above_max_value` code otherwise.
+fn check_integer_max(param: &str, value: i64, max: i64, message: String) -> Result<(), ValidationError> {
+    if value <= max {
+        return Ok(());
+    }
+    Err(ValidationError::invalid(param, message).with_code("integer_above_max_value"))
+}
+
+pub fn validate_temperature(temperature: Option<f32>) -> Result<(), ValidationError> {
     if let Some(temp) = temperature {
-        if temp < 0.0 || temp > 2.0 {
-            return Err(format!("Temperature must be between 0.0 and 2.0, got {}", temp));
-        }
+        let message = format!("Temperature must be between 0.0 and 2.0, got {}", temp);
+        check_decimal_range("temperature", temp, 0.0, 2.0, message)?;
     }
     Ok(())
 }
 
-pub fn validate_top_p(top_p: Option<f32>) -> Result<(), String> {
+pub fn validate_top_p(top_p: Option<f32>) -> Result<(), ValidationError> {
     if let Some(p) = top_p {
-        if p < 0.0 || p > 1.0 {
-            return Err(format!("Top_p must be between 0.0 and 1.0, got {}", p));
-        }
+        let message = format!("Top_p must be between 0.0 and 1.0, got {}", p);
+        check_decimal_range("top_p", p, 0.0, 1.0, message)?;
     }
     Ok(())
 }
 
-pub fn validate_n(n: Option<i32>) -> Result<(), String> {
+/// Most choices a request can ask for, as on the real API.
+pub const MAX_N: i32 = 128;
+
+pub fn validate_n(n: Option<i32>) -> Result<(), ValidationError> {
     if let Some(value) = n {
-        if value <= 0 {
-            return Err(format!("n must be a positive integer, got {}", value));
-        }
+        let message = format!("n must be between 1 and {}, got {}", MAX_N, value);
+        check_integer_min("n", value.into(), 1, message.clone())?;
+        check_integer_max("n", value.into(), MAX_N.into(), message)?;
     }
     Ok(())
 }
 
-pub fn validate_max_tokens(max_tokens: Option<u32>) -> Result<(), String> {
+pub fn validate_max_tokens(max_tokens: Option<u32>) -> Result<(), ValidationError> {
     if let Some(value) = max_tokens {
-        if value <= 0 {
-            return Err(format!("max_tokens must be a positive integer, got {}", value));
-        }
+        let message = format!("max_tokens must be a positive integer, got {}", value);
+        check_integer_min("max_tokens", value.into(), 1, message)?;
     }
     Ok(())
 }
 
-pub fn validate_presence_penalty(presence_penalty: Option<f32>) -> Result<(), String> {
+pub fn validate_presence_penalty(presence_penalty: Option<f32>) -> Result<(), ValidationError> {
     if let Some(value) = presence_penalty {
-        if value < -2.0 || value > 2.0 {
-            return Err(format!("Presence penalty must be between -2.0 and 2.0, got {}", value));
-        }
+        let message = format!("Presence penalty must be between -2.0 and 2.0, got {}", value);
+        check_decimal_range("presence_penalty", value, -2.0, 2.0, message)?;
     }
     Ok(())
 }
 
-pub fn validate_frequency_penalty(frequency_penalty: Option<f32>) -> Result<(), String> {
+pub fn validate_frequency_penalty(frequency_penalty: Option<f32>) -> Result<(), ValidationError> {
     if let Some(value) = frequency_penalty {
-        if value < -2.0 || value > 2.0 {
-            return Err(format!("Frequency penalty must be between -2.0 and 2.0, got {}", value));
-        }
+        let message = format!("Frequency penalty must be between -2.0 and 2.0, got {}", value);
+        check_decimal_range("frequency_penalty", value, -2.0, 2.0, message)?;
     }
     Ok(())
 }
 
-pub fn validate_best_of(best_of: Option<i32>, n: Option<i32>) -> Result<(), String> {
+pub fn validate_best_of(best_of: Option<i32>, n: Option<i32>, stream: Option<bool>) -> Result<(), ValidationError> {
     if let Some(best_of_value) = best_of {
-        if best_of_value <= 0 {
-            return Err(format!("best_of must be a positive integer, got {}", best_of_value));
+        let message = format!("best_of must be a positive integer, got {}", best_of_value);
+        check_integer_min("best_of", best_of_value.into(), 1, message)?;
+
+        // Candidates are only ranked once all are complete.
+        if best_of_value > 1 && stream == Some(true) {
+            return Err(ValidationError::invalid(
+                "best_of",
+                "Cannot stream results with best_of greater than 1",
+            ));
         }
 
         if let Some(n_value) = n {
             if best_of_value < n_value {
-                return Err(format!(
-                    "best_of must be greater than or equal to n, got best_of={} and n={}",
-                    best_of_value, n_value
+                return Err(ValidationError::invalid(
+                    "best_of",
+                    format!(
+                        "best_of must be greater than or equal to n, got best_of={} and n={}",
+                        best_of_value, n_value
+                    ),
                 ));
             }
         }
@@ -72,37 +117,62 @@ pub fn validate_best_of(best_of: Option<i32>, n: Option<i32>) -> Result<(), Stri
     Ok(())
 }
 
-pub fn validate_logprobs(logprobs: Option<u32>) -> Result<(), String> {
+pub fn validate_logprobs(logprobs: Option<u32>) -> Result<(), ValidationError> {
     if let Some(value) = logprobs {
-        #[allow(unused_comparisons)]
-        if value < 0 {
-            return Err(format!("logprobs must be a non-negative integer, got {}", value));
-        }
+        let message = format!("logprobs must be a non-negative integer, got {}", value);
+        check_integer_min("logprobs", value.into(), 0, message)?;
     }
     Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The `stop` parameter: one sequence or a list of them, written as a
+/// plain JSON string or array as clients send it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
 pub enum StopSequence {
     Single(String),
     Multiple(Vec<String>),
 }
 
-pub fn validate_stop(stop: Option<StopSequence>) -> Result<(), String> {
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawStopSequence {
+    Single(String),
+    Multiple(Vec<String>),
+    Invalid(serde_json::Value),
+}
+
+impl<'de> Deserialize<'de> for StopSequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawStopSequence::deserialize(deserializer)? {
+            RawStopSequence::Single(stop) => Ok(StopSequence::Single(stop)),
+            RawStopSequence::Multiple(stops) => Ok(StopSequence::Multiple(stops)),
+            RawStopSequence::Invalid(value) => Err(de::Error::custom(format!(
+                "stop must be a string or an array of strings, got {}",
+                value
+            ))),
+        }
+    }
+}
+
+pub fn validate_stop(stop: Option<&StopSequence>) -> Result<(), ValidationError> {
     if let Some(stop_value) = stop {
         match stop_value {
             StopSequence::Single(s) => {
                 if s.is_empty() {
-                    return Err("Stop sequence cannot be empty".to_string());
+                    return Err(ValidationError::invalid("stop", "Stop sequence cannot be empty"));
                 }
             }
             StopSequence::Multiple(sequences) => {
                 if sequences.is_empty() {
-                    return Err("Stop sequences array cannot be empty".to_string());
+                    return Err(ValidationError::invalid("stop", "Stop sequences array cannot be empty"));
                 }
                 for (i, sequence) in sequences.iter().enumerate() {
                     if sequence.is_empty() {
-                        return Err(format!("Stop sequence at index {} cannot be empty", i));
+                        return Err(ValidationError::invalid(
+                            "stop",
+                            format!("Stop sequence at index {} cannot be empty", i),
+                        ));
                     }
                 }
             }
@@ -111,6 +181,45 @@ pub fn validate_stop(stop: Option<StopSequence>) -> Result<(), String> {
     Ok(())
 }
 
+/// Most entries the API accepts in a `logit_bias` map.
+pub const MAX_LOGIT_BIAS_ENTRIES: usize = 300;
+
+pub fn validate_logit_bias(logit_bias: Option<&HashMap<String, i32>>) -> Result<(), ValidationError> {
+    let invalid = |message: String| Err(ValidationError::invalid("logit_bias", message));
+    if let Some(bias) = logit_bias {
+        if bias.len() > MAX_LOGIT_BIAS_ENTRIES {
+            return invalid(format!(
+                "logit_bias must have at most {} entries, got {}",
+                MAX_LOGIT_BIAS_ENTRIES,
+                bias.len()
+            ));
+        }
+
+        // Keys are checked first, then values in token order, so the same
+        // map always reports the same entry.
+        if let Some(key) = bias.keys().filter(|key| key.parse::<u32>().is_err()).min() {
+            return invalid(format!(
+                "Invalid key in logit_bias: {:?}. Keys must be token IDs, non-negative integers",
+                key
+            ));
+        }
+        let mut entries: Vec<(u32, &String, i32)> = bias
+            .iter()
+            .filter_map(|(key, value)| Some((key.parse().ok()?, key, *value)))
+            .collect();
+        entries.sort_unstable();
+        for (_, key, value) in entries {
+            if !(-100..=100).contains(&value) {
+                return invalid(format!(
+                    "Invalid value for logit_bias token {}: {}. Biases must be between -100 and 100",
+                    key, value
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +249,8 @@ mod tests {
         assert!(validate_n(None).is_ok());
         assert!(validate_n(Some(1)).is_ok());
         assert!(validate_n(Some(100)).is_ok());
+        assert!(validate_n(Some(MAX_N)).is_ok());
+        assert!(validate_n(Some(MAX_N + 1)).is_err());
         assert!(validate_n(Some(0)).is_err());
         assert!(validate_n(Some(-1)).is_err());
     }
@@ -175,14 +286,19 @@ mod tests {
     #[test]
     fn test_validate_best_of() {
         // Test basic positive integer validation
-        assert!(validate_best_of(None, None).is_ok());
-        assert!(validate_best_of(Some(1), None).is_ok());
-        assert!(validate_best_of(Some(0), None).is_err());
+        assert!(validate_best_of(None, None, None).is_ok());
+        assert!(validate_best_of(Some(1), None, None).is_ok());
+        assert!(validate_best_of(Some(0), None, None).is_err());
 
         // Test relationship with n
-        assert!(validate_best_of(Some(5), Some(3)).is_ok());
-        assert!(validate_best_of(Some(5), Some(5)).is_ok());
-        assert!(validate_best_of(Some(3), Some(5)).is_err());
+        assert!(validate_best_of(Some(5), Some(3), None).is_ok());
+        assert!(validate_best_of(Some(5), Some(5), None).is_ok());
+        assert!(validate_best_of(Some(3), Some(5), None).is_err());
+
+        // Test streaming, which only works with a single candidate
+        assert!(validate_best_of(Some(1), None, Some(true)).is_ok());
+        assert!(validate_best_of(Some(2), None, Some(true)).is_err());
+        assert!(validate_best_of(Some(2), None, Some(false)).is_ok());
     }
 
     #[test]
@@ -201,18 +317,71 @@ mod tests {
         assert!(validate_stop(None).is_ok());
 
         // Test single string cases
-        assert!(validate_stop(Some(StopSequence::Single("stop".to_string()))).is_ok());
-        assert!(validate_stop(Some(StopSequence::Single("".to_string()))).is_err());
+        assert!(validate_stop(Some(&StopSequence::Single("stop".to_string()))).is_ok());
+        assert!(validate_stop(Some(&StopSequence::Single("".to_string()))).is_err());
 
         // Test array cases
-        assert!(validate_stop(Some(StopSequence::Multiple(vec![
+        assert!(validate_stop(Some(&StopSequence::Multiple(vec![
             "stop1".to_string(),
             "stop2".to_string()
         ]))).is_ok());
-        assert!(validate_stop(Some(StopSequence::Multiple(vec![]))).is_err());
-        assert!(validate_stop(Some(StopSequence::Multiple(vec![
+        assert!(validate_stop(Some(&StopSequence::Multiple(vec![]))).is_err());
+        assert!(validate_stop(Some(&StopSequence::Multiple(vec![
             "valid".to_string(),
             "".to_string()
         ]))).is_err());
     }
+
+    #[test]
+    fn test_parse_stop() {
+        let stop: StopSequence = serde_json::from_str(r#""\n""#).unwrap();
+        assert_eq!(stop, StopSequence::Single("\n".to_string()));
+        let stop: StopSequence = serde_json::from_str(r#"["\n", "END"]"#).unwrap();
+        assert_eq!(stop, StopSequence::Multiple(vec!["\n".to_string(), "END".to_string()]));
+        assert_eq!(serde_json::to_string(&stop).unwrap(), r#"["\n","END"]"#);
+
+        let error = serde_json::from_str::<StopSequence>("5").unwrap_err();
+        assert!(error.to_string().contains("stop must be a string or an array of strings"));
+    }
+
+    #[test]
+    fn test_validate_logit_bias() {
+        let bias = |entries: &[(&str, i32)]| -> HashMap<String, i32> {
+            entries.iter().map(|(key, value)| (key.to_string(), *value)).collect()
+        };
+        assert!(validate_logit_bias(None).is_ok());
+        assert!(validate_logit_bias(Some(&bias(&[("50256", -100), ("1917", 100)]))).is_ok());
+        assert!(validate_logit_bias(Some(&bias(&[("hello", 1)]))).is_err());
+        assert!(validate_logit_bias(Some(&bias(&[("-5", 1)]))).is_err());
+        assert!(validate_logit_bias(Some(&bias(&[("1917", 101)]))).is_err());
+        assert!(validate_logit_bias(Some(&bias(&[("1917", -101)]))).is_err());
+        let error = validate_logit_bias(Some(&bias(&[("10", 200), ("9", 200), ("x", 0)]))).unwrap_err();
+        assert!(error.message.starts_with("Invalid key in logit_bias: \"x\""), "{}", error);
+        let error = validate_logit_bias(Some(&bias(&[("10", 200), ("9", 200)]))).unwrap_err();
+        assert!(error.message.starts_with("Invalid value for logit_bias token 9:"), "{}", error);
+
+        let full: HashMap<String, i32> =
+            (0..=MAX_LOGIT_BIAS_ENTRIES).map(|id| (id.to_string(), 1)).collect();
+        assert!(validate_logit_bias(Some(&full)).is_err());
+    }
+
+    #[test]
+    fn test_validation_error_details() {
+        let error = validate_temperature(Some(2.5)).unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("temperature"));
+        assert_eq!(error.code.as_deref(), Some("decimal_above_max_value"));
+        let error = validate_top_p(Some(-0.1)).unwrap_err();
+        assert_eq!(error.code.as_deref(), Some("decimal_below_min_value"));
+        let error = validate_n(Some(0)).unwrap_err();
+        assert_eq!((error.param.as_deref(), error.code.as_deref()), (Some("n"), Some("integer_below_min_value")));
+        let error = validate_n(Some(i32::MAX)).unwrap_err();
+        assert_eq!(error.code.as_deref(), Some("integer_above_max_value"));
+
+        let error = validate_stop(Some(&StopSequence::Multiple(vec![]))).unwrap_err();
+        let error = crate::models::ErrorResponse::from(error);
+        assert_eq!(error.status, http::StatusCode::BAD_REQUEST);
+        assert_eq!(error.error.r#type, "invalid_request_error");
+        assert_eq!(error.error.param.as_deref(), Some("stop"));
+        assert_eq!(error.error.code, None);
+    }
 }
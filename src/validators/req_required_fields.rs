@@ -5,7 +5,7 @@ use crate::validators::ValidationError;
 pub fn validate_required_fields(req: &CompletionRequest) -> Result<(), ValidationError> {
     // Validate model field
     if req.model.trim().is_empty() {
-        return Err(ValidationError::new("model field must not be empty"));
+        return Err(ValidationError::invalid("model", "model field must not be empty"));
     }
 
     // Note: prompt is already handled by the Optional<Value> type in the struct
@@ -1,22 +1,89 @@
+use crate::models::{ErrorResponse, OpenAIError};
+use crate::service::MockResponse;
+use http::StatusCode;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// An invalid request field, returned as a `400 Bad Request` in the API's
+/// error envelope.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValidationError {
-    message: String,
+    /// Human readable description of the problem.
+    pub message: String,
+
+    /// The offending request parameter, if the error is about one.
+    #[serde(default)]
+    pub param: Option<String>,
+
+    /// Machine readable error code, e.g. `integer_below_min_value`.
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// Error `type`, `invalid_request_error` unless set otherwise.
+    #[serde(default = "default_error_type", rename = "type")]
+    pub error_type: String,
+}
+
+fn default_error_type() -> String {
+    "invalid_request_error".to_string()
 }
 
 impl ValidationError {
     pub fn new(message: &str) -> Self {
         Self {
             message: message.to_string(),
+            param: None,
+            code: None,
+            error_type: default_error_type(),
+        }
+    }
+
+    /// An error about request parameter `param`.
+    pub fn invalid(param: &str, message: impl Into<String>) -> Self {
+        Self {
+            param: Some(param.to_string()),
+            ..Self::new(&message.into())
+        }
+    }
+
+    /// Sets the machine readable error code.
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Sets the error `type`.
+    pub fn with_error_type(mut self, error_type: &str) -> Self {
+        self.error_type = error_type.to_string();
+        self
+    }
+
+    /// The `400 Bad Request` response reporting the error.
+    pub fn to_response(&self) -> MockResponse {
+        ErrorResponse::from(self.clone()).to_response()
+    }
+}
+
+impl From<ValidationError> for OpenAIError {
+    fn from(error: ValidationError) -> Self {
+        OpenAIError {
+            message: error.message,
+            r#type: error.error_type,
+            param: error.param,
+            code: error.code,
         }
     }
 }
 
+impl From<ValidationError> for ErrorResponse {
+    fn from(error: ValidationError) -> Self {
+        ErrorResponse::new(StatusCode::BAD_REQUEST, error.into())
+    }
+}
+
 impl std::fmt::Display for ValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.message)
     }
 }
 
-impl std::error::Error for ValidationError {}
\ No newline at end of file
+impl std::error::Error for ValidationError {}